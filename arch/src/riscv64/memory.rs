@@ -1,3 +1,6 @@
+use config::mm::PAGE_SIZE;
+use sbi_rt::HartMask;
+
 pub unsafe fn sfence_vma_vaddr(vaddr: usize) {
     core::arch::riscv64::sfence_vma_vaddr(vaddr);
 }
@@ -5,3 +8,16 @@ pub unsafe fn sfence_vma_vaddr(vaddr: usize) {
 pub unsafe fn sfence_vma_all() {
     core::arch::riscv64::sfence_vma_all();
 }
+
+/// Flush the TLB entries of the page at `vaddr` on all harts, which is needed
+/// when unmapping a page of a memory space that may be active on other harts.
+/// It returns after all harts have flushed, or `false` if the SBI call fails.
+pub fn sfence_vma_vaddr_all_harts(vaddr: usize) -> bool {
+    // Hart mask base of `usize::MAX` selects all harts.
+    let ret = sbi_rt::remote_sfence_vma(HartMask::from_mask_base(0, usize::MAX), vaddr, PAGE_SIZE);
+    if ret.is_err() {
+        log::error!("[sfence_vma_vaddr_all_harts] remote sfence failed, {ret:?}");
+        return false;
+    }
+    true
+}
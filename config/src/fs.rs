@@ -1,6 +1,37 @@
-use super::mm::PAGE_SIZE;
+use super::{mm::PAGE_SIZE, utils::register_mut_const};
 
 /// Max file descriptors counts
 pub const MAX_FDS: usize = 1024;
 
 pub const PIPE_BUF_LEN: usize = 16 * PAGE_SIZE;
+
+/// Max length of a file name
+pub const NAME_MAX: usize = 255;
+
+register_mut_const!(
+    /// Age in milliseconds after which dirty pages are written back by the
+    /// writeback task.
+    pub DIRTY_EXPIRE_MS,
+    usize,
+    30_000
+);
+register_mut_const!(
+    /// Interval in milliseconds at which the writeback task wakes up.
+    pub DIRTY_WRITEBACK_INTERVAL_MS,
+    usize,
+    5_000
+);
+register_mut_const!(
+    /// Percentage of memory in dirty pages at which the writeback task starts
+    /// to write back all dirty pages regardless of their age.
+    pub DIRTY_BACKGROUND_RATIO,
+    usize,
+    10
+);
+register_mut_const!(
+    /// Percentage of memory in dirty pages at which writers are throttled to
+    /// write back dirty pages themselves.
+    pub DIRTY_RATIO,
+    usize,
+    20
+);
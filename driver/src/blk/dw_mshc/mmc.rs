@@ -670,4 +670,8 @@ impl BlockDevice for MMC {
     fn write_block(&self, block_id: usize, buf: &[u8]) {
         self.base_write_blocks(block_id, buf)
     }
+
+    fn flush(&self) {
+        // Blocks are written through to the card without caching.
+    }
 }
@@ -130,4 +130,8 @@ impl BlockDevice for Vf2SDImpl {
     fn write_block(&self, block_id: usize, buf: &[u8]) {
         self.base_write_blocks(block_id, buf)
     }
+
+    fn flush(&self) {
+        // Blocks are written through to the card without caching.
+    }
 }
@@ -4,7 +4,11 @@ use core::ptr::NonNull;
 use config::board::BLOCK_SIZE;
 use device_core::{BlockDevice, DevId, Device, DeviceMajor, DeviceMeta, DeviceType};
 use log::error;
-use memory::{alloc_frames, dealloc_frame, PhysAddr, PhysPageNum, VirtAddr};
+use memory::{
+    alloc_frames, dealloc_frame,
+    reclaim::{register_shrinker, Shrinker},
+    PhysAddr, PhysPageNum, VirtAddr,
+};
 use page::BufferCache;
 use sync::mutex::SpinNoIrqLock;
 use virtio_drivers::{device::blk::VirtIOBlk, transport::mmio::MmioTransport, BufferDirection};
@@ -69,6 +73,27 @@ impl BlockDevice for VirtIoBlkDev {
     fn write_block(&self, block_id: usize, buf: &[u8]) {
         self.cache.lock().write_block(block_id, buf)
     }
+
+    fn flush(&self) {
+        self.cache.lock().flush();
+        self.device
+            .lock()
+            .flush()
+            .expect("Error when flushing VirtIOBlk");
+    }
+}
+
+impl Shrinker for VirtIoBlkDev {
+    fn name(&self) -> &'static str {
+        "virtio-blk buffer cache"
+    }
+
+    fn scan_objects(&self, nr_to_scan: usize) -> usize {
+        // The cache may be locked by the caller allocating a block page.
+        self.cache
+            .try_lock()
+            .map_or(0, |mut cache| cache.shrink(nr_to_scan))
+    }
 }
 
 impl VirtIoBlkDev {
@@ -98,6 +123,7 @@ impl VirtIoBlkDev {
                     cache: SpinNoIrqLock::new(BufferCache::new()),
                 });
                 blk_dev.cache.lock().init_device(blk_dev.clone());
+                register_shrinker(blk_dev.clone());
                 Some(blk_dev)
             }
             Err(e) => {
@@ -0,0 +1,458 @@
+//! Linux native asynchronous I/O.
+//!
+//! Each iocb submitted by `io_submit` runs as a future spawned on the
+//! executor in the address space of the submitting task, and its result is
+//! queued in the AIO context as an `IoEvent` to be reaped by `io_getevents`.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult, SyscallResult};
+use vfs::eventfd::EventFdFile;
+use vfs_core::{File, PollEvents};
+
+use crate::{
+    mm::{UserReadPtr, UserWritePtr},
+    syscall::IoVec,
+    task::{spawn_user_io_task, Task, Tid},
+};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// Max number of events of an AIO context, which is `aio-max-nr` in Linux.
+pub const AIO_MAX_NR: u32 = 65536;
+
+/// Opcodes of iocbs, defined in <linux/aio_abi.h>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum IocbCmd {
+    Pread = 0,
+    Pwrite = 1,
+    Fsync = 2,
+    Fdsync = 3,
+    Poll = 5,
+    Preadv = 7,
+    Pwritev = 8,
+}
+
+impl TryFrom<u16> for IocbCmd {
+    type Error = SysError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Pread),
+            1 => Ok(Self::Pwrite),
+            2 => Ok(Self::Fsync),
+            3 => Ok(Self::Fdsync),
+            5 => Ok(Self::Poll),
+            7 => Ok(Self::Preadv),
+            8 => Ok(Self::Pwritev),
+            _ => Err(SysError::EINVAL),
+        }
+    }
+}
+
+bitflags! {
+    /// Flags of an iocb.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct IocbFlags: u32 {
+        /// Notify the completion through the eventfd `aio_resfd`.
+        const RESFD = 1 << 0;
+        /// `aio_reqprio` is valid, which is ignored.
+        const IOPRIO = 1 << 1;
+    }
+}
+
+/// I/O control block, defined in <linux/aio_abi.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Iocb {
+    /// Data returned in `IoEvent::data`.
+    pub aio_data: u64,
+    pub aio_key: u32,
+    pub aio_rw_flags: i32,
+    pub aio_lio_opcode: u16,
+    pub aio_reqprio: i16,
+    pub aio_fildes: u32,
+    /// Buffer, iovec array or poll events, depending on the opcode.
+    pub aio_buf: u64,
+    pub aio_nbytes: u64,
+    pub aio_offset: i64,
+    pub aio_reserved2: u64,
+    pub aio_flags: u32,
+    pub aio_resfd: u32,
+}
+
+/// Completion event, defined in <linux/aio_abi.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct IoEvent {
+    /// `aio_data` of the iocb.
+    pub data: u64,
+    /// User address of the iocb.
+    pub obj: u64,
+    pub res: i64,
+    pub res2: i64,
+}
+
+/// A request submitted but not completed yet.
+struct AioRequest {
+    /// Task that submitted the request.
+    tid: Tid,
+    /// Only poll requests can be canceled, other requests are always
+    /// completed.
+    cancelable: bool,
+    canceled: bool,
+    waker: Option<Waker>,
+}
+
+pub struct AioContext {
+    /// User address of the ring, which also identifies the context.
+    id: usize,
+    /// Max number of events, including requests in flight.
+    nr_events: u32,
+    inner: Mutex<AioContextInner>,
+}
+
+struct AioContextInner {
+    /// Requests in flight keyed by the user address of their iocbs.
+    requests: BTreeMap<usize, AioRequest>,
+    /// Completed events not reaped by `io_getevents` yet.
+    events: VecDeque<IoEvent>,
+    /// Wakers of tasks waiting for events or the completion of all
+    /// requests.
+    wakers: Vec<Waker>,
+}
+
+impl AioContextInner {
+    /// Add `waker` to the wakers unless it is there already, since a task
+    /// may poll many times before it is woken.
+    fn add_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+}
+
+impl AioContext {
+    pub fn new(id: usize, nr_events: u32) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            nr_events,
+            inner: Mutex::new(AioContextInner {
+                requests: BTreeMap::new(),
+                events: VecDeque::new(),
+                wakers: Vec::new(),
+            }),
+        })
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Submit the iocb at user address `iocb_ptr`. Errors found before the
+    /// request is queued are returned at once, and others are reported in the
+    /// event.
+    pub fn submit(self: &Arc<Self>, task: &Arc<Task>, iocb_ptr: usize) -> SysResult<()> {
+        let iocb = UserReadPtr::<Iocb>::from(iocb_ptr).read(task)?;
+        log::info!("[AioContext::submit] iocb {iocb_ptr:#x}: {iocb:?}");
+        if iocb.aio_reserved2 != 0 {
+            return Err(SysError::EINVAL);
+        }
+        let flags = IocbFlags::from_bits(iocb.aio_flags).ok_or(SysError::EINVAL)?;
+        let resfd = if flags.contains(IocbFlags::RESFD) {
+            let file = task.with_fd_table(|table| table.get_file(iocb.aio_resfd as usize))?;
+            Some(
+                file.downcast_arc::<EventFdFile>()
+                    .map_err(|_| SysError::EINVAL)?,
+            )
+        } else {
+            None
+        };
+        let cmd = IocbCmd::try_from(iocb.aio_lio_opcode)?;
+        let file = task.with_fd_table(|table| table.get_file(iocb.aio_fildes as usize))?;
+        match cmd {
+            IocbCmd::Pread | IocbCmd::Preadv if !file.flags().readable() => {
+                return Err(SysError::EBADF)
+            }
+            IocbCmd::Pwrite | IocbCmd::Pwritev if !file.flags().writable() => {
+                return Err(SysError::EBADF)
+            }
+            IocbCmd::Pread | IocbCmd::Pwrite | IocbCmd::Preadv | IocbCmd::Pwritev
+                if iocb.aio_offset < 0 =>
+            {
+                return Err(SysError::EINVAL)
+            }
+            _ => {}
+        }
+
+        {
+            let mut inner = self.inner.lock();
+            if inner.requests.len() + inner.events.len() >= self.nr_events as usize {
+                return Err(SysError::EAGAIN);
+            }
+            let request = AioRequest {
+                tid: task.tid(),
+                cancelable: cmd == IocbCmd::Poll,
+                canceled: false,
+                waker: None,
+            };
+            if inner.requests.try_insert(iocb_ptr, request).is_err() {
+                // The iocb is in flight.
+                return Err(SysError::EINVAL);
+            }
+        }
+
+        let ctx = self.clone();
+        let task_clone = task.clone();
+        spawn_user_io_task(task.clone(), async move {
+            let ret = ctx.execute(&task_clone, cmd, &iocb, iocb_ptr, file).await;
+            log::info!("[AioContext] iocb {iocb_ptr:#x} returns {ret:?}");
+            let res = match ret {
+                Ok(n) => n as i64,
+                Err(e) => -(e.code() as i64),
+            };
+            ctx.complete(IoEvent {
+                data: iocb.aio_data,
+                obj: iocb_ptr as u64,
+                res,
+                res2: 0,
+            });
+            if let Some(eventfd) = resfd {
+                eventfd.signal(1);
+            }
+        });
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        task: &Arc<Task>,
+        cmd: IocbCmd,
+        iocb: &Iocb,
+        iocb_ptr: usize,
+        file: Arc<dyn File>,
+    ) -> SyscallResult {
+        let offset = iocb.aio_offset as usize;
+        let len = iocb.aio_nbytes as usize;
+        match cmd {
+            IocbCmd::Pread => {
+                let mut buf =
+                    UserWritePtr::<u8>::from(iocb.aio_buf as usize).into_mut_slice(task, len)?;
+                file.read_at(offset, &mut buf).await
+            }
+            IocbCmd::Pwrite => {
+                let buf = UserReadPtr::<u8>::from(iocb.aio_buf as usize).into_slice(task, len)?;
+                file.write_at(offset, &buf).await
+            }
+            IocbCmd::Preadv => {
+                let iovs =
+                    UserReadPtr::<IoVec>::from(iocb.aio_buf as usize).read_array(task, len)?;
+                let mut total_len = 0;
+                for iov in iovs.iter().filter(|iov| iov.len != 0) {
+                    let mut buf =
+                        UserWritePtr::<u8>::from(iov.base).into_mut_slice(task, iov.len)?;
+                    let read_len = file.read_at(offset + total_len, &mut buf).await?;
+                    total_len += read_len;
+                    if read_len < iov.len {
+                        break;
+                    }
+                }
+                Ok(total_len)
+            }
+            IocbCmd::Pwritev => {
+                let iovs =
+                    UserReadPtr::<IoVec>::from(iocb.aio_buf as usize).read_array(task, len)?;
+                let mut total_len = 0;
+                for iov in iovs.iter().filter(|iov| iov.len != 0) {
+                    let buf = UserReadPtr::<u8>::from(iov.base).into_slice(task, iov.len)?;
+                    let write_len = file.write_at(offset + total_len, &buf).await?;
+                    total_len += write_len;
+                    if write_len < iov.len {
+                        break;
+                    }
+                }
+                Ok(total_len)
+            }
+            IocbCmd::Fsync | IocbCmd::Fdsync => {
+                file.inode().sync()?;
+                Ok(0)
+            }
+            IocbCmd::Poll => {
+                let events = PollEvents::from_bits_truncate(iocb.aio_buf as i16);
+                let revents = AioPollFuture {
+                    ctx: self,
+                    iocb_ptr,
+                    file,
+                    events,
+                }
+                .await;
+                Ok(revents.bits() as u16 as usize)
+            }
+        }
+    }
+
+    /// Queue the event of a completed request.
+    fn complete(&self, event: IoEvent) {
+        let mut inner = self.inner.lock();
+        inner.requests.remove(&(event.obj as usize));
+        inner.events.push_back(event);
+        for waker in inner.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Cancel the request of the iocb at `iocb_ptr`. Its event is still
+    /// delivered to the context.
+    pub fn cancel(&self, iocb_ptr: usize) -> SysResult<()> {
+        let mut inner = self.inner.lock();
+        let request = inner.requests.get_mut(&iocb_ptr).ok_or(SysError::EINVAL)?;
+        if !request.cancelable {
+            return Err(SysError::EINVAL);
+        }
+        request.canceled = true;
+        if let Some(waker) = request.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Cancel all requests that can be canceled, or only those submitted by
+    /// `tid` if given. Used when the context is destroyed or the task goes
+    /// away.
+    pub fn cancel_all(&self, tid: Option<Tid>) {
+        let mut inner = self.inner.lock();
+        for request in inner.requests.values_mut() {
+            if request.cancelable && tid.is_none_or(|tid| request.tid == tid) {
+                request.canceled = true;
+                if let Some(waker) = request.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    /// Pop at most `nr` events.
+    pub fn reap(&self, nr: usize) -> Vec<IoEvent> {
+        let mut inner = self.inner.lock();
+        let n = nr.min(inner.events.len());
+        inner.events.drain(..n).collect()
+    }
+
+    /// Put `events` reaped but failed to be delivered back to the front.
+    pub fn unreap(&self, events: Vec<IoEvent>) {
+        let mut inner = self.inner.lock();
+        for event in events.into_iter().rev() {
+            inner.events.push_front(event);
+        }
+    }
+}
+
+/// Wait until at least `min_nr` events are ready.
+pub struct AioEventsFuture {
+    ctx: Arc<AioContext>,
+    min_nr: usize,
+}
+
+impl AioEventsFuture {
+    pub fn new(ctx: Arc<AioContext>, min_nr: usize) -> Self {
+        Self { ctx, min_nr }
+    }
+}
+
+impl Future for AioEventsFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.ctx.inner.lock();
+        if inner.events.len() >= self.min_nr {
+            Poll::Ready(())
+        } else {
+            inner.add_waker(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+/// Wait until all requests in flight complete, or only those submitted by
+/// `tid` if given.
+pub struct AioDrainFuture {
+    ctx: Arc<AioContext>,
+    tid: Option<Tid>,
+}
+
+impl AioDrainFuture {
+    pub fn new(ctx: Arc<AioContext>, tid: Option<Tid>) -> Self {
+        Self { ctx, tid }
+    }
+}
+
+impl Future for AioDrainFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.ctx.inner.lock();
+        let drained = !inner
+            .requests
+            .values()
+            .any(|request| self.tid.is_none_or(|tid| request.tid == tid));
+        if drained {
+            Poll::Ready(())
+        } else {
+            inner.add_waker(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+/// Cancel the requests of `ctxs` and wait until those that can not be
+/// canceled complete, only for requests submitted by `tid` if given. This is
+/// done when the task exits or its address space is replaced by execve, since
+/// the requests access the memory and the files of the task.
+pub async fn drain_contexts(ctxs: Vec<Arc<AioContext>>, tid: Option<Tid>) {
+    for ctx in ctxs {
+        ctx.cancel_all(tid);
+        AioDrainFuture::new(ctx, tid).await;
+    }
+}
+
+/// Wait until any of `events` is ready on `file`, or the request is
+/// canceled, in which case no event is returned.
+struct AioPollFuture<'a> {
+    ctx: &'a AioContext,
+    iocb_ptr: usize,
+    file: Arc<dyn File>,
+    events: PollEvents,
+}
+
+impl Future for AioPollFuture<'_> {
+    type Output = PollEvents;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut inner = self.ctx.inner.lock();
+            let Some(request) = inner.requests.get_mut(&self.iocb_ptr) else {
+                return Poll::Ready(PollEvents::empty());
+            };
+            if request.canceled {
+                return Poll::Ready(PollEvents::empty());
+            }
+            request.waker = Some(cx.waker().clone());
+        }
+        let result = unsafe { Pin::new_unchecked(&mut self.file.poll(self.events)).poll(cx) };
+        match result {
+            Poll::Ready(revents) if !revents.is_empty() => Poll::Ready(revents),
+            _ => Poll::Pending,
+        }
+    }
+}
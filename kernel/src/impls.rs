@@ -8,11 +8,12 @@ use log::Level;
 use logging::{ColorCode, LogIf};
 use memory::{KernelMappingIf, PageTable, PhysAddr, VirtAddr};
 use net::HasSignalIf;
+use systype::{SysError, SysResult};
 use vfs::{procfs::KernelProcIf, sys_root_dentry};
 use vfs_core::{Dentry, SysRootDentryIf};
 
 use crate::{
-    mm::kernel_page_table_mut,
+    mm::{kernel_page_table_mut, oom},
     processor::hart::{current_task_ref, local_hart},
 };
 
@@ -110,6 +111,22 @@ impl KernelProcIf for KernelProcIfImpl {
     fn exe() -> alloc::string::String {
         current_task_ref().elf().dentry().path()
     }
+
+    fn oom_score() -> usize {
+        oom::oom_score(current_task_ref())
+    }
+
+    fn oom_score_adj() -> i32 {
+        current_task_ref().oom_score_adj()
+    }
+
+    fn set_oom_score_adj(adj: i32) -> SysResult<()> {
+        if !(oom::OOM_SCORE_ADJ_MIN..=oom::OOM_SCORE_ADJ_MAX).contains(&adj) {
+            return Err(SysError::EINVAL);
+        }
+        current_task_ref().set_oom_score_adj(adj);
+        Ok(())
+    }
 }
 
 struct SysRootDentryIfImpl;
@@ -0,0 +1,828 @@
+//! io_uring: asynchronous I/O through rings shared with user space.
+//!
+//! User space puts submission queue entries (SQEs) into the SQ ring, and the
+//! kernel posts completion queue entries (CQEs) into the CQ ring. Since
+//! syscalls are already futures, every chain of linked SQEs is simply run as a
+//! future spawned on the executor, in the address space of the submitting
+//! task.
+
+mod op;
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    mem::size_of,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use arch::time::get_time_duration;
+use async_trait::async_trait;
+use async_utils::{get_waker, yield_now};
+use config::mm::{round_up_to_page, PAGE_SIZE};
+pub use op::{is_supported, IORING_OP_LAST};
+use page::Page;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult, SyscallResult};
+use vfs_core::{arc_zero, File, FileMeta, OpenFlags, PollEvents};
+
+use crate::task::{spawn_kernel_task, spawn_user_io_task, Task, Tid};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// All rings alive, to find chains in flight on behalf of a task that exits or
+/// an address space that is replaced by execve.
+static RINGS: Mutex<Vec<Weak<IoUring>>> = Mutex::new(Vec::new());
+
+/// Offsets passed to mmap to map the rings, defined in <linux/io_uring.h>.
+pub const IORING_OFF_SQ_RING: usize = 0;
+pub const IORING_OFF_CQ_RING: usize = 0x800_0000;
+pub const IORING_OFF_SQES: usize = 0x1000_0000;
+
+/// Max number of entries of the SQ ring.
+pub const IORING_MAX_ENTRIES: u32 = 4096;
+/// Max number of entries of the CQ ring.
+pub const IORING_MAX_CQ_ENTRIES: u32 = 2 * IORING_MAX_ENTRIES;
+/// Max number of files registered by `IORING_REGISTER_FILES`.
+pub const IORING_MAX_FIXED_FILES: usize = 1 << 15;
+
+/// Set in the flags of the SQ ring when the SQ thread is idle and needs to be
+/// woken by `IORING_ENTER_SQ_WAKEUP`.
+const IORING_SQ_NEED_WAKEUP: u32 = 1;
+
+/// Idle time of the SQ thread before it sleeps, if not given by user.
+const DEFAULT_SQ_THREAD_IDLE: Duration = Duration::from_secs(1);
+
+bitflags! {
+    /// Flags of `io_uring_setup`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SetupFlags: u32 {
+        /// Busy-waiting for I/O completion, which is not supported.
+        const IOPOLL = 1 << 0;
+        /// A kernel thread polls the SQ ring.
+        const SQPOLL = 1 << 1;
+        /// Bind the SQ thread to `sq_thread_cpu`, which is ignored.
+        const SQ_AFF = 1 << 2;
+        /// Use `cq_entries` for the size of the CQ ring.
+        const CQSIZE = 1 << 3;
+        /// Clamp the number of entries to the max instead of failing.
+        const CLAMP = 1 << 4;
+        /// Share the async backend of another ring, which is not supported.
+        const ATTACH_WQ = 1 << 5;
+        /// Start the ring disabled, which is not supported.
+        const R_DISABLED = 1 << 6;
+    }
+
+    /// Flags of `io_uring_enter`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EnterFlags: u32 {
+        /// Wait for `min_complete` completions.
+        const GETEVENTS = 1 << 0;
+        /// Wake up the SQ thread.
+        const SQ_WAKEUP = 1 << 1;
+        /// Wait for free entries in the SQ ring, which are always available
+        /// since SQEs are consumed at once.
+        const SQ_WAIT = 1 << 2;
+    }
+
+    /// Flags of an SQE.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SqeFlags: u8 {
+        /// `fd` is an index into the files registered.
+        const FIXED_FILE = 1 << 0;
+        /// Issue after all inflight requests complete, which is not supported.
+        const IO_DRAIN = 1 << 1;
+        /// The next SQE starts after this one completes successfully.
+        const IO_LINK = 1 << 2;
+        /// Like `IO_LINK`, but the chain is not broken by a failure.
+        const IO_HARDLINK = 1 << 3;
+        /// Always issue asynchronously, which is how all SQEs are issued.
+        const ASYNC = 1 << 4;
+        /// Select a buffer from a registered group, which is not supported.
+        const BUFFER_SELECT = 1 << 5;
+    }
+
+    /// Features reported by `io_uring_setup`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Features: u32 {
+        /// SQEs are copied at submission, the memory can be reused at once.
+        const SUBMIT_STABLE = 1 << 2;
+        /// An offset of -1 for read and write means the current position.
+        const RW_CUR_POS = 1 << 3;
+    }
+}
+
+/// Offsets of the fields in the SQ ring, defined in <linux/io_uring.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SqRingOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Offsets of the fields in the CQ ring, defined in <linux/io_uring.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct CqRingOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Parameters of `io_uring_setup`, defined in <linux/io_uring.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct IoUringParams {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: SqRingOffsets,
+    pub cq_off: CqRingOffsets,
+}
+
+/// Submission queue entry, defined in <linux/io_uring.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct IoUringSqe {
+    pub opcode: u8,
+    pub flags: u8,
+    pub ioprio: u16,
+    pub fd: i32,
+    /// Offset into the file, or `addr2`.
+    pub off: u64,
+    pub addr: u64,
+    pub len: u32,
+    /// Flags specific to the opcode, e.g. `rw_flags`, `poll_events`,
+    /// `timeout_flags` and `accept_flags`.
+    pub op_flags: u32,
+    pub user_data: u64,
+    pub buf_index: u16,
+    pub personality: u16,
+    pub splice_fd_in: i32,
+    pub addr3: u64,
+    __pad2: u64,
+}
+
+/// Completion queue entry, defined in <linux/io_uring.h>.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct IoUringCqe {
+    pub user_data: u64,
+    pub res: i32,
+    pub flags: u32,
+}
+
+// Layout of the SQ ring.
+const SQ_HEAD: usize = 0;
+const SQ_TAIL: usize = 4;
+const SQ_RING_MASK: usize = 8;
+const SQ_RING_ENTRIES: usize = 12;
+const SQ_FLAGS: usize = 16;
+const SQ_DROPPED: usize = 20;
+const SQ_ARRAY: usize = 64;
+
+// Layout of the CQ ring.
+const CQ_HEAD: usize = 0;
+const CQ_TAIL: usize = 4;
+const CQ_RING_MASK: usize = 8;
+const CQ_RING_ENTRIES: usize = 12;
+const CQ_OVERFLOW: usize = 16;
+const CQ_FLAGS: usize = 20;
+const CQ_CQES: usize = 64;
+
+/// Memory shared with user space. The pages are not necessarily contiguous in
+/// the kernel, which is fine since every field of the rings is naturally
+/// aligned and never crosses a page boundary.
+struct RingRegion {
+    pages: Vec<Arc<Page>>,
+}
+
+impl RingRegion {
+    fn new(size: usize) -> Self {
+        let pages = (0..round_up_to_page(size) / PAGE_SIZE)
+            .map(|_| {
+                let page = Page::new();
+                page.fill_zero();
+                page
+            })
+            .collect();
+        Self { pages }
+    }
+
+    fn ptr<T>(&self, offset: usize) -> *mut T {
+        debug_assert!(offset % PAGE_SIZE + size_of::<T>() <= PAGE_SIZE);
+        let page = &self.pages[offset / PAGE_SIZE];
+        page.bytes_array()[offset % PAGE_SIZE..].as_mut_ptr() as *mut T
+    }
+
+    fn atomic(&self, offset: usize) -> &AtomicU32 {
+        unsafe { AtomicU32::from_ptr(self.ptr(offset)) }
+    }
+
+    fn read<T: Copy>(&self, offset: usize) -> T {
+        unsafe { self.ptr::<T>(offset).read_volatile() }
+    }
+
+    fn write<T>(&self, offset: usize, value: T) {
+        unsafe { self.ptr::<T>(offset).write_volatile(value) }
+    }
+}
+
+pub struct IoUring {
+    flags: SetupFlags,
+    sq_entries: u32,
+    cq_entries: u32,
+    sq_ring: RingRegion,
+    cq_ring: RingRegion,
+    sqes: RingRegion,
+    /// Task that set up the ring, on behalf of which the SQ thread submits.
+    owner: Weak<Task>,
+    /// Serializes consumers of the SQ ring.
+    sq_lock: Mutex<()>,
+    /// Serializes producers of the CQ ring.
+    cq_lock: Mutex<()>,
+    /// Number of CQEs posted, used by timeouts waiting for completions.
+    completions: AtomicUsize,
+    /// Wakers of tasks waiting for CQEs.
+    cq_wakers: Mutex<Vec<Waker>>,
+    /// Waker of the SQ thread when it sleeps.
+    sq_waker: Mutex<Option<Waker>>,
+    sq_thread_idle: Duration,
+    /// Files registered by `IORING_REGISTER_FILES`.
+    files: Mutex<Option<Vec<Option<Arc<dyn File>>>>>,
+    /// Set when the file of the ring is closed, which stops the SQ thread.
+    closed: AtomicBool,
+    chains: Mutex<Chains>,
+}
+
+/// Chains spawned but not completed yet.
+struct Chains {
+    next_id: usize,
+    inflight: BTreeMap<usize, Chain>,
+    /// Wakers of tasks waiting for chains to complete.
+    wakers: Vec<Waker>,
+}
+
+struct Chain {
+    /// Task on behalf of which the chain runs.
+    tid: Tid,
+    /// Address space in which the chain runs.
+    mm: usize,
+    canceled: bool,
+    /// Waker of the chain, woken when it is canceled.
+    waker: Option<Waker>,
+}
+
+/// Chains to be canceled.
+#[derive(Debug, Clone, Copy)]
+pub enum ChainOwner {
+    /// All chains of the ring.
+    All,
+    /// Chains running on behalf of the task.
+    Task(Tid),
+    /// Chains running in the address space.
+    MemorySpace(usize),
+}
+
+impl ChainOwner {
+    fn owns(&self, chain: &Chain) -> bool {
+        match *self {
+            Self::All => true,
+            Self::Task(tid) => chain.tid == tid,
+            Self::MemorySpace(mm) => chain.mm == mm,
+        }
+    }
+}
+
+impl IoUring {
+    /// Create the rings and fill the offsets in `params`. `params.sq_entries`
+    /// and `params.cq_entries` should be powers of two.
+    pub fn new(owner: &Arc<Task>, params: &mut IoUringParams) -> Arc<Self> {
+        let flags = SetupFlags::from_bits_truncate(params.flags);
+        let (sq_entries, cq_entries) = (params.sq_entries, params.cq_entries);
+        let sq_ring = RingRegion::new(SQ_ARRAY + sq_entries as usize * size_of::<u32>());
+        sq_ring.write(SQ_RING_MASK, sq_entries - 1);
+        sq_ring.write(SQ_RING_ENTRIES, sq_entries);
+        let cq_ring = RingRegion::new(CQ_CQES + cq_entries as usize * size_of::<IoUringCqe>());
+        cq_ring.write(CQ_RING_MASK, cq_entries - 1);
+        cq_ring.write(CQ_RING_ENTRIES, cq_entries);
+        let sqes = RingRegion::new(sq_entries as usize * size_of::<IoUringSqe>());
+
+        params.features = (Features::SUBMIT_STABLE | Features::RW_CUR_POS).bits();
+        params.sq_off = SqRingOffsets {
+            head: SQ_HEAD as u32,
+            tail: SQ_TAIL as u32,
+            ring_mask: SQ_RING_MASK as u32,
+            ring_entries: SQ_RING_ENTRIES as u32,
+            flags: SQ_FLAGS as u32,
+            dropped: SQ_DROPPED as u32,
+            array: SQ_ARRAY as u32,
+            ..Default::default()
+        };
+        params.cq_off = CqRingOffsets {
+            head: CQ_HEAD as u32,
+            tail: CQ_TAIL as u32,
+            ring_mask: CQ_RING_MASK as u32,
+            ring_entries: CQ_RING_ENTRIES as u32,
+            overflow: CQ_OVERFLOW as u32,
+            cqes: CQ_CQES as u32,
+            flags: CQ_FLAGS as u32,
+            ..Default::default()
+        };
+        let sq_thread_idle = match params.sq_thread_idle {
+            0 => DEFAULT_SQ_THREAD_IDLE,
+            ms => Duration::from_millis(ms as u64),
+        };
+
+        let ring = Arc::new(Self {
+            flags,
+            sq_entries,
+            cq_entries,
+            sq_ring,
+            cq_ring,
+            sqes,
+            owner: Arc::downgrade(owner),
+            sq_lock: Mutex::new(()),
+            cq_lock: Mutex::new(()),
+            completions: AtomicUsize::new(0),
+            cq_wakers: Mutex::new(Vec::new()),
+            sq_waker: Mutex::new(None),
+            sq_thread_idle,
+            files: Mutex::new(None),
+            closed: AtomicBool::new(false),
+            chains: Mutex::new(Chains {
+                next_id: 0,
+                inflight: BTreeMap::new(),
+                wakers: Vec::new(),
+            }),
+        });
+        RINGS.lock().push(Arc::downgrade(&ring));
+        if flags.contains(SetupFlags::SQPOLL) {
+            spawn_kernel_task(sq_thread(Arc::downgrade(&ring)));
+        }
+        ring
+    }
+
+    pub fn flags(&self) -> SetupFlags {
+        self.flags
+    }
+
+    /// Pages mapped at `offset` by mmap.
+    pub fn pages(&self, offset: usize) -> SysResult<&[Arc<Page>]> {
+        match offset {
+            IORING_OFF_SQ_RING => Ok(&self.sq_ring.pages),
+            IORING_OFF_CQ_RING => Ok(&self.cq_ring.pages),
+            IORING_OFF_SQES => Ok(&self.sqes.pages),
+            _ => Err(SysError::EINVAL),
+        }
+    }
+
+    /// Number of SQEs not consumed yet.
+    fn sq_pending(&self) -> u32 {
+        let head = self.sq_ring.atomic(SQ_HEAD).load(Ordering::Relaxed);
+        let tail = self.sq_ring.atomic(SQ_TAIL).load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+
+    /// Number of CQEs not reaped by user space yet.
+    pub fn cq_ready(&self) -> u32 {
+        let head = self.cq_ring.atomic(CQ_HEAD).load(Ordering::Acquire);
+        let tail = self.cq_ring.atomic(CQ_TAIL).load(Ordering::Relaxed);
+        tail.wrapping_sub(head)
+    }
+
+    /// Consume at most `to_submit` SQEs and spawn them on behalf of `task`.
+    /// Returns the number of SQEs consumed.
+    pub fn submit(self: &Arc<Self>, task: &Arc<Task>, to_submit: u32) -> usize {
+        let _guard = self.sq_lock.lock();
+        let head_atomic = self.sq_ring.atomic(SQ_HEAD);
+        let mut head = head_atomic.load(Ordering::Relaxed);
+        let tail = self.sq_ring.atomic(SQ_TAIL).load(Ordering::Acquire);
+        let mask = self.sq_entries - 1;
+        let mut submitted = 0;
+        let mut chain = Vec::new();
+        while submitted < to_submit as usize && head != tail {
+            let index: u32 = self
+                .sq_ring
+                .read(SQ_ARRAY + (head & mask) as usize * size_of::<u32>());
+            head = head.wrapping_add(1);
+            if index >= self.sq_entries {
+                // Invalid index is dropped silently and counted.
+                self.sq_ring
+                    .atomic(SQ_DROPPED)
+                    .fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            let sqe: IoUringSqe = self.sqes.read(index as usize * size_of::<IoUringSqe>());
+            submitted += 1;
+            let flags = SqeFlags::from_bits_truncate(sqe.flags);
+            chain.push(sqe);
+            if !flags.intersects(SqeFlags::IO_LINK | SqeFlags::IO_HARDLINK) {
+                self.spawn_chain(task, core::mem::take(&mut chain));
+            }
+        }
+        // A chain is ended by the end of the submission.
+        if !chain.is_empty() {
+            self.spawn_chain(task, chain);
+        }
+        head_atomic.store(head, Ordering::Release);
+        submitted
+    }
+
+    /// Run linked SQEs one by one. If one fails and is not hard linked, the
+    /// rest of the chain is canceled, and so is the whole chain when it is
+    /// canceled by `cancel_chains`.
+    fn spawn_chain(self: &Arc<Self>, task: &Arc<Task>, chain: Vec<IoUringSqe>) {
+        let ring = self.clone();
+        let task_clone = task.clone();
+        let id = {
+            let mut chains = self.chains.lock();
+            let id = chains.next_id;
+            chains.next_id += 1;
+            chains.inflight.insert(
+                id,
+                Chain {
+                    tid: task.tid(),
+                    mm: task.raw_mm_pointer(),
+                    canceled: false,
+                    waker: None,
+                },
+            );
+            id
+        };
+        spawn_user_io_task(task.clone(), async move {
+            let mut canceled = false;
+            for sqe in chain {
+                if canceled || ring.is_chain_canceled(id) {
+                    ring.post_cqe(sqe.user_data, -SysError::ECANCELED.code());
+                    continue;
+                }
+                let ret = ChainFuture {
+                    ring: &ring,
+                    id,
+                    future: op::execute(&ring, &task_clone, &sqe),
+                }
+                .await;
+                log::info!("[io_uring] opcode {} returns {ret:?}", sqe.opcode);
+                let flags = SqeFlags::from_bits_truncate(sqe.flags);
+                if flags.contains(SqeFlags::IO_LINK) && op::is_failed(&sqe, &ret) {
+                    canceled = true;
+                }
+                let res = match ret {
+                    Ok(n) => n as i32,
+                    Err(e) => -e.code(),
+                };
+                ring.post_cqe(sqe.user_data, res);
+            }
+            let mut chains = ring.chains.lock();
+            chains.inflight.remove(&id);
+            for waker in chains.wakers.drain(..) {
+                waker.wake();
+            }
+        });
+    }
+
+    fn is_chain_canceled(&self, id: usize) -> bool {
+        self.chains
+            .lock()
+            .inflight
+            .get(&id)
+            .is_some_and(|chain| chain.canceled)
+    }
+
+    /// Cancel chains in flight of `owner`. The SQEs not completed yet are
+    /// completed with `ECANCELED`.
+    fn cancel_chains(&self, owner: ChainOwner) {
+        let mut chains = self.chains.lock();
+        for chain in chains.inflight.values_mut() {
+            if owner.owns(chain) {
+                chain.canceled = true;
+                if let Some(waker) = chain.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    /// Post a CQE, which is dropped and counted in `overflow` if the CQ ring
+    /// is full.
+    pub fn post_cqe(&self, user_data: u64, res: i32) {
+        {
+            let _guard = self.cq_lock.lock();
+            let head = self.cq_ring.atomic(CQ_HEAD).load(Ordering::Acquire);
+            let tail_atomic = self.cq_ring.atomic(CQ_TAIL);
+            let tail = tail_atomic.load(Ordering::Relaxed);
+            if tail.wrapping_sub(head) >= self.cq_entries {
+                log::warn!("[io_uring] CQ ring overflows, drop CQE of {user_data:#x}");
+                self.cq_ring
+                    .atomic(CQ_OVERFLOW)
+                    .fetch_add(1, Ordering::Relaxed);
+            } else {
+                let index = (tail & (self.cq_entries - 1)) as usize;
+                self.cq_ring.write(
+                    CQ_CQES + index * size_of::<IoUringCqe>(),
+                    IoUringCqe {
+                        user_data,
+                        res,
+                        flags: 0,
+                    },
+                );
+                tail_atomic.store(tail.wrapping_add(1), Ordering::Release);
+            }
+            self.completions.fetch_add(1, Ordering::Release);
+        }
+        for waker in self.cq_wakers.lock().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn completions(&self) -> usize {
+        self.completions.load(Ordering::Acquire)
+    }
+
+    /// Register `waker` to be woken when the next CQE is posted, unless it is
+    /// registered already.
+    fn register_cq_waker(&self, waker: &Waker) {
+        let mut wakers = self.cq_wakers.lock();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    /// Wake the SQ thread if it sleeps.
+    pub fn wake_sq_thread(&self) {
+        self.sq_ring
+            .atomic(SQ_FLAGS)
+            .fetch_and(!IORING_SQ_NEED_WAKEUP, Ordering::AcqRel);
+        if let Some(waker) = self.sq_waker.lock().take() {
+            waker.wake();
+        }
+    }
+
+    /// Get the file registered at `index`.
+    pub fn fixed_file(&self, index: usize) -> SysResult<Arc<dyn File>> {
+        self.files
+            .lock()
+            .as_ref()
+            .and_then(|files| files.get(index).cloned().flatten())
+            .ok_or(SysError::EBADF)
+    }
+
+    pub fn register_files(&self, files: Vec<Option<Arc<dyn File>>>) -> SysResult<()> {
+        let mut registered = self.files.lock();
+        if registered.is_some() {
+            return Err(SysError::EBUSY);
+        }
+        *registered = Some(files);
+        Ok(())
+    }
+
+    pub fn unregister_files(&self) -> SysResult<()> {
+        self.files.lock().take().map(|_| ()).ok_or(SysError::ENXIO)
+    }
+}
+
+impl Drop for IoUring {
+    fn drop(&mut self) {
+        RINGS.lock().retain(|ring| ring.strong_count() != 0);
+    }
+}
+
+/// Cancel chains of `owner` in flight in all rings and wait until they
+/// complete. This is done when the task exits or its address space is
+/// replaced by execve, since the chains access the memory and the files of
+/// the task.
+pub async fn cancel_chains(owner: ChainOwner) {
+    let rings: Vec<Arc<IoUring>> = RINGS.lock().iter().filter_map(Weak::upgrade).collect();
+    for ring in rings {
+        ring.cancel_chains(owner);
+        ChainDrainFuture { ring, owner }.await;
+    }
+}
+
+/// Run an operation of the chain `id` until it completes or the chain is
+/// canceled.
+struct ChainFuture<'a, F> {
+    ring: &'a IoUring,
+    id: usize,
+    future: F,
+}
+
+impl<F: Future<Output = SyscallResult>> Future for ChainFuture<'_, F> {
+    type Output = SyscallResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        {
+            let mut chains = this.ring.chains.lock();
+            let chain = chains.inflight.get_mut(&this.id).unwrap();
+            if chain.canceled {
+                return Poll::Ready(Err(SysError::ECANCELED));
+            }
+            chain.waker = Some(cx.waker().clone());
+        }
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx)
+    }
+}
+
+/// Wait until all chains of `owner` complete.
+struct ChainDrainFuture {
+    ring: Arc<IoUring>,
+    owner: ChainOwner,
+}
+
+impl Future for ChainDrainFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut chains = self.ring.chains.lock();
+        if !chains.inflight.values().any(|chain| self.owner.owns(chain)) {
+            return Poll::Ready(());
+        }
+        if !chains.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            chains.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Wait until at least `min_complete` CQEs are ready.
+pub struct CqWaitFuture {
+    ring: Arc<IoUring>,
+    min_complete: u32,
+}
+
+impl CqWaitFuture {
+    pub fn new(ring: Arc<IoUring>, min_complete: u32) -> Self {
+        Self { ring, min_complete }
+    }
+}
+
+impl Future for CqWaitFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.ring.cq_ready() >= self.min_complete {
+            return Poll::Ready(());
+        }
+        self.ring.register_cq_waker(cx.waker());
+        // Check again in case a CQE is posted before the waker is registered.
+        if self.ring.cq_ready() >= self.min_complete {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// The SQ thread of a ring set up with `IORING_SETUP_SQPOLL`, which consumes
+/// the SQ ring without `io_uring_enter`. It sleeps after idle for
+/// `sq_thread_idle`, and sets `IORING_SQ_NEED_WAKEUP` to ask user space to
+/// wake it up.
+async fn sq_thread(ring: Weak<IoUring>) {
+    let mut last_active = get_time_duration();
+    loop {
+        let Some(r) = ring.upgrade() else {
+            break;
+        };
+        let Some(owner) = r.owner.upgrade() else {
+            break;
+        };
+        if r.closed.load(Ordering::Acquire) {
+            break;
+        }
+        if r.submit(&owner, u32::MAX) > 0 {
+            last_active = get_time_duration();
+        } else if get_time_duration() - last_active >= r.sq_thread_idle {
+            r.sq_ring
+                .atomic(SQ_FLAGS)
+                .fetch_or(IORING_SQ_NEED_WAKEUP, Ordering::AcqRel);
+            // SQEs may be added before user space sees the flag.
+            if r.sq_pending() == 0 {
+                drop(owner);
+                drop(r);
+                SqWakeupFuture { ring: ring.clone() }.await;
+            } else {
+                r.wake_sq_thread();
+            }
+            last_active = get_time_duration();
+            continue;
+        }
+        drop(owner);
+        drop(r);
+        yield_now().await;
+    }
+    log::info!("[io_uring] SQ thread exits");
+}
+
+/// Wait until `IORING_SQ_NEED_WAKEUP` is cleared by `io_uring_enter`, or the
+/// ring is closed.
+struct SqWakeupFuture {
+    ring: Weak<IoUring>,
+}
+
+impl Future for SqWakeupFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Some(ring) = self.ring.upgrade() else {
+            return Poll::Ready(());
+        };
+        *ring.sq_waker.lock() = Some(cx.waker().clone());
+        let need_wakeup =
+            ring.sq_ring.atomic(SQ_FLAGS).load(Ordering::Acquire) & IORING_SQ_NEED_WAKEUP != 0;
+        if !need_wakeup || ring.closed.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// File returned by `io_uring_setup`.
+pub struct IoUringFile {
+    meta: FileMeta,
+    ring: Arc<IoUring>,
+}
+
+impl IoUringFile {
+    pub fn new(ring: Arc<IoUring>) -> Arc<Self> {
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        *meta.flags.lock() = OpenFlags::O_RDWR;
+        Arc::new(Self { meta, ring })
+    }
+
+    pub fn ring(&self) -> &Arc<IoUring> {
+        &self.ring
+    }
+}
+
+impl Drop for IoUringFile {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+        self.ring.cancel_chains(ChainOwner::All);
+        if let Some(waker) = self.ring.sq_waker.lock().take() {
+            waker.wake();
+        }
+    }
+}
+
+#[async_trait]
+impl File for IoUringFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> SyscallResult {
+        Err(SysError::EINVAL)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SyscallResult {
+        Err(SysError::EINVAL)
+    }
+
+    /// The ring is readable when there are CQEs.
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if self.ring.cq_ready() == 0 {
+                self.ring.register_cq_waker(&waker);
+            }
+            // Check again in case a CQE is posted before the waker is
+            // registered.
+            if self.ring.cq_ready() > 0 {
+                res |= PollEvents::IN;
+            }
+        }
+        if events.contains(PollEvents::OUT) {
+            res |= PollEvents::OUT;
+        }
+        res
+    }
+}
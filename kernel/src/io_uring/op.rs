@@ -0,0 +1,303 @@
+//! Execution of SQEs.
+
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use arch::time::get_time_duration;
+use systype::{SysError, SysResult, SyscallResult};
+use time::timespec::TimeSpec;
+use timer::{Timer, TIMER_MANAGER};
+use vfs_core::{File, OpenFlags, PollEvents};
+
+use super::{IoUring, IoUringSqe, SqeFlags};
+use crate::{
+    mm::{UserReadPtr, UserWritePtr},
+    net::{socket::Socket, CLOEXEC, NONBLOCK},
+    syscall::IoVec,
+    task::Task,
+};
+
+// Opcodes of SQEs, defined in <linux/io_uring.h>.
+const IORING_OP_NOP: u8 = 0;
+const IORING_OP_READV: u8 = 1;
+const IORING_OP_WRITEV: u8 = 2;
+const IORING_OP_FSYNC: u8 = 3;
+const IORING_OP_POLL_ADD: u8 = 6;
+const IORING_OP_TIMEOUT: u8 = 11;
+const IORING_OP_ACCEPT: u8 = 13;
+const IORING_OP_CONNECT: u8 = 16;
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+const IORING_OP_SEND: u8 = 26;
+const IORING_OP_RECV: u8 = 27;
+
+/// Number of opcodes reported by `IORING_REGISTER_PROBE`.
+pub const IORING_OP_LAST: u8 = 28;
+
+/// Whether `opcode` is supported, reported by `IORING_REGISTER_PROBE`.
+pub fn is_supported(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        IORING_OP_NOP
+            | IORING_OP_READV
+            | IORING_OP_WRITEV
+            | IORING_OP_FSYNC
+            | IORING_OP_POLL_ADD
+            | IORING_OP_TIMEOUT
+            | IORING_OP_ACCEPT
+            | IORING_OP_CONNECT
+            | IORING_OP_READ
+            | IORING_OP_WRITE
+            | IORING_OP_SEND
+            | IORING_OP_RECV
+    )
+}
+
+/// The timeout in `addr` is absolute.
+const IORING_TIMEOUT_ABS: u32 = 1 << 0;
+
+/// Whether the result breaks a chain of `IOSQE_IO_LINK`. A short read or write
+/// also breaks the chain.
+pub fn is_failed(sqe: &IoUringSqe, ret: &SyscallResult) -> bool {
+    match ret {
+        Err(_) => true,
+        Ok(n) => match sqe.opcode {
+            IORING_OP_READ | IORING_OP_WRITE | IORING_OP_SEND | IORING_OP_RECV => {
+                *n < sqe.len as usize
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Execute an SQE in the address space of `task`, and return the result
+/// posted in the CQE.
+pub async fn execute(ring: &Arc<IoUring>, task: &Arc<Task>, sqe: &IoUringSqe) -> SyscallResult {
+    let flags = SqeFlags::from_bits(sqe.flags).ok_or(SysError::EINVAL)?;
+    if flags.intersects(SqeFlags::IO_DRAIN | SqeFlags::BUFFER_SELECT) {
+        return Err(SysError::EINVAL);
+    }
+    match sqe.opcode {
+        IORING_OP_NOP => Ok(0),
+        IORING_OP_READ => {
+            let file = get_file(ring, task, sqe)?;
+            let mut buf = UserWritePtr::<u8>::from(sqe.addr as usize)
+                .into_mut_slice(task, sqe.len as usize)?;
+            read_at(&file, sqe.off, &mut buf).await
+        }
+        IORING_OP_WRITE => {
+            let file = get_file(ring, task, sqe)?;
+            let buf =
+                UserReadPtr::<u8>::from(sqe.addr as usize).into_slice(task, sqe.len as usize)?;
+            write_at(&file, sqe.off, &buf).await
+        }
+        IORING_OP_READV => {
+            let file = get_file(ring, task, sqe)?;
+            let iovs =
+                UserReadPtr::<IoVec>::from(sqe.addr as usize).read_array(task, sqe.len as usize)?;
+            let mut total_len = 0;
+            for iov in iovs.iter().filter(|iov| iov.len != 0) {
+                let mut buf = UserWritePtr::<u8>::from(iov.base).into_mut_slice(task, iov.len)?;
+                let off = advance_offset(sqe.off, total_len);
+                let len = read_at(&file, off, &mut buf).await?;
+                total_len += len;
+                if len < iov.len {
+                    break;
+                }
+            }
+            Ok(total_len)
+        }
+        IORING_OP_WRITEV => {
+            let file = get_file(ring, task, sqe)?;
+            let iovs =
+                UserReadPtr::<IoVec>::from(sqe.addr as usize).read_array(task, sqe.len as usize)?;
+            let mut total_len = 0;
+            for iov in iovs.iter().filter(|iov| iov.len != 0) {
+                let buf = UserReadPtr::<u8>::from(iov.base).into_slice(task, iov.len)?;
+                let off = advance_offset(sqe.off, total_len);
+                let len = write_at(&file, off, &buf).await?;
+                total_len += len;
+                if len < iov.len {
+                    break;
+                }
+            }
+            Ok(total_len)
+        }
+        IORING_OP_FSYNC => {
+            let file = get_file(ring, task, sqe)?;
+            file.inode().sync()?;
+            Ok(0)
+        }
+        IORING_OP_POLL_ADD => {
+            let file = get_file(ring, task, sqe)?;
+            let events = PollEvents::from_bits_truncate(sqe.op_flags as i16);
+            let revents = PollAddFuture { file, events }.await;
+            Ok(revents.bits() as u16 as usize)
+        }
+        IORING_OP_TIMEOUT => {
+            if sqe.len != 1 || sqe.op_flags & !IORING_TIMEOUT_ABS != 0 {
+                return Err(SysError::EINVAL);
+            }
+            let timeout = UserReadPtr::<TimeSpec>::from(sqe.addr as usize).read(task)?;
+            if !timeout.is_valid() {
+                return Err(SysError::EINVAL);
+            }
+            let expire = if sqe.op_flags & IORING_TIMEOUT_ABS != 0 {
+                timeout.into()
+            } else {
+                get_time_duration() + Duration::from(timeout)
+            };
+            // A timeout with a count completes after `count` other CQEs are
+            // posted.
+            let target = (sqe.off != 0).then(|| ring.completions() + sqe.off as usize);
+            TimeoutFuture {
+                ring: ring.clone(),
+                expire,
+                target,
+                in_timer_manager: false,
+            }
+            .await
+        }
+        IORING_OP_ACCEPT => {
+            let socket = get_socket(ring, task, sqe)?;
+            let accept_flags = sqe.op_flags as i32;
+            if accept_flags & !(NONBLOCK | CLOEXEC) != 0 {
+                return Err(SysError::EINVAL);
+            }
+            let new_sk = socket.sk.accept().await?;
+            let mut flags = OpenFlags::empty();
+            if accept_flags & NONBLOCK != 0 {
+                new_sk.set_nonblocking();
+                flags |= OpenFlags::O_NONBLOCK;
+            }
+            if accept_flags & CLOEXEC != 0 {
+                flags |= OpenFlags::O_CLOEXEC;
+            }
+            let peer_addr = new_sk.peer_addr()?;
+            // `off` is `addr2`, which points to the length of the address.
+            task.write_sockaddr(sqe.addr as usize, sqe.off as usize, peer_addr)?;
+            let new_socket = Arc::new(Socket::from_another(&socket, new_sk));
+            task.with_mut_fd_table(|table| table.alloc(new_socket, flags))
+        }
+        IORING_OP_CONNECT => {
+            let socket = get_socket(ring, task, sqe)?;
+            // `off` is the length of the address.
+            let remote_addr = task.read_sockaddr(sqe.addr as usize, sqe.off as usize)?;
+            socket.sk.connect(remote_addr).await?;
+            Ok(0)
+        }
+        IORING_OP_SEND => {
+            let socket = get_socket(ring, task, sqe)?;
+            let buf =
+                UserReadPtr::<u8>::from(sqe.addr as usize).into_slice(task, sqe.len as usize)?;
+            socket.sk.sendto(&buf, None).await
+        }
+        IORING_OP_RECV => {
+            let socket = get_socket(ring, task, sqe)?;
+            let mut buf = UserWritePtr::<u8>::from(sqe.addr as usize)
+                .into_mut_slice(task, sqe.len as usize)?;
+            let (bytes, _) = socket.sk.recvfrom(&mut buf).await?;
+            Ok(bytes)
+        }
+        opcode => {
+            log::warn!("[io_uring] unsupported opcode {opcode}");
+            Err(SysError::EINVAL)
+        }
+    }
+}
+
+fn get_file(ring: &IoUring, task: &Arc<Task>, sqe: &IoUringSqe) -> SysResult<Arc<dyn File>> {
+    if sqe.fd < 0 {
+        return Err(SysError::EBADF);
+    }
+    if SqeFlags::from_bits_truncate(sqe.flags).contains(SqeFlags::FIXED_FILE) {
+        ring.fixed_file(sqe.fd as usize)
+    } else {
+        task.with_fd_table(|table| table.get_file(sqe.fd as usize))
+    }
+}
+
+fn get_socket(ring: &IoUring, task: &Arc<Task>, sqe: &IoUringSqe) -> SysResult<Arc<Socket>> {
+    get_file(ring, task, sqe)?
+        .downcast_arc::<Socket>()
+        .map_err(|_| SysError::ENOTSOCK)
+}
+
+/// Offset of the next iovec, where `u64::MAX` means the file position.
+fn advance_offset(off: u64, len: usize) -> u64 {
+    if off == u64::MAX {
+        off
+    } else {
+        off + len as u64
+    }
+}
+
+/// Read at `off`, or at the file position and advance it if `off` is -1.
+async fn read_at(file: &Arc<dyn File>, off: u64, buf: &mut [u8]) -> SyscallResult {
+    if off == u64::MAX {
+        file.read(buf).await
+    } else {
+        file.read_at(off as usize, buf).await
+    }
+}
+
+/// Write at `off`, or at the file position and advance it if `off` is -1.
+async fn write_at(file: &Arc<dyn File>, off: u64, buf: &[u8]) -> SyscallResult {
+    if off == u64::MAX {
+        file.write(buf).await
+    } else {
+        file.write_at(off as usize, buf).await
+    }
+}
+
+/// Wait until any of `events` is ready on `file`.
+struct PollAddFuture {
+    file: Arc<dyn File>,
+    events: PollEvents,
+}
+
+impl Future for PollAddFuture {
+    type Output = PollEvents;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = unsafe { Pin::new_unchecked(&mut self.file.poll(self.events)).poll(cx) };
+        match result {
+            Poll::Ready(revents) if !revents.is_empty() => Poll::Ready(revents),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// Wait until `expire`, or until the number of CQEs posted reaches `target`.
+struct TimeoutFuture {
+    ring: Arc<IoUring>,
+    expire: Duration,
+    target: Option<usize>,
+    in_timer_manager: bool,
+}
+
+impl Future for TimeoutFuture {
+    type Output = SyscallResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(target) = self.target {
+            self.ring.register_cq_waker(cx.waker());
+            if self.ring.completions() >= target {
+                return Poll::Ready(Ok(0));
+            }
+        }
+        if get_time_duration() >= self.expire {
+            return Poll::Ready(Err(SysError::ETIME));
+        }
+        if !self.in_timer_manager {
+            TIMER_MANAGER.add_timer(Timer::new_waker_timer(self.expire, cx.waker().clone()));
+            self.in_timer_manager = true;
+        }
+        Poll::Pending
+    }
+}
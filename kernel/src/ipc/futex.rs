@@ -1,11 +1,16 @@
-use alloc::vec::Vec;
-use core::{cmp::min, hash::Hash, ops::DerefMut, task::Waker};
+use alloc::{sync::Arc, vec::Vec};
+use core::{cmp::min, hash::Hash, ops::DerefMut, sync::atomic::Ordering, task::Waker};
 
 use hashbrown::HashMap;
 use memory::{PhysAddr, VirtAddr};
 use spin::Lazy;
 use sync::mutex::SpinNoIrqLock;
-use systype::{SysError, SyscallResult};
+use systype::{SysError, SysResult, SyscallResult};
+
+use crate::{
+    mm::{FutexAddr, UserReadPtr},
+    task::Task,
+};
 type Tid = usize;
 
 #[derive(Clone, Copy, Default)]
@@ -29,10 +34,25 @@ pub enum FutexHashKey {
     Private { mm: usize, vaddr: VirtAddr },
 }
 
+/// Bitset of waiters matched by any `FUTEX_WAKE_BITSET`, used by plain
+/// `FUTEX_WAIT` and `FUTEX_WAKE`.
+pub const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// Set in the futex word of a PI futex when there are waiters, so that the
+/// owner unlocks it through `FUTEX_UNLOCK_PI`.
+pub const FUTEX_WAITERS: u32 = 0x8000_0000;
+/// Set in the futex word of a PI futex when the owner died without unlocking
+/// it.
+pub const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+/// Tid of the owner in the futex word of a PI futex.
+pub const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
 #[derive(Debug)]
 pub struct FutexWaiter {
     pub tid: Tid,
     pub waker: Waker,
+    /// Only woken by `FUTEX_WAKE_BITSET` with a bitset that intersects it.
+    pub bitset: u32,
 }
 
 impl FutexWaiter {
@@ -42,51 +62,84 @@ impl FutexWaiter {
 }
 
 /// `futex`: 一个32位的值，又称为`futex word`，将其地址传递给futex()系统调用
-pub struct FutexManager(HashMap<FutexHashKey, Vec<FutexWaiter>>);
+pub struct FutexManager {
+    waiters: HashMap<FutexHashKey, Vec<FutexWaiter>>,
+    /// Owners of PI futexes known by the kernel, i.e. those acquired or
+    /// contended through `FUTEX_LOCK_PI`.
+    pi_owners: HashMap<FutexHashKey, Tid>,
+}
 
 impl FutexManager {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self {
+            waiters: HashMap::new(),
+            pi_owners: HashMap::new(),
+        }
     }
 
     pub fn add_waiter(&mut self, key: &FutexHashKey, waiter: FutexWaiter) {
         log::info!("[futex::add_waiter] {:?} in {:?} ", waiter, key);
-        if let Some(waiters) = self.0.get_mut(key) {
+        if let Some(waiters) = self.waiters.get_mut(key) {
             waiters.push(waiter);
         } else {
             let mut waiters = Vec::new();
             waiters.push(waiter);
-            self.0.insert(*key, waiters);
+            self.waiters.insert(*key, waiters);
         }
     }
 
     /// 用于移除任务，任务可能是过期了，也可能是被信号中断了
-    pub fn remove_waiter(&mut self, key: &FutexHashKey, tid: Tid) {
-        if let Some(waiters) = self.0.get_mut(key) {
+    ///
+    /// Returns false if the task is not waiting on `key`, i.e. it has been
+    /// woken or requeued.
+    pub fn remove_waiter(&mut self, key: &FutexHashKey, tid: Tid) -> bool {
+        if let Some(waiters) = self.waiters.get_mut(key) {
             for i in 0..waiters.len() {
                 if waiters[i].tid == tid {
                     waiters.swap_remove(i);
-                    break;
+                    return true;
                 }
             }
         }
+        false
+    }
+
+    pub fn has_waiters(&self, key: &FutexHashKey) -> bool {
+        self.waiters
+            .get(key)
+            .is_some_and(|waiters| !waiters.is_empty())
+    }
+
+    /// Take a waiter out of `key` without waking it.
+    pub fn pop_waiter(&mut self, key: &FutexHashKey) -> Option<FutexWaiter> {
+        self.waiters.get_mut(key).and_then(|waiters| waiters.pop())
     }
 
     pub fn wake(&mut self, key: &FutexHashKey, n: u32) -> SyscallResult {
-        if let Some(waiters) = self.0.get_mut(key) {
-            let n = min(n as usize, waiters.len());
-            for _ in 0..n {
-                let waiter = waiters.pop().unwrap();
-                log::info!("[futex_wake] {:?} has been woken", waiter);
-                waiter.wake();
+        self.wake_bitset(key, n, FUTEX_BITSET_MATCH_ANY)
+    }
+
+    /// Wake at most `n` waiters on `key` whose bitset intersects `bitset`.
+    pub fn wake_bitset(&mut self, key: &FutexHashKey, n: u32, bitset: u32) -> SyscallResult {
+        if let Some(waiters) = self.waiters.get_mut(key) {
+            let mut n_wake = 0;
+            let mut i = waiters.len();
+            while i > 0 && n_wake < n as usize {
+                i -= 1;
+                if waiters[i].bitset & bitset != 0 {
+                    let waiter = waiters.remove(i);
+                    log::info!("[futex_wake] {:?} has been woken", waiter);
+                    waiter.wake();
+                    n_wake += 1;
+                }
             }
             log::info!(
                 "[futex_wake] wake {} waiters in key {:?}, expect to wake {} waiters",
-                n,
+                n_wake,
                 key,
                 n,
             );
-            Ok(n)
+            Ok(n_wake)
         } else {
             log::debug!("can not find key {key:?}");
             Err(SysError::EINVAL)
@@ -99,12 +152,12 @@ impl FutexManager {
         new: FutexHashKey,
         n_req: usize,
     ) -> SyscallResult {
-        let mut old_waiters = self.0.remove(&old).ok_or_else(|| {
+        let mut old_waiters = self.waiters.remove(&old).ok_or_else(|| {
             log::info!("[futex] no waiters in key {:?}", old);
             SysError::EINVAL
         })?;
         let n = min(n_req as usize, old_waiters.len());
-        if let Some(new_waiters) = self.0.get_mut(&new) {
+        if let Some(new_waiters) = self.waiters.get_mut(&new) {
             for _ in 0..n {
                 new_waiters.push(old_waiters.pop().unwrap());
             }
@@ -113,15 +166,210 @@ impl FutexManager {
             for _ in 0..n {
                 new_waiters.push(old_waiters.pop().unwrap());
             }
-            self.0.insert(new, new_waiters);
+            self.waiters.insert(new, new_waiters);
         }
 
         if !old_waiters.is_empty() {
-            self.0.insert(old, old_waiters);
+            self.waiters.insert(old, old_waiters);
         }
 
         Ok(n)
     }
+
+    pub fn set_pi_owner(&mut self, key: FutexHashKey, tid: Tid) {
+        self.pi_owners.insert(key, tid);
+    }
+
+    pub fn clear_pi_owner(&mut self, key: &FutexHashKey) {
+        self.pi_owners.remove(key);
+    }
+
+    /// Called when the task `tid` exits. For every PI futex still owned by it,
+    /// wake a waiter which will take over the futex with `FUTEX_OWNER_DIED`
+    /// set.
+    pub fn exit_pi_owner(&mut self, tid: Tid) {
+        let keys: Vec<FutexHashKey> = self
+            .pi_owners
+            .iter()
+            .filter(|(_, &owner)| owner == tid)
+            .map(|(&key, _)| key)
+            .collect();
+        for key in keys {
+            self.pi_owners.remove(&key);
+            if let Some(waiter) = self.pop_waiter(&key) {
+                waiter.wake();
+            }
+        }
+    }
+}
+
+/// Max number of entries walked in a robust futex list, which protects the
+/// kernel from a circular or corrupted list.
+const ROBUST_LIST_LIMIT: usize = 2048;
+
+/// Walk the robust futex list of the exiting `task`, mark the locks still held
+/// by it with `FUTEX_OWNER_DIED` and wake a waiter of each, so that other
+/// threads will not wait for a dead owner forever.
+pub fn exit_robust_list(task: &Arc<Task>) {
+    let head_addr = task.robust_list();
+    if head_addr == 0 {
+        return;
+    }
+    let Ok(head) = UserReadPtr::<RobustListHead>::from(head_addr).read(task) else {
+        return;
+    };
+    let (mut entry, mut pi) = fetch_robust_entry(head.list);
+    let (pending, pending_pi) = fetch_robust_entry(head.list_op_pending);
+    let mut limit = ROBUST_LIST_LIMIT;
+    while entry != head_addr {
+        // Fetch the next entry before the lock is released, since a waiter
+        // may free the memory of the lock once woken.
+        let next = UserReadPtr::<usize>::from(entry).read(task);
+        // The lock being operated on is handled below.
+        if entry != pending {
+            handle_futex_death(task, entry.wrapping_add(head.futex_offset), pi, false);
+        }
+        let Ok(next) = next else {
+            return;
+        };
+        (entry, pi) = fetch_robust_entry(next);
+        limit -= 1;
+        if limit == 0 {
+            log::warn!("[exit_robust_list] list of task {} is too long", task.tid());
+            break;
+        }
+    }
+    if pending != 0 {
+        handle_futex_death(
+            task,
+            pending.wrapping_add(head.futex_offset),
+            pending_pi,
+            true,
+        );
+    }
+}
+
+/// Bit 0 of a robust list entry is set if the lock is a PI futex.
+fn fetch_robust_entry(uentry: usize) -> (usize, bool) {
+    (uentry & !1, uentry & 1 != 0)
+}
+
+fn handle_futex_death(task: &Arc<Task>, uaddr: usize, pi: bool, pending_op: bool) {
+    let futex = FutexAddr::from(uaddr);
+    if uaddr % 4 != 0 || futex.check_write(task).is_err() {
+        return;
+    }
+    let word = futex.as_atomic();
+    let mut cur = word.load(Ordering::Acquire);
+    loop {
+        // The task died after releasing the lock but before removing it from
+        // `list_op_pending`, a waiter may have missed the wake up.
+        if pending_op && !pi && cur == 0 {
+            wake_robust_futex(task, uaddr);
+            return;
+        }
+        if (cur & FUTEX_TID_MASK) as Tid != task.tid() {
+            return;
+        }
+        let new = (cur & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
+        match word.compare_exchange(cur, new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => break,
+            Err(actual) => cur = actual,
+        }
+    }
+    // Waiters of a PI futex are woken by `FutexManager::exit_pi_owner`.
+    if !pi {
+        wake_robust_futex(task, uaddr);
+    }
+}
+
+/// The lock may be either process-private or shared.
+fn wake_robust_futex(task: &Arc<Task>, uaddr: usize) {
+    let mut manager = futex_manager();
+    let key = FutexHashKey::Shared {
+        paddr: VirtAddr::from(uaddr).to_paddr(),
+    };
+    let _ = manager.wake(&key, 1);
+    let key = FutexHashKey::Private {
+        mm: task.raw_mm_pointer(),
+        vaddr: uaddr.into(),
+    };
+    let _ = manager.wake(&key, 1);
+}
+
+/// Operation encoded in `val3` of `FUTEX_WAKE_OP`, defined in
+/// <linux/futex.h>.
+#[derive(Debug, Clone, Copy)]
+pub struct FutexWakeOp {
+    op: u32,
+    oparg: u32,
+    cmp: u32,
+    cmparg: i32,
+}
+
+impl FutexWakeOp {
+    const OP_SET: u32 = 0;
+    const OP_ADD: u32 = 1;
+    const OP_OR: u32 = 2;
+    const OP_ANDN: u32 = 3;
+    const OP_XOR: u32 = 4;
+    /// Use `1 << oparg` as the operand.
+    const OP_OPARG_SHIFT: u32 = 8;
+
+    const CMP_EQ: u32 = 0;
+    const CMP_NE: u32 = 1;
+    const CMP_LT: u32 = 2;
+    const CMP_LE: u32 = 3;
+    const CMP_GT: u32 = 4;
+    const CMP_GE: u32 = 5;
+
+    pub fn decode(val3: u32) -> SysResult<Self> {
+        let mut op = val3 >> 28;
+        let cmp = (val3 >> 24) & 0xf;
+        // Both arguments are signed 12-bit integers.
+        let mut oparg = (((val3 >> 12) & 0xfff) as i32) << 20 >> 20;
+        let cmparg = ((val3 & 0xfff) as i32) << 20 >> 20;
+        if op & Self::OP_OPARG_SHIFT != 0 {
+            op &= !Self::OP_OPARG_SHIFT;
+            oparg = 1 << (oparg & 31);
+        }
+        if op > Self::OP_XOR || cmp > Self::CMP_GE {
+            return Err(SysError::ENOSYS);
+        }
+        Ok(Self {
+            op,
+            oparg: oparg as u32,
+            cmp,
+            cmparg,
+        })
+    }
+
+    /// New value of the futex word at `uaddr2`.
+    pub fn apply(&self, old: u32) -> u32 {
+        match self.op {
+            Self::OP_SET => self.oparg,
+            Self::OP_ADD => old.wrapping_add(self.oparg),
+            Self::OP_OR => old | self.oparg,
+            Self::OP_ANDN => old & !self.oparg,
+            Self::OP_XOR => old ^ self.oparg,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether waiters on `uaddr2` should be woken, given the old value of
+    /// the futex word.
+    pub fn test(&self, old: u32) -> bool {
+        let old = old as i32;
+        match self.cmp {
+            Self::CMP_EQ => old == self.cmparg,
+            Self::CMP_NE => old != self.cmparg,
+            Self::CMP_LT => old < self.cmparg,
+            Self::CMP_LE => old <= self.cmparg,
+            Self::CMP_GT => old > self.cmparg,
+            Self::CMP_GE => old >= self.cmparg,
+            _ => unreachable!(),
+        }
+    }
 }
 
 bitflags! {
@@ -161,8 +409,13 @@ bitflags! {
         const WaitBitset = 9;
         const WakeBitset = 10;
         const WaitRequeuePi = 11;
+        const CmpRequeuePi = 12;
         /// Tells the kernel that the futex is process-private and not shared
         /// with another process.
         const Private = 128;
+        /// Measure the timeout of `FUTEX_WAIT_BITSET` and
+        /// `FUTEX_WAIT_REQUEUE_PI` against `CLOCK_REALTIME` instead of
+        /// `CLOCK_MONOTONIC`.
+        const ClockRealtime = 256;
     }
 }
@@ -1,5 +1,36 @@
 pub mod futex;
+pub mod msg;
+pub mod sem;
 pub mod shm;
+
+/// Key for creating a new IPC object that can not be found by other processes.
+pub const IPC_PRIVATE: i32 = 0;
+
+// Control commands used by msgctl, semctl and shmctl.
+/// Remove identifier.
+pub const IPC_RMID: i32 = 0;
+/// Set `ipc_perm` options.
+pub const IPC_SET: i32 = 1;
+/// Get `ipc_perm` options.
+pub const IPC_STAT: i32 = 2;
+/// Flag set by glibc and musl on architectures with the old IPC layout, it is
+/// ignored since we only support the 64-bit layout.
+pub const IPC_64: i32 = 0x100;
+
+bitflags! {
+    // Defined in <bits/ipc.h>
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct IpcFlags: i32 {
+        /// Create key if key does not exist.
+        const IPC_CREAT = 0o1000;
+        /// Fail if key exists.
+        const IPC_EXCL = 0o2000;
+        /// Return error on wait.
+        const IPC_NOWAIT = 0o4000;
+    }
+}
+
+/// Defined in <asm-generic/ipcbuf.h>
 #[repr(C)]
 #[derive(Default, Clone, Copy, Debug)]
 pub struct IpcPerm {
@@ -8,6 +39,27 @@ pub struct IpcPerm {
     gid: u32,
     cuid: u32,
     cgid: u32,
-    mode: u16,
+    mode: u32,
     seq: u16,
+    __pad2: u16,
+    __unused1: usize,
+    __unused2: usize,
+}
+
+impl IpcPerm {
+    pub fn new(key: i32, mode: u32) -> Self {
+        Self {
+            key,
+            mode: mode & 0o777,
+            ..Default::default()
+        }
+    }
+
+    /// Update the permissions by `IPC_SET`, only the owner and the mode can be
+    /// changed.
+    pub fn set(&mut self, perm: &IpcPerm) {
+        self.uid = perm.uid;
+        self.gid = perm.gid;
+        self.mode = (self.mode & !0o777) | (perm.mode & 0o777);
+    }
 }
@@ -0,0 +1,337 @@
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use arch::time::get_time_sec;
+use hashbrown::HashMap;
+use recycle_allocator::RecycleAllocator;
+use spin::Lazy;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+
+use super::IpcPerm;
+
+/// Max size of a message in bytes.
+pub const MSGMAX: usize = 8192;
+/// Default max size of a message queue in bytes.
+pub const MSGMNB: usize = 16384;
+
+bitflags! {
+    // Defined in <bits/msq.h>
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MsgFlags: i32 {
+        /// Return error if no message is available or the queue is full.
+        const IPC_NOWAIT = 0o4000;
+        /// Truncate the message text if it is longer than `msgsz` bytes.
+        const MSG_NOERROR = 0o10000;
+        /// Receive the first message whose type is not `msgtyp`.
+        const MSG_EXCEPT = 0o20000;
+        /// Copy the message without removing it from the queue.
+        const MSG_COPY = 0o40000;
+    }
+}
+
+/// Defined in <asm-generic/msgbuf.h>
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MsqIdDs {
+    /// Ownership and permissions
+    pub msg_perm: IpcPerm,
+    /// Time of last msgsnd(2)
+    pub msg_stime: usize,
+    /// Time of last msgrcv(2)
+    pub msg_rtime: usize,
+    /// Time of creation or last modification by msgctl()
+    pub msg_ctime: usize,
+    /// Number of bytes in queue
+    pub msg_cbytes: usize,
+    /// Number of messages in queue
+    pub msg_qnum: usize,
+    /// Maximum number of bytes allowed in queue
+    pub msg_qbytes: usize,
+    /// PID of last msgsnd(2)
+    pub msg_lspid: i32,
+    /// PID of last msgrcv(2)
+    pub msg_lrpid: i32,
+    __unused4: usize,
+    __unused5: usize,
+}
+
+impl MsqIdDs {
+    pub fn new(key: i32, mode: u32) -> Self {
+        Self {
+            msg_perm: IpcPerm::new(key, mode),
+            msg_stime: 0,
+            msg_rtime: 0,
+            msg_ctime: get_time_sec(),
+            msg_cbytes: 0,
+            msg_qnum: 0,
+            msg_qbytes: MSGMNB,
+            msg_lspid: 0,
+            msg_lrpid: 0,
+            __unused4: 0,
+            __unused5: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub mtype: isize,
+    pub mtext: Vec<u8>,
+}
+
+pub struct MsgQueue {
+    inner: SpinNoIrqLock<MsgQueueInner>,
+}
+
+struct MsgQueueInner {
+    msqid_ds: MsqIdDs,
+    msgs: VecDeque<Message>,
+    /// Set by `IPC_RMID`, all waiting tasks will fail with `EIDRM`.
+    removed: bool,
+    send_wakers: VecDeque<Waker>,
+    recv_wakers: VecDeque<Waker>,
+}
+
+impl MsgQueueInner {
+    /// Find the message to be received according to `msgtyp`:
+    ///
+    /// + If msgtyp is 0, then the first message in the queue is read.
+    /// + If msgtyp is greater than 0, then the first message in the queue of
+    ///   type msgtyp is read, unless MSG_EXCEPT was specified in msgflg, in
+    ///   which case the first message in the queue of type not equal to msgtyp
+    ///   will be read.
+    /// + If msgtyp is less than 0, then the first message in the queue with the
+    ///   lowest type less than or equal to the absolute value of msgtyp will be
+    ///   read.
+    fn find(&self, msgtyp: isize, except: bool) -> Option<usize> {
+        if msgtyp == 0 {
+            (!self.msgs.is_empty()).then_some(0)
+        } else if msgtyp > 0 {
+            self.msgs
+                .iter()
+                .position(|msg| (msg.mtype == msgtyp) != except)
+        } else {
+            self.msgs
+                .iter()
+                .enumerate()
+                // NOTE: `-msgtyp` overflows when `msgtyp` is `isize::MIN`.
+                .filter(|(_, msg)| msg.mtype.unsigned_abs() <= msgtyp.unsigned_abs())
+                .min_by_key(|(_, msg)| msg.mtype)
+                .map(|(i, _)| i)
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in self.send_wakers.drain(..).chain(self.recv_wakers.drain(..)) {
+            waker.wake();
+        }
+    }
+}
+
+impl MsgQueue {
+    pub fn new(key: i32, mode: u32) -> Arc<Self> {
+        Arc::new(Self {
+            inner: SpinNoIrqLock::new(MsgQueueInner {
+                msqid_ds: MsqIdDs::new(key, mode),
+                msgs: VecDeque::new(),
+                removed: false,
+                send_wakers: VecDeque::new(),
+                recv_wakers: VecDeque::new(),
+            }),
+        })
+    }
+
+    pub fn msqid_ds(&self) -> MsqIdDs {
+        self.inner.lock().msqid_ds
+    }
+
+    /// Change the permissions and the max size of the queue by `IPC_SET`.
+    pub fn set(&self, msqid_ds: &MsqIdDs) -> SysResult<()> {
+        let mut inner = self.inner.lock();
+        if msqid_ds.msg_qbytes > MSGMNB {
+            return Err(SysError::EPERM);
+        }
+        inner.msqid_ds.msg_perm.set(&msqid_ds.msg_perm);
+        inner.msqid_ds.msg_qbytes = msqid_ds.msg_qbytes;
+        inner.msqid_ds.msg_ctime = get_time_sec();
+        // Senders may be able to send with a larger limit.
+        inner.wake_all();
+        Ok(())
+    }
+
+    /// Mark the queue as removed and wake up all waiting tasks.
+    pub fn remove(&self) {
+        let mut inner = self.inner.lock();
+        inner.removed = true;
+        inner.msgs.clear();
+        inner.wake_all();
+    }
+}
+
+/// Send a message to the queue, waiting until there is enough space in the
+/// queue unless `IPC_NOWAIT` is set.
+pub struct MsgSendFuture {
+    queue: Arc<MsgQueue>,
+    msg: Option<Message>,
+    flags: MsgFlags,
+    pid: usize,
+}
+
+impl MsgSendFuture {
+    pub fn new(queue: Arc<MsgQueue>, msg: Message, flags: MsgFlags, pid: usize) -> Self {
+        Self {
+            queue,
+            msg: Some(msg),
+            flags,
+            pid,
+        }
+    }
+}
+
+impl Future for MsgSendFuture {
+    type Output = SysResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut inner = this.queue.inner.lock();
+        if inner.removed {
+            return Poll::Ready(Err(SysError::EIDRM));
+        }
+        let len = this.msg.as_ref().unwrap().mtext.len();
+        let ds = &inner.msqid_ds;
+        // Like Linux, the number of messages is also limited by `msg_qbytes` to
+        // avoid the queue filled with a large number of empty messages.
+        if ds.msg_cbytes + len > ds.msg_qbytes || ds.msg_qnum + 1 > ds.msg_qbytes {
+            if this.flags.contains(MsgFlags::IPC_NOWAIT) {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.send_wakers.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        inner.msgs.push_back(this.msg.take().unwrap());
+        let ds = &mut inner.msqid_ds;
+        ds.msg_cbytes += len;
+        ds.msg_qnum += 1;
+        ds.msg_lspid = this.pid as i32;
+        ds.msg_stime = get_time_sec();
+        for waker in inner.recv_wakers.drain(..) {
+            waker.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receive a message of `msgtyp` from the queue, waiting until there is such
+/// a message unless `IPC_NOWAIT` is set.
+pub struct MsgRecvFuture {
+    queue: Arc<MsgQueue>,
+    msgtyp: isize,
+    /// Max size of the message text.
+    size: usize,
+    flags: MsgFlags,
+    pid: usize,
+}
+
+impl MsgRecvFuture {
+    pub fn new(
+        queue: Arc<MsgQueue>,
+        msgtyp: isize,
+        size: usize,
+        flags: MsgFlags,
+        pid: usize,
+    ) -> Self {
+        Self {
+            queue,
+            msgtyp,
+            size,
+            flags,
+            pid,
+        }
+    }
+}
+
+impl Future for MsgRecvFuture {
+    type Output = SysResult<Message>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.queue.inner.lock();
+        if inner.removed {
+            return Poll::Ready(Err(SysError::EIDRM));
+        }
+        let Some(i) = inner.find(self.msgtyp, self.flags.contains(MsgFlags::MSG_EXCEPT)) else {
+            if self.flags.contains(MsgFlags::IPC_NOWAIT) {
+                return Poll::Ready(Err(SysError::ENOMSG));
+            }
+            inner.recv_wakers.push_back(cx.waker().clone());
+            return Poll::Pending;
+        };
+        if inner.msgs[i].mtext.len() > self.size && !self.flags.contains(MsgFlags::MSG_NOERROR) {
+            return Poll::Ready(Err(SysError::E2BIG));
+        }
+        let mut msg = inner.msgs.remove(i).unwrap();
+        let ds = &mut inner.msqid_ds;
+        ds.msg_cbytes -= msg.mtext.len();
+        ds.msg_qnum -= 1;
+        ds.msg_lrpid = self.pid as i32;
+        ds.msg_rtime = get_time_sec();
+        for waker in inner.send_wakers.drain(..) {
+            waker.wake();
+        }
+        msg.mtext.truncate(self.size);
+        Poll::Ready(Ok(msg))
+    }
+}
+
+pub struct MsgQueueManager {
+    /// Message queues indexed by msqid.
+    queues: HashMap<usize, Arc<MsgQueue>>,
+    /// Key to msqid, queues created with `IPC_PRIVATE` are not here.
+    keys: HashMap<i32, usize>,
+    id_allocator: RecycleAllocator,
+}
+
+impl MsgQueueManager {
+    pub fn new() -> Self {
+        Self {
+            queues: HashMap::new(),
+            keys: HashMap::new(),
+            id_allocator: RecycleAllocator::new(1),
+        }
+    }
+
+    pub fn get(&self, msqid: usize) -> Option<Arc<MsgQueue>> {
+        self.queues.get(&msqid).cloned()
+    }
+
+    pub fn get_by_key(&self, key: i32) -> Option<usize> {
+        self.keys.get(&key).copied()
+    }
+
+    /// Create a new message queue and return its msqid.
+    pub fn create(&mut self, key: i32, mode: u32) -> usize {
+        let msqid = self.id_allocator.alloc();
+        self.queues.insert(msqid, MsgQueue::new(key, mode));
+        if key != super::IPC_PRIVATE {
+            self.keys.insert(key, msqid);
+        }
+        msqid
+    }
+
+    /// Remove the message queue, waking up all waiting readers and writer
+    /// processes.
+    pub fn remove(&mut self, msqid: usize) -> SysResult<()> {
+        let queue = self.queues.remove(&msqid).ok_or(SysError::EINVAL)?;
+        self.keys.retain(|_, id| *id != msqid);
+        self.id_allocator.dealloc(msqid);
+        queue.remove();
+        Ok(())
+    }
+}
+
+pub static MSG_QUEUE_MANAGER: Lazy<SpinNoIrqLock<MsgQueueManager>> =
+    Lazy::new(|| SpinNoIrqLock::new(MsgQueueManager::new()));
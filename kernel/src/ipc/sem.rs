@@ -0,0 +1,338 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::task::Waker;
+
+use arch::time::get_time_sec;
+use hashbrown::HashMap;
+use recycle_allocator::RecycleAllocator;
+use spin::Lazy;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+
+use super::IpcPerm;
+
+/// Max number of semaphores per set.
+pub const SEMMSL: usize = 32000;
+/// Max number of operations per semop call.
+pub const SEMOPM: usize = 500;
+/// Max value of a semaphore.
+pub const SEMVMX: i32 = 32767;
+
+bitflags! {
+    // Defined in <sys/sem.h>
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SemFlags: i16 {
+        /// Return error instead of blocking.
+        const IPC_NOWAIT = 0o4000;
+        /// Undo the operation on exit.
+        const SEM_UNDO = 0x1000;
+    }
+}
+
+/// `struct sembuf`, an operation on a single semaphore.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SemBuf {
+    /// Semaphore number
+    pub sem_num: u16,
+    /// Semaphore operation
+    pub sem_op: i16,
+    /// Operation flags
+    pub sem_flg: i16,
+}
+
+impl SemBuf {
+    pub fn flags(&self) -> SemFlags {
+        SemFlags::from_bits_truncate(self.sem_flg)
+    }
+}
+
+/// Defined in <asm-generic/sembuf.h>
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SemIdDs {
+    /// Ownership and permissions
+    pub sem_perm: IpcPerm,
+    /// Last semop time
+    pub sem_otime: usize,
+    /// Creation time/time of last modification via semctl()
+    pub sem_ctime: usize,
+    /// No. of semaphores in set
+    pub sem_nsems: usize,
+    __unused3: usize,
+    __unused4: usize,
+}
+
+impl SemIdDs {
+    pub fn new(key: i32, mode: u32, nsems: usize) -> Self {
+        Self {
+            sem_perm: IpcPerm::new(key, mode),
+            sem_otime: 0,
+            sem_ctime: get_time_sec(),
+            sem_nsems: nsems,
+            __unused3: 0,
+            __unused4: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Semaphore {
+    /// Semaphore value
+    semval: i32,
+    /// PID of process that last modified the semaphore value
+    sempid: i32,
+}
+
+struct SemWaiter {
+    tid: usize,
+    waker: Waker,
+    sem_num: u16,
+    /// Waiting for the semaphore value to become zero, otherwise waiting for
+    /// the value to increase.
+    zero: bool,
+}
+
+pub struct SemSet {
+    inner: SpinNoIrqLock<SemSetInner>,
+}
+
+struct SemSetInner {
+    semid_ds: SemIdDs,
+    sems: Vec<Semaphore>,
+    waiters: Vec<SemWaiter>,
+    /// Adjustments of `SEM_UNDO` operations indexed by pid, which will be
+    /// applied when the process exits.
+    undos: BTreeMap<usize, Vec<i32>>,
+    /// Set by `IPC_RMID`, all waiting tasks will fail with `EIDRM`.
+    removed: bool,
+}
+
+impl SemSetInner {
+    fn wake_all(&mut self) {
+        for waiter in self.waiters.drain(..) {
+            waiter.waker.wake();
+        }
+    }
+}
+
+impl SemSet {
+    pub fn new(key: i32, mode: u32, nsems: usize) -> Arc<Self> {
+        Arc::new(Self {
+            inner: SpinNoIrqLock::new(SemSetInner {
+                semid_ds: SemIdDs::new(key, mode, nsems),
+                sems: vec![Semaphore::default(); nsems],
+                waiters: Vec::new(),
+                undos: BTreeMap::new(),
+                removed: false,
+            }),
+        })
+    }
+
+    pub fn nsems(&self) -> usize {
+        self.inner.lock().sems.len()
+    }
+
+    pub fn semid_ds(&self) -> SemIdDs {
+        self.inner.lock().semid_ds
+    }
+
+    pub fn set(&self, semid_ds: &SemIdDs) {
+        let mut inner = self.inner.lock();
+        inner.semid_ds.sem_perm.set(&semid_ds.sem_perm);
+        inner.semid_ds.sem_ctime = get_time_sec();
+    }
+
+    /// Perform all operations in `sops` atomically, that is, the operations
+    /// are performed only if all of them can be performed.
+    ///
+    /// Return `Ok(true)` if operations are performed, `Ok(false)` if the
+    /// caller should wait, the waker has been registered and will be woken
+    /// when the set changes.
+    pub fn semop(&self, sops: &[SemBuf], pid: usize, tid: usize, waker: &Waker) -> SysResult<bool> {
+        let mut inner = self.inner.lock();
+        if inner.removed {
+            return Err(SysError::EIDRM);
+        }
+        let nsems = inner.sems.len();
+        if sops.iter().any(|sop| sop.sem_num as usize >= nsems) {
+            return Err(SysError::EFBIG);
+        }
+
+        let mut vals: Vec<i32> = inner.sems.iter().map(|sem| sem.semval).collect();
+        for sop in sops {
+            let val = &mut vals[sop.sem_num as usize];
+            let op = sop.sem_op as i32;
+            let block = if op > 0 {
+                if *val + op > SEMVMX {
+                    return Err(SysError::ERANGE);
+                }
+                *val += op;
+                false
+            } else if op == 0 {
+                *val != 0
+            } else if *val >= -op {
+                *val += op;
+                false
+            } else {
+                true
+            };
+            if block {
+                if sop.flags().contains(SemFlags::IPC_NOWAIT) {
+                    return Err(SysError::EAGAIN);
+                }
+                inner.waiters.push(SemWaiter {
+                    tid,
+                    waker: waker.clone(),
+                    sem_num: sop.sem_num,
+                    zero: op == 0,
+                });
+                return Ok(false);
+            }
+        }
+
+        for sop in sops {
+            let sem = &mut inner.sems[sop.sem_num as usize];
+            sem.semval = vals[sop.sem_num as usize];
+            sem.sempid = pid as i32;
+            if sop.sem_op != 0 && sop.flags().contains(SemFlags::SEM_UNDO) {
+                let undo = inner.undos.entry(pid).or_insert_with(|| vec![0; nsems]);
+                undo[sop.sem_num as usize] -= sop.sem_op as i32;
+            }
+        }
+        inner.semid_ds.sem_otime = get_time_sec();
+        inner.wake_all();
+        Ok(true)
+    }
+
+    pub fn remove_waiter(&self, tid: usize) {
+        self.inner.lock().waiters.retain(|waiter| waiter.tid != tid);
+    }
+
+    pub fn getval(&self, sem_num: usize) -> i32 {
+        self.inner.lock().sems[sem_num].semval
+    }
+
+    pub fn getpid(&self, sem_num: usize) -> i32 {
+        self.inner.lock().sems[sem_num].sempid
+    }
+
+    pub fn getall(&self) -> Vec<u16> {
+        self.inner
+            .lock()
+            .sems
+            .iter()
+            .map(|sem| sem.semval as u16)
+            .collect()
+    }
+
+    /// Return the number of tasks waiting for the value of the semaphore to
+    /// increase (`GETNCNT`) or to become zero (`GETZCNT`).
+    pub fn waiting_count(&self, sem_num: usize, zero: bool) -> usize {
+        self.inner
+            .lock()
+            .waiters
+            .iter()
+            .filter(|waiter| waiter.sem_num as usize == sem_num && waiter.zero == zero)
+            .count()
+    }
+
+    /// Set the value of semaphores from `first`, undo entries of them are
+    /// cleared in all processes.
+    pub fn setvals(&self, first: usize, vals: &[i32], pid: usize) -> SysResult<()> {
+        if vals.iter().any(|&val| !(0..=SEMVMX).contains(&val)) {
+            return Err(SysError::ERANGE);
+        }
+        let mut inner = self.inner.lock();
+        for (i, &val) in vals.iter().enumerate() {
+            inner.sems[first + i] = Semaphore {
+                semval: val,
+                sempid: pid as i32,
+            };
+        }
+        for undo in inner.undos.values_mut() {
+            undo[first..first + vals.len()].fill(0);
+        }
+        inner.semid_ds.sem_ctime = get_time_sec();
+        inner.wake_all();
+        Ok(())
+    }
+
+    /// Apply the `SEM_UNDO` adjustments of the exiting process.
+    pub fn exit(&self, pid: usize) {
+        let mut inner = self.inner.lock();
+        let Some(undo) = inner.undos.remove(&pid) else {
+            return;
+        };
+        for (sem, adj) in inner.sems.iter_mut().zip(undo) {
+            if adj != 0 {
+                // Like Linux, the value is clamped instead of blocking the
+                // exiting process.
+                sem.semval = (sem.semval + adj).clamp(0, SEMVMX);
+                sem.sempid = pid as i32;
+            }
+        }
+        inner.wake_all();
+    }
+
+    /// Mark the set as removed and wake up all waiting tasks.
+    pub fn remove(&self) {
+        let mut inner = self.inner.lock();
+        inner.removed = true;
+        inner.wake_all();
+    }
+}
+
+pub struct SemSetManager {
+    /// Semaphore sets indexed by semid.
+    sets: HashMap<usize, Arc<SemSet>>,
+    /// Key to semid, sets created with `IPC_PRIVATE` are not here.
+    keys: HashMap<i32, usize>,
+    id_allocator: RecycleAllocator,
+}
+
+impl SemSetManager {
+    pub fn new() -> Self {
+        Self {
+            sets: HashMap::new(),
+            keys: HashMap::new(),
+            id_allocator: RecycleAllocator::new(1),
+        }
+    }
+
+    pub fn get(&self, semid: usize) -> Option<Arc<SemSet>> {
+        self.sets.get(&semid).cloned()
+    }
+
+    pub fn get_by_key(&self, key: i32) -> Option<usize> {
+        self.keys.get(&key).copied()
+    }
+
+    /// Create a new semaphore set and return its semid.
+    pub fn create(&mut self, key: i32, mode: u32, nsems: usize) -> usize {
+        let semid = self.id_allocator.alloc();
+        self.sets.insert(semid, SemSet::new(key, mode, nsems));
+        if key != super::IPC_PRIVATE {
+            self.keys.insert(key, semid);
+        }
+        semid
+    }
+
+    /// Remove the semaphore set, awakening all processes blocked in semop.
+    pub fn remove(&mut self, semid: usize) -> SysResult<()> {
+        let set = self.sets.remove(&semid).ok_or(SysError::EINVAL)?;
+        self.keys.retain(|_, id| *id != semid);
+        self.id_allocator.dealloc(semid);
+        set.remove();
+        Ok(())
+    }
+
+    /// Apply the `SEM_UNDO` adjustments of the exiting process to all sets.
+    pub fn exit(&self, pid: usize) {
+        for set in self.sets.values() {
+            set.exit(pid);
+        }
+    }
+}
+
+pub static SEM_SET_MANAGER: Lazy<SpinNoIrqLock<SemSetManager>> =
+    Lazy::new(|| SpinNoIrqLock::new(SemSetManager::new()));
@@ -12,8 +12,10 @@
 #![feature(new_zeroed_alloc)]
 #![allow(clippy::mut_from_ref)]
 
+mod aio;
 mod boot;
 mod impls;
+mod io_uring;
 mod ipc;
 mod mm;
 mod net;
@@ -71,6 +73,9 @@ fn rust_main(hart_id: usize, dtb_addr: usize) {
             task::spawn_init_proc();
         });
 
+        task::spawn_kernel_task(vfs::writeback::writeback_task());
+        task::spawn_kernel_task(vfs::reclaim::reclaim_task());
+
         // utils::spawn_timer_tasks_ms(
         //     || {
         //         poll_interfaces();
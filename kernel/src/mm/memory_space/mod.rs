@@ -20,7 +20,10 @@ use config::{
     },
     process::USER_STACK_PRE_ALLOC_SIZE,
 };
-use memory::{pte::PTEFlags, PageTable, PhysAddr, VirtAddr, VirtPageNum};
+use memory::{
+    pte::{PTEFlags, SwapEntry},
+    PageTable, PhysAddr, VirtAddr, VirtPageNum,
+};
 use page::Page;
 use range_map::RangeMap;
 use systype::{SysError, SysResult};
@@ -30,7 +33,10 @@ use xmas_elf::ElfFile;
 use self::vm_area::VmArea;
 use super::{kernel_page_table, PageFaultAccessType};
 use crate::{
-    mm::memory_space::vm_area::{MapPerm, VmAreaType},
+    mm::{
+        memory_space::vm_area::{MapPerm, VmAreaType},
+        swap::SwapSlot,
+    },
     processor::{env::SumGuard, hart::current_task_ref},
     syscall::MmapFlags,
     task::{
@@ -50,6 +56,8 @@ pub struct MemorySpace {
     /// Map of `VmArea`s in this memory space.
     /// NOTE: stores range that is lazy allocated
     areas: SyncUnsafeCell<RangeMap<VirtAddr, VmArea>>,
+    /// Page to start scanning from when swapping out pages next time.
+    swap_cursor: VirtPageNum,
 }
 
 impl MemorySpace {
@@ -58,6 +66,7 @@ impl MemorySpace {
         Self {
             page_table: SyncUnsafeCell::new(PageTable::new()),
             areas: SyncUnsafeCell::new(RangeMap::new()),
+            swap_cursor: VirtPageNum::from(0),
         }
     }
 
@@ -66,6 +75,7 @@ impl MemorySpace {
         Self {
             page_table: SyncUnsafeCell::new(PageTable::from_kernel(kernel_page_table())),
             areas: SyncUnsafeCell::new(RangeMap::new()),
+            swap_cursor: VirtPageNum::from(0),
         }
     }
 
@@ -444,7 +454,7 @@ impl MemorySpace {
                 if let Some(page) = area.pages.get(&vpn) {
                     let pte = user_space.page_table_mut().find_leaf_pte(vpn).unwrap();
                     let (pte_flags, ppn) = match area.vma_type {
-                        VmAreaType::Shm => {
+                        VmAreaType::Shm | VmAreaType::IoUring => {
                             // If shared memory,
                             // then we don't need to modify the pte flags,
                             // i.e. no copy-on-write.
@@ -466,6 +476,11 @@ impl MemorySpace {
                     // lazy allocated area
                 }
             }
+            // Swap slots are shared by cloning `area`, and each memory space
+            // reads its own copy back.
+            for (&vpn, slot) in area.swap_slots.iter() {
+                memory_space.page_table_mut().map_swap(vpn, slot.entry());
+            }
             memory_space.push_vma_lazily(new_area);
         }
         memory_space
@@ -514,6 +529,38 @@ impl MemorySpace {
         Ok(start)
     }
 
+    /// Map `pages` of an io_uring ring, which are shared between the kernel
+    /// and user space and mapped eagerly.
+    pub fn alloc_mmap_io_uring(
+        &mut self,
+        addr: VirtAddr,
+        length: usize,
+        perm: MapPerm,
+        flags: MmapFlags,
+        pages: &[Arc<Page>],
+    ) -> SysResult<VirtAddr> {
+        const MMAP_RANGE: Range<VirtAddr> =
+            VirtAddr::from_usize_range(U_SEG_FILE_BEG..U_SEG_FILE_END);
+        if length > pages.len() * PAGE_SIZE {
+            return Err(SysError::EINVAL);
+        }
+        let range = if flags.contains(MmapFlags::MAP_FIXED) {
+            addr..addr + length
+        } else {
+            self.areas_mut()
+                .find_free_range(MMAP_RANGE, length)
+                .ok_or(SysError::ENOMEM)?
+        };
+        let start = range.start;
+        let mut vma = VmArea::new(range, perm, VmAreaType::IoUring);
+        for (vpn, page) in vma.range_vpn().zip(pages) {
+            self.page_table_mut().map(vpn, page.ppn(), perm.into());
+            vma.pages.insert(vpn, page.clone());
+        }
+        self.push_vma_lazily(vma);
+        Ok(start)
+    }
+
     pub fn alloc_mmap_anonymous(
         &mut self,
         addr: VirtAddr,
@@ -564,6 +611,7 @@ impl MemorySpace {
         let page_table = self.page_table_mut();
         let inode = file.inode();
         let mut vma = VmArea::new_mmap(range, perm, flags, Some(file.clone()), offset);
+        vma.memfd_write_mapping = vma.map_memfd_writable(perm)?;
         let mut range_vpn = vma.range_vpn();
         let length = cmp::min(length, MMAP_PRE_ALLOC_PAGES * PAGE_SIZE);
         for offset_aligned in (offset..offset + length).step_by(PAGE_SIZE) {
@@ -669,19 +717,80 @@ impl MemorySpace {
             .areas_mut()
             .get_key_value_mut(range.start)
             .ok_or(SysError::ENOMEM)?;
+        let memfd_write_mapping = area
+            .map_memfd_writable(perm)
+            .map_err(|_| SysError::EACCES)?;
         if range == old_range {
             area.set_perm_and_flush(self.page_table_mut(), perm);
+            area.memfd_write_mapping = memfd_write_mapping;
         } else {
             debug_assert!(old_range.end >= range.end);
             // do split and remap
             let (_, middle, _) = self.split_area(old_range, range);
             if let Some(middle) = middle {
                 middle.set_perm_and_flush(self.page_table_mut(), perm);
+                middle.memfd_write_mapping = memfd_write_mapping;
             }
         }
         Ok(())
     }
 
+    /// Count of pages mapped in this memory space.
+    pub fn rss(&self) -> usize {
+        self.areas().iter().map(|(_, vma)| vma.pages.len()).sum()
+    }
+
+    /// Swap out at most `nr_to_swap` anonymous pages referenced by no one
+    /// else. Pages are scanned in turn from where the last scan stopped.
+    /// Returns the count of pages swapped out.
+    pub fn swap_out(&mut self, nr_to_swap: usize) -> usize {
+        let mut vpns: Vec<_> = self
+            .areas()
+            .iter()
+            .filter(|(_, vma)| vma.is_swappable())
+            .flat_map(|(_, vma)| vma.pages.iter())
+            .filter(|(_, page)| Arc::strong_count(page) == 1)
+            .map(|(&vpn, _)| vpn)
+            .collect();
+        let pos = vpns.partition_point(|&vpn| vpn < self.swap_cursor);
+        vpns.rotate_left(pos);
+        let mut swapped = 0;
+        for vpn in vpns.into_iter().take(nr_to_swap) {
+            let vma = self.areas_mut().get_mut(vpn.to_vaddr()).unwrap();
+            if let Err(e) = vma.swap_out_page(self.page_table_mut(), vpn) {
+                log::warn!("[MemorySpace::swap_out] fail to swap out {vpn:?}, {e:?}");
+                break;
+            }
+            swapped += 1;
+            self.swap_cursor = vpn + 1;
+        }
+        swapped
+    }
+
+    /// Swap slots of pages swapped out to swap area `area`, so that the pages
+    /// can be read back without the lock of this memory space held.
+    pub fn swap_slots_in_area(&self, area: usize) -> Vec<(VirtPageNum, SwapSlot)> {
+        self.areas()
+            .iter()
+            .flat_map(|(_, vma)| vma.swap_slots_in_area(area))
+            .collect()
+    }
+
+    /// Map pages read back from swap slots, see `VmArea::map_swapped_in`.
+    /// Returns the count of pages mapped.
+    pub fn map_swapped_in(&mut self, pages: Vec<(VirtPageNum, SwapEntry, Arc<Page>)>) -> usize {
+        let mut mapped = 0;
+        for (vpn, entry, page) in pages {
+            let Some(vma) = self.areas_mut().get_mut(vpn.to_vaddr()) else {
+                continue;
+            };
+            if vma.map_swapped_in(self.page_table_mut(), vpn, entry, page) {
+                mapped += 1;
+            }
+        }
+        mapped
+    }
+
     pub fn handle_page_fault(
         &mut self,
         va: VirtAddr,
@@ -1,16 +1,20 @@
 use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use core::ops::{Range, RangeBounds};
 
-use arch::memory::sfence_vma_vaddr;
+use arch::memory::{sfence_vma_vaddr, sfence_vma_vaddr_all_harts};
 use async_utils::block_on;
 use config::mm::{round_down_to_page, PAGE_SIZE};
-use memory::{pte::PTEFlags, VirtAddr, VirtPageNum};
+use memory::{
+    pte::{PTEFlags, SwapEntry},
+    VirtAddr, VirtPageNum,
+};
 use page::Page;
 use systype::{SysError, SysResult};
+use vfs::memfd::{MemfdInode, MemfdWriteMapping};
 use vfs_core::File;
 
 use crate::{
-    mm::{PageFaultAccessType, PageTable},
+    mm::{swap::SwapSlot, PageFaultAccessType, PageTable},
     processor::env::SumGuard,
     syscall::MmapFlags,
 };
@@ -28,6 +32,8 @@ pub enum VmAreaType {
     Mmap,
     /// Shared memory
     Shm,
+    /// Rings of io_uring shared with the kernel
+    IoUring,
 }
 
 bitflags! {
@@ -103,6 +109,8 @@ pub struct VmArea {
     range_va: Range<VirtAddr>,
     /// Hold pages with RAII.
     pub pages: BTreeMap<VirtPageNum, Arc<Page>>,
+    /// Hold swap slots of pages swapped out with RAII.
+    pub swap_slots: BTreeMap<VirtPageNum, SwapSlot>,
     /// Map permission of this area.
     pub map_perm: MapPerm,
     /// Type of this area.
@@ -115,6 +123,9 @@ pub struct VmArea {
     pub backed_file: Option<Arc<dyn File>>,
     /// Start offset in the file.
     pub offset: usize,
+    /// Keep the backed memfd from being sealed against writing while this
+    /// area maps it shared and writable.
+    pub memfd_write_mapping: Option<MemfdWriteMapping>,
 }
 
 impl core::fmt::Debug for VmArea {
@@ -130,6 +141,7 @@ impl core::fmt::Debug for VmArea {
 impl Drop for VmArea {
     fn drop(&mut self) {
         log::debug!("[VmArea::drop] drop {self:?}",);
+        self.mark_shared_pages_dirty();
     }
 }
 
@@ -140,11 +152,13 @@ impl VmArea {
         let new = Self {
             range_va,
             pages: BTreeMap::new(),
+            swap_slots: BTreeMap::new(),
             vma_type,
             map_perm,
             backed_file: None,
             mmap_flags: MmapFlags::default(),
             offset: 0,
+            memfd_write_mapping: None,
         };
         log::debug!("[VmArea::new] {new:?}");
         new
@@ -161,11 +175,13 @@ impl VmArea {
         let new = Self {
             range_va,
             pages: BTreeMap::new(),
+            swap_slots: BTreeMap::new(),
             vma_type: VmAreaType::Mmap,
             map_perm,
             backed_file: file,
             mmap_flags,
             offset,
+            memfd_write_mapping: None,
         };
         log::debug!("[VmArea::new_mmap] {new:?}");
         new
@@ -176,11 +192,13 @@ impl VmArea {
         Self {
             range_va: another.range_va(),
             pages: BTreeMap::new(),
+            swap_slots: BTreeMap::new(),
             vma_type: another.vma_type,
             map_perm: another.map_perm,
             backed_file: another.backed_file.clone(),
             mmap_flags: another.mmap_flags,
             offset: another.offset,
+            memfd_write_mapping: another.memfd_write_mapping.clone(),
         }
     }
 
@@ -220,6 +238,26 @@ impl VmArea {
         self.map_perm = perm;
     }
 
+    /// Account this area as a writable shared mapping of its backed memfd if
+    /// it would be one with `perm`. Fails with `EPERM` if the memfd is sealed
+    /// against writing.
+    pub fn map_memfd_writable(&self, perm: MapPerm) -> SysResult<Option<MemfdWriteMapping>> {
+        if !perm.contains(MapPerm::W) || !self.mmap_flags.contains(MmapFlags::MAP_SHARED) {
+            return Ok(None);
+        }
+        if let Some(mapping) = self.memfd_write_mapping.as_ref() {
+            return Ok(Some(mapping.clone()));
+        }
+        match self
+            .backed_file
+            .as_ref()
+            .map(|file| file.inode().downcast_arc::<MemfdInode>())
+        {
+            Some(Ok(memfd)) => memfd.map_writable().map(Some),
+            _ => Ok(None),
+        }
+    }
+
     pub fn get_page(&self, vpn: VirtPageNum) -> &Arc<Page> {
         self.pages.get(&vpn).expect("no page found for vpn")
     }
@@ -281,13 +319,135 @@ impl VmArea {
         }
     }
 
+    /// Mark pages of a writable shared file mapping as dirty in the page cache,
+    /// so that changes made through the mapping are written back instead of
+    /// being lost when the pages are reclaimed.
+    fn mark_shared_pages_dirty(&self) {
+        if !self.mmap_flags.contains(MmapFlags::MAP_SHARED)
+            || !self.map_perm.contains(MapPerm::W)
+            || self.pages.is_empty()
+        {
+            return;
+        }
+        let Some(file) = self.backed_file.as_ref() else {
+            return;
+        };
+        let inode = file.inode();
+        let Some(page_cache) = inode.page_cache() else {
+            return;
+        };
+        for &vpn in self.pages.keys() {
+            let offset = self.offset + (vpn - self.start_vpn()) * PAGE_SIZE;
+            page_cache.mark_dirty(round_down_to_page(offset));
+        }
+        inode.mark_dirty();
+    }
+
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        self.mark_shared_pages_dirty();
         let vpns: Vec<_> = self.pages.keys().cloned().collect();
         for vpn in vpns {
             page_table.unmap(vpn);
             unsafe { sfence_vma_vaddr(vpn.to_vaddr().into()) };
             self.pages.remove(&vpn);
         }
+        for vpn in core::mem::take(&mut self.swap_slots).into_keys() {
+            page_table.unmap_swap(vpn);
+        }
+    }
+
+    /// Whether pages of this area are anonymous memory, which can be swapped
+    /// out.
+    pub fn is_swappable(&self) -> bool {
+        match self.vma_type {
+            VmAreaType::Heap | VmAreaType::Stack => true,
+            VmAreaType::Mmap => {
+                self.mmap_flags.contains(MmapFlags::MAP_ANONYMOUS)
+                    && !self.mmap_flags.contains(MmapFlags::MAP_SHARED)
+            }
+            _ => false,
+        }
+    }
+
+    /// Swap out the page at `vpn`, which should be referenced by no one else.
+    pub fn swap_out_page(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> SysResult<()> {
+        debug_assert!(self.is_swappable());
+        let slot = SwapSlot::alloc().ok_or(SysError::ENOSPC)?;
+        let page = self.get_page(vpn).clone();
+        let pte_flags = page_table.find_leaf_pte(vpn).unwrap().flags();
+        // Unmap the page before writing it out so that it is not modified
+        // afterwards. The memory space may be active on other harts, whose
+        // TLBs must be flushed as well.
+        page_table.map_swap(vpn, slot.entry());
+        let result = if sfence_vma_vaddr_all_harts(vpn.to_vaddr().into()) {
+            slot.write_page(&page)
+        } else {
+            Err(SysError::EAGAIN)
+        };
+        if let Err(e) = result {
+            page_table.map_force(vpn, page.ppn(), pte_flags);
+            unsafe { sfence_vma_vaddr(vpn.to_vaddr().into()) };
+            return Err(e);
+        }
+        self.pages.remove(&vpn);
+        self.swap_slots.insert(vpn, slot);
+        Ok(())
+    }
+
+    /// Read the page at `vpn` swapped out to `entry` back into a new page.
+    fn swap_in_page(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        entry: SwapEntry,
+    ) -> SysResult<()> {
+        let slot = self
+            .swap_slots
+            .get(&vpn)
+            .expect("no swap slot for swap pte");
+        debug_assert_eq!(slot.entry(), entry);
+        let page = Page::try_new().ok_or(SysError::ENOMEM)?;
+        slot.read_page(&page)?;
+        self.map_swapped_in(page_table, vpn, entry, page);
+        Ok(())
+    }
+
+    /// Swap slots of pages swapped out to swap area `area`.
+    pub fn swap_slots_in_area(
+        &self,
+        area: usize,
+    ) -> impl Iterator<Item = (VirtPageNum, SwapSlot)> + '_ {
+        self.swap_slots
+            .iter()
+            .filter(move |(_, slot)| slot.entry().area() == area)
+            .map(|(&vpn, slot)| (vpn, slot.clone()))
+    }
+
+    /// Map `page` read back from the swap slot `entry` at `vpn`, unless the
+    /// page at `vpn` is no longer swapped out to `entry`. Returns whether the
+    /// page is mapped.
+    pub fn map_swapped_in(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        entry: SwapEntry,
+        page: Arc<Page>,
+    ) -> bool {
+        if !self
+            .swap_slots
+            .get(&vpn)
+            .is_some_and(|slot| slot.entry() == entry)
+        {
+            return false;
+        }
+        // The page is private to this area now, even if the slot is shared
+        // with other areas after fork.
+        page_table.unmap_swap(vpn);
+        page_table.map(vpn, page.ppn(), self.map_perm.into());
+        unsafe { sfence_vma_vaddr(vpn.to_vaddr().into()) };
+        self.pages.insert(vpn, page);
+        self.swap_slots.remove(&vpn);
+        true
     }
 
     /// Copy the data to start_va + offset.
@@ -341,6 +501,11 @@ impl VmArea {
                     .into_iter()
                     .map(|(&k, v)| (k, v.clone())),
             );
+            left_vma.swap_slots.extend(
+                self.swap_slots
+                    .range(left_vma.range_vpn())
+                    .map(|(&k, v)| (k, v.clone())),
+            );
             left_vma.offset += left_vma.start_va() - self.start_va();
             left = Some(left_vma)
         }
@@ -353,6 +518,11 @@ impl VmArea {
                     .into_iter()
                     .map(|(&k, v)| (k, v.clone())),
             );
+            middle_vma.swap_slots.extend(
+                self.swap_slots
+                    .range(middle_vma.range_vpn())
+                    .map(|(&k, v)| (k, v.clone())),
+            );
             middle_vma.offset += middle_vma.start_va() - self.start_va();
             middle = Some(middle_vma)
         }
@@ -365,6 +535,11 @@ impl VmArea {
                     .into_iter()
                     .map(|(&k, v)| (k, v.clone())),
             );
+            right_vma.swap_slots.extend(
+                self.swap_slots
+                    .range(right_vma.range_vpn())
+                    .map(|(&k, v)| (k, v.clone())),
+            );
             right_vma.offset += right_vma.start_va() - self.start_va();
             right = Some(right_vma)
         }
@@ -395,6 +570,10 @@ impl VmArea {
             return Err(SysError::EFAULT);
         }
 
+        if let Some(entry) = page_table.find_swap_entry(vpn) {
+            return self.swap_in_page(page_table, vpn, entry);
+        }
+
         let page: Arc<Page>;
         let pte = page_table.find_leaf_pte(vpn);
         if let Some(pte) = pte {
@@ -416,7 +595,7 @@ impl VmArea {
                 );
 
                 // copy the data
-                page = Page::new();
+                page = Page::try_new().ok_or(SysError::ENOMEM)?;
                 page.copy_from_slice(old_page.bytes_array());
 
                 // unmap old page and map new page
@@ -444,7 +623,7 @@ impl VmArea {
             match self.vma_type {
                 VmAreaType::Heap | VmAreaType::Stack => {
                     // lazy allcation for heap
-                    page = Page::new();
+                    page = Page::try_new().ok_or(SysError::ENOMEM)?;
                     page.fill_zero();
                     page_table.map(vpn, page.ppn(), self.map_perm.into());
                     self.pages.insert(vpn, page);
@@ -466,7 +645,7 @@ impl VmArea {
                             let page = block_on(async { file.get_page_at(offset_aligned).await })?
                                 .unwrap();
                             if access_type.contains(PageFaultAccessType::WRITE) {
-                                let new_page = Page::new();
+                                let new_page = Page::try_new().ok_or(SysError::ENOMEM)?;
                                 new_page.copy_from_slice(page.bytes_array());
                                 page_table.map(vpn, new_page.ppn(), self.map_perm.into());
                                 self.pages.insert(vpn, new_page);
@@ -487,7 +666,7 @@ impl VmArea {
                             todo!()
                         } else {
                             // private anonymous area
-                            page = Page::new();
+                            page = Page::try_new().ok_or(SysError::ENOMEM)?;
                             page.fill_zero();
                             page_table.map(vpn, page.ppn(), self.map_perm.into());
                             self.pages.insert(vpn, page);
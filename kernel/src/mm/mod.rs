@@ -7,6 +7,8 @@
 //! Every task or process has a memory_space to control its virtual memory.
 
 pub mod memory_space;
+pub mod oom;
+pub mod swap;
 mod user_ptr;
 
 use core::cmp;
@@ -0,0 +1,99 @@
+//! Out-of-memory killer, which kills the process with the most resident pages
+//! when no more memory can be reclaimed.
+//!
+//! The badness of a process is its count of resident pages, adjusted by its
+//! `oom_score_adj` in thousandths of all frames. A process with
+//! `oom_score_adj` of `OOM_SCORE_ADJ_MIN` is never killed.
+
+use alloc::sync::{Arc, Weak};
+
+use config::process::INIT_PROC_PID;
+use memory::{
+    free_frames,
+    reclaim::{high_watermark, is_reclaiming, reclaim_frames},
+    total_frames,
+};
+use signal::{Sig, SigDetails, SigInfo};
+use sync::mutex::SpinNoIrqLock;
+
+use crate::task::{Task, TASK_MANAGER};
+
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// Frames to reclaim at least when a page fault of user memory runs out of
+/// memory.
+const RECLAIM_BATCH: usize = 32;
+
+/// Process killed last time. No more process is killed until it exits, since
+/// its memory is freed only then.
+static OOM_VICTIM: SpinNoIrqLock<Option<Weak<Task>>> = SpinNoIrqLock::new(None);
+
+/// Returns the badness and the count of resident pages of the process, or
+/// `None` if it should not be killed.
+fn oom_badness(task: &Arc<Task>) -> Option<(usize, usize)> {
+    let adj = task.oom_score_adj();
+    if adj == OOM_SCORE_ADJ_MIN || task.pid() == INIT_PROC_PID || task.is_zombie() {
+        return None;
+    }
+    let rss = task.try_rss()?;
+    let points = rss as isize + adj as isize * total_frames() as isize / 1000;
+    Some((points.max(1) as usize, rss))
+}
+
+/// Badness of the process normalized to [0, 1000], which is shown in
+/// `/proc/self/oom_score`.
+pub fn oom_score(task: &Arc<Task>) -> usize {
+    oom_badness(task).map_or(0, |(points, _)| {
+        (points * 1000 / total_frames().max(1)).min(1000)
+    })
+}
+
+/// Kill the process with the highest badness by `SIGKILL`.
+fn out_of_memory() {
+    let mut victim = OOM_VICTIM.lock();
+    if let Some(task) = victim.as_ref().and_then(Weak::upgrade) {
+        if !task.is_zombie() {
+            log::warn!(
+                "[out_of_memory] process {} killed before is still exiting",
+                task.pid()
+            );
+            return;
+        }
+    }
+    let chosen = TASK_MANAGER
+        .tasks()
+        .into_iter()
+        .filter(|task| task.is_leader())
+        .filter_map(|task| oom_badness(&task).map(|badness| (task, badness)))
+        .max_by_key(|(_, (points, _))| *points);
+    let Some((task, (points, rss))) = chosen else {
+        log::error!("[out_of_memory] out of memory and no process can be killed");
+        return;
+    };
+    log::error!(
+        "[out_of_memory] out of memory: kill process {} ({}), resident pages {rss}, oom_score_adj {}, badness {points}",
+        task.pid(),
+        task.elf_ref().dentry().path(),
+        task.oom_score_adj()
+    );
+    task.receive_siginfo(
+        SigInfo {
+            sig: Sig::SIGKILL,
+            code: SigInfo::KERNEL,
+            details: SigDetails::None,
+        },
+        false,
+    );
+    *victim = Some(Arc::downgrade(&task));
+}
+
+/// Handle a page fault of user memory running out of memory in a context
+/// holding no locks, where memory can also be reclaimed by swapping. Kill some
+/// process only if nothing can be reclaimed.
+pub fn reclaim_or_kill() {
+    let nr_to_free = RECLAIM_BATCH.max(high_watermark().saturating_sub(free_frames()));
+    if reclaim_frames(nr_to_free, false) == 0 && !is_reclaiming() {
+        out_of_memory();
+    }
+}
@@ -0,0 +1,407 @@
+//! Swap of anonymous memory to swap files.
+//!
+//! A swap area is a file prepared by mkswap, whose first page is a header and
+//! the other pages are slots holding swapped out pages. Pages of anonymous
+//! `VmArea`s referenced by no one else are written to free slots when memory
+//! runs low, and their ptes are replaced by non-present ptes holding the
+//! `SwapEntry`. They are read back into new pages on page fault.
+//!
+//! A slot is referenced by the `SwapSlot`s of all areas sharing it after fork,
+//! and each of them reads its own copy of the page back, so that no page is
+//! shared through swap.
+//!
+//! Swapping out writes files, which may allocate frames with locks held, so it
+//! is only done by the reclaim task and by user page faults running out of
+//! memory, but never by direct reclaim of other allocations.
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use async_utils::block_on;
+use config::mm::PAGE_SIZE;
+use memory::{
+    pte::SwapEntry,
+    reclaim::{register_shrinker, Shrinker},
+};
+use page::Page;
+use spin::Once;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{File, Inode};
+
+use crate::task::{Task, TASK_MANAGER};
+
+/// Signature at the end of the header page written by mkswap.
+const SWAP_MAGIC: &[u8] = b"SWAPSPACE2";
+const SWAP_VERSION: u32 = 1;
+
+// Offsets of fields of `union swap_header` in the header page.
+const VERSION_OFFSET: usize = 1024;
+const LAST_PAGE_OFFSET: usize = 1028;
+const NR_BADPAGES_OFFSET: usize = 1032;
+const BADPAGES_OFFSET: usize = 1536;
+
+/// Count of a slot that can never be used, i.e. the header or a bad page.
+const SWAP_MAP_BAD: u16 = u16::MAX;
+
+bitflags! {
+    // Defined in <sys/swap.h>
+    #[derive(Debug, Clone, Copy)]
+    pub struct SwapFlags: i32 {
+        /// Priority of the area is given in the lower bits.
+        const SWAP_FLAG_PREFER = 0x8000;
+        const SWAP_FLAG_PRIO_MASK = 0x7fff;
+        /// Discard freed slots, which is ignored.
+        const SWAP_FLAG_DISCARD = 0x10000;
+        const SWAP_FLAG_DISCARD_ONCE = 0x20000;
+        const SWAP_FLAG_DISCARD_PAGES = 0x40000;
+    }
+}
+
+struct SwapArea {
+    file: Arc<dyn File>,
+    prio: i32,
+    /// Count of references to each slot, 0 if it is free.
+    swap_map: Vec<u16>,
+    nr_free: usize,
+    /// Slot to start searching for a free one.
+    cursor: usize,
+    /// Whether free slots can be allocated, which is false when the area is
+    /// being turned off.
+    writable: bool,
+}
+
+impl SwapArea {
+    fn alloc_slot(&mut self) -> Option<usize> {
+        let nr_slots = self.swap_map.len();
+        let slot = (self.cursor..nr_slots)
+            .chain(0..self.cursor)
+            .find(|&slot| self.swap_map[slot] == 0)?;
+        self.swap_map[slot] = 1;
+        self.nr_free -= 1;
+        self.cursor = (slot + 1) % nr_slots;
+        Some(slot)
+    }
+
+    fn nr_used(&self) -> usize {
+        self.swap_map
+            .iter()
+            .filter(|&&cnt| cnt != 0 && cnt != SWAP_MAP_BAD)
+            .count()
+    }
+}
+
+struct SwapAreas {
+    areas: Vec<Option<SwapArea>>,
+    /// Priority of the last area turned on without a given priority.
+    least_priority: i32,
+}
+
+static SWAP_AREAS: SpinNoIrqLock<SwapAreas> = SpinNoIrqLock::new(SwapAreas {
+    areas: Vec::new(),
+    least_priority: -1,
+});
+
+/// Process whose memory space was scanned last time by `swap_out`.
+static SWAP_CURSOR: AtomicUsize = AtomicUsize::new(0);
+
+/// A reference to a swap slot holding a swapped out page. The slot is freed
+/// when all references are dropped.
+pub struct SwapSlot(SwapEntry);
+
+impl SwapSlot {
+    /// Allocate a free slot from the area with the highest priority, returns
+    /// `None` if there is no free slot.
+    pub fn alloc() -> Option<Self> {
+        let mut swap = SWAP_AREAS.lock();
+        let (idx, area) = swap
+            .areas
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, area)| Some((idx, area.as_mut()?)))
+            .filter(|(_, area)| area.writable && area.nr_free > 0)
+            .max_by_key(|(_, area)| area.prio)?;
+        let slot = area.alloc_slot()?;
+        Some(Self(SwapEntry::new(idx, slot)))
+    }
+
+    pub fn entry(&self) -> SwapEntry {
+        self.0
+    }
+
+    fn file(&self) -> Arc<dyn File> {
+        SWAP_AREAS.lock().areas[self.0.area()]
+            .as_ref()
+            .expect("swap area of slot is turned off")
+            .file
+            .clone()
+    }
+
+    /// Write `page` into the slot.
+    pub fn write_page(&self, page: &Page) -> SysResult<()> {
+        let file = self.file();
+        let offset = self.0.slot() * PAGE_SIZE;
+        let len = block_on(async { file.base_write_at(offset, page.bytes_array()).await })?;
+        if len != PAGE_SIZE {
+            log::error!(
+                "[SwapSlot::write_page] short write {len} at slot {:?}",
+                self.0
+            );
+            return Err(SysError::EIO);
+        }
+        Ok(())
+    }
+
+    /// Read the page in the slot into `page`.
+    pub fn read_page(&self, page: &Page) -> SysResult<()> {
+        let file = self.file();
+        let offset = self.0.slot() * PAGE_SIZE;
+        let len = block_on(async { file.base_read_at(offset, page.bytes_array()).await })?;
+        if len != PAGE_SIZE {
+            log::error!(
+                "[SwapSlot::read_page] short read {len} at slot {:?}",
+                self.0
+            );
+            return Err(SysError::EIO);
+        }
+        Ok(())
+    }
+}
+
+impl Clone for SwapSlot {
+    fn clone(&self) -> Self {
+        let mut swap = SWAP_AREAS.lock();
+        let area = swap.areas[self.0.area()].as_mut().unwrap();
+        let cnt = &mut area.swap_map[self.0.slot()];
+        debug_assert!(*cnt != 0 && *cnt < SWAP_MAP_BAD - 1);
+        *cnt += 1;
+        Self(self.0)
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        let mut swap = SWAP_AREAS.lock();
+        let area = swap.areas[self.0.area()].as_mut().unwrap();
+        let cnt = &mut area.swap_map[self.0.slot()];
+        debug_assert!(*cnt != 0 && *cnt != SWAP_MAP_BAD);
+        *cnt -= 1;
+        if *cnt == 0 {
+            area.nr_free += 1;
+        }
+    }
+}
+
+fn has_free_slots() -> bool {
+    SWAP_AREAS
+        .lock()
+        .areas
+        .iter()
+        .flatten()
+        .any(|area| area.writable && area.nr_free > 0)
+}
+
+/// Swap out at most `nr_to_swap` anonymous pages of processes whose memory
+/// spaces are not locked. Returns the count of pages swapped out.
+pub fn swap_out(nr_to_swap: usize) -> usize {
+    if !has_free_slots() {
+        return 0;
+    }
+    // Start from the process after the one scanned last time, so that
+    // processes are swapped out in turn.
+    let cursor = SWAP_CURSOR.load(Ordering::Relaxed);
+    let mut tasks: Vec<_> = TASK_MANAGER
+        .tasks()
+        .into_iter()
+        .filter(|task| task.is_leader() && !task.is_zombie())
+        .collect();
+    tasks.sort_by_key(|task| (task.pid() <= cursor, task.pid()));
+    let mut swapped = 0;
+    for task in tasks {
+        if swapped >= nr_to_swap {
+            break;
+        }
+        SWAP_CURSOR.store(task.pid(), Ordering::Relaxed);
+        swapped += task
+            .try_with_mut_memory_space(|m| m.swap_out(nr_to_swap - swapped))
+            .unwrap_or(0);
+    }
+    log::info!("[swap_out] want {nr_to_swap} pages, swapped out {swapped}");
+    swapped
+}
+
+struct SwapShrinker;
+
+impl Shrinker for SwapShrinker {
+    fn name(&self) -> &'static str {
+        "anonymous memory"
+    }
+
+    fn seeks(&self) -> usize {
+        8
+    }
+
+    fn scan_objects(&self, nr_to_scan: usize) -> usize {
+        swap_out(nr_to_scan)
+    }
+
+    fn direct_reclaim(&self) -> bool {
+        false
+    }
+}
+
+static SWAP_SHRINKER: Once<()> = Once::new();
+
+/// Turn on swapping to `file`, which should be prepared by mkswap.
+pub fn swapon(file: Arc<dyn File>, flags: SwapFlags) -> SysResult<()> {
+    let inode = file.inode();
+    if !inode.itype().is_file() {
+        // Only swap files are supported since there is no block device file.
+        return Err(SysError::EINVAL);
+    }
+    // Swap files are read and written bypassing the page cache, which file
+    // systems in memory only, e.g. tmpfs, do not support.
+    let has_device = inode
+        .try_super_block()
+        .is_some_and(|super_block| super_block.meta().device.is_some());
+    if !has_device || inode.page_cache().is_none() {
+        return Err(SysError::EINVAL);
+    }
+    if SWAP_AREAS
+        .lock()
+        .areas
+        .iter()
+        .flatten()
+        .any(|area| Arc::ptr_eq(&area.file.inode(), &inode))
+    {
+        return Err(SysError::EBUSY);
+    }
+
+    // Write dirty pages back and drop the page cache, so that neither stale
+    // pages are read nor they are written back over swapped out pages.
+    inode.write_dirty_pages()?;
+    inode.page_cache().unwrap().truncate(0);
+
+    let mut header = vec![0; PAGE_SIZE];
+    let len = block_on(async { file.base_read_at(0, &mut header).await })?;
+    if len != PAGE_SIZE || &header[PAGE_SIZE - SWAP_MAGIC.len()..] != SWAP_MAGIC {
+        log::warn!("[swapon] {} has no swap signature", file.dentry().path());
+        return Err(SysError::EINVAL);
+    }
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap()) as usize;
+    if read_u32(VERSION_OFFSET) != SWAP_VERSION as usize {
+        return Err(SysError::EINVAL);
+    }
+    let nr_slots = (read_u32(LAST_PAGE_OFFSET) + 1).min(file.size() / PAGE_SIZE);
+    let nr_badpages = read_u32(NR_BADPAGES_OFFSET);
+    if nr_slots < 2 || BADPAGES_OFFSET + nr_badpages * 4 > PAGE_SIZE - SWAP_MAGIC.len() {
+        return Err(SysError::EINVAL);
+    }
+    let mut swap_map = vec![0; nr_slots];
+    swap_map[0] = SWAP_MAP_BAD;
+    for i in 0..nr_badpages {
+        let slot = read_u32(BADPAGES_OFFSET + i * 4);
+        if slot < nr_slots {
+            swap_map[slot] = SWAP_MAP_BAD;
+        }
+    }
+    let nr_free = swap_map.iter().filter(|&&cnt| cnt == 0).count();
+
+    let mut swap = SWAP_AREAS.lock();
+    let prio = if flags.contains(SwapFlags::SWAP_FLAG_PREFER) {
+        (flags & SwapFlags::SWAP_FLAG_PRIO_MASK).bits()
+    } else {
+        swap.least_priority -= 1;
+        swap.least_priority
+    };
+    let idx = match swap.areas.iter().position(Option::is_none) {
+        Some(idx) => idx,
+        None if swap.areas.len() < SwapEntry::MAX_AREAS => {
+            swap.areas.push(None);
+            swap.areas.len() - 1
+        }
+        None => return Err(SysError::EPERM),
+    };
+    log::info!(
+        "[swapon] {} as area {idx}, {nr_free} free pages, priority {prio}",
+        file.dentry().path()
+    );
+    swap.areas[idx] = Some(SwapArea {
+        file,
+        prio,
+        swap_map,
+        nr_free,
+        cursor: 1,
+        writable: true,
+    });
+    inode.set_swap_file(true);
+    drop(swap);
+    SWAP_SHRINKER.call_once(|| register_shrinker(Arc::new(SwapShrinker)));
+    Ok(())
+}
+
+/// Turn off swapping to the file of `inode`, after reading all pages swapped
+/// out to it back into memory.
+pub fn swapoff(inode: &Arc<dyn Inode>) -> SysResult<()> {
+    let find_area = |inode: &Arc<dyn Inode>| {
+        SWAP_AREAS.lock().areas.iter().position(|area| {
+            area.as_ref()
+                .is_some_and(|area| Arc::ptr_eq(&area.file.inode(), inode))
+        })
+    };
+    let idx = find_area(inode).ok_or(SysError::EINVAL)?;
+    let set_writable = |writable: bool| {
+        if let Some(area) = SWAP_AREAS.lock().areas[idx].as_mut() {
+            area.writable = writable;
+        }
+    };
+    let nr_used = || SWAP_AREAS.lock().areas[idx].as_ref().unwrap().nr_used();
+
+    set_writable(false);
+    while nr_used() > 0 {
+        let mut swapped_in = 0;
+        for task in TASK_MANAGER
+            .tasks()
+            .into_iter()
+            .filter(|task| task.is_leader())
+        {
+            match swap_in_task(&task, idx) {
+                Ok(nr) => swapped_in += nr,
+                Err(e) => {
+                    set_writable(true);
+                    return Err(e);
+                }
+            }
+        }
+        // Slots may still be referenced by memory spaces no task owns, e.g.
+        // one being dropped.
+        if swapped_in == 0 && nr_used() > 0 {
+            set_writable(true);
+            return Err(SysError::EBUSY);
+        }
+    }
+    log::info!("[swapoff] turn off swap area {idx}");
+    // Pages of the file may have been read into the page cache while it is
+    // written by swap.
+    if let Some(page_cache) = inode.page_cache() {
+        page_cache.truncate(0);
+    }
+    SWAP_AREAS.lock().areas[idx] = None;
+    inode.set_swap_file(false);
+    Ok(())
+}
+
+/// Read pages of the memory space of `task` swapped out to area `idx` back.
+/// The memory space is not locked while reading, so that the process is not
+/// blocked on its page faults meanwhile. Returns the count of pages read.
+fn swap_in_task(task: &Task, idx: usize) -> SysResult<usize> {
+    let slots = task.with_mut_memory_space(|m| m.swap_slots_in_area(idx));
+    let mut pages = Vec::with_capacity(slots.len());
+    for (vpn, slot) in slots.iter() {
+        let page = Page::try_new().ok_or(SysError::ENOMEM)?;
+        slot.read_page(&page)?;
+        pages.push((*vpn, slot.entry(), page));
+    }
+    Ok(task.with_mut_memory_space(|m| m.map_swapped_in(pages)))
+}
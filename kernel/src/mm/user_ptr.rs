@@ -11,6 +11,7 @@ use core::{
     marker::PhantomData,
     mem,
     ops::{self, ControlFlow},
+    sync::atomic::AtomicU32,
 };
 
 use memory::VirtAddr;
@@ -18,7 +19,7 @@ use net::{IpAddress, IpEndpoint, IpListenEndpoint};
 use riscv::register::scause;
 use systype::{SysError, SysResult};
 
-use super::memory_space::vm_area::MapPerm;
+use super::{memory_space::vm_area::MapPerm, oom::reclaim_or_kill};
 use crate::{
     net::{
         addr::{SockAddr, SockAddrIn, SockAddrIn6, SockAddrUn},
@@ -523,7 +524,17 @@ impl Task {
         let mut readable_len = 0;
         while readable_len < len {
             if test_fn(curr_vaddr.0) {
-                self.with_mut_memory_space(|m| m.handle_page_fault(curr_vaddr, access))?
+                let handle_page_fault =
+                    || self.with_mut_memory_space(|m| m.handle_page_fault(curr_vaddr, access));
+                let mut result = handle_page_fault();
+                if let Err(SysError::ENOMEM) = result {
+                    // Retry once after reclaiming memory.
+                    unsafe { set_kernel_trap() };
+                    reclaim_or_kill();
+                    unsafe { set_kernel_user_rw_trap() };
+                    result = handle_page_fault();
+                }
+                result?
             }
 
             let next_page_beg: VirtAddr = VirtAddr::from(curr_vaddr.floor().next());
@@ -599,6 +610,16 @@ impl FutexAddr {
     pub fn read(&self) -> u32 {
         unsafe { atomic_load_acquire(self.addr.0 as *const u32) }
     }
+    /// Ensure that the futex word can be modified by the kernel, which is
+    /// required by `FUTEX_WAKE_OP` and the PI futex operations.
+    pub fn check_write(&self, task: &Arc<Task>) -> SysResult<()> {
+        task.just_ensure_user_area(self.addr, size_of::<u32>(), PageFaultAccessType::RW)
+    }
+    /// The futex word for atomic read-modify-write, `check_write` should be
+    /// called before.
+    pub fn as_atomic(&self) -> &AtomicU32 {
+        unsafe { AtomicU32::from_ptr(self.addr.0 as *mut u32) }
+    }
 }
 
 impl From<usize> for FutexAddr {
@@ -645,13 +666,26 @@ impl Task {
                 })
             }
             SaFamily::AF_UNIX => {
-                if unlikely(addrlen < mem::size_of::<SockAddrUn>()) {
+                // The path of a Unix domain socket address may be shorter than
+                // `sun_path`, the rest is filled with null bytes.
+                if unlikely(
+                    addrlen < mem::size_of::<u16>() || addrlen > mem::size_of::<SockAddrUn>(),
+                ) {
                     log::error!("[audit_sockaddr] AF_UNIX addrlen error");
                     return Err(SysError::EINVAL);
                 }
-                Ok(SockAddr {
-                    ipv6: unsafe { *(addr as *const _) },
-                })
+                let mut unix = SockAddrUn {
+                    family: SaFamily::AF_UNIX.into(),
+                    path: [0; 108],
+                };
+                let path_len = addrlen - mem::size_of::<u16>();
+                unix.path[..path_len].copy_from_slice(unsafe {
+                    core::slice::from_raw_parts(
+                        (addr + mem::size_of::<u16>()) as *const u8,
+                        path_len,
+                    )
+                });
+                Ok(SockAddr { unix })
             }
         }
     }
@@ -681,7 +715,7 @@ impl Task {
                 SaFamily::AF_UNIX => {
                     UserWritePtr::<SockAddrUn>::from(addr).write(self, sockaddr.unix)?;
                     UserWritePtr::<u32>::from(addrlen)
-                        .write(self, mem::size_of::<SockAddrUn>() as u32)?;
+                        .write(self, sockaddr.unix.addrlen() as u32)?;
                 }
             }
         }
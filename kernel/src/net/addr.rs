@@ -6,6 +6,7 @@
 use alloc::{format, string::String};
 use core::{
     fmt::{self, Display},
+    mem::size_of,
     panic,
 };
 
@@ -77,6 +78,26 @@ pub struct SockAddrUn {
     pub path: [u8; 108],
 }
 
+impl SockAddrUn {
+    /// Length of the address, which is `sizeof(sa_family_t)` for an unnamed
+    /// socket. The path of an abstract socket address is not terminated by a
+    /// null byte.
+    pub fn addrlen(&self) -> usize {
+        let path_len = if self.path[0] != 0 {
+            match self.path.iter().position(|&c| c == 0) {
+                Some(pos) => pos + 1,
+                None => self.path.len(),
+            }
+        } else {
+            self.path
+                .iter()
+                .rposition(|&c| c != 0)
+                .map_or(0, |last| last + 1)
+        };
+        size_of::<u16>() + path_len
+    }
+}
+
 impl fmt::Display for SockAddrUn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let path = match self.path.iter().position(|&x| x == 0) {
@@ -142,6 +163,21 @@ impl SockAddr {
         }
     }
 
+    /// Length of the address written to user space.
+    pub fn addrlen(&self) -> usize {
+        unsafe {
+            match SaFamily::try_from(self.family).unwrap() {
+                SaFamily::AF_INET => size_of::<SockAddrIn>(),
+                SaFamily::AF_INET6 => size_of::<SockAddrIn6>(),
+                SaFamily::AF_UNIX => self.unix.addrlen(),
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, size_of::<Self>()) }
+    }
+
     pub fn from_endpoint(endpoint: IpEndpoint) -> Self {
         match endpoint.addr {
             IpAddress::Ipv4(v4) => Self {
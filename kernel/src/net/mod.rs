@@ -1,7 +1,7 @@
 use systype::SysError;
 pub mod addr;
 pub mod socket;
-mod unix;
+pub mod unix;
 
 #[repr(u16)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
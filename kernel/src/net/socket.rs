@@ -10,9 +10,8 @@ use net::{
     udp::UdpSocket,
     IpEndpoint, IpListenEndpoint, NetPollState,
 };
-use spin::Mutex;
 use systype::{SysError, SysResult, SyscallResult};
-use unix::UnixSocket;
+use unix::{UnixAncillary, UnixSocket};
 use vfs_core::*;
 
 use super::*;
@@ -29,7 +28,7 @@ impl Sock {
         match self {
             Sock::Tcp(tcp) => tcp.set_nonblocking(true),
             Sock::Udp(udp) => udp.set_nonblocking(true),
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.set_nonblocking(true),
         }
     }
 
@@ -54,26 +53,29 @@ impl Sock {
                 }
                 udp.bind(local_addr)
             }
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.bind(local_addr),
         }
     }
 
-    pub fn listen(&self) -> SysResult<()> {
+    pub fn listen(&self, backlog: usize) -> SysResult<()> {
         match self {
             Sock::Tcp(tcp) => tcp.listen(current_task().waker_ref().as_ref().unwrap()),
             Sock::Udp(_udp) => Err(SysError::EOPNOTSUPP),
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.listen(backlog),
         }
     }
 
-    pub async fn accept(&self) -> SysResult<TcpSocket> {
+    pub async fn accept(&self) -> SysResult<Sock> {
         match self {
             Sock::Tcp(tcp) => {
                 let new_tcp = tcp.accept().await?;
-                Ok(new_tcp)
+                Ok(Sock::Tcp(new_tcp))
             }
             Sock::Udp(_udp) => Err(SysError::EOPNOTSUPP),
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => {
+                let new_unix = unix.accept().await?;
+                Ok(Sock::Unix(new_unix))
+            }
         }
     }
 
@@ -87,7 +89,7 @@ impl Sock {
                 let remote_addr = remote_addr.into_endpoint();
                 udp.connect(remote_addr)
             }
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.connect(remote_addr).await,
         }
     }
 
@@ -101,7 +103,7 @@ impl Sock {
                 let peer_addr = SockAddr::from_endpoint(udp.peer_addr()?);
                 Ok(peer_addr)
             }
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.peer_addr(),
         }
     }
 
@@ -115,37 +117,63 @@ impl Sock {
                 let local_addr = SockAddr::from_endpoint(udp.local_addr()?);
                 Ok(local_addr)
             }
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => Ok(unix.local_addr()),
         }
     }
     pub async fn sendto(&self, buf: &[u8], remote_addr: Option<SockAddr>) -> SysResult<usize> {
+        self.sendmsg(buf, remote_addr, UnixAncillary::default(), false)
+            .await
+    }
+    pub async fn recvfrom(&self, buf: &mut [u8]) -> SysResult<(usize, SockAddr)> {
+        let (len, remote_addr, _) = self.recvmsg(buf, false).await?;
+        Ok((len, remote_addr))
+    }
+    /// Send the message with the ancillary data, which is only supported by
+    /// Unix domain sockets. `nonblock` makes this call nonblocking as
+    /// `MSG_DONTWAIT` does.
+    pub async fn sendmsg(
+        &self,
+        buf: &[u8],
+        remote_addr: Option<SockAddr>,
+        ancillary: UnixAncillary,
+        nonblock: bool,
+    ) -> SysResult<usize> {
         match self {
-            Sock::Tcp(tcp) => tcp.send(buf).await,
+            Sock::Unix(unix) => unix.sendmsg(buf, remote_addr, ancillary, nonblock).await,
+            _ if !ancillary.is_empty() => Err(SysError::EINVAL),
+            Sock::Tcp(tcp) => tcp.send(buf, nonblock).await,
             Sock::Udp(udp) => match remote_addr {
-                Some(addr) => udp.send_to(buf, addr.into_endpoint()).await,
-                None => udp.send(buf).await,
+                Some(addr) => udp.send_to(buf, addr.into_endpoint(), nonblock).await,
+                None => udp.send(buf, nonblock).await,
             },
-            Sock::Unix(_) => unimplemented!(),
         }
     }
-    pub async fn recvfrom(&self, buf: &mut [u8]) -> SysResult<(usize, SockAddr)> {
+
+    pub async fn recvmsg(
+        &self,
+        buf: &mut [u8],
+        nonblock: bool,
+    ) -> SysResult<(usize, SockAddr, UnixAncillary)> {
         match self {
             Sock::Tcp(tcp) => {
-                let bytes = tcp.recv(buf).await?;
-                Ok((bytes, SockAddr::from_endpoint(tcp.peer_addr()?)))
+                let bytes = tcp.recv(buf, nonblock).await?;
+                let remote_addr = SockAddr::from_endpoint(tcp.peer_addr()?);
+                Ok((bytes, remote_addr, UnixAncillary::default()))
             }
             Sock::Udp(udp) => {
-                let (len, endpoint) = udp.recv_from(buf).await?;
-                Ok((len, SockAddr::from_endpoint(endpoint)))
+                let (len, endpoint) = udp.recv_from(buf, nonblock).await?;
+                let remote_addr = SockAddr::from_endpoint(endpoint);
+                Ok((len, remote_addr, UnixAncillary::default()))
             }
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.recvmsg(buf, nonblock).await,
         }
     }
+
     pub async fn poll(&self) -> NetPollState {
         match self {
             Sock::Tcp(tcp) => tcp.poll().await,
             Sock::Udp(udp) => udp.poll().await,
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.poll().await,
         }
     }
 
@@ -153,7 +181,7 @@ impl Sock {
         match self {
             Sock::Tcp(tcp) => tcp.shutdown(how),
             Sock::Udp(udp) => udp.shutdown(),
-            Sock::Unix(_) => unimplemented!(),
+            Sock::Unix(unix) => unix.shutdown(how),
         }
     }
 }
@@ -174,41 +202,44 @@ unsafe impl Send for Socket {}
 impl Socket {
     pub fn new(domain: SaFamily, types: SocketType, nonblock: bool) -> Self {
         let sk = match domain {
-            SaFamily::AF_UNIX => Sock::Unix(UnixSocket {}),
+            SaFamily::AF_UNIX => Sock::Unix(UnixSocket::new(types)),
             SaFamily::AF_INET | SaFamily::AF_INET6 => match types {
                 SocketType::STREAM => Sock::Tcp(TcpSocket::new_v4()),
                 SocketType::DGRAM => Sock::Udp(UdpSocket::new()),
                 _ => unimplemented!(),
             },
         };
+        Self::with_sock(types, sk, nonblock)
+    }
+
+    /// Create a pair of connected Unix domain sockets.
+    pub fn new_unix_pair(types: SocketType, nonblock: bool) -> (Self, Self) {
+        let (a, b) = UnixSocket::new_pair(types);
+        (
+            Self::with_sock(types, Sock::Unix(a), nonblock),
+            Self::with_sock(types, Sock::Unix(b), nonblock),
+        )
+    }
+
+    fn with_sock(types: SocketType, sk: Sock, nonblock: bool) -> Self {
         let flags = if nonblock {
             sk.set_nonblocking();
             OpenFlags::O_RDWR | OpenFlags::O_NONBLOCK
         } else {
             OpenFlags::O_RDWR
         };
-        Self {
-            types,
-            sk,
-            meta: FileMeta {
-                dentry: Arc::<usize>::new_zeroed(),
-                inode: Arc::<usize>::new_zeroed(),
-                pos: 0.into(),
-                flags: Mutex::new(flags),
-            },
-        }
+        let meta = FileMeta::new(Arc::<usize>::new_zeroed(), Arc::<usize>::new_zeroed());
+        *meta.flags.lock() = flags;
+        Self { types, sk, meta }
     }
 
     pub fn from_another(another: &Self, sk: Sock) -> Self {
+        let meta = FileMeta::new(Arc::<usize>::new_zeroed(), Arc::<usize>::new_zeroed());
+        *meta.flags.lock() = OpenFlags::O_RDWR;
         Self {
             types: another.types,
             sk,
-            meta: FileMeta {
-                dentry: Arc::<usize>::new_zeroed(),
-                inode: Arc::<usize>::new_zeroed(),
-                pos: 0.into(),
-                flags: Mutex::new(OpenFlags::O_RDWR),
-            },
+            meta,
         }
     }
 }
@@ -1 +1,709 @@
-pub struct UnixSocket {}
+//! Unix domain sockets used for communication between processes on the same
+//! host.
+//!
+//! A socket can be bound to a path in the file system, where a socket inode is
+//! created, or to a name in the abstract namespace. Bound sockets are
+//! registered in `UNIX_TABLE` so that peers can find them by address.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    mem,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::Waker,
+};
+
+use async_utils::{get_waker, suspend_now};
+use net::{NetPollState, SHUT_RD, SHUT_RDWR, SHUT_WR};
+use spin::Lazy;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{File, InodeMode, InodeType};
+
+use super::{
+    addr::{SockAddr, SockAddrUn},
+    SaFamily, SocketType,
+};
+use crate::processor::hart::{current_task, current_task_ref};
+
+/// Size of the receive buffer of a socket.
+const UNIX_BUF_SIZE: usize = 64 * 1024;
+
+/// Max length of the pending connection queue of a listening socket.
+const SOMAXCONN: usize = 4096;
+
+/// Bound sockets indexed by their names. A path name is stored as the
+/// absolute path of the socket inode.
+static UNIX_TABLE: Lazy<SpinNoIrqLock<BTreeMap<UnixAddr, Weak<UnixEndpoint>>>> =
+    Lazy::new(|| SpinNoIrqLock::new(BTreeMap::new()));
+
+/// Used to generate names in the abstract namespace for autobind.
+static AUTOBIND_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    Unnamed,
+    /// Path name in the file system.
+    Path(String),
+    /// Name in the abstract namespace, without the leading null byte.
+    Abstract(Vec<u8>),
+}
+
+impl UnixAddr {
+    pub fn from_sockaddr(addr: &SockAddrUn) -> Self {
+        let path = &addr.path;
+        if path[0] != 0 {
+            let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+            return Self::Path(String::from_utf8_lossy(&path[..len]).into_owned());
+        }
+        // The length of an abstract name is not kept in `SockAddr`, so trailing
+        // null bytes are not a part of the name.
+        match path.iter().rposition(|&c| c != 0) {
+            Some(last) => Self::Abstract(path[1..=last].to_vec()),
+            None => Self::Unnamed,
+        }
+    }
+
+    pub fn to_sockaddr(&self) -> SockAddr {
+        let mut unix = SockAddrUn {
+            family: SaFamily::AF_UNIX.into(),
+            path: [0; 108],
+        };
+        match self {
+            Self::Unnamed => {}
+            Self::Path(path) => {
+                let len = path.len().min(unix.path.len() - 1);
+                unix.path[..len].copy_from_slice(&path.as_bytes()[..len]);
+            }
+            Self::Abstract(name) => {
+                let len = name.len().min(unix.path.len() - 1);
+                unix.path[1..=len].copy_from_slice(&name[..len]);
+            }
+        }
+        SockAddr { unix }
+    }
+}
+
+/// `struct ucred`, credentials of the sending process.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct UCred {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Ancillary data passed along with a message.
+#[derive(Default)]
+pub struct UnixAncillary {
+    /// Files passed by `SCM_RIGHTS`, which are installed in the fd table of
+    /// the receiver.
+    pub files: Vec<Arc<dyn File>>,
+    /// Credentials passed by `SCM_CREDENTIALS`.
+    pub cred: Option<UCred>,
+}
+
+impl UnixAncillary {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.cred.is_none()
+    }
+}
+
+/// A message in the receive queue. Data of stream sockets may be received
+/// across messages, but not across ancillary data.
+struct UnixMessage {
+    data: Vec<u8>,
+    /// Address of the sender.
+    from: UnixAddr,
+    ancillary: UnixAncillary,
+}
+
+enum UnixState {
+    Unconnected,
+    Listening {
+        /// Server side endpoints of the connections not yet accepted.
+        backlog: VecDeque<Arc<UnixEndpoint>>,
+        max: usize,
+    },
+    /// For datagram sockets, the peer is the default destination.
+    Connected(Weak<UnixEndpoint>),
+}
+
+/// Shared part of a socket that peers refer to.
+struct UnixEndpoint {
+    types: SocketType,
+    inner: SpinNoIrqLock<UnixEndpointInner>,
+}
+
+struct UnixEndpointInner {
+    state: UnixState,
+    /// Address the socket is bound to, as given to bind(2).
+    addr: UnixAddr,
+    /// Key of the socket in `UNIX_TABLE`.
+    name: Option<UnixAddr>,
+    peer_addr: UnixAddr,
+    recv_queue: VecDeque<UnixMessage>,
+    recv_bytes: usize,
+    /// No more data can be received, either shut down for reading or the peer
+    /// stopped sending.
+    recv_shutdown: bool,
+    /// No more data can be sent.
+    send_shutdown: bool,
+    /// Wakers of tasks waiting for any change of the endpoint.
+    wakers: VecDeque<Waker>,
+}
+
+impl UnixEndpoint {
+    fn new(types: SocketType) -> Arc<Self> {
+        Arc::new(Self {
+            types,
+            inner: SpinNoIrqLock::new(UnixEndpointInner {
+                state: UnixState::Unconnected,
+                addr: UnixAddr::Unnamed,
+                name: None,
+                peer_addr: UnixAddr::Unnamed,
+                recv_queue: VecDeque::new(),
+                recv_bytes: 0,
+                recv_shutdown: false,
+                send_shutdown: false,
+                wakers: VecDeque::new(),
+            }),
+        })
+    }
+
+    fn is_connection_oriented(&self) -> bool {
+        self.types != SocketType::DGRAM
+    }
+
+    /// Put a message into the receive queue of this endpoint, return the
+    /// number of bytes queued. The ancillary data is taken if the message is
+    /// queued.
+    fn enqueue(
+        &self,
+        buf: &[u8],
+        from: &UnixAddr,
+        ancillary: &mut UnixAncillary,
+        waker: &Waker,
+    ) -> SysResult<usize> {
+        let mut inner = self.inner.lock();
+        if inner.recv_shutdown {
+            return Err(SysError::EPIPE);
+        }
+        let space = UNIX_BUF_SIZE - inner.recv_bytes;
+        let len = if self.types == SocketType::STREAM {
+            buf.len().min(space)
+        } else if buf.len() > UNIX_BUF_SIZE {
+            return Err(SysError::EMSGSIZE);
+        } else if buf.len() <= space {
+            buf.len()
+        } else {
+            0
+        };
+        if len == 0 && !buf.is_empty() {
+            inner.add_waker(waker);
+            return Err(SysError::EAGAIN);
+        }
+        inner.recv_queue.push_back(UnixMessage {
+            data: buf[..len].to_vec(),
+            from: from.clone(),
+            ancillary: mem::take(ancillary),
+        });
+        inner.recv_bytes += len;
+        inner.wake_all();
+        Ok(len)
+    }
+
+    /// Stop receiving data and wake up the tasks waiting on the endpoint.
+    fn shutdown_recv(&self) {
+        let mut inner = self.inner.lock();
+        inner.recv_shutdown = true;
+        inner.wake_all();
+    }
+
+    /// Called when the last reference from a file is dropped. The peer of a
+    /// connection oriented socket sees the end of file.
+    fn close(self: &Arc<Self>) {
+        let mut inner = self.inner.lock();
+        let state = mem::replace(&mut inner.state, UnixState::Unconnected);
+        inner.recv_shutdown = true;
+        inner.send_shutdown = true;
+        inner.recv_queue.clear();
+        inner.recv_bytes = 0;
+        inner.wake_all();
+        let name = inner.name.take();
+        drop(inner);
+
+        if let Some(name) = name {
+            let mut table = UNIX_TABLE.lock();
+            if table
+                .get(&name)
+                .is_some_and(|ep| ep.as_ptr() == Arc::as_ptr(self))
+            {
+                table.remove(&name);
+            }
+        }
+        match state {
+            UnixState::Connected(peer) if self.is_connection_oriented() => {
+                if let Some(peer) = peer.upgrade() {
+                    let mut peer_inner = peer.inner.lock();
+                    peer_inner.recv_shutdown = true;
+                    peer_inner.send_shutdown = true;
+                    peer_inner.wake_all();
+                }
+            }
+            UnixState::Listening { backlog, .. } => {
+                for endpoint in backlog {
+                    endpoint.close();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl UnixEndpointInner {
+    fn state_peer(&self) -> Option<Weak<UnixEndpoint>> {
+        match &self.state {
+            UnixState::Connected(peer) => Some(peer.clone()),
+            _ => None,
+        }
+    }
+
+    /// Register `waker` unless it is registered already, which happens when
+    /// a task polls the endpoint repeatedly without being woken.
+    fn add_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push_back(waker.clone());
+        }
+    }
+
+    fn wake_all(&mut self) {
+        while let Some(waker) = self.wakers.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+pub struct UnixSocket {
+    endpoint: Arc<UnixEndpoint>,
+    nonblock: AtomicBool,
+}
+
+impl UnixSocket {
+    pub fn new(types: SocketType) -> Self {
+        Self::from_endpoint(UnixEndpoint::new(types))
+    }
+
+    fn from_endpoint(endpoint: Arc<UnixEndpoint>) -> Self {
+        Self {
+            endpoint,
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a pair of connected sockets.
+    pub fn new_pair(types: SocketType) -> (Self, Self) {
+        let (a, b) = (UnixEndpoint::new(types), UnixEndpoint::new(types));
+        a.inner.lock().state = UnixState::Connected(Arc::downgrade(&b));
+        b.inner.lock().state = UnixState::Connected(Arc::downgrade(&a));
+        (Self::from_endpoint(a), Self::from_endpoint(b))
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Relaxed)
+    }
+
+    /// Look up the endpoint bound to `addr`.
+    fn lookup(addr: &UnixAddr) -> SysResult<Arc<UnixEndpoint>> {
+        let name = match addr {
+            UnixAddr::Unnamed => return Err(SysError::EINVAL),
+            UnixAddr::Path(path) => {
+                let dentry = current_task().resolve_path(path)?;
+                if dentry.inode()?.itype() != InodeType::Socket {
+                    return Err(SysError::ECONNREFUSED);
+                }
+                UnixAddr::Path(dentry.path())
+            }
+            UnixAddr::Abstract(_) => addr.clone(),
+        };
+        UNIX_TABLE
+            .lock()
+            .get(&name)
+            .and_then(|endpoint| endpoint.upgrade())
+            .ok_or(SysError::ECONNREFUSED)
+    }
+
+    pub fn bind(&self, addr: SockAddr) -> SysResult<()> {
+        let addr = UnixAddr::from_sockaddr(unsafe { &addr.unix });
+        if self.endpoint.inner.lock().addr != UnixAddr::Unnamed {
+            return Err(SysError::EINVAL);
+        }
+        let (addr, name) = match addr {
+            // Autobind to a name in the abstract namespace.
+            UnixAddr::Unnamed => {
+                let id = AUTOBIND_ID.fetch_add(1, Ordering::Relaxed);
+                let addr = UnixAddr::Abstract(format!("{:05x}", id & 0xfffff).into_bytes());
+                (addr.clone(), addr)
+            }
+            UnixAddr::Path(ref path) => {
+                let dentry = current_task().resolve_path_nofollow(path)?;
+                if !dentry.is_negetive() {
+                    return Err(SysError::EADDRINUSE);
+                }
+                let parent = dentry.parent().ok_or(SysError::EADDRINUSE)?;
+                parent.create(
+                    dentry.name(),
+                    InodeMode::SOCKET
+                        | InodeMode::OWNER_MASK
+                        | InodeMode::GROUP_MASK
+                        | InodeMode::OTHER_MASK,
+                )?;
+                let name = UnixAddr::Path(dentry.path());
+                (addr, name)
+            }
+            UnixAddr::Abstract(_) => (addr.clone(), addr),
+        };
+        let mut table = UNIX_TABLE.lock();
+        // A path name is free as long as the socket inode does not exist, while
+        // an abstract name is free once the socket is closed.
+        if matches!(name, UnixAddr::Abstract(_))
+            && table.get(&name).is_some_and(|ep| ep.strong_count() > 0)
+        {
+            return Err(SysError::EADDRINUSE);
+        }
+        table.insert(name.clone(), Arc::downgrade(&self.endpoint));
+        drop(table);
+        log::info!("[UnixSocket::bind] bind to {addr:?}");
+        let mut inner = self.endpoint.inner.lock();
+        inner.addr = addr;
+        inner.name = Some(name);
+        Ok(())
+    }
+
+    pub fn listen(&self, backlog: usize) -> SysResult<()> {
+        if !self.endpoint.is_connection_oriented() {
+            return Err(SysError::EOPNOTSUPP);
+        }
+        let mut inner = self.endpoint.inner.lock();
+        if inner.addr == UnixAddr::Unnamed {
+            return Err(SysError::EINVAL);
+        }
+        let backlog = backlog.clamp(1, SOMAXCONN);
+        match &mut inner.state {
+            UnixState::Unconnected => {
+                inner.state = UnixState::Listening {
+                    backlog: VecDeque::new(),
+                    max: backlog,
+                }
+            }
+            UnixState::Listening { max, .. } => *max = backlog,
+            UnixState::Connected(_) => return Err(SysError::EINVAL),
+        }
+        Ok(())
+    }
+
+    pub async fn accept(&self) -> SysResult<UnixSocket> {
+        let endpoint = self
+            .block_on(false, |waker| {
+                let mut inner = self.endpoint.inner.lock();
+                let UnixState::Listening { backlog, .. } = &mut inner.state else {
+                    return Err(SysError::EINVAL);
+                };
+                match backlog.pop_front() {
+                    Some(endpoint) => {
+                        // Wake up the tasks waiting for room in the backlog.
+                        inner.wake_all();
+                        Ok(endpoint)
+                    }
+                    None => {
+                        inner.add_waker(waker);
+                        Err(SysError::EAGAIN)
+                    }
+                }
+            })
+            .await?;
+        Ok(Self::from_endpoint(endpoint))
+    }
+
+    pub async fn connect(&self, addr: SockAddr) -> SysResult<()> {
+        let addr = UnixAddr::from_sockaddr(unsafe { &addr.unix });
+        let target = Self::lookup(&addr)?;
+        if target.types != self.endpoint.types {
+            return Err(SysError::EPROTOTYPE);
+        }
+        if !self.endpoint.is_connection_oriented() {
+            let peer_addr = target.inner.lock().addr.clone();
+            let mut inner = self.endpoint.inner.lock();
+            inner.state = UnixState::Connected(Arc::downgrade(&target));
+            inner.peer_addr = peer_addr;
+            return Ok(());
+        }
+
+        match self.endpoint.inner.lock().state {
+            UnixState::Unconnected => {}
+            UnixState::Listening { .. } => return Err(SysError::EINVAL),
+            UnixState::Connected(_) => return Err(SysError::EISCONN),
+        }
+        let local_addr = self.endpoint.inner.lock().addr.clone();
+        let server = UnixEndpoint::new(self.endpoint.types);
+        self.block_on(false, |waker| {
+            let mut target_inner = target.inner.lock();
+            let target_addr = target_inner.addr.clone();
+            let UnixState::Listening { backlog, max } = &mut target_inner.state else {
+                return Err(SysError::ECONNREFUSED);
+            };
+            if backlog.len() >= *max {
+                target_inner.add_waker(waker);
+                return Err(SysError::EAGAIN);
+            }
+            {
+                let mut server_inner = server.inner.lock();
+                server_inner.state = UnixState::Connected(Arc::downgrade(&self.endpoint));
+                server_inner.addr = target_addr.clone();
+                server_inner.peer_addr = local_addr.clone();
+            }
+            backlog.push_back(server.clone());
+            target_inner.wake_all();
+            drop(target_inner);
+
+            let mut inner = self.endpoint.inner.lock();
+            inner.state = UnixState::Connected(Arc::downgrade(&server));
+            inner.peer_addr = target_addr;
+            Ok(())
+        })
+        .await
+    }
+
+    pub fn local_addr(&self) -> SockAddr {
+        self.endpoint.inner.lock().addr.to_sockaddr()
+    }
+
+    pub fn peer_addr(&self) -> SysResult<SockAddr> {
+        let inner = self.endpoint.inner.lock();
+        match inner.state {
+            UnixState::Connected(_) => Ok(inner.peer_addr.to_sockaddr()),
+            _ => Err(SysError::ENOTCONN),
+        }
+    }
+
+    /// Send the message with the ancillary data, which is received along with
+    /// the first byte of the message.
+    pub async fn sendmsg(
+        &self,
+        buf: &[u8],
+        addr: Option<SockAddr>,
+        mut ancillary: UnixAncillary,
+        nonblock: bool,
+    ) -> SysResult<usize> {
+        let (peer, from) = {
+            let inner = self.endpoint.inner.lock();
+            if inner.send_shutdown {
+                return Err(SysError::EPIPE);
+            }
+            (inner.state_peer(), inner.addr.clone())
+        };
+        let peer = match addr {
+            Some(addr) if !self.endpoint.is_connection_oriented() => {
+                let target = Self::lookup(&UnixAddr::from_sockaddr(unsafe { &addr.unix }))?;
+                if target.types != self.endpoint.types {
+                    return Err(SysError::EPROTOTYPE);
+                }
+                target
+            }
+            _ => match peer {
+                Some(peer) => peer
+                    .upgrade()
+                    .ok_or(if self.endpoint.is_connection_oriented() {
+                        SysError::EPIPE
+                    } else {
+                        SysError::ECONNREFUSED
+                    })?,
+                None => return Err(SysError::ENOTCONN),
+            },
+        };
+        if self.endpoint.types != SocketType::STREAM {
+            return self
+                .block_on(nonblock, |waker| {
+                    peer.enqueue(buf, &from, &mut ancillary, waker)
+                })
+                .await;
+        }
+        // Block until all the data is sent for a stream socket.
+        let mut sent = 0;
+        while sent < buf.len() {
+            match self
+                .block_on(nonblock, |waker| {
+                    peer.enqueue(&buf[sent..], &from, &mut ancillary, waker)
+                })
+                .await
+            {
+                Ok(len) => sent += len,
+                Err(_) if sent > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Receive a message and the ancillary data sent with it.
+    pub async fn recvmsg(
+        &self,
+        buf: &mut [u8],
+        nonblock: bool,
+    ) -> SysResult<(usize, SockAddr, UnixAncillary)> {
+        let endpoint = &self.endpoint;
+        let (len, from, ancillary) = self
+            .block_on(nonblock, |waker| {
+                let mut inner = endpoint.inner.lock();
+                if inner.recv_queue.is_empty() {
+                    if inner.recv_shutdown {
+                        return Ok((0, inner.peer_addr.clone(), UnixAncillary::default()));
+                    }
+                    if endpoint.is_connection_oriented()
+                        && !matches!(inner.state, UnixState::Connected(_))
+                    {
+                        return Err(SysError::ENOTCONN);
+                    }
+                    inner.add_waker(waker);
+                    return Err(SysError::EAGAIN);
+                }
+                let mut len = 0;
+                let front = inner.recv_queue.front_mut().unwrap();
+                let from = front.from.clone();
+                let ancillary = mem::take(&mut front.ancillary);
+                if endpoint.types == SocketType::STREAM {
+                    while len < buf.len() {
+                        let Some(msg) = inner.recv_queue.front_mut() else {
+                            break;
+                        };
+                        // Do not mix the data sent with different ancillary
+                        // data.
+                        if len > 0 && !msg.ancillary.is_empty() {
+                            break;
+                        }
+                        let n = msg.data.len().min(buf.len() - len);
+                        buf[len..len + n].copy_from_slice(&msg.data[..n]);
+                        msg.data.drain(..n);
+                        if msg.data.is_empty() {
+                            inner.recv_queue.pop_front();
+                        }
+                        inner.recv_bytes -= n;
+                        len += n;
+                    }
+                } else {
+                    // The rest of a message that does not fit in the buffer is
+                    // discarded.
+                    let msg = inner.recv_queue.pop_front().unwrap();
+                    len = msg.data.len().min(buf.len());
+                    buf[..len].copy_from_slice(&msg.data[..len]);
+                    inner.recv_bytes -= msg.data.len();
+                }
+                inner.wake_all();
+                Ok((len, from, ancillary))
+            })
+            .await?;
+        Ok((len, from.to_sockaddr(), ancillary))
+    }
+
+    pub fn shutdown(&self, how: u8) -> SysResult<()> {
+        let (recv, send) = match how {
+            SHUT_RD => (true, false),
+            SHUT_WR => (false, true),
+            SHUT_RDWR => (true, true),
+            _ => return Err(SysError::EINVAL),
+        };
+        let peer = {
+            let mut inner = self.endpoint.inner.lock();
+            if self.endpoint.is_connection_oriented()
+                && !matches!(inner.state, UnixState::Connected(_))
+            {
+                return Err(SysError::ENOTCONN);
+            }
+            inner.recv_shutdown |= recv;
+            inner.send_shutdown |= send;
+            inner.wake_all();
+            inner.state_peer()
+        };
+        if send && self.endpoint.is_connection_oriented() {
+            if let Some(peer) = peer.and_then(|peer| peer.upgrade()) {
+                peer.shutdown_recv();
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn poll(&self) -> NetPollState {
+        let waker = get_waker().await;
+        let endpoint = &self.endpoint;
+        let mut inner = endpoint.inner.lock();
+        let readable = !inner.recv_queue.is_empty()
+            || inner.recv_shutdown
+            || matches!(&inner.state, UnixState::Listening { backlog, .. } if !backlog.is_empty());
+        let hangup = inner.recv_shutdown && inner.send_shutdown;
+        let peer = inner.state_peer();
+        if !readable {
+            inner.add_waker(&waker);
+        }
+        drop(inner);
+        let writable = match peer.map(|peer| peer.upgrade()) {
+            Some(Some(peer)) => {
+                let mut peer_inner = peer.inner.lock();
+                let writable = peer_inner.recv_bytes < UNIX_BUF_SIZE || peer_inner.recv_shutdown;
+                if !writable {
+                    peer_inner.add_waker(&waker);
+                }
+                writable
+            }
+            // Sending fails immediately.
+            Some(None) => true,
+            None => !endpoint.is_connection_oriented(),
+        };
+        NetPollState {
+            readable,
+            writable,
+            hangup,
+        }
+    }
+
+    /// Call `f` until it does not return `EAGAIN` unless the socket or this
+    /// call is nonblocking, `f` should register the waker before returning
+    /// `EAGAIN`.
+    async fn block_on<F, T>(&self, nonblock: bool, mut f: F) -> SysResult<T>
+    where
+        F: FnMut(&Waker) -> SysResult<T>,
+    {
+        let waker = get_waker().await;
+        if nonblock || self.is_nonblocking() {
+            return f(&waker);
+        }
+        loop {
+            match f(&waker) {
+                Err(SysError::EAGAIN) => {
+                    suspend_now().await;
+                    let task = current_task_ref();
+                    let mask = *task.sig_mask_ref();
+                    if task.with_sig_pending(|pending| pending.has_expect_signals(!mask)) {
+                        log::warn!("[UnixSocket::block_on] has signal");
+                        return Err(SysError::EINTR);
+                    }
+                }
+                ret => return ret,
+            }
+        }
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        self.endpoint.close();
+    }
+}
@@ -99,6 +99,29 @@ impl Hart {
         unsafe { enable_interrupt() };
     }
 
+    /// Switch to the address space of `task` to run a kernel future on behalf
+    /// of it, e.g. an io_uring request. Unlike `enter_user_task_switch`, the
+    /// time statistics and the fp registers of `task` are left untouched
+    /// since its user code is not run.
+    pub fn enter_user_io_switch(&mut self, task: &Arc<Task>, env: &mut EnvContext) {
+        debug_assert!(self.task.is_none());
+        unsafe { disable_interrupt() };
+        unsafe { env.auto_sum() };
+        self.set_task(Arc::clone(task));
+        core::mem::swap(self.env_mut(), env);
+        unsafe { task.switch_page_table() };
+        unsafe { enable_interrupt() };
+    }
+
+    pub fn leave_user_io_switch(&mut self, env: &mut EnvContext) {
+        unsafe { disable_interrupt() };
+        unsafe { env.auto_sum() };
+        unsafe { mm::switch_kernel_page_table() };
+        core::mem::swap(self.env_mut(), env);
+        self.clear_task();
+        unsafe { enable_interrupt() };
+    }
+
     pub fn kernel_task_switch(&mut self, env: &mut EnvContext) {
         unsafe { disable_interrupt() };
         self.change_env(env);
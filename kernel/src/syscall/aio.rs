@@ -0,0 +1,195 @@
+use async_utils::{Select2Futures, SelectOutput};
+use config::mm::PAGE_SIZE;
+use memory::VirtAddr;
+use systype::{SysError, SyscallResult};
+use time::timespec::TimeSpec;
+use timer::timelimited_task::TimeLimitedTaskFuture;
+
+use super::{MmapFlags, Syscall};
+use crate::{
+    aio::{AioContext, AioDrainFuture, AioEventsFuture, IoEvent, AIO_MAX_NR},
+    mm::{memory_space::vm_area::MapPerm, UserRdWrPtr, UserReadPtr, UserWritePtr},
+    task::signal::IntrBySignalFuture,
+};
+
+impl Syscall<'_> {
+    /// The io_setup() system call creates an asynchronous I/O context suitable
+    /// for concurrently processing `nr_events` operations. The `ctx_idp`
+    /// argument must not point to an AIO context that already exists, and must
+    /// be initialized to 0 prior to the call. On successful creation of the
+    /// AIO context, `*ctx_idp` is filled in with the resulting handle.
+    pub fn sys_io_setup(&self, nr_events: u32, ctx_idp: UserRdWrPtr<usize>) -> SyscallResult {
+        let task = self.task;
+        let mut ctx_id = ctx_idp.into_mut(task)?;
+        if *ctx_id != 0 || nr_events == 0 {
+            return Err(SysError::EINVAL);
+        }
+        if nr_events > AIO_MAX_NR {
+            return Err(SysError::EAGAIN);
+        }
+        // The context is identified by the address of its ring in user space
+        // as Linux does, which libaio peeks at. The ring is left zeroed so
+        // that its magic never matches and libaio always falls back to
+        // `io_getevents`.
+        let ring = task.with_mut_memory_space(|m| {
+            m.alloc_mmap_anonymous(
+                VirtAddr::from(0),
+                PAGE_SIZE,
+                MapPerm::U | MapPerm::RW,
+                MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS,
+            )
+        })?;
+        let ctx = AioContext::new(ring.bits(), nr_events);
+        log::info!(
+            "[sys_io_setup] nr_events:{nr_events}, ctx_id:{:#x}",
+            ctx.id()
+        );
+        *ctx_id = ctx.id();
+        task.with_mut_aio_ctxs(|ctxs| ctxs.insert(ctx.id(), ctx));
+        Ok(0)
+    }
+
+    /// The io_destroy() system call will attempt to cancel all outstanding
+    /// asynchronous I/O operations against `ctx_id`, will block on the
+    /// completion of all operations that could not be canceled, and will
+    /// destroy the `ctx_id`.
+    pub async fn sys_io_destroy(&self, ctx_id: usize) -> SyscallResult {
+        let task = self.task;
+        let ctx = task
+            .with_mut_aio_ctxs(|ctxs| ctxs.remove(&ctx_id))
+            .ok_or(SysError::EINVAL)?;
+        ctx.cancel_all(None);
+        let ring = VirtAddr::from(ctx.id());
+        task.with_mut_memory_space(|m| m.unmap(ring..ring + PAGE_SIZE))?;
+        AioDrainFuture::new(ctx, None).await;
+        Ok(0)
+    }
+
+    /// The io_submit() system call queues `nr` I/O request blocks for
+    /// processing in the AIO context `ctx_id`. The `iocbpp` argument should
+    /// be an array of `nr` AIO control blocks, which will be submitted to
+    /// context `ctx_id`.
+    ///
+    /// On success, io_submit() returns the number of iocbs submitted (which
+    /// may be less than `nr`, or 0 if `nr` is zero).
+    pub fn sys_io_submit(
+        &self,
+        ctx_id: usize,
+        nr: isize,
+        iocbpp: UserReadPtr<usize>,
+    ) -> SyscallResult {
+        let task = self.task;
+        if nr < 0 {
+            return Err(SysError::EINVAL);
+        }
+        let ctx = task
+            .with_aio_ctxs(|ctxs| ctxs.get(&ctx_id).cloned())
+            .ok_or(SysError::EINVAL)?;
+        let iocbs = iocbpp.read_array(task, nr as usize)?;
+        for (i, &iocb) in iocbs.iter().enumerate() {
+            // The error of the first iocb is returned, otherwise the number of
+            // iocbs submitted.
+            if let Err(e) = ctx.submit(task, iocb) {
+                log::info!("[sys_io_submit] iocb #{i} {iocb:#x} fails with {e:?}");
+                return if i == 0 { Err(e) } else { Ok(i) };
+            }
+        }
+        Ok(iocbs.len())
+    }
+
+    /// The io_cancel() system call attempts to cancel an asynchronous I/O
+    /// operation previously submitted using io_submit(). Only poll requests
+    /// can be canceled, whose events are still delivered to the context.
+    pub fn sys_io_cancel(
+        &self,
+        ctx_id: usize,
+        iocb: usize,
+        _result: UserWritePtr<IoEvent>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let ctx = task
+            .with_aio_ctxs(|ctxs| ctxs.get(&ctx_id).cloned())
+            .ok_or(SysError::EINVAL)?;
+        ctx.cancel(iocb)?;
+        // The result is always delivered through the context, and
+        // `EINPROGRESS` indicates the cancellation is in progress.
+        Err(SysError::EINPROGRESS)
+    }
+
+    /// The io_getevents() system call attempts to read at least `min_nr`
+    /// events and up to `nr` events from the completion queue of the AIO
+    /// context specified by `ctx_id`.
+    ///
+    /// The `timeout` argument specifies the amount of time to wait for events,
+    /// and is specified as a relative timeout. If `timeout` is NULL, then
+    /// io_getevents() blocks until at least `min_nr` events have been seen.
+    pub async fn sys_io_getevents(
+        &self,
+        ctx_id: usize,
+        min_nr: isize,
+        nr: isize,
+        events: UserWritePtr<IoEvent>,
+        timeout: UserReadPtr<TimeSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        if min_nr < 0 || nr < 0 || min_nr > nr {
+            return Err(SysError::EINVAL);
+        }
+        let ctx = task
+            .with_aio_ctxs(|ctxs| ctxs.get(&ctx_id).cloned())
+            .ok_or(SysError::EINVAL)?;
+        let timeout = if timeout.is_null() {
+            None
+        } else {
+            let timeout = timeout.read(task)?;
+            if !timeout.is_valid() {
+                return Err(SysError::EINVAL);
+            }
+            Some(timeout.into())
+        };
+        log::info!(
+            "[sys_io_getevents] ctx_id:{ctx_id:#x}, min_nr:{min_nr}, nr:{nr}, timeout:{timeout:?}"
+        );
+
+        let mut interrupted = false;
+        if min_nr > 0 {
+            task.set_interruptable();
+            task.set_wake_up_signal(!*task.sig_mask_ref());
+            let intr_future = IntrBySignalFuture {
+                task: task.clone(),
+                mask: *task.sig_mask_ref(),
+            };
+            let events_future = AioEventsFuture::new(ctx.clone(), min_nr as usize);
+            interrupted = match timeout {
+                Some(timeout) => {
+                    match Select2Futures::new(
+                        TimeLimitedTaskFuture::new(timeout, events_future),
+                        intr_future,
+                    )
+                    .await
+                    {
+                        // Events ready are reaped even if it times out.
+                        SelectOutput::Output1(_) => false,
+                        SelectOutput::Output2(_) => true,
+                    }
+                }
+                None => matches!(
+                    Select2Futures::new(events_future, intr_future).await,
+                    SelectOutput::Output2(_)
+                ),
+            };
+            task.set_running();
+        }
+
+        let reaped = ctx.reap(nr as usize);
+        if reaped.is_empty() && interrupted {
+            return Err(SysError::EINTR);
+        }
+        // Events are not lost if they can not be copied to the user.
+        if let Err(e) = events.write_array(task, &reaped) {
+            ctx.unreap(reaped);
+            return Err(e);
+        }
+        Ok(reaped.len())
+    }
+}
@@ -285,6 +285,11 @@ pub enum SyscallNo {
     PKEY_MPROTECT = 288,
     PKEY_ALLOC = 289,
     PKEY_FREE = 290,
+    PIDFD_SEND_SIGNAL = 424,
+    IO_URING_SETUP = 425,
+    IO_URING_ENTER = 426,
+    IO_URING_REGISTER = 427,
+    PIDFD_OPEN = 434,
 }
 
 impl core::fmt::Display for SyscallNo {
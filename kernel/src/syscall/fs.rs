@@ -1,7 +1,8 @@
-use alloc::{ffi::CString, sync::Arc, vec, vec::Vec};
+use alloc::{ffi::CString, sync::Arc, vec};
 use core::{
     cmp, default,
     ops::{Deref, DerefMut},
+    ptr,
 };
 
 use arch::time::get_time_duration;
@@ -9,12 +10,23 @@ use async_utils::{Select2Futures, SelectOutput};
 use config::{board::BLOCK_SIZE, fs::PIPE_BUF_LEN};
 use driver::BLOCK_DEVICE;
 use strum::FromRepr;
-use systype::{SysError, SyscallResult};
+use systype::{SysError, SysResult, SyscallResult};
 use time::timespec::TimeSpec;
-use vfs::{fd_table::FdFlags, pipefs::new_pipe, simplefs::dentry, sys_root_dentry, FS_MANAGER};
+use vfs::{
+    all_super_blocks,
+    eventfd::{EventFdFile, EventFdFlags},
+    fd_table::FdFlags,
+    memfd::{MemfdFile, MemfdFlags, MemfdInode, SealFlags, MFD_NAME_MAX_LEN},
+    pipefs::new_pipe,
+    simplefs::dentry,
+    sys_root_dentry, FS_MANAGER,
+};
 use vfs_core::{
-    is_absolute_path, split_parent_and_name, AtFd, Dentry, Inode, InodeMode, InodeType, MountFlags,
-    OpenFlags, Path, RenameFlags, SeekFrom, Stat, StatFs, AT_REMOVEDIR, AT_SYMLINK_FOLLOW,
+    inotify::{InotifyFile, InotifyFlags, InotifyMask},
+    is_absolute_path,
+    lock::{release_posix_locks, FileLock, FileLockFuture, FileLockType, LockOwner},
+    split_parent_and_name, AtFd, Dentry, File, Inode, InodeMode, InodeType, MountFlags, OpenFlags,
+    Path, RenameFlags, SeekFrom, Stat, StatFs, AT_REMOVEDIR, AT_SYMLINK_FOLLOW,
     AT_SYMLINK_NOFOLLOW,
 };
 
@@ -43,10 +55,100 @@ pub enum FcntlOp {
     F_SETFD = 2,
     F_GETFL = 3,
     F_SETFL = 4,
+    F_GETLK = 5,
+    F_SETLK = 6,
+    F_SETLKW = 7,
+    F_OFD_GETLK = 36,
+    F_OFD_SETLK = 37,
+    F_OFD_SETLKW = 38,
+    F_ADD_SEALS = 1033,
+    F_GET_SEALS = 1034,
     #[default]
     F_UNIMPL,
 }
 
+// Types of record locks, defined in <bits/fcntl-linux.h>.
+const F_RDLCK: i16 = 0;
+const F_WRLCK: i16 = 1;
+const F_UNLCK: i16 = 2;
+
+// Operations of flock(2), defined in <sys/file.h>.
+const LOCK_SH: i32 = 1;
+const LOCK_EX: i32 = 2;
+const LOCK_NB: i32 = 4;
+const LOCK_UN: i32 = 8;
+
+// Defined in <bits/fcntl-linux.h>
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Flock {
+    /// Type of lock: F_RDLCK, F_WRLCK, or F_UNLCK.
+    pub l_type: i16,
+    /// How to interpret `l_start`: SEEK_SET, SEEK_CUR, or SEEK_END.
+    pub l_whence: i16,
+    /// Starting offset for lock.
+    pub l_start: i64,
+    /// Number of bytes to lock, where 0 means until the end of file.
+    pub l_len: i64,
+    /// PID of process blocking our lock (set by F_GETLK and F_OFD_GETLK).
+    pub l_pid: i32,
+}
+
+impl Flock {
+    /// Convert to a lock of `owner` on `file`.
+    fn to_file_lock(&self, file: &Arc<dyn File>, owner: LockOwner) -> SysResult<FileLock> {
+        let ltype = match self.l_type {
+            F_RDLCK => FileLockType::Read,
+            F_WRLCK => FileLockType::Write,
+            F_UNLCK => FileLockType::Unlock,
+            _ => return Err(SysError::EINVAL),
+        };
+        let base = match self.l_whence {
+            0 => 0,
+            1 => file.pos() as i64,
+            2 => file.size() as i64,
+            _ => return Err(SysError::EINVAL),
+        };
+        let start = base.checked_add(self.l_start).ok_or(SysError::EINVAL)?;
+        // A negative `l_len` locks the bytes before `start`.
+        let (start, end) = match self.l_len {
+            0 => (start, None),
+            len if len > 0 => (
+                start,
+                Some(start.checked_add(len - 1).ok_or(SysError::EINVAL)?),
+            ),
+            len => (start + len, Some(start - 1)),
+        };
+        if start < 0 {
+            return Err(SysError::EINVAL);
+        }
+        let end = end.map_or(usize::MAX, |end| end as usize);
+        Ok(FileLock::new_record(owner, ltype, start as usize, end))
+    }
+
+    /// Describe the conflicting `lock`, as returned by F_GETLK.
+    fn from_file_lock(lock: &FileLock) -> Self {
+        Self {
+            l_type: match lock.ltype {
+                FileLockType::Read => F_RDLCK,
+                _ => F_WRLCK,
+            },
+            l_whence: 0,
+            l_start: lock.start as i64,
+            l_len: if lock.end == usize::MAX {
+                0
+            } else {
+                (lock.end - lock.start + 1) as i64
+            },
+            l_pid: match lock.owner {
+                LockOwner::Process(pid) => pid as i32,
+                // OFD locks are not owned by a process.
+                LockOwner::File(_) => -1,
+            },
+        }
+    }
+}
+
 // Defined in <bits/struct_stat.h>
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
@@ -253,7 +355,12 @@ impl Syscall<'_> {
     /// set to indicate the error.
     pub fn sys_close(&self, fd: usize) -> SyscallResult {
         let task = self.task;
-        task.with_mut_fd_table(|table| table.remove(fd))?;
+        let file = task.with_mut_fd_table(|table| {
+            let file = table.get_file(fd)?;
+            table.remove(fd)?;
+            Ok(file)
+        })?;
+        release_posix_locks(task.pid(), &file.inode());
         Ok(0)
     }
 
@@ -390,7 +497,13 @@ impl Syscall<'_> {
         if oldfd == newfd {
             return Err(SysError::EINVAL);
         }
-        task.with_mut_fd_table(|table| table.dup3(oldfd, newfd, flags))
+        let old_file = task.with_fd_table(|table| table.get_file(newfd)).ok();
+        let ret = task.with_mut_fd_table(|table| table.dup3(oldfd, newfd, flags))?;
+        // `newfd` is closed silently.
+        if let Some(old_file) = old_file {
+            release_posix_locks(task.pid(), &old_file.inode());
+        }
+        Ok(ret)
     }
 
     pub fn sys_fstat(&self, fd: usize, stat_buf: UserWritePtr<Kstat>) -> SyscallResult {
@@ -522,6 +635,137 @@ impl Syscall<'_> {
         Ok(0)
     }
 
+    /// eventfd() creates an "eventfd object" that can be used as an event
+    /// wait/notify mechanism by user-space applications, and by the kernel to
+    /// notify user-space applications of events. The object contains an
+    /// unsigned 64-bit integer counter that is maintained by the kernel. This
+    /// counter is initialized with the value specified in the argument
+    /// initval.
+    ///
+    /// The following values may be bitwise ORed in flags:
+    /// + EFD_CLOEXEC: Set the close-on-exec (FD_CLOEXEC) flag on the new file
+    ///   descriptor.
+    /// + EFD_NONBLOCK: Set the O_NONBLOCK file status flag on the open file
+    ///   description.
+    /// + EFD_SEMAPHORE: Provide semaphore-like semantics for reads from the new
+    ///   file descriptor.
+    pub fn sys_eventfd2(&self, initval: u32, flags: i32) -> SyscallResult {
+        let task = self.task;
+        let flags = EventFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let eventfd = EventFdFile::new(initval as u64, flags);
+        let fd_flags = if flags.contains(EventFdFlags::CLOEXEC) {
+            OpenFlags::O_CLOEXEC
+        } else {
+            OpenFlags::empty()
+        };
+        let fd = task.with_mut_fd_table(|table| table.alloc(eventfd, fd_flags))?;
+        log::info!("[sys_eventfd2] fd: {fd}, initval: {initval}, flags: {flags:?}");
+        Ok(fd)
+    }
+
+    /// memfd_create() creates an anonymous file and returns a file descriptor
+    /// that refers to it. The file behaves like a regular file, but lives in
+    /// memory only. `name` is used for debugging purposes.
+    ///
+    /// + MFD_CLOEXEC: Set the close-on-exec (FD_CLOEXEC) flag on the new file
+    ///   descriptor.
+    /// + MFD_ALLOW_SEALING: Allow sealing operations on this file.
+    pub fn sys_memfd_create(&self, name: UserReadPtr<u8>, flags: u32) -> SyscallResult {
+        let task = self.task;
+        let flags = MemfdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        if flags.contains(MemfdFlags::HUGETLB) {
+            return Err(SysError::EINVAL);
+        }
+        let name = name.read_cstr(&task)?;
+        if name.len() > MFD_NAME_MAX_LEN {
+            return Err(SysError::EINVAL);
+        }
+        let memfd = MemfdFile::new(flags);
+        let fd_flags = if flags.contains(MemfdFlags::CLOEXEC) {
+            OpenFlags::O_CLOEXEC
+        } else {
+            OpenFlags::empty()
+        };
+        let fd = task.with_mut_fd_table(|table| table.alloc(memfd, fd_flags))?;
+        log::info!("[sys_memfd_create] fd: {fd}, name: {name}, flags: {flags:?}");
+        Ok(fd)
+    }
+
+    /// inotify_init1() initializes a new inotify instance and returns a file
+    /// descriptor associated with a new inotify event queue.
+    ///
+    /// + IN_NONBLOCK: Set the O_NONBLOCK file status flag on the open file
+    ///   description.
+    /// + IN_CLOEXEC: Set the close-on-exec (FD_CLOEXEC) flag on the new file
+    ///   descriptor.
+    pub fn sys_inotify_init1(&self, flags: i32) -> SyscallResult {
+        let task = self.task;
+        let flags = InotifyFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let inotify = InotifyFile::new(flags);
+        let fd_flags = if flags.contains(InotifyFlags::CLOEXEC) {
+            OpenFlags::O_CLOEXEC
+        } else {
+            OpenFlags::empty()
+        };
+        let fd = task.with_mut_fd_table(|table| table.alloc(inotify, fd_flags))?;
+        log::info!("[sys_inotify_init1] fd: {fd}, flags: {flags:?}");
+        Ok(fd)
+    }
+
+    /// inotify_add_watch() adds a new watch, or modifies an existing watch,
+    /// for the file whose location is specified in pathname; the caller must
+    /// have read permission for this file. The fd argument is a file
+    /// descriptor referring to the inotify instance whose watch list is to be
+    /// modified. The events to be monitored for pathname are specified in the
+    /// mask bit-mask argument.
+    ///
+    /// On success, inotify_add_watch() returns a unique watch descriptor for
+    /// this inotify instance, for the filesystem object (inode) that
+    /// corresponds to pathname.
+    pub fn sys_inotify_add_watch(
+        &self,
+        fd: usize,
+        pathname: UserReadPtr<u8>,
+        mask: u32,
+    ) -> SyscallResult {
+        let task = self.task;
+        let mask = InotifyMask::from_bits_truncate(mask);
+        let pathname = pathname.read_cstr(&task)?;
+        log::info!("[sys_inotify_add_watch] fd: {fd}, path: {pathname}, mask: {mask:?}");
+        let inotify = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<InotifyFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        let flags = if mask.contains(InotifyMask::DONT_FOLLOW) {
+            OpenFlags::O_NOFOLLOW
+        } else {
+            OpenFlags::empty()
+        };
+        let inode = task.at_helper(AtFd::FdCwd, &pathname, flags)?.inode()?;
+        if mask.contains(InotifyMask::ONLYDIR) && !inode.itype().is_dir() {
+            return Err(SysError::ENOTDIR);
+        }
+        let wd = inotify.add_watch(&inode, mask)?;
+        Ok(wd as usize)
+    }
+
+    /// inotify_rm_watch() removes the watch associated with the watch
+    /// descriptor wd from the inotify instance associated with the file
+    /// descriptor fd.
+    ///
+    /// Removing a watch causes an IN_IGNORED event to be generated for this
+    /// watch descriptor.
+    pub fn sys_inotify_rm_watch(&self, fd: usize, wd: i32) -> SyscallResult {
+        let task = self.task;
+        log::info!("[sys_inotify_rm_watch] fd: {fd}, wd: {wd}");
+        let inotify = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<InotifyFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        inotify.rm_watch(wd)?;
+        Ok(0)
+    }
+
     /// unlink() deletes a name from the filesystem. If that name was the last
     /// link to a file and no processes have the file open, the file is
     /// deleted and the space it was using is made available for reuse.
@@ -567,7 +811,7 @@ impl Syscall<'_> {
     }
 
     // TODO:
-    pub fn sys_fcntl(&self, fd: usize, op: isize, arg: usize) -> SyscallResult {
+    pub async fn sys_fcntl(&self, fd: usize, op: isize, arg: usize) -> SyscallResult {
         let task = self.task;
         let op = FcntlOp::from_repr(op).unwrap_or_default();
         log::info!("[sys_fcntl] fd: {fd}, op: {op:?}, arg: {arg}");
@@ -601,6 +845,71 @@ impl Syscall<'_> {
                 file.set_flags(flags.status());
                 Ok(0)
             }
+            FcntlOp::F_GETLK | FcntlOp::F_OFD_GETLK => {
+                let file = self.get_lock_file(fd)?;
+                let mut flock = UserRdWrPtr::<Flock>::from(arg).into_mut(task)?;
+                let owner = if op == FcntlOp::F_OFD_GETLK {
+                    if flock.l_pid != 0 {
+                        return Err(SysError::EINVAL);
+                    }
+                    file.meta().lock_owner()
+                } else {
+                    LockOwner::Process(task.pid())
+                };
+                let lock = flock.to_file_lock(&file, owner)?;
+                if lock.ltype == FileLockType::Unlock {
+                    return Err(SysError::EINVAL);
+                }
+                match file.inode().meta().locks.test(&lock) {
+                    Some(conflict) => *flock = Flock::from_file_lock(&conflict),
+                    None => flock.l_type = F_UNLCK,
+                }
+                Ok(0)
+            }
+            FcntlOp::F_SETLK | FcntlOp::F_SETLKW | FcntlOp::F_OFD_SETLK | FcntlOp::F_OFD_SETLKW => {
+                let file = self.get_lock_file(fd)?;
+                let flock = UserReadPtr::<Flock>::from(arg).read(task)?;
+                let owner = if matches!(op, FcntlOp::F_OFD_SETLK | FcntlOp::F_OFD_SETLKW) {
+                    if flock.l_pid != 0 {
+                        return Err(SysError::EINVAL);
+                    }
+                    file.meta().lock_owner()
+                } else {
+                    LockOwner::Process(task.pid())
+                };
+                let lock = flock.to_file_lock(&file, owner)?;
+                // A read lock requires the file open for reading, and a write
+                // lock requires it open for writing.
+                match lock.ltype {
+                    FileLockType::Read if !file.flags().readable() => return Err(SysError::EBADF),
+                    FileLockType::Write if !file.flags().writable() => return Err(SysError::EBADF),
+                    _ => {}
+                }
+                let wait = matches!(op, FcntlOp::F_SETLKW | FcntlOp::F_OFD_SETLKW);
+                self.lock_file(file.inode(), lock, wait).await?;
+                Ok(0)
+            }
+            FcntlOp::F_ADD_SEALS => {
+                let seals = SealFlags::from_bits(arg as _).ok_or(SysError::EINVAL)?;
+                let file = task.with_fd_table(|table| table.get_file(fd))?;
+                let memfd = file
+                    .inode()
+                    .downcast_arc::<MemfdInode>()
+                    .map_err(|_| SysError::EINVAL)?;
+                if !file.flags().writable() {
+                    return Err(SysError::EPERM);
+                }
+                memfd.add_seals(seals)?;
+                Ok(0)
+            }
+            FcntlOp::F_GET_SEALS => {
+                let file = task.with_fd_table(|table| table.get_file(fd))?;
+                let memfd = file
+                    .inode()
+                    .downcast_arc::<MemfdInode>()
+                    .map_err(|_| SysError::EINVAL)?;
+                Ok(memfd.seals().bits() as _)
+            }
             _ => {
                 log::warn!("fcntl cmd: {op:?} not implemented");
                 Ok(0)
@@ -608,6 +917,60 @@ impl Syscall<'_> {
         }
     }
 
+    /// flock() applies or removes an advisory lock on the open file specified
+    /// by `fd`. The argument `operation` is one of LOCK_SH (shared lock),
+    /// LOCK_EX (exclusive lock) and LOCK_UN (remove lock), optionally ORed
+    /// with LOCK_NB to make a nonblocking request.
+    ///
+    /// Locks created by flock() are associated with an open file description,
+    /// and are released when all duplicate file descriptors are closed.
+    pub async fn sys_flock(&self, fd: usize, operation: i32) -> SyscallResult {
+        let file = self.get_lock_file(fd)?;
+        log::info!("[sys_flock] fd: {fd}, operation: {operation:#x}");
+        let ltype = match operation & !LOCK_NB {
+            LOCK_SH => FileLockType::Read,
+            LOCK_EX => FileLockType::Write,
+            LOCK_UN => FileLockType::Unlock,
+            _ => return Err(SysError::EINVAL),
+        };
+        let lock = FileLock::new_flock(file.meta().lock_owner(), ltype);
+        self.lock_file(file.inode(), lock, operation & LOCK_NB == 0)
+            .await?;
+        Ok(0)
+    }
+
+    /// Get the file of `fd` to be locked. Anonymous files like sockets and
+    /// eventfds have no inode to keep locks.
+    fn get_lock_file(&self, fd: usize) -> SysResult<Arc<dyn File>> {
+        let file = self.task.with_fd_table(|table| table.get_file(fd))?;
+        if file.inode().is_dummy() {
+            return Err(SysError::EINVAL);
+        }
+        Ok(file)
+    }
+
+    /// Acquire or release `lock` on `inode`. If `wait` is true, block until
+    /// the lock can be acquired, which can be interrupted by signals.
+    async fn lock_file(&self, inode: Arc<dyn Inode>, lock: FileLock, wait: bool) -> SysResult<()> {
+        if !wait {
+            return FileLockFuture::new(inode, lock, false).await;
+        }
+        let task = self.task;
+        task.set_interruptable();
+        task.set_wake_up_signal(!*task.sig_mask_ref());
+        let intr_future = IntrBySignalFuture {
+            task: task.clone(),
+            mask: *task.sig_mask_ref(),
+        };
+        let ret =
+            match Select2Futures::new(FileLockFuture::new(inode, lock, true), intr_future).await {
+                SelectOutput::Output1(ret) => ret,
+                SelectOutput::Output2(_) => Err(SysError::EINTR),
+            };
+        task.set_running();
+        ret
+    }
+
     /// The writev() system call writes iovcnt buffers of data described by iov
     /// to the file associated with the file descriptor fd ("gather
     /// output").
@@ -725,6 +1088,104 @@ impl Syscall<'_> {
         Ok(ret)
     }
 
+    /// The copy_file_range() system call performs an in-kernel copy between
+    /// two file descriptors without the additional cost of transferring data
+    /// from the kernel to user space and then back into the kernel. It copies
+    /// up to `len` bytes of data from the source file descriptor `fd_in` to the
+    /// target file descriptor `fd_out`, overwriting any data that exists
+    /// within the requested range of the target file.
+    ///
+    /// If `off_in` is NULL, then bytes are read from `fd_in` starting from the
+    /// file offset, and the file offset is adjusted by the number of bytes
+    /// copied. If `off_in` is not NULL, then it must point to a buffer that
+    /// specifies the starting offset where bytes from `fd_in` will be read.
+    /// The file offset of `fd_in` is not changed, but `off_in` is adjusted
+    /// appropriately. `off_out` is handled in the same way for `fd_out`.
+    pub async fn sys_copy_file_range(
+        &self,
+        fd_in: usize,
+        off_in: UserRdWrPtr<usize>,
+        fd_out: usize,
+        off_out: UserRdWrPtr<usize>,
+        len: usize,
+        flags: u32,
+    ) -> SyscallResult {
+        let task = self.task;
+        if flags != 0 {
+            return Err(SysError::EINVAL);
+        }
+        let (in_file, out_file) =
+            task.with_fd_table(|table| Ok((table.get_file(fd_in)?, table.get_file(fd_out)?)))?;
+        if !in_file.flags().readable()
+            || !out_file.flags().writable()
+            || out_file.flags().contains(OpenFlags::O_APPEND)
+        {
+            return Err(SysError::EBADF);
+        }
+        // Anonymous files like sockets have no inode to look into.
+        if in_file.inode().is_dummy() || out_file.inode().is_dummy() {
+            return Err(SysError::EINVAL);
+        }
+        if in_file.itype().is_dir() || out_file.itype().is_dir() {
+            return Err(SysError::EISDIR);
+        }
+        if !in_file.itype().is_file() || !out_file.itype().is_file() {
+            return Err(SysError::EINVAL);
+        }
+        let mut off_in = if off_in.is_null() {
+            None
+        } else {
+            Some(off_in.into_mut(task)?)
+        };
+        let mut off_out = if off_out.is_null() {
+            None
+        } else {
+            Some(off_out.into_mut(task)?)
+        };
+        let pos_in = match &off_in {
+            Some(off) => **off,
+            None => in_file.pos(),
+        };
+        let pos_out = match &off_out {
+            Some(off) => **off,
+            None => out_file.pos(),
+        };
+        log::info!(
+            "[sys_copy_file_range] fd_in: {fd_in}, pos_in: {pos_in}, fd_out: {fd_out}, pos_out: {pos_out}, len: {len}"
+        );
+
+        let (in_inode, out_inode) = (in_file.inode(), out_file.inode());
+        // Copying between page caches is only done within a file system.
+        if in_inode.page_cache().is_some()
+            && out_inode.page_cache().is_some()
+            && !ptr::addr_eq(
+                in_inode.meta().super_block.as_ptr(),
+                out_inode.meta().super_block.as_ptr(),
+            )
+        {
+            return Err(SysError::EXDEV);
+        }
+        if ptr::addr_eq(Arc::as_ptr(&in_inode), Arc::as_ptr(&out_inode))
+            && pos_in < pos_out.saturating_add(len)
+            && pos_out < pos_in.saturating_add(len)
+        {
+            return Err(SysError::EINVAL);
+        }
+
+        let copied = in_file
+            .copy_file_range(pos_in, out_file.as_ref(), pos_out, len)
+            .await?;
+        match off_in.as_mut() {
+            Some(off) => **off = pos_in + copied,
+            None => in_file.set_pos(pos_in + copied),
+        }
+        match off_out.as_mut() {
+            Some(off) => **off = pos_out + copied,
+            None => out_file.set_pos(pos_out + copied),
+        }
+        Ok(copied)
+    }
+
     /// access() checks whether the calling process can access the file
     /// pathname. If pathname is a symbolic link, it is dereferenced.
     // TODO:
@@ -937,6 +1398,52 @@ impl Syscall<'_> {
         file.inode().truncate(length as usize)
     }
 
+    /// sync() causes all pending modifications to filesystem metadata and
+    /// cached file data to be written to the underlying filesystems.
+    pub fn sys_sync(&self) -> SyscallResult {
+        for sb in all_super_blocks() {
+            // sync() is always successful.
+            if let Err(e) = sb.sync(1) {
+                log::warn!("[sys_sync] fail to sync {}, err {e:?}", sb.fs_type().name());
+            }
+        }
+        Ok(0)
+    }
+
+    /// syncfs() is like sync(), but synchronizes just the filesystem
+    /// containing file referred to by the open file descriptor `fd`.
+    pub fn sys_syncfs(&self, fd: usize) -> SyscallResult {
+        let file = self.task.with_fd_table(|table| table.get_file(fd))?;
+        // Anonymous files, e.g. sockets and memfd, are on no file system to
+        // sync.
+        if let Some(super_block) = file.inode().try_super_block() {
+            super_block.sync(1)?;
+        }
+        Ok(0)
+    }
+
+    /// fsync() transfers ("flushes") all modified in-core data of (i.e.,
+    /// modified buffer cache pages for) the file referred to by the file
+    /// descriptor `fd` to the disk device so that all changed information can
+    /// be retrieved even if the system crashes or is rebooted. It also
+    /// flushes metadata information associated with the file.
+    pub fn sys_fsync(&self, fd: usize) -> SyscallResult {
+        let file = self.task.with_fd_table(|table| table.get_file(fd))?;
+        log::info!("[sys_fsync] file path {}", file.dentry().path());
+        file.inode().sync()?;
+        Ok(0)
+    }
+
+    /// fdatasync() is similar to fsync(), but does not flush modified
+    /// metadata unless that metadata is needed in order to allow a subsequent
+    /// data retrieval to be correctly handled.
+    ///
+    /// Metadata shares the block cache with data in lwext4, so it is flushed
+    /// as well.
+    pub fn sys_fdatasync(&self, fd: usize) -> SyscallResult {
+        self.sys_fsync(fd)
+    }
+
     /// Modify the permissions of a file or directory relative to a certain
     /// directory or location
     pub fn sys_fchmodat(&self) -> SyscallResult {
@@ -1,13 +1,20 @@
+use core::{mem::size_of, sync::atomic::Ordering, time::Duration};
+
+use arch::time::get_time_duration;
 use async_utils::suspend_now;
 use bitflags::Flags;
 use memory::VirtAddr;
-use systype::{SysError, SyscallResult};
-use time::timespec::TimeSpec;
+use systype::{SysError, SysResult, SyscallResult};
+use time::{timespec::TimeSpec, CLOCK_DEVIATION, CLOCK_REALTIME};
 
 use super::Syscall;
 use crate::{
-    ipc::futex::{futex_manager, FutexHashKey, FutexOp, FutexWaiter, RobustListHead},
+    ipc::futex::{
+        futex_manager, FutexHashKey, FutexOp, FutexWaiter, FutexWakeOp, RobustListHead,
+        FUTEX_BITSET_MATCH_ANY, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS,
+    },
     mm::{FutexAddr, UserReadPtr, UserWritePtr},
+    task::{Tid, TASK_MANAGER},
 };
 
 impl Syscall<'_> {
@@ -39,15 +46,9 @@ impl Syscall<'_> {
         uaddr.check(&task)?;
         let is_private = futex_op.contains(FutexOp::Private);
         futex_op.remove(FutexOp::Private);
-        let key = if is_private {
-            FutexHashKey::Private {
-                mm: task.raw_mm_pointer(),
-                vaddr: uaddr.addr,
-            }
-        } else {
-            let paddr = VirtAddr::from(uaddr.raw()).to_paddr();
-            FutexHashKey::Shared { paddr }
-        };
+        let realtime = futex_op.contains(FutexOp::ClockRealtime);
+        futex_op.remove(FutexOp::ClockRealtime);
+        let key = self.futex_key(uaddr.raw(), is_private);
         log::info!(
             "[sys_futex] {:?} uaddr:{:#x} key:{:?}",
             futex_op,
@@ -57,61 +58,50 @@ impl Syscall<'_> {
 
         match futex_op {
             FutexOp::Wait => {
-                let res = uaddr.read();
-                if res != val {
-                    log::info!(
-                        "[futex_wait] value in {} addr is {res} but expect {val}",
-                        uaddr.addr.0
-                    );
-                    return Err(SysError::EAGAIN);
-                }
-                futex_manager().add_waiter(
-                    &key,
-                    FutexWaiter {
-                        tid: task.tid(),
-                        waker: task.waker().clone().unwrap(),
-                    },
-                );
-                task.set_interruptable();
-                let wake_up_signal = !*task.sig_mask_ref();
-                task.set_wake_up_signal(wake_up_signal);
-                if timeout == 0 {
-                    suspend_now().await;
-                } else {
-                    let timeout = UserReadPtr::<TimeSpec>::from(timeout as usize).read(&task)?;
-                    log::info!("[futex_wait] waiting for {:?}", timeout);
-                    if !timeout.is_valid() {
-                        return Err(SysError::EINVAL);
-                    }
-                    let rem = task.suspend_timeout(timeout.into()).await;
-                    if rem.is_zero() {
-                        futex_manager().remove_waiter(&key, task.tid());
-                    }
-                }
-                if task.with_sig_pending(|p| p.has_expect_signals(wake_up_signal)) {
-                    log::info!("[sys_futex] Woken by signal");
-                    futex_manager().remove_waiter(&key, task.tid());
-                    return Err(SysError::EINTR);
+                let deadline = self.futex_deadline(timeout, None)?;
+                self.futex_wait(&uaddr, key, val, FUTEX_BITSET_MATCH_ANY, deadline)
+                    .await
+            }
+            FutexOp::WaitBitset => {
+                if val3 == 0 {
+                    return Err(SysError::EINVAL);
                 }
-                log::info!("[sys_futex] I was woken");
-                task.set_running();
-                Ok(0)
+                let deadline = self.futex_deadline(timeout, Some(realtime))?;
+                self.futex_wait(&uaddr, key, val, val3, deadline).await
             }
             FutexOp::Wake => {
                 let n_wake = futex_manager().wake(&key, val)?;
                 return Ok(n_wake);
             }
+            FutexOp::WakeBitset => {
+                if val3 == 0 {
+                    return Err(SysError::EINVAL);
+                }
+                futex_manager().wake_bitset(&key, val, val3)
+            }
+            FutexOp::WakeOp => {
+                let op = FutexWakeOp::decode(val3)?;
+                let uaddr2 = FutexAddr::from(uaddr2);
+                uaddr2.check_write(&task)?;
+                let key2 = self.futex_key(uaddr2.raw(), is_private);
+                let old = uaddr2
+                    .as_atomic()
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |old| {
+                        Some(op.apply(old))
+                    })
+                    .unwrap();
+                let mut manager = futex_manager();
+                let mut n_wake = manager.wake(&key, val).unwrap_or(0);
+                if op.test(old) {
+                    // `timeout` is interpreted as `val2`, the number of waiters
+                    // to wake on `uaddr2`.
+                    n_wake += manager.wake(&key2, timeout as u32).unwrap_or(0);
+                }
+                Ok(n_wake)
+            }
             FutexOp::Requeue => {
                 let n_wake = futex_manager().wake(&key, val)?;
-                let new_key = if is_private {
-                    FutexHashKey::Private {
-                        mm: task.raw_mm_pointer(),
-                        vaddr: uaddr2.into(),
-                    }
-                } else {
-                    let paddr = VirtAddr::from(uaddr2).to_paddr();
-                    FutexHashKey::Shared { paddr }
-                };
+                let new_key = self.futex_key(uaddr2, is_private);
                 futex_manager().requeue_waiters(key, new_key, timeout)?;
                 Ok(n_wake)
             }
@@ -120,58 +110,323 @@ impl Syscall<'_> {
                     return Err(SysError::EAGAIN);
                 }
                 let n_wake = futex_manager().wake(&key, val)?;
-                let new_key = if is_private {
-                    FutexHashKey::Private {
-                        mm: task.raw_mm_pointer(),
-                        vaddr: uaddr2.into(),
-                    }
-                } else {
-                    let paddr = VirtAddr::from(uaddr2).to_paddr();
-                    FutexHashKey::Shared { paddr }
-                };
+                let new_key = self.futex_key(uaddr2, is_private);
                 futex_manager().requeue_waiters(key, new_key, timeout)?;
                 Ok(n_wake)
             }
+            FutexOp::LockPi => {
+                uaddr.check_write(&task)?;
+                // The timeout of `FUTEX_LOCK_PI` is always measured against
+                // `CLOCK_REALTIME`.
+                let deadline = self.futex_deadline(timeout, Some(true))?;
+                self.futex_lock_pi(&uaddr, key, deadline).await
+            }
+            FutexOp::TrylockPi => {
+                uaddr.check_write(&task)?;
+                let mut manager = futex_manager();
+                if futex_lock_pi_atomic(&uaddr, task.tid(), false)? {
+                    manager.set_pi_owner(key, task.tid());
+                    Ok(0)
+                } else {
+                    Err(SysError::EAGAIN)
+                }
+            }
+            FutexOp::UnlockPi => {
+                uaddr.check_write(&task)?;
+                let word = uaddr.as_atomic();
+                let mut manager = futex_manager();
+                let cur = word.load(Ordering::Acquire);
+                if (cur & FUTEX_TID_MASK) as Tid != task.tid() {
+                    return Err(SysError::EPERM);
+                }
+                // Hand the lock off to a waiter directly, so that it will not
+                // be stolen before the waiter runs.
+                if let Some(waiter) = manager.pop_waiter(&key) {
+                    let waiters = if manager.has_waiters(&key) {
+                        FUTEX_WAITERS
+                    } else {
+                        0
+                    };
+                    word.store(waiter.tid as u32 | waiters, Ordering::Release);
+                    manager.set_pi_owner(key, waiter.tid);
+                    waiter.wake();
+                } else {
+                    word.store(0, Ordering::Release);
+                    manager.clear_pi_owner(&key);
+                }
+                Ok(0)
+            }
+            FutexOp::WaitRequeuePi => {
+                if uaddr2 == uaddr.raw() {
+                    return Err(SysError::EINVAL);
+                }
+                let uaddr2 = FutexAddr::from(uaddr2);
+                uaddr2.check_write(&task)?;
+                let key2 = self.futex_key(uaddr2.raw(), is_private);
+                let deadline = self.futex_deadline(timeout, Some(realtime))?;
+                let res = self
+                    .futex_wait(&uaddr, key, val, FUTEX_BITSET_MATCH_ANY, deadline)
+                    .await;
+                {
+                    // The task may have been requeued to `uaddr2` and then
+                    // been handed the lock by `FUTEX_UNLOCK_PI`.
+                    let mut manager = futex_manager();
+                    manager.remove_waiter(&key2, task.tid());
+                    if (uaddr2.read() & FUTEX_TID_MASK) as Tid == task.tid() {
+                        return Ok(0);
+                    }
+                }
+                res?;
+                self.futex_lock_pi(&uaddr2, key2, deadline).await
+            }
+            FutexOp::CmpRequeuePi => {
+                // Only one waiter can be woken to take the lock.
+                if val != 1 {
+                    return Err(SysError::EINVAL);
+                }
+                if uaddr.read() != val3 {
+                    return Err(SysError::EAGAIN);
+                }
+                let uaddr2 = FutexAddr::from(uaddr2);
+                uaddr2.check_write(&task)?;
+                let key2 = self.futex_key(uaddr2.raw(), is_private);
+                let mut manager = futex_manager();
+                let n_wake = manager.wake(&key, val).unwrap_or(0);
+                let n_requeue = manager.requeue_waiters(key, key2, timeout).unwrap_or(0);
+                if n_requeue > 0 {
+                    // Take the lock on behalf of a requeued waiter if it is
+                    // free, otherwise make the owner wake the waiters when
+                    // unlocking.
+                    let waiter = manager.pop_waiter(&key2).unwrap();
+                    if futex_lock_pi_atomic(&uaddr2, waiter.tid, true)? {
+                        if manager.has_waiters(&key2) {
+                            uaddr2.as_atomic().fetch_or(FUTEX_WAITERS, Ordering::AcqRel);
+                        }
+                        manager.set_pi_owner(key2, waiter.tid);
+                        waiter.wake();
+                    } else {
+                        let owner = (uaddr2.read() & FUTEX_TID_MASK) as Tid;
+                        manager.set_pi_owner(key2, owner);
+                        manager.add_waiter(&key2, waiter);
+                    }
+                }
+                Ok(n_wake + n_requeue)
+            }
+            _ => {
+                log::warn!("[sys_futex] unsupported futex op {futex_op:?}");
+                Err(SysError::ENOSYS)
+            }
+        }
+    }
+
+    fn futex_key(&self, addr: usize, is_private: bool) -> FutexHashKey {
+        if is_private {
+            FutexHashKey::Private {
+                mm: self.task.raw_mm_pointer(),
+                vaddr: addr.into(),
+            }
+        } else {
+            let paddr = VirtAddr::from(addr).to_paddr();
+            FutexHashKey::Shared { paddr }
+        }
+    }
 
-            _ => panic!("unimplemented futexop {:?}", futex_op),
+    /// Read the timeout of a futex operation, and return the time when it
+    /// expires. `realtime` is `None` for a relative timeout, otherwise the
+    /// timeout is absolute and measured against `CLOCK_REALTIME` if true or
+    /// `CLOCK_MONOTONIC` if false.
+    fn futex_deadline(
+        &self,
+        timeout: usize,
+        realtime: Option<bool>,
+    ) -> SysResult<Option<Duration>> {
+        if timeout == 0 {
+            return Ok(None);
+        }
+        let timeout = UserReadPtr::<TimeSpec>::from(timeout).read(&self.task)?;
+        log::info!("[sys_futex] timeout {:?}", timeout);
+        if !timeout.is_valid() {
+            return Err(SysError::EINVAL);
         }
+        let timeout: Duration = timeout.into();
+        let deadline = match realtime {
+            None => get_time_duration() + timeout,
+            Some(true) => timeout.saturating_sub(unsafe { CLOCK_DEVIATION[CLOCK_REALTIME] }),
+            Some(false) => timeout,
+        };
+        Ok(Some(deadline))
     }
 
-    /// actually this syscall has no actual effect
-    pub fn sys_get_robust_list(
+    /// Sleep until woken by another task, a signal or `deadline`. Returns true
+    /// if timed out.
+    async fn futex_sleep(&self, deadline: Option<Duration>) -> bool {
+        let task = self.task;
+        task.set_interruptable();
+        task.set_wake_up_signal(!*task.sig_mask_ref());
+        let timed_out = match deadline {
+            None => {
+                suspend_now().await;
+                false
+            }
+            Some(deadline) => {
+                let now = get_time_duration();
+                deadline <= now || task.suspend_timeout(deadline - now).await.is_zero()
+            }
+        };
+        task.set_running();
+        timed_out
+    }
+
+    async fn futex_wait(
         &self,
-        _pid: i32,
-        _robust_list_head: UserWritePtr<RobustListHead>,
-        _len_ptr: UserWritePtr<usize>,
+        uaddr: &FutexAddr,
+        key: FutexHashKey,
+        val: u32,
+        bitset: u32,
+        deadline: Option<Duration>,
     ) -> SyscallResult {
-        // let Some(task) = TASK_MANAGER.get(pid as usize) else {
-        //     return Err(SysError::ESRCH);
-        // };
-        // if !task.is_leader() {
-        //     return Err(SysError::ESRCH);
-        // }
-        // // UserReadPtr::<RobustListHead>::from(value)
-        // len_ptr.write(&task, mem::size_of::<RobustListHead>())?;
-        // robust_list_head.write(&task, unsafe {
-        //     *task.with_futexes(|futexes| futexes.robust_list.load(Ordering::SeqCst))
-        // })?;
+        let task = self.task;
+        let res = uaddr.read();
+        if res != val {
+            log::info!(
+                "[futex_wait] value in {} addr is {res} but expect {val}",
+                uaddr.addr.0
+            );
+            return Err(SysError::EAGAIN);
+        }
+        futex_manager().add_waiter(
+            &key,
+            FutexWaiter {
+                tid: task.tid(),
+                waker: task.waker().clone().unwrap(),
+                bitset,
+            },
+        );
+        let timed_out = self.futex_sleep(deadline).await;
+        let wake_up_signal = !*task.sig_mask_ref();
+        if task.with_sig_pending(|p| p.has_expect_signals(wake_up_signal)) {
+            log::info!("[sys_futex] Woken by signal");
+            futex_manager().remove_waiter(&key, task.tid());
+            return Err(SysError::EINTR);
+        }
+        // The waiter has been removed if it is woken just at the timeout.
+        if timed_out && futex_manager().remove_waiter(&key, task.tid()) {
+            log::info!("[sys_futex] Timeout");
+            return Err(SysError::ETIMEDOUT);
+        }
+        log::info!("[sys_futex] I was woken");
         Ok(0)
     }
 
-    /// actually this syscall has no actual effect
-    pub fn sys_set_robust_list(
+    async fn futex_lock_pi(
+        &self,
+        uaddr: &FutexAddr,
+        key: FutexHashKey,
+        deadline: Option<Duration>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let tid = task.tid();
+        loop {
+            {
+                let mut manager = futex_manager();
+                if futex_lock_pi_atomic(uaddr, tid, true)? {
+                    manager.set_pi_owner(key, tid);
+                    return Ok(0);
+                }
+                let owner = (uaddr.read() & FUTEX_TID_MASK) as Tid;
+                manager.set_pi_owner(key, owner);
+                manager.add_waiter(
+                    &key,
+                    FutexWaiter {
+                        tid,
+                        waker: task.waker().clone().unwrap(),
+                        bitset: FUTEX_BITSET_MATCH_ANY,
+                    },
+                );
+            }
+            let timed_out = self.futex_sleep(deadline).await;
+            {
+                let mut manager = futex_manager();
+                manager.remove_waiter(&key, tid);
+                // The lock is handed off by `FUTEX_UNLOCK_PI` with the manager
+                // locked, so it can be checked safely here.
+                if (uaddr.read() & FUTEX_TID_MASK) as Tid == tid {
+                    return Ok(0);
+                }
+            }
+            let wake_up_signal = !*task.sig_mask_ref();
+            if task.with_sig_pending(|p| p.has_expect_signals(wake_up_signal)) {
+                return Err(SysError::EINTR);
+            }
+            if timed_out {
+                return Err(SysError::ETIMEDOUT);
+            }
+            // Woken since the owner died, try to take the lock again.
+        }
+    }
+
+    /// Returns the robust futex list of the thread whose thread ID is `pid`,
+    /// or of the calling thread if `pid` is 0.
+    pub fn sys_get_robust_list(
         &self,
-        _robust_list_head: UserReadPtr<RobustListHead>,
-        _len: usize,
+        pid: i32,
+        head_ptr: UserWritePtr<usize>,
+        len_ptr: UserWritePtr<usize>,
     ) -> SyscallResult {
-        // let task = self.task;
-        // if len != mem::size_of::<RobustListHead>() {
-        //     return Err(SysError::EINVAL);
-        // }
-        // let mut head = robust_list_head.into_ref(&task)?;
-        // task.with_mut_futexes(|futexes| {
-        //     futexes.robust_list.store(head.ptr_mut(), Ordering::SeqCst)
-        // });
+        let task = self.task;
+        let target = if pid == 0 {
+            task.clone()
+        } else {
+            TASK_MANAGER.get(pid as Tid).ok_or(SysError::ESRCH)?
+        };
+        len_ptr.write(&task, size_of::<RobustListHead>())?;
+        head_ptr.write(&task, target.robust_list())?;
         Ok(0)
     }
+
+    /// Registers the head of the robust futex list of the calling thread. The
+    /// list is walked by the kernel when the thread exits, see
+    /// `exit_robust_list`.
+    pub fn sys_set_robust_list(&self, head: usize, len: usize) -> SyscallResult {
+        if len != size_of::<RobustListHead>() {
+            return Err(SysError::EINVAL);
+        }
+        self.task.set_robust_list(head);
+        Ok(0)
+    }
+}
+
+fn futex_owner_died(tid: Tid) -> bool {
+    TASK_MANAGER
+        .get(tid)
+        .map_or(true, |task| task.is_terminated() || task.is_zombie())
+}
+
+/// Try to acquire the PI futex at `uaddr` for `tid`, returns whether the lock
+/// is acquired. A lock whose owner has died is taken over with
+/// `FUTEX_OWNER_DIED` set. If the lock is held by another task and
+/// `set_waiters` is true, `FUTEX_WAITERS` is set so that the owner will unlock
+/// it through the kernel.
+fn futex_lock_pi_atomic(uaddr: &FutexAddr, tid: Tid, set_waiters: bool) -> SysResult<bool> {
+    let word = uaddr.as_atomic();
+    let mut cur = word.load(Ordering::Acquire);
+    loop {
+        let owner = (cur & FUTEX_TID_MASK) as Tid;
+        if owner == tid {
+            return Err(SysError::EDEADLK);
+        }
+        let (new, acquired) = if owner == 0 {
+            ((cur & !FUTEX_TID_MASK) | tid as u32, true)
+        } else if futex_owner_died(owner) {
+            ((cur & FUTEX_WAITERS) | FUTEX_OWNER_DIED | tid as u32, true)
+        } else if set_waiters && cur & FUTEX_WAITERS == 0 {
+            (cur | FUTEX_WAITERS, false)
+        } else {
+            return Ok(false);
+        };
+        match word.compare_exchange(cur, new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => return Ok(acquired),
+            Err(actual) => cur = actual,
+        }
+    }
 }
@@ -4,20 +4,25 @@ use core::{
     mem::{self, size_of},
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use async_utils::{Select2Futures, SelectOutput};
 use memory::VirtAddr;
 use signal::SigSet;
+use strum::FromRepr;
 use systype::{SysError, SyscallResult};
 use time::timespec::TimeSpec;
 use timer::timelimited_task::{TimeLimitedTaskFuture, TimeLimitedTaskOutput};
-use vfs::fd_table::Fd;
-use vfs_core::{File, PollEvents};
+use vfs::{
+    epoll::{EpollEvent, EpollEvents, EpollFile, EpollWaitFuture},
+    fd_table::Fd,
+};
+use vfs_core::{File, OpenFlags, PollEvents};
 
 use super::Syscall;
 use crate::{
-    mm::{UserRdWrPtr, UserReadPtr, UserSlice},
+    mm::{UserRdWrPtr, UserReadPtr, UserSlice, UserWritePtr},
     task::signal::IntrBySignalFuture,
 };
 
@@ -77,6 +82,16 @@ impl FdSet {
     }
 }
 
+// Defined in <sys/epoll.h>
+#[derive(FromRepr, Debug, Eq, PartialEq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[repr(i32)]
+pub enum EpollCtlOp {
+    EPOLL_CTL_ADD = 1,
+    EPOLL_CTL_DEL = 2,
+    EPOLL_CTL_MOD = 3,
+}
+
 pub struct PPollFuture {
     polls: Vec<(PollEvents, Arc<dyn File>)>,
 }
@@ -363,4 +378,155 @@ impl Syscall<'_> {
         }
         Ok(ret)
     }
+
+    /// epoll_create1() creates a new epoll instance and returns a file
+    /// descriptor referring to that instance.
+    ///
+    /// If flags is 0, then epoll_create1() is the same as epoll_create(). The
+    /// following value can be included in flags to obtain different behavior:
+    /// + EPOLL_CLOEXEC: Set the close-on-exec (FD_CLOEXEC) flag on the new file
+    ///   descriptor.
+    pub fn sys_epoll_create1(&self, flags: i32) -> SyscallResult {
+        let task = self.task;
+        let flags = OpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        if !flags.difference(OpenFlags::O_CLOEXEC).is_empty() {
+            return Err(SysError::EINVAL);
+        }
+        let epoll = EpollFile::new();
+        let fd = task.with_mut_fd_table(|table| table.alloc(epoll, flags))?;
+        log::info!("[sys_epoll_create1] epoll fd: {fd}, flags: {flags:?}");
+        Ok(fd)
+    }
+
+    /// This system call is used to add, modify, or remove entries in the
+    /// interest list of the epoll instance referred to by the file descriptor
+    /// epfd. It requests that the operation op be performed for the target
+    /// file descriptor, fd.
+    ///
+    /// The event argument describes the object linked to the file descriptor
+    /// fd. It is ignored when op is `EPOLL_CTL_DEL`.
+    pub fn sys_epoll_ctl(
+        &self,
+        epfd: usize,
+        op: i32,
+        fd: usize,
+        event: UserReadPtr<EpollEvent>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let epoll = task.with_fd_table(|table| table.get_file(epfd))?;
+        let file = task.with_fd_table(|table| table.get_file(fd))?;
+        if epfd == fd {
+            return Err(SysError::EINVAL);
+        }
+        let epoll = epoll
+            .downcast_arc::<EpollFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        let op = EpollCtlOp::from_repr(op).ok_or(SysError::EINVAL)?;
+        log::info!("[sys_epoll_ctl] epfd: {epfd}, op: {op:?}, fd: {fd}");
+        match op {
+            EpollCtlOp::EPOLL_CTL_ADD => {
+                let event = event.read(task)?;
+                log::info!("[sys_epoll_ctl] event: {event:?}");
+                epoll.add(fd, &file, event)?;
+            }
+            EpollCtlOp::EPOLL_CTL_MOD => {
+                let event = event.read(task)?;
+                log::info!("[sys_epoll_ctl] event: {event:?}");
+                if event.events.contains(EpollEvents::EXCLUSIVE) {
+                    return Err(SysError::EINVAL);
+                }
+                epoll.modify(fd, event)?;
+            }
+            EpollCtlOp::EPOLL_CTL_DEL => epoll.delete(fd)?,
+        }
+        Ok(0)
+    }
+
+    /// The epoll_pwait() system call waits for events on the epoll instance
+    /// referred to by the file descriptor epfd. The buffer pointed to by events
+    /// is used to return information from the ready list about file
+    /// descriptors in the interest list that have some events available. Up to
+    /// maxevents are returned by epoll_pwait().
+    ///
+    /// The timeout argument specifies the number of milliseconds that
+    /// epoll_pwait() will block. Specifying a timeout of -1 causes
+    /// epoll_pwait() to block indefinitely, while specifying a timeout equal
+    /// to zero cause epoll_pwait() to return immediately, even if no events
+    /// are available.
+    pub async fn sys_epoll_pwait(
+        &self,
+        epfd: usize,
+        events: UserWritePtr<EpollEvent>,
+        maxevents: i32,
+        timeout: i32,
+        sigmask: UserReadPtr<SigSet>,
+    ) -> SyscallResult {
+        let task = self.task;
+        if maxevents <= 0 {
+            return Err(SysError::EINVAL);
+        }
+        let maxevents = maxevents as usize;
+        let epoll = task
+            .with_fd_table(|table| table.get_file(epfd))?
+            .downcast_arc::<EpollFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        let new_mask = if sigmask.is_null() {
+            None
+        } else {
+            Some(sigmask.read(task)?)
+        };
+        log::info!(
+            "[sys_epoll_pwait] epfd: {epfd}, maxevents: {maxevents}, timeout: {timeout}, sigmask: {new_mask:?}"
+        );
+
+        let ready = if timeout == 0 {
+            epoll.try_wait(maxevents)
+        } else {
+            let old_mask = if let Some(mask) = new_mask {
+                Some(mem::replace(task.sig_mask(), mask))
+            } else {
+                None
+            };
+            task.set_interruptable();
+            task.set_wake_up_signal(!*task.sig_mask_ref());
+            let intr_future = IntrBySignalFuture {
+                task: task.clone(),
+                mask: *task.sig_mask_ref(),
+            };
+            let wait_future = EpollWaitFuture::new(epoll, maxevents);
+            let ret = if timeout > 0 {
+                let timeout = Duration::from_millis(timeout as u64);
+                match Select2Futures::new(
+                    TimeLimitedTaskFuture::new(timeout, wait_future),
+                    intr_future,
+                )
+                .await
+                {
+                    SelectOutput::Output1(TimeLimitedTaskOutput::Ok(ready)) => Ok(ready),
+                    SelectOutput::Output1(TimeLimitedTaskOutput::TimeOut) => {
+                        log::debug!("[sys_epoll_pwait]: timeout");
+                        Ok(Vec::new())
+                    }
+                    SelectOutput::Output2(_) => Err(SysError::EINTR),
+                }
+            } else {
+                match Select2Futures::new(wait_future, intr_future).await {
+                    SelectOutput::Output1(ready) => Ok(ready),
+                    SelectOutput::Output2(_) => Err(SysError::EINTR),
+                }
+            };
+            task.set_running();
+            // restore old signal mask
+            if let Some(mask) = old_mask {
+                *task.sig_mask() = mask;
+            }
+            ret?
+        };
+
+        log::info!("[sys_epoll_pwait] ready events: {ready:?}");
+        if !ready.is_empty() {
+            events.write_array(task, &ready)?;
+        }
+        Ok(ready.len())
+    }
 }
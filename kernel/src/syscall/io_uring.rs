@@ -0,0 +1,255 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::mem::{self, size_of};
+
+use async_utils::{Select2Futures, SelectOutput};
+use signal::SigSet;
+use systype::{SysError, SyscallResult};
+use vfs_core::{File, OpenFlags};
+
+use super::Syscall;
+use crate::{
+    io_uring::{
+        is_supported, CqWaitFuture, EnterFlags, IoUring, IoUringFile, IoUringParams, SetupFlags,
+        IORING_MAX_CQ_ENTRIES, IORING_MAX_ENTRIES, IORING_MAX_FIXED_FILES, IORING_OP_LAST,
+    },
+    mm::{UserRdWrPtr, UserReadPtr, UserWritePtr},
+    task::signal::IntrBySignalFuture,
+};
+
+// Opcodes of `io_uring_register`, defined in <linux/io_uring.h>.
+const IORING_REGISTER_FILES: usize = 2;
+const IORING_UNREGISTER_FILES: usize = 3;
+const IORING_REGISTER_PROBE: usize = 8;
+
+/// Set in `io_uring_probe_op.flags` if the opcode is supported.
+const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+
+/// Header of `struct io_uring_probe`, followed by `ops_len` entries of
+/// `ProbeOp`.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+struct Probe {
+    last_op: u8,
+    ops_len: u8,
+    resv: u16,
+    resv2: [u32; 3],
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+struct ProbeOp {
+    op: u8,
+    resv: u8,
+    flags: u16,
+    resv2: u32,
+}
+
+impl Syscall<'_> {
+    /// The io_uring_setup() system call sets up a submission queue (SQ) and
+    /// completion queue (CQ) with at least `entries` entries, and returns a
+    /// file descriptor which can be used to perform subsequent operations on
+    /// the io_uring instance. The SQ and CQ are shared between user space and
+    /// the kernel, and are mapped by mmap() with the offsets written back in
+    /// `params`.
+    pub fn sys_io_uring_setup(
+        &self,
+        entries: u32,
+        params: UserRdWrPtr<IoUringParams>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let mut p = params.into_mut(task)?;
+        let flags = SetupFlags::from_bits(p.flags).ok_or(SysError::EINVAL)?;
+        log::info!("[sys_io_uring_setup] entries:{entries}, flags:{flags:?}");
+        if flags.intersects(SetupFlags::IOPOLL | SetupFlags::ATTACH_WQ | SetupFlags::R_DISABLED)
+            || p.resv.iter().any(|&r| r != 0)
+        {
+            return Err(SysError::EINVAL);
+        }
+        if entries == 0 {
+            return Err(SysError::EINVAL);
+        }
+        let clamp = |entries: u32, max: u32| {
+            if entries <= max {
+                Ok(entries)
+            } else if flags.contains(SetupFlags::CLAMP) {
+                Ok(max)
+            } else {
+                Err(SysError::EINVAL)
+            }
+        };
+        let sq_entries = clamp(entries, IORING_MAX_ENTRIES)?.next_power_of_two();
+        let cq_entries = if flags.contains(SetupFlags::CQSIZE) {
+            if p.cq_entries == 0 {
+                return Err(SysError::EINVAL);
+            }
+            let cq_entries = clamp(p.cq_entries, IORING_MAX_CQ_ENTRIES)?.next_power_of_two();
+            if cq_entries < sq_entries {
+                return Err(SysError::EINVAL);
+            }
+            cq_entries
+        } else {
+            2 * sq_entries
+        };
+        p.sq_entries = sq_entries;
+        p.cq_entries = cq_entries;
+        let ring = IoUring::new(task, &mut p);
+        let file = IoUringFile::new(ring);
+        task.with_mut_fd_table(|table| table.alloc(file, OpenFlags::O_CLOEXEC))
+    }
+
+    /// The io_uring_enter() system call submits at most `to_submit` SQEs from
+    /// the SQ, and waits for at least `min_complete` CQEs if
+    /// `IORING_ENTER_GETEVENTS` is set. If `sig` is not null, the signal mask
+    /// of the task is replaced by it while waiting.
+    ///
+    /// Returns the number of SQEs consumed.
+    pub async fn sys_io_uring_enter(
+        &self,
+        fd: usize,
+        to_submit: u32,
+        min_complete: u32,
+        flags: u32,
+        sig: UserReadPtr<SigSet>,
+        sigsz: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = EnterFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let ring = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<IoUringFile>()
+            .map_err(|_| SysError::EOPNOTSUPP)?
+            .ring()
+            .clone();
+        log::info!(
+            "[sys_io_uring_enter] fd:{fd}, to_submit:{to_submit}, min_complete:{min_complete}, flags:{flags:?}"
+        );
+
+        let submitted = if ring.flags().contains(SetupFlags::SQPOLL) {
+            // SQEs are consumed by the SQ thread.
+            if flags.contains(EnterFlags::SQ_WAKEUP) {
+                ring.wake_sq_thread();
+            }
+            to_submit as usize
+        } else {
+            ring.submit(task, to_submit)
+        };
+
+        if !flags.contains(EnterFlags::GETEVENTS) || min_complete == 0 {
+            return Ok(submitted);
+        }
+        if ring.cq_ready() >= min_complete {
+            return Ok(submitted);
+        }
+        let new_mask = if sig.is_null() {
+            None
+        } else {
+            if sigsz != size_of::<SigSet>() {
+                return Err(SysError::EINVAL);
+            }
+            Some(sig.read(task)?)
+        };
+        let old_mask = new_mask.map(|mask| mem::replace(task.sig_mask(), mask));
+        task.set_interruptable();
+        task.set_wake_up_signal(!*task.sig_mask_ref());
+        let intr_future = IntrBySignalFuture {
+            task: task.clone(),
+            mask: *task.sig_mask_ref(),
+        };
+        let ret =
+            match Select2Futures::new(CqWaitFuture::new(ring, min_complete), intr_future).await {
+                SelectOutput::Output1(_) => Ok(submitted),
+                // Interrupted after some SQEs are submitted is not an error.
+                SelectOutput::Output2(_) if submitted > 0 => Ok(submitted),
+                SelectOutput::Output2(_) => Err(SysError::EINTR),
+            };
+        task.set_running();
+        if let Some(old_mask) = old_mask {
+            *task.sig_mask() = old_mask;
+        }
+        ret
+    }
+
+    /// The io_uring_register() system call registers resources, e.g. files,
+    /// for use by the io_uring instance referenced by `fd`.
+    pub fn sys_io_uring_register(
+        &self,
+        fd: usize,
+        opcode: usize,
+        arg: usize,
+        nr_args: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let ring = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<IoUringFile>()
+            .map_err(|_| SysError::EOPNOTSUPP)?
+            .ring()
+            .clone();
+        log::info!("[sys_io_uring_register] fd:{fd}, opcode:{opcode}, nr_args:{nr_args}");
+        match opcode {
+            IORING_REGISTER_FILES => {
+                if nr_args == 0 || nr_args > IORING_MAX_FIXED_FILES {
+                    return Err(SysError::EINVAL);
+                }
+                let fds = UserReadPtr::<i32>::from(arg).read_array(task, nr_args)?;
+                // An fd of -1 leaves the slot empty, which can be updated
+                // later.
+                let files = fds
+                    .into_iter()
+                    .map(|fd| match fd {
+                        -1 => Ok(None),
+                        fd if fd < 0 => Err(SysError::EBADF),
+                        // A ring registered to itself or another ring would
+                        // never be released.
+                        fd => match task.with_fd_table(|table| table.get_file(fd as usize))? {
+                            file if file.is::<IoUringFile>() => Err(SysError::EBADF),
+                            file => Ok(Some(file)),
+                        },
+                    })
+                    .collect::<Result<Vec<Option<Arc<dyn File>>>, _>>()?;
+                ring.register_files(files)?;
+                Ok(0)
+            }
+            IORING_UNREGISTER_FILES => {
+                if arg != 0 || nr_args != 0 {
+                    return Err(SysError::EINVAL);
+                }
+                ring.unregister_files()?;
+                Ok(0)
+            }
+            IORING_REGISTER_PROBE => {
+                if nr_args > 256 {
+                    return Err(SysError::EINVAL);
+                }
+                let last_op = IORING_OP_LAST - 1;
+                UserWritePtr::<Probe>::from(arg).write(
+                    task,
+                    Probe {
+                        last_op,
+                        ops_len: nr_args.min(last_op as usize + 1) as u8,
+                        ..Default::default()
+                    },
+                )?;
+                let ops = UserWritePtr::<ProbeOp>::from(arg + size_of::<Probe>());
+                let mut ops = ops.into_mut_slice(task, nr_args)?;
+                for (op, probe_op) in ops.iter_mut().enumerate() {
+                    let op = op as u8;
+                    *probe_op = ProbeOp {
+                        op,
+                        flags: if is_supported(op) {
+                            IO_URING_OP_SUPPORTED
+                        } else {
+                            0
+                        },
+                        ..Default::default()
+                    };
+                }
+                Ok(0)
+            }
+            _ => {
+                log::warn!("[sys_io_uring_register] unsupported opcode {opcode}");
+                Err(SysError::EINVAL)
+            }
+        }
+    }
+}
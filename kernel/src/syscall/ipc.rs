@@ -0,0 +1,750 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{future::Future, mem::size_of, time::Duration};
+
+use arch::time::get_time_duration;
+use async_utils::{suspend_now, Select2Futures, SelectOutput};
+use config::fs::NAME_MAX;
+use signal::{
+    sigevent::SigEvent,
+    siginfo::{SigDetails, SigInfo},
+    sigset::Sig,
+};
+use systype::{SysError, SysResult, SyscallResult};
+use time::{timespec::TimeSpec, CLOCK_DEVIATION, CLOCK_REALTIME};
+use timer::timelimited_task::{TimeLimitedTaskFuture, TimeLimitedTaskOutput};
+use vfs::mqueue::{mqueue_root, MqAttr, MqNotify, MqueueFile, MqueueInode, MQ_PRIO_MAX};
+use vfs_core::{File, InodeMode, OpenFlags};
+
+use super::Syscall;
+use crate::{
+    ipc::{
+        msg::{
+            Message, MsgFlags, MsgRecvFuture, MsgSendFuture, MsqIdDs, MSGMAX, MSG_QUEUE_MANAGER,
+        },
+        sem::{SemBuf, SemIdDs, SEMMSL, SEMOPM, SEM_SET_MANAGER},
+        IpcFlags, IPC_64, IPC_PRIVATE, IPC_RMID, IPC_SET, IPC_STAT,
+    },
+    mm::{UserReadPtr, UserWritePtr},
+    task::{signal::IntrBySignalFuture, Task, TASK_MANAGER},
+};
+
+impl Syscall<'_> {
+    /// The msgget() system call returns the System V message queue identifier
+    /// associated with the value of the key argument. It may be used either to
+    /// obtain the identifier of a previously created message queue (when
+    /// msgflg is zero and key does not have the value IPC_PRIVATE), or to
+    /// create a new set.
+    ///
+    /// A new message queue is created if key has the value IPC_PRIVATE or key
+    /// isn't IPC_PRIVATE, no message queue with the given key key exists, and
+    /// IPC_CREAT is specified in msgflg.
+    pub fn sys_msgget(&self, key: i32, msgflg: i32) -> SyscallResult {
+        let flags = IpcFlags::from_bits_truncate(msgflg);
+        let mode = (msgflg & 0o777) as u32;
+        log::info!("[sys_msgget] key: {key}, flags: {flags:?}, mode: {mode:o}");
+        let mut manager = MSG_QUEUE_MANAGER.lock();
+        if key == IPC_PRIVATE {
+            return Ok(manager.create(key, mode));
+        }
+        match manager.get_by_key(key) {
+            Some(msqid) => {
+                // IPC_CREAT and IPC_EXCL were specified in msgflg, but a message queue
+                // already exists for key.
+                if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                    return Err(SysError::EEXIST);
+                }
+                Ok(msqid)
+            }
+            None if flags.contains(IpcFlags::IPC_CREAT) => Ok(manager.create(key, mode)),
+            // No message queue exists for key and msgflg did not specify IPC_CREAT.
+            None => Err(SysError::ENOENT),
+        }
+    }
+
+    /// The msgsnd() system call appends a copy of the message pointed to by
+    /// msgp to the message queue whose identifier is specified by msqid.
+    ///
+    /// The msgp argument is a pointer to a caller-defined structure of the
+    /// following general form:
+    ///
+    /// ```c
+    /// struct msgbuf {
+    ///     long mtype;       /* message type, must be > 0 */
+    ///     char mtext[1];    /* message data */
+    /// };
+    /// ```
+    ///
+    /// If sufficient space is available in the queue, msgsnd() succeeds
+    /// immediately. If insufficient space is available in the queue, then the
+    /// default behavior of msgsnd() is to block until space becomes available.
+    /// If IPC_NOWAIT is specified in msgflg, then the call instead fails with
+    /// the error EAGAIN.
+    pub async fn sys_msgsnd(
+        &self,
+        msqid: usize,
+        msgp: usize,
+        msgsz: usize,
+        msgflg: i32,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = MsgFlags::from_bits_truncate(msgflg);
+        log::info!("[sys_msgsnd] msqid: {msqid}, msgsz: {msgsz}, flags: {flags:?}");
+        if msgsz > MSGMAX {
+            return Err(SysError::EINVAL);
+        }
+        let queue = MSG_QUEUE_MANAGER
+            .lock()
+            .get(msqid)
+            .ok_or(SysError::EINVAL)?;
+        let mtype = UserReadPtr::<isize>::from(msgp).read(task)?;
+        if mtype <= 0 {
+            return Err(SysError::EINVAL);
+        }
+        let mtext = if msgsz > 0 {
+            UserReadPtr::<u8>::from(msgp + size_of::<isize>()).read_array(task, msgsz)?
+        } else {
+            Default::default()
+        };
+        let send_future = MsgSendFuture::new(queue, Message { mtype, mtext }, flags, task.pid());
+
+        task.set_interruptable();
+        task.set_wake_up_signal(!*task.sig_mask_ref());
+        let intr_future = IntrBySignalFuture {
+            task: task.clone(),
+            mask: *task.sig_mask_ref(),
+        };
+        let ret = match Select2Futures::new(send_future, intr_future).await {
+            SelectOutput::Output1(ret) => ret,
+            SelectOutput::Output2(_) => Err(SysError::EINTR),
+        };
+        task.set_running();
+        ret.map(|_| 0)
+    }
+
+    /// The msgrcv() system call removes a message from the queue specified by
+    /// msqid and places it in the buffer pointed to by msgp.
+    ///
+    /// The argument msgsz specifies the maximum size in bytes for the member
+    /// mtext of the structure pointed to by the msgp argument. If the message
+    /// text has length greater than msgsz, then the behavior depends on
+    /// whether MSG_NOERROR is specified in msgflg. If MSG_NOERROR is
+    /// specified, then the message text will be truncated; if MSG_NOERROR is
+    /// not specified, then the message isn't removed from the queue and the
+    /// system call fails returning -1 with errno set to E2BIG.
+    ///
+    /// If no message of the requested type is available and IPC_NOWAIT isn't
+    /// specified in msgflg, the calling process is blocked until a message of
+    /// the requested type is placed in the queue, the message queue is
+    /// removed, or the calling process catches a signal.
+    ///
+    /// On success, msgrcv() returns the number of bytes actually copied into
+    /// the mtext array.
+    pub async fn sys_msgrcv(
+        &self,
+        msqid: usize,
+        msgp: usize,
+        msgsz: isize,
+        msgtyp: isize,
+        msgflg: i32,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = MsgFlags::from_bits_truncate(msgflg);
+        log::info!(
+            "[sys_msgrcv] msqid: {msqid}, msgsz: {msgsz}, msgtyp: {msgtyp}, flags: {flags:?}"
+        );
+        if msgsz < 0 {
+            return Err(SysError::EINVAL);
+        }
+        if flags.contains(MsgFlags::MSG_COPY) {
+            log::warn!("[sys_msgrcv] MSG_COPY is not supported");
+            return Err(SysError::ENOSYS);
+        }
+        let queue = MSG_QUEUE_MANAGER
+            .lock()
+            .get(msqid)
+            .ok_or(SysError::EINVAL)?;
+        let recv_future = MsgRecvFuture::new(queue, msgtyp, msgsz as usize, flags, task.pid());
+
+        task.set_interruptable();
+        task.set_wake_up_signal(!*task.sig_mask_ref());
+        let intr_future = IntrBySignalFuture {
+            task: task.clone(),
+            mask: *task.sig_mask_ref(),
+        };
+        let ret = match Select2Futures::new(recv_future, intr_future).await {
+            SelectOutput::Output1(ret) => ret,
+            SelectOutput::Output2(_) => Err(SysError::EINTR),
+        };
+        task.set_running();
+        let msg = ret?;
+
+        UserWritePtr::<isize>::from(msgp).write(task, msg.mtype)?;
+        if !msg.mtext.is_empty() {
+            UserWritePtr::<u8>::from(msgp + size_of::<isize>()).write_array(task, &msg.mtext)?;
+        }
+        Ok(msg.mtext.len())
+    }
+
+    /// msgctl() performs the control operation specified by cmd on the System
+    /// V message queue with identifier msqid.
+    ///
+    /// + IPC_STAT: Copy information from the kernel data structure associated
+    ///   with msqid into the msqid_ds structure pointed to by buf.
+    /// + IPC_SET: Write the values of some members of the msqid_ds structure
+    ///   pointed to by buf to the kernel data structure associated with this
+    ///   message queue, updating also its msg_ctime member.
+    /// + IPC_RMID: Immediately remove the message queue, awakening all waiting
+    ///   reader and writer processes (with an error return and errno set to
+    ///   EIDRM).
+    pub fn sys_msgctl(&self, msqid: usize, cmd: i32, buf: usize) -> SyscallResult {
+        let task = self.task;
+        let cmd = cmd & !IPC_64;
+        log::info!("[sys_msgctl] msqid: {msqid}, cmd: {cmd}");
+        match cmd {
+            IPC_STAT => {
+                let queue = MSG_QUEUE_MANAGER
+                    .lock()
+                    .get(msqid)
+                    .ok_or(SysError::EINVAL)?;
+                UserWritePtr::<MsqIdDs>::from(buf).write(task, queue.msqid_ds())?;
+                Ok(0)
+            }
+            IPC_SET => {
+                let queue = MSG_QUEUE_MANAGER
+                    .lock()
+                    .get(msqid)
+                    .ok_or(SysError::EINVAL)?;
+                let msqid_ds = UserReadPtr::<MsqIdDs>::from(buf).read(task)?;
+                queue.set(&msqid_ds)?;
+                Ok(0)
+            }
+            IPC_RMID => {
+                MSG_QUEUE_MANAGER.lock().remove(msqid)?;
+                Ok(0)
+            }
+            cmd => {
+                log::error!("[sys_msgctl] unimplemented cmd {cmd}");
+                Err(SysError::EINVAL)
+            }
+        }
+    }
+
+    /// The semget() system call returns the System V semaphore set identifier
+    /// associated with the argument key. It may be used either to obtain the
+    /// identifier of a previously created semaphore set (when semflg is zero
+    /// and key does not have the value IPC_PRIVATE), or to create a new set.
+    ///
+    /// A new set of nsems semaphores is created if key has the value
+    /// IPC_PRIVATE or if no existing semaphore set is associated with key and
+    /// IPC_CREAT is specified in semflg.
+    pub fn sys_semget(&self, key: i32, nsems: i32, semflg: i32) -> SyscallResult {
+        let flags = IpcFlags::from_bits_truncate(semflg);
+        let mode = (semflg & 0o777) as u32;
+        log::info!("[sys_semget] key: {key}, nsems: {nsems}, flags: {flags:?}, mode: {mode:o}");
+        if nsems < 0 || nsems as usize > SEMMSL {
+            return Err(SysError::EINVAL);
+        }
+        let nsems = nsems as usize;
+        let mut manager = SEM_SET_MANAGER.lock();
+        if key == IPC_PRIVATE {
+            if nsems == 0 {
+                return Err(SysError::EINVAL);
+            }
+            return Ok(manager.create(key, mode, nsems));
+        }
+        match manager.get_by_key(key) {
+            Some(semid) => {
+                // IPC_CREAT and IPC_EXCL were specified in semflg, but a semaphore set
+                // already exists for key.
+                if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                    return Err(SysError::EEXIST);
+                }
+                // A semaphore set corresponding to key already exists, but nsems is larger
+                // than the number of semaphores in that set.
+                if nsems > manager.get(semid).unwrap().nsems() {
+                    return Err(SysError::EINVAL);
+                }
+                Ok(semid)
+            }
+            None if flags.contains(IpcFlags::IPC_CREAT) => {
+                if nsems == 0 {
+                    return Err(SysError::EINVAL);
+                }
+                Ok(manager.create(key, mode, nsems))
+            }
+            // No semaphore set exists for key and semflg did not specify IPC_CREAT.
+            None => Err(SysError::ENOENT),
+        }
+    }
+
+    /// semop() performs operations on selected semaphores in the set
+    /// indicated by semid. Each of the nsops elements in the array pointed to
+    /// by sops is a structure that specifies an operation to be performed on
+    /// a single semaphore.
+    ///
+    /// The set of operations contained in sops is performed in array order,
+    /// and atomically, that is, the operations are performed either as a
+    /// complete unit, or not at all.
+    pub async fn sys_semop(
+        &self,
+        semid: usize,
+        sops: UserReadPtr<SemBuf>,
+        nsops: usize,
+    ) -> SyscallResult {
+        self.sys_semtimedop(semid, sops, nsops, UserReadPtr::null())
+            .await
+    }
+
+    /// semtimedop() behaves identically to semop() except that in those cases
+    /// where the calling thread would sleep, the duration of that sleep is
+    /// limited by the amount of elapsed time specified by the timespec
+    /// structure whose address is passed in the timeout argument. If the
+    /// specified time limit has been reached, semtimedop() fails with errno
+    /// set to EAGAIN (and none of the operations in sops is performed). If
+    /// the timeout argument is NULL, then semtimedop() behaves exactly like
+    /// semop().
+    pub async fn sys_semtimedop(
+        &self,
+        semid: usize,
+        sops: UserReadPtr<SemBuf>,
+        nsops: usize,
+        timeout: UserReadPtr<TimeSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        if nsops == 0 {
+            return Err(SysError::EINVAL);
+        }
+        if nsops > SEMOPM {
+            return Err(SysError::E2BIG);
+        }
+        let sops = sops.read_array(task, nsops)?;
+        let mut timeout: Option<Duration> = if timeout.is_null() {
+            None
+        } else {
+            let timeout = timeout.read(task)?;
+            if !timeout.is_valid() {
+                return Err(SysError::EINVAL);
+            }
+            Some(timeout.into())
+        };
+        log::info!("[sys_semtimedop] semid: {semid}, sops: {sops:?}, timeout: {timeout:?}");
+        let set = SEM_SET_MANAGER.lock().get(semid).ok_or(SysError::EINVAL)?;
+
+        let waker = task.waker().clone().unwrap();
+        let wake_up_signal = !*task.sig_mask_ref();
+        while !set.semop(&sops, task.pid(), task.tid(), &waker)? {
+            task.set_interruptable();
+            task.set_wake_up_signal(wake_up_signal);
+            if let Some(limit) = timeout {
+                let rem = task.suspend_timeout(limit).await;
+                timeout = Some(rem);
+            } else {
+                suspend_now().await;
+            }
+            task.set_running();
+            set.remove_waiter(task.tid());
+            if task.with_sig_pending(|p| p.has_expect_signals(wake_up_signal)) {
+                log::info!("[sys_semtimedop] woken by signal");
+                return Err(SysError::EINTR);
+            }
+            if timeout.is_some_and(|rem| rem.is_zero()) {
+                log::info!("[sys_semtimedop] timeout");
+                return Err(SysError::EAGAIN);
+            }
+        }
+        Ok(0)
+    }
+
+    /// semctl() performs the control operation specified by cmd on the System
+    /// V semaphore set identified by semid, or on the semnum-th semaphore of
+    /// that set. (The semaphores in a set are numbered starting at 0.)
+    ///
+    /// This function has three or four arguments, depending on cmd. When
+    /// there are four, the fourth is a union semun, which is passed by value.
+    pub fn sys_semctl(&self, semid: usize, semnum: usize, cmd: i32, arg: usize) -> SyscallResult {
+        /// Get sempid.
+        const GETPID: i32 = 11;
+        /// Get semval.
+        const GETVAL: i32 = 12;
+        /// Get all semval's.
+        const GETALL: i32 = 13;
+        /// Get semncnt.
+        const GETNCNT: i32 = 14;
+        /// Get semzcnt.
+        const GETZCNT: i32 = 15;
+        /// Set semval.
+        const SETVAL: i32 = 16;
+        /// Set all semval's.
+        const SETALL: i32 = 17;
+
+        let task = self.task;
+        let cmd = cmd & !IPC_64;
+        log::info!("[sys_semctl] semid: {semid}, semnum: {semnum}, cmd: {cmd}, arg: {arg:#x}");
+        if cmd == IPC_RMID {
+            SEM_SET_MANAGER.lock().remove(semid)?;
+            return Ok(0);
+        }
+        let set = SEM_SET_MANAGER.lock().get(semid).ok_or(SysError::EINVAL)?;
+        let nsems = set.nsems();
+        if matches!(cmd, GETPID | GETVAL | GETNCNT | GETZCNT | SETVAL) && semnum >= nsems {
+            return Err(SysError::EINVAL);
+        }
+        match cmd {
+            IPC_STAT => {
+                UserWritePtr::<SemIdDs>::from(arg).write(task, set.semid_ds())?;
+                Ok(0)
+            }
+            IPC_SET => {
+                let semid_ds = UserReadPtr::<SemIdDs>::from(arg).read(task)?;
+                set.set(&semid_ds);
+                Ok(0)
+            }
+            GETPID => Ok(set.getpid(semnum) as usize),
+            GETVAL => Ok(set.getval(semnum) as usize),
+            GETNCNT => Ok(set.waiting_count(semnum, false)),
+            GETZCNT => Ok(set.waiting_count(semnum, true)),
+            GETALL => {
+                UserWritePtr::<u16>::from(arg).write_array(task, &set.getall())?;
+                Ok(0)
+            }
+            SETVAL => {
+                set.setvals(semnum, &[arg as i32], task.pid())?;
+                Ok(0)
+            }
+            SETALL => {
+                let vals: Vec<i32> = UserReadPtr::<u16>::from(arg)
+                    .read_array(task, nsems)?
+                    .into_iter()
+                    .map(|val| val as i32)
+                    .collect();
+                set.setvals(0, &vals, task.pid())?;
+                Ok(0)
+            }
+            cmd => {
+                log::error!("[sys_semctl] unimplemented cmd {cmd}");
+                Err(SysError::EINVAL)
+            }
+        }
+    }
+
+    /// mq_open() creates a new POSIX message queue or opens an existing queue.
+    /// The queue is identified by name, which has the form /somename; that
+    /// is, a null-terminated string of up to NAME_MAX (i.e., 255) characters
+    /// consisting of an initial slash, followed by one or more characters,
+    /// none of which are slashes.
+    ///
+    /// If O_CREAT is specified in oflag, then mode specifies the permissions
+    /// to be placed on the new queue, and attr specifies the maximum number of
+    /// messages and the maximum size of messages that the queue will allow.
+    /// If attr is NULL, then the queue is created with implementation-defined
+    /// default attributes.
+    ///
+    /// On success, mq_open() returns a message queue descriptor for use by
+    /// other message queue functions.
+    pub fn sys_mq_open(
+        &self,
+        name: UserReadPtr<u8>,
+        oflag: i32,
+        mode: u32,
+        attr: UserReadPtr<MqAttr>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = OpenFlags::from_bits(oflag).ok_or(SysError::EINVAL)?;
+        let mode = InodeMode::from_bits_truncate(mode & 0o777);
+        let name = mq_name(name.read_cstr(task)?)?;
+        log::info!("[sys_mq_open] name: {name}, flags: {flags:?}, mode: {mode:?}");
+        let root = mqueue_root();
+        let dentry = root.lookup(&name)?;
+        if flags.contains(OpenFlags::O_CREAT) {
+            if !dentry.is_negetive() {
+                if flags.contains(OpenFlags::O_EXCL) {
+                    return Err(SysError::EEXIST);
+                }
+            } else {
+                let attr = if attr.is_null() {
+                    None
+                } else {
+                    Some(attr.read(task)?)
+                };
+                root.create(&name, InodeMode::FILE | mode)?;
+                if let Some(attr) = attr {
+                    let mqueue = dentry
+                        .inode()?
+                        .downcast_arc::<MqueueInode>()
+                        .map_err(|_| SysError::EINVAL)?;
+                    if let Err(err) = mqueue.set_limits(&attr) {
+                        root.unlink(&name)?;
+                        return Err(err);
+                    }
+                }
+            }
+        } else if dentry.is_negetive() {
+            return Err(SysError::ENOENT);
+        }
+
+        let file = dentry.open()?;
+        file.set_flags(flags);
+        task.with_mut_fd_table(|table| table.alloc(file, flags))
+    }
+
+    /// mq_unlink() removes the specified message queue name. The message queue
+    /// name is removed immediately. The queue itself is destroyed once any
+    /// other processes that have the queue open close their descriptors
+    /// referring to the queue.
+    pub fn sys_mq_unlink(&self, name: UserReadPtr<u8>) -> SyscallResult {
+        let task = self.task;
+        let name = mq_name(name.read_cstr(task)?)?;
+        log::info!("[sys_mq_unlink] name: {name}");
+        mqueue_root().unlink(&name)?;
+        Ok(0)
+    }
+
+    /// mq_timedsend() adds the message pointed to by msg_ptr to the message
+    /// queue referred to by the message queue descriptor mqdes. The msg_len
+    /// argument specifies the length of the message pointed to by msg_ptr;
+    /// this length must be less than or equal to the queue's mq_msgsize
+    /// attribute.
+    ///
+    /// The msg_prio argument is a nonnegative integer that specifies the
+    /// priority of this message. Messages are placed on the queue in
+    /// decreasing order of priority, with newer messages of the same priority
+    /// being placed after older messages with the same priority.
+    ///
+    /// If the message queue is already full, then by default, mq_timedsend()
+    /// blocks until sufficient space becomes available, or until abs_timeout
+    /// expires, or the call is interrupted by a signal handler.
+    pub async fn sys_mq_timedsend(
+        &self,
+        mqdes: usize,
+        msg_ptr: UserReadPtr<u8>,
+        msg_len: usize,
+        msg_prio: u32,
+        abs_timeout: UserReadPtr<TimeSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        log::info!("[sys_mq_timedsend] mqdes: {mqdes}, msg_len: {msg_len}, msg_prio: {msg_prio}");
+        let mqueue = task
+            .with_fd_table(|table| table.get_file(mqdes))?
+            .downcast_arc::<MqueueFile>()
+            .map_err(|_| SysError::EBADF)?;
+        if !mqueue.flags().writable() {
+            return Err(SysError::EBADF);
+        }
+        if msg_prio >= MQ_PRIO_MAX {
+            return Err(SysError::EINVAL);
+        }
+        let msg = msg_ptr.read_array(task, msg_len)?;
+        let timeout = mq_timeout(task, abs_timeout)?;
+        let send_future = mqueue.send(msg, msg_prio, task.pid())?;
+        mq_wait(task, send_future, timeout).await?;
+        Ok(0)
+    }
+
+    /// mq_timedreceive() removes the oldest message with the highest priority
+    /// from the message queue referred to by the message queue descriptor
+    /// mqdes, and places it in the buffer pointed to by msg_ptr. The msg_len
+    /// argument specifies the size of the buffer pointed to by msg_ptr; this
+    /// must be greater than or equal to the mq_msgsize attribute of the queue.
+    /// If msg_prio is not NULL, then the buffer to which it points is used to
+    /// return the priority associated with the received message.
+    ///
+    /// If the queue is empty, then, by default, mq_timedreceive() blocks until
+    /// a message becomes available, or until abs_timeout expires, or the call
+    /// is interrupted by a signal handler.
+    ///
+    /// On success, mq_timedreceive() returns the number of bytes in the
+    /// received message.
+    pub async fn sys_mq_timedreceive(
+        &self,
+        mqdes: usize,
+        msg_ptr: UserWritePtr<u8>,
+        msg_len: usize,
+        msg_prio: UserWritePtr<u32>,
+        abs_timeout: UserReadPtr<TimeSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        log::info!("[sys_mq_timedreceive] mqdes: {mqdes}, msg_len: {msg_len}");
+        let mqueue = task
+            .with_fd_table(|table| table.get_file(mqdes))?
+            .downcast_arc::<MqueueFile>()
+            .map_err(|_| SysError::EBADF)?;
+        if !mqueue.flags().readable() {
+            return Err(SysError::EBADF);
+        }
+        let timeout = mq_timeout(task, abs_timeout)?;
+        let recv_future = mqueue.receive(msg_len)?;
+        let (msg, prio) = mq_wait(task, recv_future, timeout).await?;
+
+        if !msg.is_empty() {
+            msg_ptr.write_array(task, &msg)?;
+        }
+        if !msg_prio.is_null() {
+            msg_prio.write(task, prio)?;
+        }
+        Ok(msg.len())
+    }
+
+    /// mq_notify() allows the calling process to register or unregister for
+    /// delivery of an asynchronous notification when a new message arrives on
+    /// the empty message queue referred to by the message queue descriptor
+    /// mqdes.
+    ///
+    /// If sevp is NULL, and the calling process is currently registered to
+    /// receive notifications for this message queue, then the registration is
+    /// removed; another process can then register to receive a message
+    /// notification for this queue.
+    ///
+    /// Only one process can be registered to receive notification from a
+    /// message queue. Message notification occurs only when a new message
+    /// arrives and the queue was previously empty, and no other process is
+    /// blocked in mq_timedreceive(). After delivery the registration is
+    /// removed.
+    pub fn sys_mq_notify(&self, mqdes: usize, sevp: UserReadPtr<SigEvent>) -> SyscallResult {
+        let task = self.task;
+        let mqueue = task
+            .with_fd_table(|table| table.get_file(mqdes))?
+            .downcast_arc::<MqueueFile>()
+            .map_err(|_| SysError::EBADF)?;
+        let pid = task.pid();
+        if sevp.is_null() {
+            log::info!("[sys_mq_notify] mqdes: {mqdes}, unregister");
+            mqueue.mqueue().set_notify(pid, None)?;
+            return Ok(0);
+        }
+        let sigevent = sevp.read(task)?;
+        log::info!("[sys_mq_notify] mqdes: {mqdes}, sigevent: {sigevent:?}");
+        let notify = match sigevent.sigev_notify {
+            SigEvent::SIGEV_NONE => MqNotify {
+                pid,
+                signo: 0,
+                callback: None,
+            },
+            SigEvent::SIGEV_SIGNAL => {
+                let sig = Sig::from_i32(sigevent.sigev_signo);
+                if !sig.is_valid() {
+                    return Err(SysError::EINVAL);
+                }
+                MqNotify {
+                    pid,
+                    signo: sigevent.sigev_signo,
+                    callback: Some(Box::new(move |sender| {
+                        if let Some(process) = TASK_MANAGER.get(pid) {
+                            process.receive_siginfo(
+                                SigInfo {
+                                    sig,
+                                    code: SigInfo::MESGQ,
+                                    details: SigDetails::Kill { pid: sender },
+                                },
+                                false,
+                            );
+                        }
+                    })),
+                }
+            }
+            notify => {
+                log::warn!("[sys_mq_notify] unsupported sigev_notify {notify}");
+                return Err(SysError::EINVAL);
+            }
+        };
+        mqueue.mqueue().set_notify(pid, Some(notify))?;
+        Ok(0)
+    }
+
+    /// mq_getsetattr() retrieves and modifies attributes of the message queue
+    /// referred to by the message queue descriptor mqdes. It is the system
+    /// call underlying mq_getattr() and mq_setattr().
+    ///
+    /// The only attribute that can be modified is the setting of the
+    /// O_NONBLOCK flag in mq_flags. The other fields in newattr are ignored.
+    pub fn sys_mq_getsetattr(
+        &self,
+        mqdes: usize,
+        newattr: UserReadPtr<MqAttr>,
+        oldattr: UserWritePtr<MqAttr>,
+    ) -> SyscallResult {
+        let task = self.task;
+        log::info!("[sys_mq_getsetattr] mqdes: {mqdes}");
+        let mqueue = task
+            .with_fd_table(|table| table.get_file(mqdes))?
+            .downcast_arc::<MqueueFile>()
+            .map_err(|_| SysError::EBADF)?;
+        let old = mqueue.getattr();
+        if !newattr.is_null() {
+            let attr = newattr.read(task)?;
+            if attr.mq_flags & !(OpenFlags::O_NONBLOCK.bits() as isize) != 0 {
+                return Err(SysError::EINVAL);
+            }
+            mqueue.setattr(&attr);
+        }
+        if !oldattr.is_null() {
+            oldattr.write(task, old)?;
+        }
+        Ok(0)
+    }
+}
+
+/// Check the name passed to `mq_open` and `mq_unlink` and strip the leading
+/// slash.
+fn mq_name(name: String) -> SysResult<String> {
+    let name = name.strip_prefix('/').unwrap_or(&name);
+    if name.is_empty() {
+        return Err(SysError::ENOENT);
+    }
+    if name.contains('/') {
+        return Err(SysError::EACCES);
+    }
+    if name.len() > NAME_MAX {
+        return Err(SysError::ENAMETOOLONG);
+    }
+    Ok(name.to_string())
+}
+
+/// Convert the absolute timeout measured against `CLOCK_REALTIME` to the time
+/// left, `None` for waiting forever.
+fn mq_timeout(task: &Arc<Task>, abs_timeout: UserReadPtr<TimeSpec>) -> SysResult<Option<Duration>> {
+    if abs_timeout.is_null() {
+        return Ok(None);
+    }
+    let abs_timeout = abs_timeout.read(task)?;
+    if !abs_timeout.is_valid() {
+        return Err(SysError::EINVAL);
+    }
+    let now = unsafe { CLOCK_DEVIATION[CLOCK_REALTIME] } + get_time_duration();
+    Ok(Some(Duration::from(abs_timeout).saturating_sub(now)))
+}
+
+/// Wait for the message queue operation until it completes, the timeout
+/// expires or a signal arrives.
+async fn mq_wait<T, F>(task: &Arc<Task>, future: F, timeout: Option<Duration>) -> SysResult<T>
+where
+    F: Future<Output = SysResult<T>> + Send + 'static,
+{
+    task.set_interruptable();
+    task.set_wake_up_signal(!*task.sig_mask_ref());
+    let intr_future = IntrBySignalFuture {
+        task: task.clone(),
+        mask: *task.sig_mask_ref(),
+    };
+    let ret = match timeout {
+        Some(timeout) => {
+            match Select2Futures::new(TimeLimitedTaskFuture::new(timeout, future), intr_future)
+                .await
+            {
+                SelectOutput::Output1(TimeLimitedTaskOutput::Ok(ret)) => ret,
+                SelectOutput::Output1(TimeLimitedTaskOutput::TimeOut) => Err(SysError::ETIMEDOUT),
+                SelectOutput::Output2(_) => Err(SysError::EINTR),
+            }
+        }
+        None => match Select2Futures::new(future, intr_future).await {
+            SelectOutput::Output1(ret) => ret,
+            SelectOutput::Output2(_) => Err(SysError::EINTR),
+        },
+    };
+    task.set_running();
+    ret
+}
@@ -4,8 +4,13 @@ use systype::{SysError, SyscallResult};
 
 use super::Syscall;
 use crate::{
+    io_uring::IoUringFile,
     ipc::shm::{SharedMemory, SHARED_MEMORY_KEY_ALLOCATOR, SHARED_MEMORY_MANAGER},
-    mm::{memory_space::vm_area::MapPerm, UserWritePtr},
+    mm::{
+        memory_space::vm_area::MapPerm,
+        swap::{self, SwapFlags},
+        UserReadPtr, UserWritePtr,
+    },
 };
 
 bitflags! {
@@ -130,6 +135,25 @@ impl Syscall<'_> {
             task.with_mut_memory_space(|m| m.unmap(addr..(addr + length).round_up()))?;
         }
 
+        // Rings of io_uring are mapped by their offsets and always shared with
+        // the kernel.
+        if !flags.contains(MmapFlags::MAP_ANONYMOUS) {
+            let file = task.with_fd_table(|table| table.get_file(fd))?;
+            if let Ok(file) = file.downcast_arc::<IoUringFile>() {
+                if !matches!(
+                    flags.intersection(MmapFlags::MAP_TYPE_MASK),
+                    MmapFlags::MAP_SHARED | MmapFlags::MAP_SHARED_VALIDATE
+                ) {
+                    return Err(SysError::EINVAL);
+                }
+                let pages = file.ring().pages(offset)?;
+                let start_va = task.with_mut_memory_space(|m| {
+                    m.alloc_mmap_io_uring(addr, length, perm, flags, pages)
+                })?;
+                return Ok(start_va.bits());
+            }
+        }
+
         match flags.intersection(MmapFlags::MAP_TYPE_MASK) {
             MmapFlags::MAP_SHARED => {
                 if flags.contains(MmapFlags::MAP_ANONYMOUS) {
@@ -397,4 +421,39 @@ impl Syscall<'_> {
         task.with_mut_memory_space(|m| m.mprotect(new_range, perm))
             .map(|_| 0)
     }
+
+    /// swapon() sets the swap area to the file specified by path. swapoff()
+    /// stops swapping to the file specified by path.
+    ///
+    /// If the SWAP_FLAG_PREFER flag is specified in the swapon() swapflags
+    /// argument, the new swap area will have a higher priority than default.
+    /// The priority is encoded within swapflags as:
+    ///
+    /// `(prio << SWAP_FLAG_PRIO_SHIFT) & SWAP_FLAG_PRIO_MASK`
+    ///
+    /// Swap pages are allocated from areas in priority order, highest priority
+    /// first.
+    ///
+    /// Only swap files on file systems backed by a device are supported, block
+    /// devices and files in memory, e.g. on tmpfs, are not.
+    pub fn sys_swapon(&self, path: UserReadPtr<u8>, swapflags: i32) -> SyscallResult {
+        let task = self.task;
+        let path = path.read_cstr(&task)?;
+        let flags = SwapFlags::from_bits(swapflags).ok_or(SysError::EINVAL)?;
+        log::info!("[sys_swapon] path {path}, flags {flags:?}");
+        let file = task.resolve_path(&path)?.open()?;
+        swap::swapon(file, flags)?;
+        Ok(0)
+    }
+
+    /// swapoff() stops swapping to the file specified by path, after reading
+    /// all pages swapped out to it back into memory.
+    pub fn sys_swapoff(&self, path: UserReadPtr<u8>) -> SyscallResult {
+        let task = self.task;
+        let path = path.read_cstr(&task)?;
+        log::info!("[sys_swapoff] path {path}");
+        let inode = task.resolve_path(&path)?.inode()?;
+        swap::swapoff(&inode)?;
+        Ok(0)
+    }
 }
@@ -1,9 +1,12 @@
 //! Implementation of syscalls
 
+mod aio;
 mod consts;
 mod fs;
 pub mod futex;
 mod io;
+mod io_uring;
+mod ipc;
 mod misc;
 mod mm;
 mod net;
@@ -13,10 +16,12 @@ mod resource;
 mod sched;
 mod signal;
 mod time;
+mod xattr;
 
 use alloc::sync::Arc;
 
 pub use consts::SyscallNo;
+pub use fs::IoVec;
 pub use mm::MmapFlags;
 pub use process::CloneFlags;
 use systype::{SysError, SysResult, SyscallResult};
@@ -100,6 +105,11 @@ impl<'a> Syscall<'a> {
                 self.sys_wait4(args[0] as _, args[1].into(), args[2] as _, args[3])
                     .await
             }
+            WAITID => {
+                self.sys_waitid(args[0] as _, args[1], args[2].into(), args[3] as _, args[4])
+                    .await
+            }
+            PIDFD_OPEN => self.sys_pidfd_open(args[0] as _, args[1] as _),
             GETTID => self.sys_gettid(),
             GETPID => self.sys_getpid(),
             GETPPID => self.sys_getppid(),
@@ -123,6 +133,8 @@ impl<'a> Syscall<'a> {
             ),
             MUNMAP => self.sys_munmap(args[0].into(), args[1]),
             MPROTECT => self.sys_mprotect(args[0].into(), args[1], args[2] as _),
+            SWAPON => self.sys_swapon(args[0].into(), args[1] as _),
+            SWAPOFF => self.sys_swapoff(args[0].into()),
             MSYNC => self.sys_do_nothing("msync"),
             MEMBARRIER => self.sys_do_nothing("membarrier"),
             MADVISE => self.sys_do_nothing("madvise"),
@@ -131,6 +143,47 @@ impl<'a> Syscall<'a> {
             SHMAT => self.sys_shmat(args[0], args[1].into(), args[2] as _),
             SHMDT => self.sys_shmdt(args[0].into()),
             SHMCTL => self.sys_shmctl(args[0], args[1] as _, args[2]),
+            MSGGET => self.sys_msgget(args[0] as _, args[1] as _),
+            MSGSND => {
+                self.sys_msgsnd(args[0], args[1], args[2], args[3] as _)
+                    .await
+            }
+            MSGRCV => {
+                self.sys_msgrcv(args[0], args[1], args[2] as _, args[3] as _, args[4] as _)
+                    .await
+            }
+            MSGCTL => self.sys_msgctl(args[0], args[1] as _, args[2]),
+            SEMGET => self.sys_semget(args[0] as _, args[1] as _, args[2] as _),
+            SEMOP => self.sys_semop(args[0], args[1].into(), args[2]).await,
+            SEMTIMEDOP => {
+                self.sys_semtimedop(args[0], args[1].into(), args[2], args[3].into())
+                    .await
+            }
+            SEMCTL => self.sys_semctl(args[0], args[1], args[2] as _, args[3]),
+            MQ_OPEN => self.sys_mq_open(args[0].into(), args[1] as _, args[2] as _, args[3].into()),
+            MQ_UNLINK => self.sys_mq_unlink(args[0].into()),
+            MQ_TIMEDSEND => {
+                self.sys_mq_timedsend(
+                    args[0],
+                    args[1].into(),
+                    args[2],
+                    args[3] as _,
+                    args[4].into(),
+                )
+                .await
+            }
+            MQ_TIMEDRECEIVE => {
+                self.sys_mq_timedreceive(
+                    args[0],
+                    args[1].into(),
+                    args[2],
+                    args[3].into(),
+                    args[4].into(),
+                )
+                .await
+            }
+            MQ_NOTIFY => self.sys_mq_notify(args[0], args[1].into()),
+            MQ_GETSETATTR => self.sys_mq_getsetattr(args[0], args[1].into(), args[2].into()),
             // File system
             READ => self.sys_read(args[0], args[1].into(), args[2]).await,
             WRITE => self.sys_write(args[0], args[1].into(), args[2]).await,
@@ -170,14 +223,31 @@ impl<'a> Syscall<'a> {
             }
             UMOUNT2 => self.sys_umount2(args[0].into(), args[1] as _).await,
             PIPE2 => self.sys_pipe2(args[0].into(), args[1] as _),
+            EVENTFD2 => self.sys_eventfd2(args[0] as _, args[1] as _),
+            MEMFD_CREATE => self.sys_memfd_create(args[0].into(), args[1] as _),
+            INOTIFY_INIT1 => self.sys_inotify_init1(args[0] as _),
+            INOTIFY_ADD_WATCH => self.sys_inotify_add_watch(args[0], args[1].into(), args[2] as _),
+            INOTIFY_RM_WATCH => self.sys_inotify_rm_watch(args[0], args[1] as _),
             IOCTL => self.sys_ioctl(args[0], args[1], args[2]),
-            FCNTL => self.sys_fcntl(args[0], args[1] as _, args[2]),
+            FCNTL => self.sys_fcntl(args[0], args[1] as _, args[2]).await,
+            FLOCK => self.sys_flock(args[0], args[1] as _).await,
             WRITEV => self.sys_writev(args[0], args[1].into(), args[2]).await,
             READV => self.sys_readv(args[0], args[1].into(), args[2]).await,
             SENDFILE => {
                 self.sys_sendfile(args[0], args[1], args[2].into(), args[3])
                     .await
             }
+            COPY_FILE_RANGE => {
+                self.sys_copy_file_range(
+                    args[0],
+                    args[1].into(),
+                    args[2],
+                    args[3].into(),
+                    args[4],
+                    args[5] as _,
+                )
+                .await
+            }
             FACCESSAT => self.sys_faccessat(args[0].into(), args[1].into(), args[2], args[3] as _),
             LSEEK => self.sys_lseek(args[0], args[1] as _, args[2]),
             UMASK => self.sys_umask(args[0] as _),
@@ -196,8 +266,10 @@ impl<'a> Syscall<'a> {
                 self.sys_readlinkat(args[0].into(), args[1].into(), args[2].into(), args[3])
                     .await
             }
-            SYNC => self.sys_do_nothing("sync"),
-            FSYNC => self.sys_do_nothing("fsync"),
+            SYNC => self.sys_sync(),
+            FSYNC => self.sys_fsync(args[0]),
+            FDATASYNC => self.sys_fdatasync(args[0]),
+            SYNCFS => self.sys_syncfs(args[0]),
             FTRUNCATE => self.sys_ftruncate(args[0], args[1] as _).await,
             FCHMODAT => self.sys_fchmodat(),
             FCHOWNAT => self.sys_do_nothing("fchownat"),
@@ -237,6 +309,77 @@ impl<'a> Syscall<'a> {
                 )
                 .await
             }
+            IO_SETUP => self.sys_io_setup(args[0] as _, args[1].into()),
+            IO_DESTROY => self.sys_io_destroy(args[0]).await,
+            IO_SUBMIT => self.sys_io_submit(args[0], args[1] as _, args[2].into()),
+            IO_CANCEL => self.sys_io_cancel(args[0], args[1], args[2].into()),
+            IO_GETEVENTS => {
+                self.sys_io_getevents(
+                    args[0],
+                    args[1] as _,
+                    args[2] as _,
+                    args[3].into(),
+                    args[4].into(),
+                )
+                .await
+            }
+            SETXATTR => self.sys_setxattr(
+                args[0].into(),
+                args[1].into(),
+                args[2].into(),
+                args[3],
+                args[4] as _,
+            ),
+            LSETXATTR => self.sys_lsetxattr(
+                args[0].into(),
+                args[1].into(),
+                args[2].into(),
+                args[3],
+                args[4] as _,
+            ),
+            FSETXATTR => self.sys_fsetxattr(
+                args[0],
+                args[1].into(),
+                args[2].into(),
+                args[3],
+                args[4] as _,
+            ),
+            GETXATTR => self.sys_getxattr(args[0].into(), args[1].into(), args[2].into(), args[3]),
+            LGETXATTR => {
+                self.sys_lgetxattr(args[0].into(), args[1].into(), args[2].into(), args[3])
+            }
+            FGETXATTR => self.sys_fgetxattr(args[0], args[1].into(), args[2].into(), args[3]),
+            LISTXATTR => self.sys_listxattr(args[0].into(), args[1].into(), args[2]),
+            LLISTXATTR => self.sys_llistxattr(args[0].into(), args[1].into(), args[2]),
+            FLISTXATTR => self.sys_flistxattr(args[0], args[1].into(), args[2]),
+            REMOVEXATTR => self.sys_removexattr(args[0].into(), args[1].into()),
+            LREMOVEXATTR => self.sys_lremovexattr(args[0].into(), args[1].into()),
+            FREMOVEXATTR => self.sys_fremovexattr(args[0], args[1].into()),
+            IO_URING_SETUP => self.sys_io_uring_setup(args[0] as _, args[1].into()),
+            IO_URING_ENTER => {
+                self.sys_io_uring_enter(
+                    args[0],
+                    args[1] as _,
+                    args[2] as _,
+                    args[3] as _,
+                    args[4].into(),
+                    args[5],
+                )
+                .await
+            }
+            IO_URING_REGISTER => self.sys_io_uring_register(args[0], args[1], args[2], args[3]),
+            EPOLL_CREATE1 => self.sys_epoll_create1(args[0] as _),
+            EPOLL_CTL => self.sys_epoll_ctl(args[0], args[1] as _, args[2], args[3].into()),
+            EPOLL_PWAIT => {
+                self.sys_epoll_pwait(
+                    args[0],
+                    args[1].into(),
+                    args[2] as _,
+                    args[3] as _,
+                    args[4].into(),
+                )
+                .await
+            }
             // Signal
             RT_SIGPROCMASK => {
                 self.sys_rt_sigprocmask(args[0], args[1].into(), args[2].into(), args[3])
@@ -245,8 +388,12 @@ impl<'a> Syscall<'a> {
             KILL => self.sys_kill(args[0] as _, args[1] as _),
             TKILL => self.sys_tkill(args[0] as _, args[1] as _),
             TGKILL => self.sys_tgkill(args[0] as _, args[1] as _, args[2] as _),
+            PIDFD_SEND_SIGNAL => {
+                self.sys_pidfd_send_signal(args[0], args[1] as _, args[2].into(), args[3] as _)
+            }
             RT_SIGRETURN => self.sys_rt_sigreturn(),
             RT_SIGSUSPEND => self.sys_rt_sigsuspend(args[0].into()).await,
+            SIGNALFD4 => self.sys_signalfd4(args[0] as _, args[1].into(), args[2], args[3] as _),
             RT_SIGTIMEDWAIT => {
                 self.sys_rt_sigtimedwait(args[0].into(), args[1].into(), args[2].into())
                     .await
@@ -260,6 +407,11 @@ impl<'a> Syscall<'a> {
             CLOCK_GETRES => self.sys_clock_getres(args[0], args[1].into()),
             GETITIMER => self.sys_getitimer(args[0] as _, args[1].into()),
             SETITIMER => self.sys_setitimer(args[0] as _, args[1].into(), args[2].into()),
+            TIMERFD_CREATE => self.sys_timerfd_create(args[0], args[1] as _),
+            TIMERFD_SETTIME => {
+                self.sys_timerfd_settime(args[0], args[1] as _, args[2].into(), args[3].into())
+            }
+            TIMERFD_GETTIME => self.sys_timerfd_gettime(args[0], args[1].into()),
             CLOCK_NANOSLEEP => {
                 self.sys_clock_nanosleep(args[0], args[1], args[2].into(), args[3].into())
                     .await
@@ -279,7 +431,7 @@ impl<'a> Syscall<'a> {
             GET_ROBUST_LIST => {
                 self.sys_get_robust_list(args[0] as _, args[1].into(), args[2].into())
             }
-            SET_ROBUST_LIST => self.sys_set_robust_list(args[0].into(), args[1]),
+            SET_ROBUST_LIST => self.sys_set_robust_list(args[0], args[1]),
             // Schedule
             SCHED_SETSCHEDULER => self.sys_sched_setscheduler(),
             SCHED_GETSCHEDULER => self.sys_sched_getscheduler(),
@@ -308,8 +460,14 @@ impl<'a> Syscall<'a> {
             SETSOCKOPT => self.sys_setsockopt(args[0], args[1], args[2], args[3], args[4]),
             GETSOCKOPT => self.sys_getsockopt(args[0], args[1], args[2], args[3], args[4]),
             SHUTDOWN => self.sys_shutdown(args[0], args[1]),
-            SOCKETPAIR => self.sys_socketpair(args[0], args[1], args[2], args[3].into()),
+            SOCKETPAIR => self.sys_socketpair(args[0], args[1] as _, args[2], args[3].into()),
             SENDMSG => self.sys_sendmsg(args[0], args[1].into(), args[2]).await,
+            RECVMSG => self.sys_recvmsg(args[0], args[1].into(), args[2]).await,
+            SENDMMSG => self.sys_sendmmsg(args[0], args[1], args[2], args[3]).await,
+            RECVMMSG => {
+                self.sys_recvmmsg(args[0], args[1], args[2], args[3], args[4].into())
+                    .await
+            }
             // Miscellaneous
             UNAME => self.sys_uname(args[0].into()),
             SYSLOG => self.sys_syslog(args[0], args[1].into(), args[2]),
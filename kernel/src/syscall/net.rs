@@ -1,18 +1,21 @@
-use alloc::{sync::Arc, vec::Vec};
-use core::intrinsics::unlikely;
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{intrinsics::unlikely, mem, ptr, slice, time::Duration};
 
-use addr::SockAddr;
+use arch::time::get_time_duration;
 use log::info;
 use socket::*;
 use systype::{SysError, SysResult, SyscallResult};
-use vfs::pipefs::new_pipe;
+use time::timespec::TimeSpec;
+use timer::timelimited_task::{TimeLimitedTaskFuture, TimeLimitedTaskOutput};
 use vfs_core::OpenFlags;
-use virtio_drivers::PAGE_SIZE;
 
 use super::{fs::IoVec, Syscall};
 use crate::{
     mm::{UserRdWrPtr, UserReadPtr, UserWritePtr},
-    net::*,
+    net::{
+        unix::{UCred, UnixAncillary},
+        *,
+    },
     task::Task,
 };
 impl Syscall<'_> {
@@ -36,6 +39,14 @@ impl Syscall<'_> {
             flags |= OpenFlags::O_CLOEXEC;
         }
         let types = SocketType::try_from(types)?;
+        if domain == SaFamily::AF_UNIX
+            && !matches!(
+                types,
+                SocketType::STREAM | SocketType::DGRAM | SocketType::SEQPACKET
+            )
+        {
+            return Err(SysError::ESOCKTNOSUPPORT);
+        }
         let socket = Socket::new(domain, types, nonblock);
         let fd = self
             .task
@@ -66,9 +77,9 @@ impl Syscall<'_> {
     /// Mark the stream socket referenced by the file descriptor `sockfd` as
     /// passive. This socket will be used later to accept connections from other
     /// (active) sockets
-    pub fn sys_listen(&self, sockfd: usize, _backlog: usize) -> SyscallResult {
+    pub fn sys_listen(&self, sockfd: usize, backlog: usize) -> SyscallResult {
         let socket = self.task.sockfd_lookup(sockfd)?;
-        socket.sk.listen()?;
+        socket.sk.listen(backlog)?;
         Ok(0)
     }
 
@@ -105,10 +116,9 @@ impl Syscall<'_> {
         task.set_running();
 
         let peer_addr = new_sk.peer_addr()?;
-        let peer_addr = SockAddr::from_endpoint(peer_addr);
         log::info!("[sys_accept] peer addr: {peer_addr}");
         task.write_sockaddr(addr, addrlen, peer_addr)?;
-        let new_socket = Arc::new(Socket::from_another(&socket, Sock::Tcp(new_sk)));
+        let new_socket = Arc::new(Socket::from_another(&socket, new_sk));
         let fd = task.with_mut_fd_table(|table| table.alloc(new_socket, OpenFlags::empty()))?;
         Ok(fd)
     }
@@ -155,7 +165,7 @@ impl Syscall<'_> {
         let socket = task.sockfd_lookup(sockfd)?;
         task.set_interruptable();
         let bytes = match socket.types {
-            SocketType::STREAM => {
+            SocketType::STREAM | SocketType::SEQPACKET => {
                 if dest_addr != 0 {
                     return Err(SysError::EISCONN);
                 }
@@ -324,21 +334,50 @@ impl Syscall<'_> {
         Ok(0)
     }
 
+    /// The socketpair() call creates an unnamed pair of connected sockets in
+    /// the specified domain, of the specified type, and using the optionally
+    /// specified protocol. The file descriptors used in referencing the new
+    /// sockets are returned in sv[0] and sv[1]. The two sockets are
+    /// indistinguishable.
     pub fn sys_socketpair(
         &self,
-        _domain: usize,
-        _types: usize,
+        domain: usize,
+        types: i32,
         _protocol: usize,
         sv: UserWritePtr<[u32; 2]>,
     ) -> SyscallResult {
         let task = self.task;
-        let (pipe_read, pipe_write) = new_pipe(PAGE_SIZE);
-        let pipe = task.with_mut_fd_table(|table| {
-            let fd_read = table.alloc(pipe_read, OpenFlags::empty())?;
-            let fd_write = table.alloc(pipe_write, OpenFlags::empty())?;
-            Ok([fd_read as u32, fd_write as u32])
+        let domain = SaFamily::try_from(domain as u16)?;
+        if domain != SaFamily::AF_UNIX {
+            return Err(SysError::EOPNOTSUPP);
+        }
+        let mut types = types;
+        let mut flags = OpenFlags::empty();
+        let mut nonblock = false;
+        if types & NONBLOCK != 0 {
+            nonblock = true;
+            types &= !NONBLOCK;
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        if types & CLOEXEC != 0 {
+            types &= !CLOEXEC;
+            flags |= OpenFlags::O_CLOEXEC;
+        }
+        let types = SocketType::try_from(types)?;
+        if !matches!(
+            types,
+            SocketType::STREAM | SocketType::DGRAM | SocketType::SEQPACKET
+        ) {
+            return Err(SysError::ESOCKTNOSUPPORT);
+        }
+        let (socket0, socket1) = Socket::new_unix_pair(types, nonblock);
+        let fds = task.with_mut_fd_table(|table| {
+            let fd0 = table.alloc(Arc::new(socket0), flags)?;
+            let fd1 = table.alloc(Arc::new(socket1), flags)?;
+            Ok([fd0 as u32, fd1 as u32])
         })?;
-        sv.write(&task, pipe)?;
+        log::info!("[sys_socketpair] new socket pair {types:?} {flags:?} in fds {fds:?}");
+        sv.write(&task, fds)?;
         Ok(0)
     }
 }
@@ -373,83 +412,215 @@ pub struct MsgHdr {
     pub flags: i32,
 }
 
+/// ```c
+/// struct cmsghdr {
+///     size_t cmsg_len;    /* Data byte count, including header */
+///     int    cmsg_level;  /* Originating protocol */
+///     int    cmsg_type;   /* Protocol-specific type */
+///     /* followed by unsigned char cmsg_data[]; */
+/// };
+/// ```
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct CMsgHdr {
-    len: usize,
-    level: i32,
-    type_: i32,
+    pub len: usize,
+    pub level: i32,
+    pub type_: i32,
+}
+
+impl CMsgHdr {
+    const fn align(len: usize) -> usize {
+        (len + mem::size_of::<usize>() - 1) & !(mem::size_of::<usize>() - 1)
+    }
+
+    /// `CMSG_LEN`, value of `cmsg_len` for the data of `len` bytes.
+    const fn cmsg_len(len: usize) -> usize {
+        Self::align(mem::size_of::<Self>()) + len
+    }
+
+    /// `CMSG_SPACE`, number of bytes taken by the message with the data of
+    /// `len` bytes.
+    const fn cmsg_space(len: usize) -> usize {
+        Self::align(mem::size_of::<Self>()) + Self::align(len)
+    }
+}
+
+const SOL_SOCKET: i32 = 1;
+/// Transfer file descriptors.
+const SCM_RIGHTS: i32 = 1;
+/// Transfer credentials of the sender.
+const SCM_CREDENTIALS: i32 = 2;
+/// Max number of file descriptors passed in a message.
+const SCM_MAX_FD: usize = 253;
+
+/// Control data was discarded due to lack of space.
+const MSG_CTRUNC: i32 = 0x8;
+/// Enable nonblocking operation for this call only.
+const MSG_DONTWAIT: usize = 0x40;
+/// Do not raise `SIGPIPE` on a broken stream, which is never raised by
+/// sockets here anyway.
+const MSG_NOSIGNAL: usize = 0x4000;
+/// Turn on `MSG_DONTWAIT` after the first message has been received by
+/// `recvmmsg`.
+const MSG_WAITFORONE: usize = 0x10000;
+/// Set close-on-exec on file descriptors received by `SCM_RIGHTS`.
+const MSG_CMSG_CLOEXEC: usize = 0x40000000;
+
+/// Max number of iovecs in a message, and of messages passed to `sendmmsg`
+/// and `recvmmsg`.
+const UIO_MAXIOV: usize = 1024;
+/// Max length of data received in a message, which is the size of the
+/// receive buffers of sockets.
+const MAX_RECV_LEN: usize = 64 * 1024;
+
+/// ```c
+/// struct mmsghdr {
+///     struct msghdr msg_hdr;  /* Message header */
+///     unsigned int  msg_len;  /* Number of bytes transmitted */
+/// };
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MMsgHdr {
+    pub hdr: MsgHdr,
+    pub len: u32,
 }
 
 impl Syscall<'_> {
+    /// Send a message through a socket, the data is gathered from
+    /// `msg_iov` and the ancillary data in `msg_control` is only supported by
+    /// Unix domain sockets.
     pub async fn sys_sendmsg(
         &self,
         sockfd: usize,
         msg: UserReadPtr<MsgHdr>,
         flags: usize,
     ) -> SyscallResult {
-        if flags != 0 {
-            log::error!("[sys_sendmsg] unsupported flags {flags}");
-        }
-        // TODO: support flags
         let task = self.task;
         let socket = task.sockfd_lookup(sockfd)?;
         let message = msg.read(&task)?;
-        if message.controllen != 0 {
-            log::warn!("[sys_sendmsg] unsupport msg control");
-        }
-        let addr = task.read_sockaddr(message.name, message.namelen as _)?;
-        let iovs = UserReadPtr::<IoVec>::from(message.iov).read_array(&task, message.iovlen)?;
-        let mut total_len = 0;
-        for (i, iov) in iovs.iter().enumerate() {
-            if unlikely(iov.len == 0) {
-                continue;
+        task.send_msghdr(&socket, &message, flags).await
+    }
+
+    /// Receive a message from a socket, the data is scattered into `msg_iov`.
+    /// File descriptors and credentials sent over Unix domain sockets are
+    /// written to `msg_control`, and `MSG_CTRUNC` is set in `msg_flags` if
+    /// some of them are discarded.
+    pub async fn sys_recvmsg(
+        &self,
+        sockfd: usize,
+        msg: UserRdWrPtr<MsgHdr>,
+        flags: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let socket = task.sockfd_lookup(sockfd)?;
+        let msg_addr = msg.as_usize();
+        let mut message = msg.read(&task)?;
+        let bytes = task.recv_msghdr(&socket, &mut message, flags, None).await?;
+        UserWritePtr::<MsgHdr>::from(msg_addr).write(&task, message)?;
+        Ok(bytes)
+    }
+
+    /// Send multiple messages in `msgvec` through a socket, the number of
+    /// bytes sent is written to `msg_len` of each message.
+    ///
+    /// Return the number of messages sent. If an error occurs after at least
+    /// one message has been sent, the error is dropped and the number of
+    /// messages sent so far is returned.
+    pub async fn sys_sendmmsg(
+        &self,
+        sockfd: usize,
+        msgvec: usize,
+        vlen: usize,
+        flags: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let socket = task.sockfd_lookup(sockfd)?;
+        let vlen = vlen.min(UIO_MAXIOV);
+        let mut count = 0;
+        for i in 0..vlen {
+            let mmsg_addr = msgvec + i * mem::size_of::<MMsgHdr>();
+            let mut mmsg = UserReadPtr::<MMsgHdr>::from(mmsg_addr).read(&task)?;
+            match task.send_msghdr(&socket, &mmsg.hdr, flags).await {
+                Ok(bytes) => {
+                    mmsg.len = bytes as u32;
+                    UserWritePtr::<MMsgHdr>::from(mmsg_addr).write(&task, mmsg)?;
+                    count += 1;
+                }
+                Err(e) if count == 0 => return Err(e),
+                Err(e) => {
+                    log::info!("[sys_sendmmsg] stop after {count} messages, err: {e:?}");
+                    break;
+                }
             }
-            let ptr = UserWritePtr::<u8>::from(iov.base);
-            log::info!("[sys_sendmsg] iov #{i}, ptr: {ptr}, len: {}", iov.len);
-            let buf = ptr.into_mut_slice(&task, iov.len)?;
-            let send_len = socket.sk.sendto(&buf, Some(addr)).await?;
-            total_len += send_len;
         }
-        Ok(total_len)
+        Ok(count)
     }
 
-    /// 目前的实现是，如果Udp Socket收到多个不同Ip地址的数据报如ip1, ip1, ip2,
-    /// ip3, ip1，recvmsg
-    // pub async fn sys_recvmsg(
-    //     &self,
-    //     sockfd: usize,
-    //     msg: UserRdWrPtr<MsgHdr>,
-    //     flags: usize,
-    // ) -> SyscallResult {
-    //     if flags != 0 {
-    //         log::error!("[sys_sendmsg] unsupported flags {flags}");
-    //     }
-    //     // TODO: support flags
-    //     let task = self.task;
-    //     let socket = task.sockfd_lookup(sockfd)?;
-    //     let msg = msg.read(&task)?;
-    //     if msg.controllen != 0 {
-    //         log::warn!("[sys_sendmsg] unsupport msg control");
-    //     }
-    //     let addr = task.read_sockaddr(msg.name, msg.namelen as _)?;
-    //     let iovs = UserReadPtr::<IoVec>::from(msg.iov).read_array(&task,
-    // msg.iovlen)?;     let mut total_len = 0;
-    //     for (i, iov) in iovs.iter().enumerate() {
-    //         if unlikely(iov.len == 0) {
-    //             continue;
-    //         }
-    //         let ptr = UserWritePtr::<u8>::from(iov.base);
-    //         log::info!("[sys_recvmsg] iov #{i}, ptr: {ptr}, len: {}", iov.len);
-    //         let buf = ptr.into_mut_slice(&task, iov.len)?;
-    //         let send_len = socket.sk.sendto(&buf, Some(addr)).await?;
-    //         total_len += send_len;
-    //     }
-    //     Ok(total_len)
-    // }
-
-    pub fn sys_sendmmsg(&self, sockfd: usize) -> SyscallResult {
-        Ok(0)
+    /// Receive multiple messages from a socket into `msgvec`, the number of
+    /// bytes received is written to `msg_len` of each message.
+    ///
+    /// If `timeout` is not null, the call returns once the timeout expires,
+    /// and the remaining time is written back.
+    ///
+    /// Return the number of messages received. If an error occurs after at
+    /// least one message has been received, the error is dropped and the
+    /// number of messages received so far is returned.
+    pub async fn sys_recvmmsg(
+        &self,
+        sockfd: usize,
+        msgvec: usize,
+        vlen: usize,
+        flags: usize,
+        timeout: UserRdWrPtr<TimeSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let socket = task.sockfd_lookup(sockfd)?;
+        let timeout_addr = timeout.as_usize();
+        let deadline = if timeout.is_null() {
+            None
+        } else {
+            let timeout = timeout.read(&task)?;
+            if !timeout.is_valid() {
+                return Err(SysError::EINVAL);
+            }
+            Some(get_time_duration() + Duration::from(timeout))
+        };
+        let vlen = vlen.min(UIO_MAXIOV);
+        let mut count = 0;
+        for i in 0..vlen {
+            let mmsg_addr = msgvec + i * mem::size_of::<MMsgHdr>();
+            let mut mmsg = UserReadPtr::<MMsgHdr>::from(mmsg_addr).read(&task)?;
+            let flags = if count > 0 && flags & MSG_WAITFORONE != 0 {
+                flags | MSG_DONTWAIT
+            } else {
+                flags
+            };
+            let timeout = deadline.map(|deadline| deadline.saturating_sub(get_time_duration()));
+            match task
+                .recv_msghdr(&socket, &mut mmsg.hdr, flags, timeout)
+                .await
+            {
+                Ok(bytes) => {
+                    mmsg.len = bytes as u32;
+                    UserWritePtr::<MMsgHdr>::from(mmsg_addr).write(&task, mmsg)?;
+                    count += 1;
+                }
+                Err(e) if count == 0 => return Err(e),
+                Err(e) => {
+                    log::info!("[sys_recvmmsg] stop after {count} messages, err: {e:?}");
+                    break;
+                }
+            }
+            if deadline.is_some_and(|deadline| get_time_duration() >= deadline) {
+                break;
+            }
+        }
+        if let Some(deadline) = deadline {
+            let remain = deadline.saturating_sub(get_time_duration());
+            UserWritePtr::<TimeSpec>::from(timeout_addr).write(&task, remain.into())?;
+        }
+        Ok(count)
     }
 }
 
@@ -459,4 +630,245 @@ impl Task {
             .downcast_arc::<Socket>()
             .map_err(|_| SysError::ENOTSOCK)
     }
+
+    /// Gather the data from `msg_iov` and send it through the socket together
+    /// with the ancillary data in `msg_control`.
+    async fn send_msghdr(
+        self: &Arc<Self>,
+        socket: &Socket,
+        message: &MsgHdr,
+        flags: usize,
+    ) -> SyscallResult {
+        if flags & !(MSG_DONTWAIT | MSG_NOSIGNAL) != 0 {
+            log::warn!("[send_msghdr] unsupported flags {flags:#x}");
+            return Err(SysError::EOPNOTSUPP);
+        }
+        let addr = if message.name != 0 {
+            Some(self.read_sockaddr(message.name, message.namelen as _)?)
+        } else {
+            None
+        };
+        if message.iovlen > UIO_MAXIOV {
+            return Err(SysError::EMSGSIZE);
+        }
+        let iovs = UserReadPtr::<IoVec>::from(message.iov).read_array(self, message.iovlen)?;
+        let mut buf = Vec::new();
+        for (i, iov) in iovs.iter().enumerate() {
+            if unlikely(iov.len == 0) {
+                continue;
+            }
+            let ptr = UserReadPtr::<u8>::from(iov.base);
+            log::info!("[send_msghdr] iov #{i}, ptr: {ptr}, len: {}", iov.len);
+            buf.extend_from_slice(&ptr.into_slice(self, iov.len)?);
+        }
+        let ancillary = self.read_ancillary(message.control, message.controllen)?;
+        let nonblock = flags & MSG_DONTWAIT != 0;
+        self.set_interruptable();
+        let ret = socket.sk.sendmsg(&buf, addr, ancillary, nonblock).await;
+        self.set_running();
+        ret
+    }
+
+    /// Receive a message from the socket, scatter the data into `msg_iov` and
+    /// fill `msg_name`, `msg_control` and `msg_flags` of `message`. Return
+    /// `EAGAIN` if `timeout` expires before a message arrives.
+    async fn recv_msghdr(
+        self: &Arc<Self>,
+        socket: &Arc<Socket>,
+        message: &mut MsgHdr,
+        flags: usize,
+        timeout: Option<Duration>,
+    ) -> SyscallResult {
+        // Flags like `MSG_PEEK`, `MSG_TRUNC` and `MSG_WAITALL` would change
+        // what is received, so they are refused rather than ignored.
+        if flags & !(MSG_DONTWAIT | MSG_WAITFORONE | MSG_CMSG_CLOEXEC) != 0 {
+            log::warn!("[recv_msghdr] unsupported flags {flags:#x}");
+            return Err(SysError::EOPNOTSUPP);
+        }
+        if message.iovlen > UIO_MAXIOV {
+            return Err(SysError::EMSGSIZE);
+        }
+        let iovs = UserReadPtr::<IoVec>::from(message.iov).read_array(self, message.iovlen)?;
+        let len = iovs
+            .iter()
+            .try_fold(0usize, |len, iov| len.checked_add(iov.len))
+            .filter(|&len| len <= isize::MAX as usize)
+            .ok_or(SysError::EINVAL)?;
+        // A message never exceeds the receive buffer of the socket.
+        let len = len.min(MAX_RECV_LEN);
+        let nonblock = flags & MSG_DONTWAIT != 0;
+        let socket = socket.clone();
+        let recv_future = async move {
+            let mut buf = vec![0; len];
+            let (bytes, remote_addr, ancillary) = socket.sk.recvmsg(&mut buf, nonblock).await?;
+            buf.truncate(bytes);
+            Ok::<_, SysError>((buf, remote_addr, ancillary))
+        };
+        self.set_interruptable();
+        let ret = match timeout {
+            Some(timeout) => match TimeLimitedTaskFuture::new(timeout, recv_future).await {
+                TimeLimitedTaskOutput::Ok(ret) => ret,
+                TimeLimitedTaskOutput::TimeOut => Err(SysError::EAGAIN),
+            },
+            None => recv_future.await,
+        };
+        self.set_running();
+        let (buf, remote_addr, ancillary) = ret?;
+
+        let mut offset = 0;
+        for iov in iovs.iter() {
+            if offset >= buf.len() {
+                break;
+            }
+            let len = iov.len.min(buf.len() - offset);
+            if unlikely(len == 0) {
+                continue;
+            }
+            UserWritePtr::<u8>::from(iov.base).write_array(self, &buf[offset..offset + len])?;
+            offset += len;
+        }
+
+        if message.name != 0 {
+            let addrlen = remote_addr.addrlen();
+            let len = addrlen.min(message.namelen as usize);
+            UserWritePtr::<u8>::from(message.name)
+                .write_array(self, &remote_addr.as_bytes()[..len])?;
+            message.namelen = addrlen as u32;
+        }
+        message.flags = 0;
+        message.controllen = self.write_ancillary(
+            message.control,
+            message.controllen,
+            ancillary,
+            flags & MSG_CMSG_CLOEXEC != 0,
+            &mut message.flags,
+        )?;
+        Ok(buf.len())
+    }
+
+    /// Parse the control messages in `msg_control` of `sendmsg`.
+    fn read_ancillary(
+        self: &Arc<Self>,
+        control: usize,
+        controllen: usize,
+    ) -> SysResult<UnixAncillary> {
+        let mut ancillary = UnixAncillary::default();
+        if control == 0 || controllen == 0 {
+            return Ok(ancillary);
+        }
+        let buf = UserReadPtr::<u8>::from(control).read_array(self, controllen)?;
+        let hdr_len = CMsgHdr::cmsg_len(0);
+        let mut offset = 0;
+        while offset + hdr_len <= buf.len() {
+            let cmsg = unsafe { ptr::read_unaligned(buf[offset..].as_ptr() as *const CMsgHdr) };
+            if cmsg.len < hdr_len || cmsg.len > buf.len() - offset {
+                return Err(SysError::EINVAL);
+            }
+            let data = &buf[offset + hdr_len..offset + cmsg.len];
+            if cmsg.level == SOL_SOCKET {
+                match cmsg.type_ {
+                    SCM_RIGHTS => {
+                        let nfds = data.len() / mem::size_of::<i32>();
+                        if ancillary.files.len() + nfds > SCM_MAX_FD {
+                            return Err(SysError::EINVAL);
+                        }
+                        for fd in data.chunks_exact(mem::size_of::<i32>()) {
+                            let fd = i32::from_ne_bytes(fd.try_into().unwrap());
+                            if fd < 0 {
+                                return Err(SysError::EBADF);
+                            }
+                            let file = self.with_fd_table(|table| table.get_file(fd as usize))?;
+                            ancillary.files.push(file);
+                        }
+                    }
+                    SCM_CREDENTIALS => {
+                        if data.len() != mem::size_of::<UCred>() {
+                            return Err(SysError::EINVAL);
+                        }
+                        let cred = unsafe { ptr::read_unaligned(data.as_ptr() as *const UCred) };
+                        // A process can only send its own credentials. All
+                        // processes run with user and group ids of 0, but
+                        // none is privileged to pretend to be another.
+                        if cred.pid != self.pid() as i32 || cred.uid != 0 || cred.gid != 0 {
+                            return Err(SysError::EPERM);
+                        }
+                        ancillary.cred = Some(cred);
+                    }
+                    _ => return Err(SysError::EINVAL),
+                }
+            }
+            offset += CMsgHdr::align(cmsg.len);
+        }
+        Ok(ancillary)
+    }
+
+    /// Write the ancillary data into `msg_control` of `recvmsg`, files are
+    /// installed in the fd table of the receiver. Return the length of the
+    /// control messages written.
+    fn write_ancillary(
+        self: &Arc<Self>,
+        control: usize,
+        controllen: usize,
+        ancillary: UnixAncillary,
+        cloexec: bool,
+        msg_flags: &mut i32,
+    ) -> SysResult<usize> {
+        if ancillary.is_empty() {
+            return Ok(0);
+        }
+        if control == 0 {
+            *msg_flags |= MSG_CTRUNC;
+            return Ok(0);
+        }
+        let mut buf = Vec::new();
+        let push_cmsg = |buf: &mut Vec<u8>, type_: i32, data: &[u8]| {
+            let start = buf.len();
+            let cmsg = CMsgHdr {
+                len: CMsgHdr::cmsg_len(data.len()),
+                level: SOL_SOCKET,
+                type_,
+            };
+            let hdr = unsafe {
+                slice::from_raw_parts(&cmsg as *const _ as *const u8, mem::size_of::<CMsgHdr>())
+            };
+            buf.extend_from_slice(hdr);
+            buf.resize(start + CMsgHdr::cmsg_len(0), 0);
+            buf.extend_from_slice(data);
+            // The padding of the last message may be truncated.
+            buf.resize((start + CMsgHdr::cmsg_space(data.len())).min(controllen), 0);
+        };
+        if let Some(cred) = ancillary.cred {
+            if buf.len() + CMsgHdr::cmsg_len(mem::size_of::<UCred>()) <= controllen {
+                let data = unsafe {
+                    slice::from_raw_parts(&cred as *const _ as *const u8, mem::size_of::<UCred>())
+                };
+                push_cmsg(&mut buf, SCM_CREDENTIALS, data);
+            } else {
+                *msg_flags |= MSG_CTRUNC;
+            }
+        }
+        if !ancillary.files.is_empty() {
+            let space = controllen.saturating_sub(buf.len() + CMsgHdr::cmsg_len(0));
+            let nfds = ancillary.files.len().min(space / mem::size_of::<i32>());
+            if nfds < ancillary.files.len() {
+                // Files which do not fit are closed.
+                *msg_flags |= MSG_CTRUNC;
+            }
+            if nfds > 0 {
+                let flags = if cloexec {
+                    OpenFlags::O_CLOEXEC
+                } else {
+                    OpenFlags::empty()
+                };
+                let mut data = Vec::with_capacity(nfds * mem::size_of::<i32>());
+                for file in ancillary.files.into_iter().take(nfds) {
+                    let fd = self.with_mut_fd_table(|table| table.alloc(file, flags))?;
+                    data.extend_from_slice(&(fd as i32).to_ne_bytes());
+                }
+                push_cmsg(&mut buf, SCM_RIGHTS, &data);
+            }
+        }
+        UserWritePtr::<u8>::from(control).write_array(self, &buf)?;
+        Ok(buf.len())
+    }
 }
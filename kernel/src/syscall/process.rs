@@ -2,18 +2,29 @@
 
 use alloc::{
     string::{String, ToString},
+    sync::Arc,
     vec::Vec,
 };
+use core::mem;
 
 use async_utils::{suspend_now, yield_now};
 use memory::VirtAddr;
-use signal::sigset::SigSet;
+use signal::{
+    siginfo::SigInfo,
+    sigset::{Sig, SigSet},
+};
 use systype::{SysError, SysResult, SyscallResult};
+use vfs_core::OpenFlags;
 
 use super::Syscall;
 use crate::{
+    aio,
+    io_uring::{self, ChainOwner},
     mm::{UserReadPtr, UserWritePtr},
-    task::{spawn_user_task, PGid, Pid, PROCESS_GROUP_MANAGER, TASK_MANAGER},
+    task::{
+        pidfd::PidFdFile, spawn_user_task, task::StopEvent, PGid, Pid, Task, PROCESS_GROUP_MANAGER,
+        TASK_MANAGER,
+    },
 };
 
 bitflags! {
@@ -75,9 +86,39 @@ bitflags! {
         const WUNTRACED = 0x00000002;
         /// Report continued child.
         const WCONTINUED = 0x00000008;
+        /// Wait for children that have been stopped by delivery of a signal,
+        /// same as `WUNTRACED` but used by `waitid`.
+        const WSTOPPED = 0x00000002;
+        /// Wait for children that have terminated.
+        const WEXITED = 0x00000004;
+        /// Leave the child in a waitable state.
+        const WNOWAIT = 0x01000000;
     }
 }
 
+/// Id type of `waitid`, defined in <bits/waitflags.h>.
+const P_ALL: i32 = 0;
+const P_PID: i32 = 1;
+const P_PGID: i32 = 2;
+const P_PIDFD: i32 = 3;
+
+/// The `siginfo_t` filled by `waitid` for `SIGCHLD`.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct WaitIdInfo {
+    pub si_signo: i32,
+    pub si_errno: i32,
+    pub si_code: i32,
+    __pad0: i32,
+    pub si_pid: i32,
+    pub si_uid: u32,
+    pub si_status: i32,
+    __pad1: i32,
+    pub si_utime: i64,
+    pub si_stime: i64,
+    __pad2: [u64; 10],
+}
+
 impl Syscall<'_> {
     /// _exit() system call terminates only the calling thread, and actions such
     /// as reparenting child processes or sending SIGCHLD to the parent
@@ -257,6 +298,174 @@ impl Syscall<'_> {
         }
     }
 
+    /// The waitid() system call provides more precise control over which child
+    /// state changes to wait for than wait4(). The child is selected by
+    /// `idtype` and `id`, which may be a pidfd with `P_PIDFD`, and the state
+    /// changes to wait for are selected by `WEXITED`, `WSTOPPED` and
+    /// `WCONTINUED` in `options`.
+    ///
+    /// On success, returns 0 and fills `infop` with the `siginfo_t` of the
+    /// child. With `WNOHANG` and no child in a waitable state, `infop` is
+    /// zeroed.
+    pub async fn sys_waitid(
+        &self,
+        idtype: i32,
+        id: usize,
+        infop: UserWritePtr<WaitIdInfo>,
+        options: i32,
+        _rusage: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let options = WaitOptions::from_bits(options).ok_or(SysError::EINVAL)?;
+        if !options
+            .intersects(WaitOptions::WEXITED | WaitOptions::WSTOPPED | WaitOptions::WCONTINUED)
+        {
+            return Err(SysError::EINVAL);
+        }
+        #[derive(Debug)]
+        enum WaitFor {
+            AnyChild,
+            Pid(Pid),
+            PGid(PGid),
+        }
+        let target = match idtype {
+            P_ALL => WaitFor::AnyChild,
+            P_PID => WaitFor::Pid(id as Pid),
+            P_PGID if id == 0 => WaitFor::PGid(task.pgid()),
+            P_PGID => WaitFor::PGid(id as PGid),
+            P_PIDFD => {
+                let pidfd = task
+                    .with_fd_table(|table| table.get_file(id))?
+                    .downcast_arc::<PidFdFile>()
+                    .map_err(|_| SysError::EBADF)?;
+                // A reaped process can not be a child any more.
+                let pid = pidfd.task().map_err(|_| SysError::ECHILD)?.pid();
+                WaitFor::Pid(pid)
+            }
+            _ => return Err(SysError::EINVAL),
+        };
+        log::info!("[sys_waitid] target: {target:?}, options: {options:?}");
+
+        // Find a child in the waitable state, returns the child and `si_code`,
+        // `si_status` of `SIGCHLD`.
+        let find_child = || -> SysResult<Option<(Arc<Task>, i32, i32)>> {
+            let children = task.children();
+            let mut found = false;
+            for child in children.values() {
+                let matched = match target {
+                    WaitFor::AnyChild => true,
+                    WaitFor::Pid(pid) => child.pid() == pid,
+                    WaitFor::PGid(pgid) => child.pgid() == pgid,
+                };
+                if !matched {
+                    continue;
+                }
+                found = true;
+                if options.contains(WaitOptions::WEXITED)
+                    && child.is_zombie()
+                    && child.with_thread_group(|tg| tg.len() == 1)
+                {
+                    let exit_code = child.exit_code();
+                    let (code, status) = if exit_code & 0x7f == 0 {
+                        (SigInfo::CLD_EXITED, (exit_code >> 8) & 0xff)
+                    } else {
+                        (SigInfo::CLD_KILLED, exit_code & 0x7f)
+                    };
+                    return Ok(Some((child.clone(), code, status)));
+                }
+                let event = child.with_mut_stop_event(|event| {
+                    let res = match *event {
+                        Some(StopEvent::Stopped(sig))
+                            if options.contains(WaitOptions::WSTOPPED) =>
+                        {
+                            (SigInfo::CLD_STOPPED, sig.raw() as i32)
+                        }
+                        Some(StopEvent::Continued) if options.contains(WaitOptions::WCONTINUED) => {
+                            (SigInfo::CLD_CONTINUED, Sig::SIGCONT.raw() as i32)
+                        }
+                        _ => return None,
+                    };
+                    if !options.contains(WaitOptions::WNOWAIT) {
+                        *event = None;
+                    }
+                    Some(res)
+                });
+                if let Some((code, status)) = event {
+                    return Ok(Some((child.clone(), code, status)));
+                }
+            }
+            if found {
+                Ok(None)
+            } else {
+                log::info!("[sys_waitid] fail: no matched child");
+                Err(SysError::ECHILD)
+            }
+        };
+
+        let (child, code, status) = loop {
+            if let Some(res) = find_child()? {
+                break res;
+            }
+            if options.contains(WaitOptions::WNOHANG) {
+                if infop.not_null() {
+                    infop.write(&task, WaitIdInfo::default())?;
+                }
+                return Ok(0);
+            }
+            task.set_interruptable();
+            task.set_wake_up_signal(!*task.sig_mask_ref() | SigSet::SIGCHLD);
+            suspend_now().await;
+            task.set_running();
+            let si = task.with_mut_sig_pending(|pending| pending.get_expect(SigSet::SIGCHLD));
+            if si.is_none() {
+                return Err(SysError::EINTR);
+            }
+        };
+
+        let pid = child.pid();
+        let utime = child.time_stat_ref().user_time();
+        let stime = child.time_stat_ref().sys_time();
+        if code == SigInfo::CLD_EXITED || code == SigInfo::CLD_KILLED {
+            if !options.contains(WaitOptions::WNOWAIT) {
+                task.time_stat().update_child_time((utime, stime));
+                task.remove_child(pid);
+                TASK_MANAGER.remove(pid);
+                PROCESS_GROUP_MANAGER.remove(task);
+            }
+        }
+        if infop.not_null() {
+            let info = WaitIdInfo {
+                si_signo: Sig::SIGCHLD.raw() as i32,
+                si_code: code,
+                si_pid: pid as i32,
+                si_status: status,
+                si_utime: utime.as_micros() as i64,
+                si_stime: stime.as_micros() as i64,
+                ..Default::default()
+            };
+            infop.write(&task, info)?;
+        }
+        Ok(0)
+    }
+
+    /// Create a file descriptor that refers to the process whose PID is
+    /// specified in `pid`. The close-on-exec flag is set on the file
+    /// descriptor.
+    pub fn sys_pidfd_open(&self, pid: i32, flags: u32) -> SyscallResult {
+        let task = self.task;
+        let flags = OpenFlags::from_bits(flags as i32).ok_or(SysError::EINVAL)?;
+        if !(flags - OpenFlags::O_NONBLOCK).is_empty() || pid <= 0 {
+            return Err(SysError::EINVAL);
+        }
+        let target = TASK_MANAGER.get(pid as Pid).ok_or(SysError::ESRCH)?;
+        // `pid` must refer to a thread group leader.
+        if !target.is_leader() {
+            return Err(SysError::EINVAL);
+        }
+        let pidfd = PidFdFile::new(&target, flags);
+        task.with_mut_fd_table(|table| table.alloc(pidfd, OpenFlags::O_CLOEXEC))
+    }
+
     /// execve() executes the program referred to by pathname. This causes the
     /// program that is currently being run by the calling process to be
     /// replaced with a new program, with newly initialized stack, heap, and
@@ -305,6 +514,11 @@ impl Syscall<'_> {
 
         let file = task.resolve_path(&path)?.open()?;
         let elf_data = file.read_all().await?;
+        // io_uring and AIO requests in flight access the old address space, and
+        // the AIO contexts belong to it.
+        io_uring::cancel_chains(ChainOwner::MemorySpace(task.raw_mm_pointer())).await;
+        let aio_ctxs = task.with_mut_aio_ctxs(|ctxs| mem::take(ctxs));
+        aio::drain_contexts(aio_ctxs.into_values().collect(), None).await;
         task.do_execve(file, &elf_data, argv, envp);
         Ok(0)
     }
@@ -1,4 +1,4 @@
-use core::mem;
+use core::mem::{self, size_of};
 
 use async_utils::suspend_now;
 use config::process::INIT_PROC_PID;
@@ -10,12 +10,15 @@ use signal::{
 };
 use systype::{SysError, SyscallResult};
 use time::timespec::TimeSpec;
+use vfs_core::OpenFlags;
 
 use super::Syscall;
 use crate::{
     mm::{UserReadPtr, UserWritePtr},
     task::{
+        pidfd::PidFdFile,
         signal::{SigAction, SIG_DFL, SIG_IGN},
+        signalfd::{SignalFdFile, SignalFdFlags},
         PROCESS_GROUP_MANAGER, TASK_MANAGER,
     },
 };
@@ -236,6 +239,67 @@ impl Syscall<'_> {
         Ok(0)
     }
 
+    /// Send the signal `sig` to the process referred to by `pidfd`. If `info`
+    /// is null, the signal is sent as if by kill(2), otherwise `si_code` of the
+    /// `siginfo_t` pointed to by `info` is used.
+    ///
+    /// If `sig` is zero, no signal is sent, but error checking is performed.
+    pub fn sys_pidfd_send_signal(
+        &self,
+        pidfd: usize,
+        signum: i32,
+        info: UserReadPtr<i32>,
+        flags: u32,
+    ) -> SyscallResult {
+        let task = self.task;
+        if flags != 0 {
+            return Err(SysError::EINVAL);
+        }
+        let pidfd = task
+            .with_fd_table(|table| table.get_file(pidfd))?
+            .downcast_arc::<PidFdFile>()
+            .map_err(|_| SysError::EBADF)?;
+        let target = pidfd.task()?;
+        if target.is_zombie() {
+            return Err(SysError::ESRCH);
+        }
+        let code = if info.is_null() {
+            SigInfo::USER
+        } else {
+            // si_signo, si_errno and si_code
+            let si = info.read_array(&task, 3)?;
+            if si[0] != signum {
+                return Err(SysError::EINVAL);
+            }
+            // A process can not send a signal pretending to be from the kernel
+            // or kill(2) to another process.
+            if (si[2] >= 0 || si[2] == SigInfo::TKILL) && target.pid() != task.pid() {
+                return Err(SysError::EPERM);
+            }
+            si[2]
+        };
+        if signum == 0 {
+            return Ok(0);
+        }
+        let sig = Sig::from_i32(signum);
+        if !sig.is_valid() {
+            return Err(SysError::EINVAL);
+        }
+        log::info!(
+            "[sys_pidfd_send_signal] send {sig:?} to process {}",
+            target.pid()
+        );
+        target.receive_siginfo(
+            SigInfo {
+                sig,
+                code,
+                details: SigDetails::Kill { pid: task.pid() },
+            },
+            false,
+        );
+        Ok(0)
+    }
+
     /// sends the signal sigum to the thread with the thread ID tid in the
     /// thread group tgid.  (By contrast, kill(2) can be used to send a
     /// signal only to a process (i.e., thread group) as a whole, and the
@@ -381,4 +445,51 @@ impl Syscall<'_> {
             Err(SysError::EAGAIN)
         }
     }
+
+    /// signalfd() creates a file descriptor that can be used to accept signals
+    /// targeted at the caller. This provides an alternative to the use of a
+    /// signal handler or sigwaitinfo(2), and has the advantage that the file
+    /// descriptor may be monitored by select(2), poll(2), and epoll(7).
+    ///
+    /// The mask argument specifies the set of signals that the caller wishes to
+    /// accept via the file descriptor. Normally, the set of signals to be
+    /// received via the file descriptor should be blocked using
+    /// sigprocmask(2), to prevent the signals being handled according to their
+    /// default dispositions.
+    ///
+    /// If the fd argument is -1, then the call creates a new file descriptor.
+    /// If fd is not -1, then it must specify a valid existing signalfd file
+    /// descriptor, and mask is used to replace the signal set associated with
+    /// that file descriptor.
+    pub fn sys_signalfd4(
+        &self,
+        fd: isize,
+        mask: UserReadPtr<SigSet>,
+        sizemask: usize,
+        flags: i32,
+    ) -> SyscallResult {
+        let task = self.task;
+        if sizemask != size_of::<SigSet>() {
+            return Err(SysError::EINVAL);
+        }
+        let flags = SignalFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let mask = mask.read(task)?;
+        log::info!("[sys_signalfd4] fd: {fd}, mask: {mask:?}, flags: {flags:?}");
+        if fd != -1 {
+            let fd = fd as usize;
+            let signalfd = task
+                .with_fd_table(|table| table.get_file(fd))?
+                .downcast_arc::<SignalFdFile>()
+                .map_err(|_| SysError::EINVAL)?;
+            signalfd.set_mask(mask);
+            return Ok(fd);
+        }
+        let signalfd = SignalFdFile::new(mask, flags);
+        let fd_flags = if flags.contains(SignalFdFlags::CLOEXEC) {
+            OpenFlags::O_CLOEXEC
+        } else {
+            OpenFlags::empty()
+        };
+        task.with_mut_fd_table(|table| table.alloc(signalfd, fd_flags))
+    }
 }
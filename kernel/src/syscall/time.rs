@@ -4,13 +4,15 @@ use core::time::Duration;
 use arch::time::{get_time_duration, get_time_ms, get_time_us};
 use systype::{SysError, SyscallResult};
 use time::{
-    timespec::TimeSpec,
+    timespec::{ITimerSpec, TimeSpec},
     timeval::{ITimerVal, TimeVal},
     tms::TMS,
     CLOCK_DEVIATION, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME,
     CLOCK_THREAD_CPUTIME_ID,
 };
 use timer::{Timer, TIMER_MANAGER};
+use vfs::timerfd::{self, TimerFdFile, TimerFdFlags, TimerFdSetFlags};
+use vfs_core::OpenFlags;
 
 use super::Syscall;
 use crate::{
@@ -136,6 +138,7 @@ impl Syscall<'_> {
                 unsafe {
                     CLOCK_DEVIATION[clockid] = Duration::from(tp) - get_time_duration();
                 }
+                timerfd::clock_was_set();
             }
             _ => {
                 log::error!("[sys_clock_gettime] unsupported clockid{}", clockid);
@@ -285,4 +288,80 @@ impl Syscall<'_> {
         }
         Ok(0)
     }
+
+    /// timerfd_create() creates a new timer object, and returns a file
+    /// descriptor that refers to that timer. The clockid argument specifies
+    /// the clock that is used to mark the progress of the timer, and must be
+    /// either CLOCK_REALTIME or CLOCK_MONOTONIC.
+    ///
+    /// The following values may be bitwise ORed in flags:
+    /// + TFD_NONBLOCK: Set the O_NONBLOCK file status flag on the open file
+    ///   description.
+    /// + TFD_CLOEXEC: Set the close-on-exec (FD_CLOEXEC) flag on the new file
+    ///   descriptor.
+    pub fn sys_timerfd_create(&self, clockid: usize, flags: i32) -> SyscallResult {
+        let task = self.task;
+        let flags = TimerFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let timerfd = TimerFdFile::new(clockid, flags)?;
+        let fd_flags = if flags.contains(TimerFdFlags::CLOEXEC) {
+            OpenFlags::O_CLOEXEC
+        } else {
+            OpenFlags::empty()
+        };
+        let fd = task.with_mut_fd_table(|table| table.alloc(timerfd, fd_flags))?;
+        log::info!("[sys_timerfd_create] fd: {fd}, clockid: {clockid}, flags: {flags:?}");
+        Ok(fd)
+    }
+
+    /// timerfd_settime() arms (starts) or disarms (stops) the timer referred
+    /// to by the file descriptor fd.
+    ///
+    /// The new_value argument specifies the initial expiration and interval
+    /// for the timer. Setting either field of new_value.it_value to a nonzero
+    /// value arms the timer. Setting both fields of new_value.it_value to zero
+    /// disarms the timer. By default, the initial expiration time specified in
+    /// new_value is interpreted relative to the current time on the timer's
+    /// clock at the time of the call, while TFD_TIMER_ABSTIME interprets it as
+    /// an absolute value on the timer's clock.
+    pub fn sys_timerfd_settime(
+        &self,
+        fd: usize,
+        flags: i32,
+        new_value: UserReadPtr<ITimerSpec>,
+        old_value: UserWritePtr<ITimerSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = TimerFdSetFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let timerfd = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<TimerFdFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        let new = new_value.read(task)?;
+        if !new.is_valid() {
+            return Err(SysError::EINVAL);
+        }
+        log::info!("[sys_timerfd_settime] fd: {fd}, flags: {flags:?}, new: {new:?}");
+        let old = timerfd.settime(flags, new);
+        if old_value.not_null() {
+            old_value.write(task, old)?;
+        }
+        Ok(0)
+    }
+
+    /// timerfd_gettime() returns, in curr_value, an itimerspec structure that
+    /// contains the current setting of the timer referred to by the file
+    /// descriptor fd.
+    pub fn sys_timerfd_gettime(
+        &self,
+        fd: usize,
+        curr_value: UserWritePtr<ITimerSpec>,
+    ) -> SyscallResult {
+        let task = self.task;
+        let timerfd = task
+            .with_fd_table(|table| table.get_file(fd))?
+            .downcast_arc::<TimerFdFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        curr_value.write(task, timerfd.gettime())?;
+        Ok(0)
+    }
 }
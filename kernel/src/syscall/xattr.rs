@@ -0,0 +1,266 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use systype::{SysError, SysResult, SyscallResult};
+use vfs_core::{Dentry, File, Inode, XattrFlags, XATTR_LIST_MAX, XATTR_SIZE_MAX};
+
+use super::Syscall;
+use crate::mm::{UserReadPtr, UserWritePtr};
+
+impl Syscall<'_> {
+    /// setxattr() sets the `value` of the extended attribute identified by
+    /// `name` and associated with the given `path` in the filesystem. The
+    /// `size` argument specifies the size (in bytes) of `value`; a zero-length
+    /// value is permitted.
+    ///
+    /// By default, the extended attribute will be created if it does not
+    /// exist, or the value will be replaced if the attribute already exists.
+    /// `XATTR_CREATE` and `XATTR_REPLACE` in `flags` make it a pure create or
+    /// a pure replace operation respectively.
+    pub fn sys_setxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        name: UserReadPtr<u8>,
+        value: UserReadPtr<u8>,
+        size: usize,
+        flags: i32,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path(&path)?;
+        self.setxattr(dentry.inode()?, dentry, name, value, size, flags)
+    }
+
+    /// lsetxattr() is identical to setxattr(), except in the case of a
+    /// symbolic link, where the extended attribute is set on the link itself,
+    /// not the file that it refers to.
+    pub fn sys_lsetxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        name: UserReadPtr<u8>,
+        value: UserReadPtr<u8>,
+        size: usize,
+        flags: i32,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path_nofollow(&path)?;
+        self.setxattr(dentry.inode()?, dentry, name, value, size, flags)
+    }
+
+    /// fsetxattr() is identical to setxattr(), only the extended attribute is
+    /// set on the open file referred to by `fd` in place of `path`.
+    pub fn sys_fsetxattr(
+        &self,
+        fd: usize,
+        name: UserReadPtr<u8>,
+        value: UserReadPtr<u8>,
+        size: usize,
+        flags: i32,
+    ) -> SyscallResult {
+        let file = self.get_xattr_file(fd)?;
+        self.setxattr(file.inode(), file.dentry(), name, value, size, flags)
+    }
+
+    /// getxattr() retrieves the value of the extended attribute identified by
+    /// `name` and associated with the given `path` in the filesystem. The
+    /// attribute value is placed in the buffer pointed to by `value`; `size`
+    /// specifies the size of that buffer.
+    ///
+    /// If `size` is specified as zero, these calls return the current size of
+    /// the named extended attribute (and leave `value` unchanged).
+    pub fn sys_getxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        name: UserReadPtr<u8>,
+        value: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path(&path)?;
+        self.getxattr(dentry.inode()?, dentry, name, value, size)
+    }
+
+    /// lgetxattr() is identical to getxattr(), except in the case of a
+    /// symbolic link, where the link itself is interrogated, not the file that
+    /// it refers to.
+    pub fn sys_lgetxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        name: UserReadPtr<u8>,
+        value: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path_nofollow(&path)?;
+        self.getxattr(dentry.inode()?, dentry, name, value, size)
+    }
+
+    /// fgetxattr() is identical to getxattr(), only the open file referred to
+    /// by `fd` is interrogated in place of `path`.
+    pub fn sys_fgetxattr(
+        &self,
+        fd: usize,
+        name: UserReadPtr<u8>,
+        value: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let file = self.get_xattr_file(fd)?;
+        self.getxattr(file.inode(), file.dentry(), name, value, size)
+    }
+
+    /// listxattr() retrieves the list of extended attribute names associated
+    /// with the given `path` in the filesystem. The retrieved list is placed
+    /// in `list`, a caller-allocated buffer whose size (in bytes) is specified
+    /// in the argument `size`. The list is the set of (null-terminated) names,
+    /// one after the other.
+    ///
+    /// If `size` is specified as zero, these calls return the current size of
+    /// the list of extended attribute names (and leave `list` unchanged).
+    pub fn sys_listxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        list: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path(&path)?;
+        self.listxattr(dentry.inode()?, dentry, list, size)
+    }
+
+    /// llistxattr() is identical to listxattr(), except in the case of a
+    /// symbolic link, where the list of names of extended attributes
+    /// associated with the link itself is retrieved, not the file that it
+    /// refers to.
+    pub fn sys_llistxattr(
+        &self,
+        path: UserReadPtr<u8>,
+        list: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path_nofollow(&path)?;
+        self.listxattr(dentry.inode()?, dentry, list, size)
+    }
+
+    /// flistxattr() is identical to listxattr(), only the open file referred
+    /// to by `fd` is interrogated in place of `path`.
+    pub fn sys_flistxattr(&self, fd: usize, list: UserWritePtr<u8>, size: usize) -> SyscallResult {
+        let file = self.get_xattr_file(fd)?;
+        self.listxattr(file.inode(), file.dentry(), list, size)
+    }
+
+    /// removexattr() removes the extended attribute identified by `name` and
+    /// associated with the given `path` in the filesystem.
+    pub fn sys_removexattr(&self, path: UserReadPtr<u8>, name: UserReadPtr<u8>) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path(&path)?;
+        let name = name.read_cstr(self.task)?;
+        dentry.inode()?.removexattr(dentry.as_ref(), &name)?;
+        Ok(0)
+    }
+
+    /// lremovexattr() is identical to removexattr(), except in the case of a
+    /// symbolic link, where the extended attribute is removed from the link
+    /// itself, not the file that it refers to.
+    pub fn sys_lremovexattr(&self, path: UserReadPtr<u8>, name: UserReadPtr<u8>) -> SyscallResult {
+        let path = path.read_cstr(self.task)?;
+        let dentry = self.task.resolve_path_nofollow(&path)?;
+        let name = name.read_cstr(self.task)?;
+        dentry.inode()?.removexattr(dentry.as_ref(), &name)?;
+        Ok(0)
+    }
+
+    /// fremovexattr() is identical to removexattr(), only the extended
+    /// attribute is removed from the open file referred to by `fd` in place of
+    /// `path`.
+    pub fn sys_fremovexattr(&self, fd: usize, name: UserReadPtr<u8>) -> SyscallResult {
+        let file = self.get_xattr_file(fd)?;
+        let name = name.read_cstr(self.task)?;
+        file.inode().removexattr(file.dentry().as_ref(), &name)?;
+        Ok(0)
+    }
+
+    /// Get the file of `fd` for extended attributes, which are not supported
+    /// by anonymous files without inodes, e.g. sockets.
+    fn get_xattr_file(&self, fd: usize) -> SysResult<Arc<dyn File>> {
+        let file = self.task.with_fd_table(|table| table.get_file(fd))?;
+        if file.inode().is_dummy() {
+            return Err(SysError::EOPNOTSUPP);
+        }
+        Ok(file)
+    }
+
+    fn setxattr(
+        &self,
+        inode: Arc<dyn Inode>,
+        dentry: Arc<dyn Dentry>,
+        name: UserReadPtr<u8>,
+        value: UserReadPtr<u8>,
+        size: usize,
+        flags: i32,
+    ) -> SyscallResult {
+        let task = self.task;
+        let flags = XattrFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+        let name = name.read_cstr(task)?;
+        if size > XATTR_SIZE_MAX {
+            return Err(SysError::E2BIG);
+        }
+        let value = if size == 0 {
+            Vec::new()
+        } else {
+            value.read_array(task, size)?
+        };
+        inode.setxattr(dentry.as_ref(), &name, &value, flags)?;
+        Ok(0)
+    }
+
+    fn getxattr(
+        &self,
+        inode: Arc<dyn Inode>,
+        dentry: Arc<dyn Dentry>,
+        name: UserReadPtr<u8>,
+        value: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let name = name.read_cstr(task)?;
+        let attr = inode.getxattr(dentry.as_ref(), &name)?;
+        log::info!(
+            "[sys_getxattr] name:{name}, size:{size}, len:{}",
+            attr.len()
+        );
+        if size == 0 {
+            return Ok(attr.len());
+        }
+        if attr.len() > size {
+            return Err(SysError::ERANGE);
+        }
+        value.write_array(task, &attr)?;
+        Ok(attr.len())
+    }
+
+    fn listxattr(
+        &self,
+        inode: Arc<dyn Inode>,
+        dentry: Arc<dyn Dentry>,
+        list: UserWritePtr<u8>,
+        size: usize,
+    ) -> SyscallResult {
+        let task = self.task;
+        let mut names = Vec::new();
+        for name in inode.listxattr(dentry.as_ref())? {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        log::info!("[sys_listxattr] size:{size}, len:{}", names.len());
+        if names.len() > XATTR_LIST_MAX {
+            return Err(SysError::E2BIG);
+        }
+        if size == 0 {
+            return Ok(names.len());
+        }
+        if names.len() > size {
+            return Err(SysError::ERANGE);
+        }
+        list.write_array(task, &names)?;
+        Ok(names.len())
+    }
+}
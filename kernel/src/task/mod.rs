@@ -1,8 +1,10 @@
 pub mod aux;
 mod manager;
+pub mod pidfd;
 pub mod resource;
 mod schedule;
 pub mod signal;
+pub mod signalfd;
 pub mod task;
 mod tid;
 
@@ -11,7 +13,7 @@ use alloc::{string::ToString, vec, vec::Vec};
 use async_utils::block_on;
 use config::process::USER_STACK_SIZE;
 pub use manager::{PROCESS_GROUP_MANAGER, TASK_MANAGER};
-pub use schedule::{spawn_kernel_task, spawn_user_task};
+pub use schedule::{spawn_kernel_task, spawn_user_io_task, spawn_user_task};
 pub use task::Task;
 pub use tid::{PGid, Pid, Tid, TID_ALLOCATOR};
 use vfs::sys_root_dentry;
@@ -0,0 +1,73 @@
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use systype::{SysError, SysResult};
+use vfs_core::{arc_zero, File, FileMeta, OpenFlags, PollEvents};
+
+use super::Task;
+
+/// A pidfd refers to a process, which is stable even if the pid is reused
+/// after the process has been reaped.
+pub struct PidFdFile {
+    meta: FileMeta,
+    /// Leader of the thread group.
+    task: Weak<Task>,
+}
+
+impl PidFdFile {
+    pub fn new(task: &Arc<Task>, flags: OpenFlags) -> Arc<Self> {
+        debug_assert!(task.is_leader());
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        *meta.flags.lock() = OpenFlags::O_RDWR | (flags & OpenFlags::O_NONBLOCK);
+        Arc::new(Self {
+            meta,
+            task: Arc::downgrade(task),
+        })
+    }
+
+    /// Return the process referred to, or `ESRCH` if it has been reaped.
+    pub fn task(&self) -> SysResult<Arc<Task>> {
+        self.task.upgrade().ok_or(SysError::ESRCH)
+    }
+}
+
+#[async_trait]
+impl File for PidFdFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    /// The pidfd becomes readable when the process exits.
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if !events.contains(PollEvents::IN) {
+            return res;
+        }
+        match self.task.upgrade() {
+            Some(task) => task.with_mut_pidfd_wakers(|wakers| {
+                // Checked with the lock held so that the exit will not be
+                // missed.
+                if task.is_zombie() {
+                    res |= PollEvents::IN;
+                } else {
+                    wakers.push_back(waker);
+                }
+            }),
+            None => res |= PollEvents::IN,
+        }
+        res
+    }
+}
@@ -12,6 +12,8 @@ use timer::{Timer, TIMER_MANAGER};
 
 use super::Task;
 use crate::{
+    aio,
+    io_uring::{self, ChainOwner},
     processor::{env::EnvContext, hart},
     task::{signal::*, task::TaskState::*},
     trap,
@@ -49,6 +51,38 @@ impl<F: Future + Send + 'static> Future for UserTaskFuture<F> {
     }
 }
 
+/// The future that runs on behalf of a user task without running its user
+/// code, e.g. an io_uring request, which accesses the memory and the files of
+/// the task.
+pub struct UserIoFuture<F: Future<Output = ()> + Send + 'static> {
+    task: Arc<Task>,
+    env: EnvContext,
+    future: F,
+}
+
+impl<F: Future<Output = ()> + Send + 'static> UserIoFuture<F> {
+    pub fn new(task: Arc<Task>, future: F) -> Self {
+        Self {
+            task,
+            env: EnvContext::new(),
+            future,
+        }
+    }
+}
+
+impl<F: Future<Output = ()> + Send + 'static> Future for UserIoFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let hart = hart::local_hart();
+        hart.enter_user_io_switch(&this.task, &mut this.env);
+        let ret = unsafe { Pin::new_unchecked(&mut this.future).poll(cx) };
+        hart.leave_user_io_switch(&mut this.env);
+        ret
+    }
+}
+
 pub struct KernelTaskFuture<F: Future<Output = ()> + Send + 'static> {
     env: EnvContext,
     future: F,
@@ -106,6 +140,9 @@ pub async fn task_loop(task: Arc<Task>) {
     }
 
     log::debug!("thread {} terminated", task.tid());
+    io_uring::cancel_chains(ChainOwner::Task(task.tid())).await;
+    let aio_ctxs = task.with_aio_ctxs(|ctxs| ctxs.values().cloned().collect());
+    aio::drain_contexts(aio_ctxs, Some(task.tid())).await;
     task.do_exit();
 }
 
@@ -126,6 +163,15 @@ pub fn spawn_kernel_task<F: Future<Output = ()> + Send + 'static>(kernel_task: F
     task.detach();
 }
 
+/// Spawn a new async kernel task that does I/O on behalf of the user task
+/// `task`
+pub fn spawn_user_io_task<F: Future<Output = ()> + Send + 'static>(task: Arc<Task>, future: F) {
+    let future = UserIoFuture::new(task, future);
+    let (runnable, task) = executor::spawn(future);
+    runnable.schedule();
+    task.detach();
+}
+
 impl Task {
     /// 返回值代表的是条件满足时，还剩余多少Duration。如果剩余的 Duration 为
     /// 0，说明就是超时了，大于 0 才是因事件唤醒
@@ -16,7 +16,7 @@ use signal::*;
 use systype::SysResult;
 use timer::{Timer, TimerEvent};
 
-use super::Task;
+use super::{task::StopEvent, Task};
 use crate::mm::UserWritePtr;
 
 #[derive(Clone, Copy, Default)]
@@ -95,6 +95,9 @@ impl Task {
         );
         self.with_mut_sig_pending(|pending| {
             pending.add(si);
+            while let Some(waker) = pending.signalfd_wakers.pop_front() {
+                waker.wake();
+            }
             if pending.should_wake.contain_signal(si.sig) && self.is_interruptable() {
                 log::info!("[Task::recv] tid {} has been woken", self.tid());
                 self.wake();
@@ -271,6 +274,8 @@ fn stop(task: &Arc<Task>, sig: Sig) {
             t.set_wake_up_signal(SigSet::SIGCONT);
         }
     });
+    task.leader()
+        .with_mut_stop_event(|event| *event = Some(StopEvent::Stopped(sig)));
     task.notify_parent(SigInfo::CLD_STOPPED, sig);
 }
 
@@ -283,6 +288,8 @@ fn cont(task: &Arc<Task>, sig: Sig) {
             t.wake();
         }
     });
+    task.leader()
+        .with_mut_stop_event(|event| *event = Some(StopEvent::Continued));
     task.notify_parent(SigInfo::CLD_CONTINUED, sig);
 }
 
@@ -0,0 +1,220 @@
+use alloc::{boxed::Box, sync::Arc, vec};
+use core::{
+    future::Future,
+    mem::size_of,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use signal::{SigDetails, SigInfo, SigSet};
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{arc_zero, File, FileMeta, OpenFlags, PollEvents};
+
+use crate::processor::hart::current_task;
+
+bitflags::bitflags! {
+    // Defined in <sys/signalfd.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SignalFdFlags: i32 {
+        const NONBLOCK = OpenFlags::O_NONBLOCK.bits();
+        const CLOEXEC = OpenFlags::O_CLOEXEC.bits();
+    }
+}
+
+/// Record read from a signalfd, defined in <sys/signalfd.h>.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SignalFdSigInfo {
+    /// Signal number
+    pub ssi_signo: u32,
+    /// Error number (unused)
+    pub ssi_errno: i32,
+    /// Signal code
+    pub ssi_code: i32,
+    /// PID of sender
+    pub ssi_pid: u32,
+    /// Real UID of sender
+    pub ssi_uid: u32,
+    /// File descriptor (SIGIO)
+    pub ssi_fd: i32,
+    /// Kernel timer ID (POSIX timers)
+    pub ssi_tid: u32,
+    /// Band event (SIGIO)
+    pub ssi_band: u32,
+    /// POSIX timer overrun count
+    pub ssi_overrun: u32,
+    /// Trap number that caused signal
+    pub ssi_trapno: u32,
+    /// Exit status or signal (SIGCHLD)
+    pub ssi_status: i32,
+    /// Integer sent by sigqueue(3)
+    pub ssi_int: i32,
+    /// Pointer sent by sigqueue(3)
+    pub ssi_ptr: u64,
+    /// User CPU time consumed (SIGCHLD)
+    pub ssi_utime: u64,
+    /// System CPU time consumed (SIGCHLD)
+    pub ssi_stime: u64,
+    /// Address that generated signal (for hardware-generated signals)
+    pub ssi_addr: u64,
+    /// Least significant bit of address (SIGBUS; since Linux 2.6.37)
+    pub ssi_addr_lsb: u16,
+    __pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    __pad: [u8; 28],
+}
+
+impl From<SigInfo> for SignalFdSigInfo {
+    fn from(si: SigInfo) -> Self {
+        let ssi_pid = match si.details {
+            SigDetails::Kill { pid } => pid as u32,
+            SigDetails::None => 0,
+        };
+        Self {
+            ssi_signo: si.sig.raw() as u32,
+            ssi_errno: 0,
+            ssi_code: si.code,
+            ssi_pid,
+            ssi_uid: 0,
+            ssi_fd: 0,
+            ssi_tid: 0,
+            ssi_band: 0,
+            ssi_overrun: 0,
+            ssi_trapno: 0,
+            ssi_status: 0,
+            ssi_int: 0,
+            ssi_ptr: 0,
+            ssi_utime: 0,
+            ssi_stime: 0,
+            ssi_addr: 0,
+            ssi_addr_lsb: 0,
+            __pad2: 0,
+            ssi_syscall: 0,
+            ssi_call_addr: 0,
+            ssi_arch: 0,
+            __pad: [0; 28],
+        }
+    }
+}
+
+/// A signalfd reads signals pending for the task that reads it, that is, the
+/// signals are dequeued from `SigPending` of the current task.
+pub struct SignalFdFile {
+    meta: FileMeta,
+    mask: SpinNoIrqLock<SigSet>,
+}
+
+impl SignalFdFile {
+    pub fn new(mask: SigSet, flags: SignalFdFlags) -> Arc<Self> {
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        let mut open_flags = OpenFlags::empty();
+        if flags.contains(SignalFdFlags::NONBLOCK) {
+            open_flags |= OpenFlags::O_NONBLOCK;
+        }
+        *meta.flags.lock() = open_flags;
+        Arc::new(Self {
+            meta,
+            mask: SpinNoIrqLock::new(Self::valid_mask(mask)),
+        })
+    }
+
+    /// It is not possible to receive SIGKILL or SIGSTOP signals via a
+    /// signalfd, these signals are silently ignored if specified in mask.
+    fn valid_mask(mut mask: SigSet) -> SigSet {
+        mask.remove(SigSet::SIGKILL | SigSet::SIGSTOP);
+        mask
+    }
+
+    pub fn set_mask(&self, mask: SigSet) {
+        *self.mask.lock() = Self::valid_mask(mask);
+    }
+
+    fn mask(&self) -> SigSet {
+        *self.mask.lock()
+    }
+}
+
+struct SignalFdReadFuture<'a> {
+    signalfd: &'a SignalFdFile,
+}
+
+impl Future for SignalFdReadFuture<'_> {
+    type Output = SysResult<SigInfo>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mask = self.signalfd.mask();
+        let nonblock = self.signalfd.flags().contains(OpenFlags::O_NONBLOCK);
+        current_task().with_mut_sig_pending(|pending| {
+            if let Some(si) = pending.dequeue_expect(mask) {
+                Poll::Ready(Ok(si))
+            } else if nonblock {
+                Poll::Ready(Err(SysError::EAGAIN))
+            } else {
+                pending.signalfd_wakers.push_back(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl File for SignalFdFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// Each successful read(2) returns one or more signalfd_siginfo structures.
+    /// If none of the signals in mask is pending for the task, then the read
+    /// either blocks until one of the signals in mask is generated for the
+    /// task, or fails with the error EAGAIN if the file descriptor has been
+    /// made nonblocking.
+    async fn read_at(&self, _offset: usize, buf: &mut [u8]) -> SysResult<usize> {
+        const SIZE: usize = size_of::<SignalFdSigInfo>();
+        if buf.len() < SIZE {
+            return Err(SysError::EINVAL);
+        }
+        let si = SignalFdReadFuture { signalfd: self }.await?;
+        let mut infos = vec![SignalFdSigInfo::from(si)];
+        // Read as many signals as possible without blocking.
+        let mask = self.mask();
+        current_task().with_mut_sig_pending(|pending| {
+            while (infos.len() + 1) * SIZE <= buf.len() {
+                let Some(si) = pending.dequeue_expect(mask) else {
+                    break;
+                };
+                infos.push(si.into());
+            }
+        });
+        log::info!("[SignalFdFile::read_at] read {infos:?}");
+        let len = infos.len() * SIZE;
+        let bytes = unsafe { core::slice::from_raw_parts(infos.as_ptr() as *const u8, len) };
+        buf[..len].copy_from_slice(bytes);
+        Ok(len)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if !events.contains(PollEvents::IN) {
+            return res;
+        }
+        let mask = self.mask();
+        current_task().with_mut_sig_pending(|pending| {
+            if pending.has_expect_signals(mask) {
+                res |= PollEvents::IN;
+            } else {
+                pending.signalfd_wakers.push_back(waker);
+            }
+        });
+        res
+    }
+}
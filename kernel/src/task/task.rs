@@ -1,5 +1,5 @@
 use alloc::{
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     ffi::CString,
     string::String,
     sync::{Arc, Weak},
@@ -31,7 +31,8 @@ use systype::{SysError, SysResult};
 use time::stat::TaskTimeStat;
 use vfs::{fd_table::FdTable, sys_root_dentry};
 use vfs_core::{
-    is_absolute_path, split_path, AtFd, Dentry, File, InodeMode, InodeType, OpenFlags, Path,
+    is_absolute_path, lock::release_all_posix_locks, split_path, AtFd, Dentry, File, InodeMode,
+    InodeType, OpenFlags, Path,
 };
 
 use super::{
@@ -41,9 +42,11 @@ use super::{
     PGid, PROCESS_GROUP_MANAGER,
 };
 use crate::{
+    aio::AioContext,
     generate_accessors, generate_atomic_accessors, generate_state_methods, generate_with_methods,
     ipc::{
-        futex::{futex_manager, FutexHashKey, RobustListHead},
+        futex::{exit_robust_list, futex_manager, FutexHashKey},
+        sem::SEM_SET_MANAGER,
         shm::SHARED_MEMORY_MANAGER,
     },
     mm::{memory_space::init_stack, MemorySpace, UserWritePtr},
@@ -85,6 +88,9 @@ pub struct Task {
     /// Map of start address of shared memory areas to their keys in the shared
     /// memory manager.
     shm_ids: Shared<BTreeMap<VirtAddr, usize>>,
+    /// AIO contexts set up by `sys_io_setup`, keyed by their ids. They belong
+    /// to the address space like the rings they are identified by.
+    aio_ctxs: Shared<BTreeMap<usize, Arc<AioContext>>>,
     /// Parent process.
     parent: Shared<Option<Weak<Task>>>,
     /// Children processes.
@@ -118,18 +124,27 @@ pub struct Task {
     time_stat: SyncUnsafeCell<TaskTimeStat>,
     /// Interval timers for the task.
     itimers: Shared<[ITimer; 3]>,
-    /// Futexes used by the task.
-    robust: Shared<RobustListHead>,
+    /// User address of the head of the robust futex list, registered by
+    /// `sys_set_robust_list`.
+    robust_list: AtomicUsize,
     /// Address of the task's thread ID.
     tid_address: SyncUnsafeCell<TidAddress>,
     /// Mask of CPUs allowed for the task.
     cpus_allowed: SyncUnsafeCell<CpuMask>,
     /// Process group ID of the task.
     pgid: Shared<PGid>,
+    /// Adjustment of the OOM killer score of the process, inherited by the
+    /// child on fork.
+    oom_score_adj: Shared<i32>,
     /// ELF file the task executes.
     elf: SyncUnsafeCell<Arc<dyn File>>,
     /// Command-line arguments for the task.
     args: SyncUnsafeCell<Vec<String>>,
+    /// Stop or continue of the process not yet reported by `sys_waitid`.
+    stop_event: SpinNoIrqLock<Option<StopEvent>>,
+    /// Wakers of tasks polling pidfds referring to the process, woken when
+    /// the process exits.
+    pidfd_wakers: SpinNoIrqLock<VecDeque<Waker>>,
 }
 
 impl core::fmt::Debug for Task {
@@ -144,6 +159,16 @@ impl Drop for Task {
     }
 }
 
+/// Change of the state of a child process reported by `sys_waitid` with
+/// `WSTOPPED` or `WCONTINUED`.
+#[derive(Debug, Clone, Copy)]
+pub enum StopEvent {
+    /// Stopped by the signal.
+    Stopped(Sig),
+    /// Resumed by `SIGCONT`.
+    Continued,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TaskState {
     /// The task is currently running or ready to run, occupying the CPU and
@@ -195,18 +220,20 @@ impl Task {
         elf: Arc<dyn File>,
         args: Vec<String>
     );
-    generate_atomic_accessors!(exit_code: i32, sig_ucontext_ptr: usize);
+    generate_atomic_accessors!(exit_code: i32, sig_ucontext_ptr: usize, robust_list: usize);
     generate_with_methods!(
         fd_table: FdTable,
         children: BTreeMap<Tid, Arc<Task>>,
         memory_space: MemorySpace,
         thread_group: ThreadGroup,
         sig_pending: SigPending,
-        robust: RobustListHead,
         sig_handlers: SigHandlers,
         state: TaskState,
         shm_ids: BTreeMap<VirtAddr, usize>,
-        itimers: [ITimer;3]
+        aio_ctxs: BTreeMap<usize, Arc<AioContext>>,
+        itimers: [ITimer;3],
+        stop_event: Option<StopEvent>,
+        pidfd_wakers: VecDeque<Waker>
     );
 
     pub fn new_init(
@@ -238,13 +265,17 @@ impl Task {
             time_stat: SyncUnsafeCell::new(TaskTimeStat::new()),
             sig_ucontext_ptr: AtomicUsize::new(0),
             itimers: new_shared([ITimer::ZERO; 3]),
-            robust: new_shared(RobustListHead::default()),
+            robust_list: AtomicUsize::new(0),
             tid_address: SyncUnsafeCell::new(TidAddress::new()),
             cpus_allowed: SyncUnsafeCell::new(CpuMask::CPU_ALL),
             shm_ids: new_shared(BTreeMap::new()),
+            aio_ctxs: new_shared(BTreeMap::new()),
             pgid: new_shared(pgid),
+            oom_score_adj: new_shared(0),
             elf: SyncUnsafeCell::new(elf_file),
             args: SyncUnsafeCell::new(args),
+            stop_event: SpinNoIrqLock::new(None),
+            pidfd_wakers: SpinNoIrqLock::new(VecDeque::new()),
         });
 
         task.thread_group.lock().push(task.clone());
@@ -312,6 +343,26 @@ impl Task {
         *self.pgid.lock() = pgid
     }
 
+    pub fn oom_score_adj(&self) -> i32 {
+        *self.oom_score_adj.lock()
+    }
+
+    pub fn set_oom_score_adj(&self, adj: i32) {
+        *self.oom_score_adj.lock() = adj
+    }
+
+    /// Count of pages resident in the memory space of the task, or `None` if
+    /// the memory space is locked by someone else.
+    pub fn try_rss(&self) -> Option<usize> {
+        self.try_with_mut_memory_space(|m| m.rss())
+    }
+
+    /// Call `f` with the memory space of the task, or return `None` if it is
+    /// locked by someone else.
+    pub fn try_with_mut_memory_space<T>(&self, f: impl FnOnce(&mut MemorySpace) -> T) -> Option<T> {
+        self.memory_space.try_lock().map(|mut m| f(&mut m))
+    }
+
     pub fn ppid(&self) -> Pid {
         self.parent()
             .expect("Call ppid without a parent")
@@ -359,9 +410,9 @@ impl Task {
         let thread_group;
         let cwd;
         let itimers;
-        let robust;
         let shm_ids;
         let pgid;
+        let oom_score_adj;
         let sig_handlers = if flags.contains(CloneFlags::SIGHAND) {
             self.sig_handlers.clone()
         } else {
@@ -375,9 +426,9 @@ impl Task {
             thread_group = self.thread_group.clone();
             itimers = self.itimers.clone();
             cwd = self.cwd.clone();
-            robust = self.robust.clone();
             shm_ids = self.shm_ids.clone();
             pgid = self.pgid.clone();
+            oom_score_adj = self.oom_score_adj.clone();
         } else {
             is_leader = true;
             leader = None;
@@ -386,18 +437,22 @@ impl Task {
             thread_group = new_shared(ThreadGroup::new());
             itimers = new_shared([ITimer::ZERO; 3]);
             cwd = new_shared(self.cwd());
-            robust = new_shared(RobustListHead::default());
             shm_ids = new_shared(BTreeMap::clone(&self.shm_ids.lock()));
             for (_, shm_id) in shm_ids.lock().iter() {
                 SHARED_MEMORY_MANAGER.attach(*shm_id, tid.0);
             }
             pgid = new_shared(self.pgid());
+            oom_score_adj = new_shared(self.oom_score_adj());
         }
 
         let memory_space;
+        let aio_ctxs;
         if flags.contains(CloneFlags::VM) {
             memory_space = self.memory_space.clone();
+            aio_ctxs = self.aio_ctxs.clone();
         } else {
+            // AIO contexts are not inherited by the child
+            aio_ctxs = new_shared(BTreeMap::new());
             memory_space =
                 new_shared(self.with_mut_memory_space(|m| MemorySpace::from_user_lazily(m)));
             // TODO: avoid flushing global entries like kernel mappings
@@ -432,14 +487,18 @@ impl Task {
             time_stat: SyncUnsafeCell::new(TaskTimeStat::new()),
             sig_ucontext_ptr: AtomicUsize::new(0),
             itimers,
-            robust,
+            robust_list: AtomicUsize::new(0),
             tid_address: SyncUnsafeCell::new(TidAddress::new()),
             cpus_allowed: SyncUnsafeCell::new(CpuMask::CPU_ALL),
             // After a fork(2), the child inherits the attached shared memory segments.
             shm_ids,
+            aio_ctxs,
             pgid,
+            oom_score_adj,
             elf: SyncUnsafeCell::new(self.elf_ref().clone()),
             args: SyncUnsafeCell::new(self.args_ref().clone()),
+            stop_event: SpinNoIrqLock::new(None),
+            pidfd_wakers: SpinNoIrqLock::new(VecDeque::new()),
         });
 
         if !flags.contains(CloneFlags::THREAD) {
@@ -518,6 +577,9 @@ impl Task {
         // Any alternate signal stack is not preserved
         *self.sig_stack() = None;
 
+        // The robust futex list lives in the old address space
+        self.set_robust_list(0);
+
         // During an execve, the dispositions of handled signals are reset
         // to the default; the dispositions of ignored signals are left unchanged
         self.with_mut_sig_handlers(|handlers| handlers.reset_user_defined());
@@ -545,6 +607,9 @@ impl Task {
             self.trap_context_mut().sepc
         );
 
+        exit_robust_list(self);
+        futex_manager().exit_pi_owner(self.tid());
+
         if let Some(address) = self.tid_address_ref().clear_child_tid {
             log::info!("[do_exit] clear_child_tid: {:x}", address);
             UserWritePtr::from(address)
@@ -641,15 +706,25 @@ impl Task {
             }
         });
 
+        // Semaphore adjustments of SEM_UNDO operations are applied when the
+        // process exits.
+        SEM_SET_MANAGER.lock().exit(self.pid());
+
+        // POSIX record locks are owned by the process and released when it
+        // exits.
+        release_all_posix_locks(self.pid());
+
         // TODO: drop most resources here instead of wait4 function parent
         // called
         self.with_mut_fd_table(|table| table.clear());
 
-        if self.is_leader() {
-            self.set_zombie();
-        } else {
-            self.leader().set_zombie();
-        }
+        let leader = self.leader();
+        leader.set_zombie();
+        leader.with_mut_pidfd_wakers(|wakers| {
+            for waker in wakers.drain(..) {
+                waker.wake();
+            }
+        });
         // When the task is not leader, which means its is not a process, it
         // will get dropped when hart leaves this task.
     }
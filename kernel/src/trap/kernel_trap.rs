@@ -10,10 +10,11 @@ use riscv::register::{
     sepc, sstatus, stval, stvec,
 };
 use signal::{Sig, SigDetails, SigInfo};
+use systype::SysError;
 use timer::TIMER_MANAGER;
 
 use crate::{
-    mm::PageFaultAccessType,
+    mm::{oom::reclaim_or_kill, PageFaultAccessType},
     processor::hart::{
         current_task_ref, local_hart, local_hart_disable_preemptable,
         local_hart_enable_preemptable, local_hart_preemptable,
@@ -87,7 +88,11 @@ pub fn kernel_trap_handler() {
                 let result = current_task_ref().with_mut_memory_space(|m| {
                     m.handle_page_fault(VirtAddr::from(stval), access_type)
                 });
-                if let Err(_e) = result {
+                if let Err(SysError::ENOMEM) = result {
+                    // Reclaim or kill some process to free memory, and retry the
+                    // access after returning, as a page fault from user does.
+                    reclaim_or_kill();
+                } else if let Err(_e) = result {
                     log::warn!(
                         "[trap_handler] encounter page fault, addr {stval:#x}, instruction {sepc:#x} scause {cause:?}",
                     );
@@ -19,7 +19,12 @@ use systype::SysError;
 use timer::TIMER_MANAGER;
 
 use super::{set_kernel_trap, TrapContext};
-use crate::{mm::PageFaultAccessType, syscall::Syscall, task::Task, trap::set_user_trap};
+use crate::{
+    mm::{oom::reclaim_or_kill, PageFaultAccessType},
+    syscall::Syscall,
+    task::Task,
+    trap::set_user_trap,
+};
 
 /// handle an interrupt, exception, or system call from user space
 /// return if it is syscall and has been interrupted
@@ -81,7 +86,12 @@ pub async fn trap_handler(task: &Arc<Task>) -> bool {
                     let result = task.with_mut_memory_space(|m| {
                         m.handle_page_fault(VirtAddr::from(stval), access_type)
                     });
-                    if let Err(_e) = result {
+                    if let Err(SysError::ENOMEM) = result {
+                        // Reclaim or kill some process to free memory, and retry the page
+                        // fault after returning to user unless this one is killed.
+                        reclaim_or_kill();
+                        yield_now().await;
+                    } else if let Err(_e) = result {
                         log::warn!(
                             "[trap_handler] encounter page fault, addr {stval:#x}, instruction {sepc:#x} scause {cause:?}",
                         );
@@ -128,6 +128,10 @@ pub trait BlockDevice: Device {
 
     /// Write data from buffer to block
     fn write_block(&self, block_id: usize, buf: &[u8]);
+
+    /// Write back cached blocks and flush the volatile write cache of the
+    /// device, so that all blocks written before are on stable storage.
+    fn flush(&self);
 }
 
 impl_downcast!(sync BlockDevice);
@@ -12,8 +12,8 @@ use vfs_core::{
 };
 
 use crate::{
-    file::Ext4FileFile, inode::Ext4FileInode, readlink, Ext4DirFile, Ext4DirInode, Ext4LinkFile,
-    Ext4LinkInode, LwExt4Dir, LwExt4File,
+    file::Ext4FileFile, inode::Ext4FileInode, mknod, readlink, Ext4DirFile, Ext4DirInode,
+    Ext4LinkFile, Ext4LinkInode, Ext4SockInode, LwExt4Dir, LwExt4File,
 };
 
 pub struct Ext4Dentry {
@@ -66,6 +66,8 @@ impl Dentry for Ext4Dentry {
                     .unwrap_or_else(|_| unreachable!());
                 Ok(Ext4LinkFile::new(self, inode))
             }
+            // Sockets bound to the path are reached through socket fds.
+            InodeType::Socket => Err(SysError::ENXIO),
             _ => todo!(),
         }
     }
@@ -86,6 +88,9 @@ impl Dentry for Ext4Dentry {
             let target = readlink(&sub_dentry.path())?;
             let sub_inode = Ext4LinkInode::new(target.to_str().unwrap(), sb);
             sub_dentry.set_inode(sub_inode)
+        } else if lwext4_check_inode_exist(&path, InodeTypes::EXT4_DE_SOCK) {
+            let mode = InodeMode::from_type(InodeType::Socket);
+            sub_dentry.set_inode(Ext4SockInode::new(mode, sb))
         }
         Ok(sub_dentry)
     }
@@ -113,7 +118,11 @@ impl Dentry for Ext4Dentry {
                 .map_err(SysError::from_i32)?;
                 Ext4FileInode::new(sb, new_file)
             }
-            _ => todo!(),
+            InodeType::Socket => {
+                mknod(&path, InodeTypes::EXT4_DE_SOCK)?;
+                Ext4SockInode::new(mode, sb)
+            }
+            _ => return Err(SysError::EPERM),
         };
         sub_dentry.set_inode(new_inode);
         Ok(sub_dentry)
@@ -124,7 +133,7 @@ impl Dentry for Ext4Dentry {
         let path = sub_dentry.path();
         match sub_dentry.inode()?.itype() {
             InodeType::Dir => lwext4_rmdir(&path).map_err(SysError::from_i32),
-            InodeType::File | InodeType::SymLink => {
+            InodeType::File | InodeType::SymLink | InodeType::Socket => {
                 lwext4_rmfile(&path).map_err(SysError::from_i32)
             }
             _ => todo!(),
@@ -150,7 +159,9 @@ impl Dentry for Ext4Dentry {
             }
             match new_itype {
                 InodeType::Dir => lwext4_rmdir(&new.path()).map_err(SysError::from_i32),
-                InodeType::File => lwext4_rmfile(&new.path()).map_err(SysError::from_i32),
+                InodeType::File | InodeType::Socket => {
+                    lwext4_rmfile(&new.path()).map_err(SysError::from_i32)
+                }
                 InodeType::SymLink => todo!(),
                 _ => todo!(),
             };
@@ -159,7 +170,7 @@ impl Dentry for Ext4Dentry {
             InodeType::Dir => {
                 lwext4_mvdir(&self.path(), &new.path()).map_err(SysError::from_i32)?;
             }
-            InodeType::File => {
+            InodeType::File | InodeType::Socket => {
                 lwext4_mvfile(&self.path(), &new.path()).map_err(SysError::from_i32)?;
             }
             InodeType::SymLink => todo!(),
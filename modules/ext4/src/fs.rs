@@ -1,7 +1,7 @@
 use alloc::sync::Arc;
 
 use device_core::BlockDevice;
-use lwext4_rust::{Ext4BlockWrapper, InodeTypes};
+use lwext4_rust::{bindings::ext4_cache_flush, Ext4BlockWrapper, InodeTypes};
 use systype::{SysError, SysResult};
 use vfs_core::{
     Dentry, FileSystemType, FileSystemTypeMeta, InodeType, MountFlags, OpenFlags, StatFs,
@@ -81,6 +81,10 @@ impl SuperBlock for Ext4SuperBlock {
     }
 
     fn sync_fs(&self, _wait: isize) -> systype::SysResult<()> {
-        todo!()
+        // lwext4 caches blocks in write back mode, flush them to the device.
+        match unsafe { ext4_cache_flush(c"/".as_ptr()) } {
+            0 => Ok(()),
+            err => Err(SysError::from_i32(err)),
+        }
     }
 }
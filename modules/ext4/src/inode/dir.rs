@@ -7,7 +7,7 @@ use lwext4_rust::{
 use systype::{SysError, SysResult};
 use vfs_core::{Inode, InodeMeta, InodeMode, InodeType, Stat, SuperBlock};
 
-use crate::{map_ext4_err, map_ext4_type, LwExt4Dir, LwExt4File, Mutex, Shared};
+use crate::{map_ext4_err, map_ext4_type, xattr, LwExt4Dir, LwExt4File, Mutex, Shared};
 
 pub struct Ext4DirInode {
     meta: InodeMeta,
@@ -63,4 +63,6 @@ impl Inode for Ext4DirInode {
     fn base_get_blk_idx(&self, offset: usize) -> SysResult<usize> {
         Err(SysError::EINVAL)
     }
+
+    xattr::impl_xattr!();
 }
@@ -7,7 +7,7 @@ use lwext4_rust::{
 use systype::{SysError, SysResult};
 use vfs_core::{Inode, InodeMeta, InodeMode, InodeType, Stat, SuperBlock};
 
-use crate::{map_ext4_err, map_ext4_type, LwExt4Dir, LwExt4File, Mutex, Shared};
+use crate::{map_ext4_err, map_ext4_type, xattr, LwExt4Dir, LwExt4File, Mutex, Shared};
 
 pub struct Ext4FileInode {
     meta: InodeMeta,
@@ -81,4 +81,14 @@ impl Inode for Ext4FileInode {
             .map_err(SysError::from_i32)?;
         Ok(blk_idx as usize)
     }
+
+    fn base_write_back(&self, offset: usize, buf: &[u8]) -> SysResult<()> {
+        let mut file = self.file.lock();
+        file.seek(offset as i64, SEEK_SET)
+            .map_err(SysError::from_i32)?;
+        file.write(buf).map_err(SysError::from_i32)?;
+        Ok(())
+    }
+
+    xattr::impl_xattr!();
 }
@@ -8,7 +8,7 @@ use lwext4_rust::{
 use systype::{SysError, SysResult};
 use vfs_core::{Inode, InodeMeta, InodeMode, InodeType, Stat, SuperBlock};
 
-use crate::{map_ext4_err, map_ext4_type, LwExt4Dir, LwExt4File, Mutex, Shared};
+use crate::{map_ext4_err, map_ext4_type, xattr, LwExt4Dir, LwExt4File, Mutex, Shared};
 
 pub struct Ext4LinkInode {
     meta: InodeMeta,
@@ -66,4 +66,6 @@ impl Inode for Ext4LinkInode {
     fn base_get_blk_idx(&self, offset: usize) -> SysResult<usize> {
         Err(SysError::EINVAL)
     }
+
+    xattr::impl_xattr!();
 }
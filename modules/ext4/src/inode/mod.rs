@@ -1,7 +1,9 @@
 mod dir;
 mod file;
 mod link;
+mod sock;
 
 pub use dir::*;
 pub use file::*;
 pub use link::*;
+pub use sock::*;
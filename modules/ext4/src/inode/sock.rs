@@ -0,0 +1,48 @@
+use alloc::sync::Arc;
+
+use systype::SysResult;
+use vfs_core::{Inode, InodeMeta, InodeMode, Stat, SuperBlock};
+
+/// Inode of a socket bound to a path, which holds no data on disk. The socket
+/// itself is found by the path.
+pub struct Ext4SockInode {
+    meta: InodeMeta,
+}
+
+impl Ext4SockInode {
+    pub fn new(mode: InodeMode, super_block: Arc<dyn SuperBlock>) -> Arc<Self> {
+        debug_assert!(mode.to_type().is_socket());
+        Arc::new(Self {
+            meta: InodeMeta::new(mode, super_block, 0),
+        })
+    }
+}
+
+impl Inode for Ext4SockInode {
+    fn meta(&self) -> &InodeMeta {
+        &self.meta
+    }
+
+    fn get_attr(&self) -> SysResult<Stat> {
+        let inner = self.meta.inner.lock();
+        let mode = self.meta.mode.bits();
+        Ok(Stat {
+            st_dev: 0,
+            st_ino: self.meta.ino as u64,
+            st_mode: mode,
+            st_nlink: inner.nlink as _,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: 512,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime: inner.atime,
+            st_mtime: inner.mtime,
+            st_ctime: inner.ctime,
+            unused: 0,
+        })
+    }
+}
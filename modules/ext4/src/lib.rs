@@ -3,7 +3,7 @@
 
 use alloc::{ffi::CString, string::String, sync::Arc, vec};
 
-use lwext4_rust::lwext4_readlink;
+use lwext4_rust::{bindings::ext4_mknod, lwext4_readlink};
 pub(crate) use lwext4_rust::{Ext4Dir as LwExt4Dir, Ext4File as LwExt4File, InodeTypes};
 use sync::mutex::SpinNoIrqLock;
 use systype::{SysError, SysResult};
@@ -16,6 +16,7 @@ mod disk;
 mod file;
 mod fs;
 mod inode;
+mod xattr;
 
 pub use dentry::*;
 pub use file::*;
@@ -43,6 +44,7 @@ pub fn map_ext4_type(value: InodeTypes) -> InodeType {
         InodeTypes::EXT4_DE_REG_FILE => InodeType::File,
         InodeTypes::EXT4_DE_DIR => InodeType::Dir,
         InodeTypes::EXT4_DE_SYMLINK => InodeType::SymLink,
+        InodeTypes::EXT4_DE_SOCK => InodeType::Socket,
         other => unimplemented!("{:?}", other),
     }
 }
@@ -53,3 +55,12 @@ pub(crate) fn readlink(path: &str) -> SysResult<CString> {
     path_buf.truncate(len + 1);
     CString::from_vec_with_nul(path_buf).map_err(|_| SysError::EINVAL)
 }
+
+/// Create a special file without data, e.g. a socket, at `path`.
+pub(crate) fn mknod(path: &str, itype: InodeTypes) -> SysResult<()> {
+    let c_path = CString::new(path).map_err(|_| SysError::EINVAL)?;
+    match unsafe { ext4_mknod(c_path.as_ptr(), itype as _, 0) } {
+        0 => Ok(()),
+        err => Err(SysError::from_i32(err)),
+    }
+}
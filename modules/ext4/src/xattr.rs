@@ -0,0 +1,141 @@
+//! Extended attributes through lwext4, which addresses inodes by path.
+
+use alloc::{ffi::CString, string::String, vec, vec::Vec};
+use core::ptr;
+
+use lwext4_rust::bindings::{ext4_getxattr, ext4_listxattr, ext4_removexattr, ext4_setxattr};
+use systype::{SysError, SysResult};
+use vfs_core::XattrFlags;
+
+fn to_cstring(s: &str) -> SysResult<CString> {
+    CString::new(s).map_err(|_| SysError::EINVAL)
+}
+
+fn map_ret(ret: i32) -> SysResult<()> {
+    match ret {
+        0 => Ok(()),
+        err => Err(SysError::from_i32(err)),
+    }
+}
+
+pub(crate) fn getxattr(path: &str, name: &str) -> SysResult<Vec<u8>> {
+    let c_path = to_cstring(path)?;
+    let c_name = to_cstring(name)?;
+    // Query the size of the value first.
+    let mut size = 0;
+    map_ret(unsafe {
+        ext4_getxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            name.len(),
+            ptr::null_mut(),
+            0,
+            &mut size,
+        )
+    })?;
+    let mut value = vec![0u8; size];
+    map_ret(unsafe {
+        ext4_getxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            name.len(),
+            value.as_mut_ptr() as _,
+            value.len(),
+            &mut size,
+        )
+    })?;
+    value.truncate(size);
+    Ok(value)
+}
+
+pub(crate) fn setxattr(path: &str, name: &str, value: &[u8], flags: XattrFlags) -> SysResult<()> {
+    // lwext4 always creates or replaces the attribute, so check the existence
+    // for `XATTR_CREATE` and `XATTR_REPLACE` here.
+    if !flags.is_empty() {
+        let exists = match getxattr(path, name) {
+            Ok(_) => true,
+            Err(SysError::ENODATA) => false,
+            Err(e) => return Err(e),
+        };
+        if flags.contains(XattrFlags::CREATE) && exists {
+            return Err(SysError::EEXIST);
+        }
+        if flags.contains(XattrFlags::REPLACE) && !exists {
+            return Err(SysError::ENODATA);
+        }
+    }
+    let c_path = to_cstring(path)?;
+    let c_name = to_cstring(name)?;
+    map_ret(unsafe {
+        ext4_setxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            name.len(),
+            value.as_ptr() as _,
+            value.len(),
+        )
+    })
+}
+
+pub(crate) fn listxattr(path: &str) -> SysResult<Vec<String>> {
+    let c_path = to_cstring(path)?;
+    // Query the size of the list first.
+    let mut size = 0;
+    map_ret(unsafe { ext4_listxattr(c_path.as_ptr(), ptr::null_mut(), 0, &mut size) })?;
+    let mut list = vec![0u8; size];
+    map_ret(unsafe { ext4_listxattr(c_path.as_ptr(), list.as_mut_ptr() as _, size, &mut size) })?;
+    list.truncate(size);
+    // Names are separated by NUL.
+    Ok(list
+        .split(|&c| c == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+pub(crate) fn removexattr(path: &str, name: &str) -> SysResult<()> {
+    let c_path = to_cstring(path)?;
+    let c_name = to_cstring(name)?;
+    map_ret(unsafe { ext4_removexattr(c_path.as_ptr(), c_name.as_ptr(), name.len()) })
+}
+
+/// Implement the xattr methods of `Inode` by the path of the dentry, shared
+/// by all kinds of inodes.
+macro_rules! impl_xattr {
+    () => {
+        fn base_getxattr(
+            &self,
+            dentry: &dyn vfs_core::Dentry,
+            name: &str,
+        ) -> systype::SysResult<alloc::vec::Vec<u8>> {
+            $crate::xattr::getxattr(&dentry.path(), name)
+        }
+
+        fn base_setxattr(
+            &self,
+            dentry: &dyn vfs_core::Dentry,
+            name: &str,
+            value: &[u8],
+            flags: vfs_core::XattrFlags,
+        ) -> systype::SysResult<()> {
+            $crate::xattr::setxattr(&dentry.path(), name, value, flags)
+        }
+
+        fn base_listxattr(
+            &self,
+            dentry: &dyn vfs_core::Dentry,
+        ) -> systype::SysResult<alloc::vec::Vec<alloc::string::String>> {
+            $crate::xattr::listxattr(&dentry.path())
+        }
+
+        fn base_removexattr(
+            &self,
+            dentry: &dyn vfs_core::Dentry,
+            name: &str,
+        ) -> systype::SysResult<()> {
+            $crate::xattr::removexattr(&dentry.path(), name)
+        }
+    };
+}
+
+pub(crate) use impl_xattr;
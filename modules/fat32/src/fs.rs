@@ -102,6 +102,8 @@ impl SuperBlock for FatSuperBlock {
     }
 
     fn sync_fs(&self, _wait: isize) -> systype::SysResult<()> {
-        todo!()
+        // fatfs writes through to the device, and directory entries of files
+        // are flushed when syncing their inodes.
+        Ok(())
     }
 }
@@ -1,8 +1,10 @@
 use alloc::sync::Arc;
 
+use fatfs::{Seek, Write};
+use systype::SysResult;
 use vfs_core::{Inode, InodeMeta, InodeMode, InodeType, Stat, SuperBlock};
 
-use crate::{FatFile, Mutex, Shared};
+use crate::{as_sys_err, FatFile, Mutex, Shared};
 
 pub struct FatFileInode {
     meta: InodeMeta,
@@ -52,4 +54,16 @@ impl Inode for FatFileInode {
             unused: 0,
         })
     }
+
+    fn base_write_back(&self, offset: usize, buf: &[u8]) -> SysResult<()> {
+        let mut file = self.file.lock();
+        file.seek(fatfs::SeekFrom::Start(offset as u64))
+            .map_err(as_sys_err)?;
+        file.write_all(buf).map_err(as_sys_err)
+    }
+
+    fn base_sync(&self) -> SysResult<()> {
+        // Update the directory entry, e.g. size and modification time.
+        self.file.lock().flush().map_err(as_sys_err)
+    }
 }
@@ -6,13 +6,16 @@ use core::{
     cell::SyncUnsafeCell,
     fmt::{self, Debug, Formatter},
     ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use bitmap_allocator::BitAlloc;
-use crate_interface::call_interface;
 use sync::mutex::SpinNoIrqLock;
 
-use crate::{PhysAddr, PhysPageNum};
+use crate::{
+    reclaim::{check_watermark, high_watermark, min_watermark, reclaim_frames},
+    PhysAddr, PhysPageNum,
+};
 
 /// Manage a frame which has the same lifecycle as the tracker.
 pub struct FrameTracker {
@@ -49,6 +52,8 @@ impl Drop for FrameTracker {
 struct FrameAllocator {
     range_ppn: SyncUnsafeCell<Range<PhysPageNum>>,
     allocator: SpinNoIrqLock<bitmap_allocator::BitAlloc16M>,
+    /// Count of free frames.
+    free: AtomicUsize,
 }
 
 impl FrameAllocator {
@@ -59,11 +64,50 @@ impl FrameAllocator {
     fn range_ppn(&self) -> Range<PhysPageNum> {
         unsafe { &*self.range_ppn.get() }.clone()
     }
+
+    /// Allocate `size` contiguous frames leaving at least `reserve` frames
+    /// free, returns the index of the first one.
+    fn alloc(&self, size: usize, reserve: usize) -> Option<usize> {
+        let mut allocator = self.allocator.lock();
+        if self.free.load(Ordering::Relaxed) < size + reserve {
+            return None;
+        }
+        let first_frame = if size == 1 {
+            allocator.alloc()
+        } else {
+            allocator.alloc_contiguous(size, 0)
+        }?;
+        self.free.fetch_sub(size, Ordering::Relaxed);
+        Some(first_frame)
+    }
+
+    /// Allocate `size` contiguous frames leaving at least `reserve` frames
+    /// free, and reclaim memory directly if there is not enough. Returns the
+    /// PPN of the first one.
+    fn alloc_or_reclaim(&self, size: usize, reserve: usize) -> Option<PhysPageNum> {
+        let first_frame = self.alloc(size, reserve).or_else(|| {
+            reclaim_frames(size + high_watermark().saturating_sub(free_frames()), true);
+            self.alloc(size, reserve)
+        });
+        check_watermark();
+        first_frame.map(|first_frame| self.range_ppn().start + first_frame)
+    }
+
+    /// Allocate `size` contiguous frames for the kernel, which may use up the
+    /// frames reserved for the kernel, and panic if there is still not enough.
+    ///
+    /// The OOM killer is not invoked here since the caller may hold any lock.
+    /// User memory is allocated by [`try_alloc_frame_tracker`] instead.
+    fn alloc_for_kernel(&self, size: usize) -> PhysPageNum {
+        self.alloc_or_reclaim(size, 0)
+            .expect("frame space not enough")
+    }
 }
 
 static FRAME_ALLOCATOR: FrameAllocator = FrameAllocator {
     range_ppn: SyncUnsafeCell::new(PhysPageNum::ZERO..PhysPageNum::ZERO),
     allocator: SpinNoIrqLock::new(bitmap_allocator::BitAlloc16M::DEFAULT),
+    free: AtomicUsize::new(0),
 };
 
 /// Initiate the frame allocator, using `VPNRange`
@@ -73,6 +117,9 @@ pub fn init_frame_allocator(start: PhysPageNum, end: PhysPageNum) {
         .lock()
         .insert(0..(end.0 - start.0));
     FRAME_ALLOCATOR.init(start..end);
+    FRAME_ALLOCATOR
+        .free
+        .store(end.0 - start.0, Ordering::Relaxed);
 
     log::info!(
         "frame allocator init finshed, start {:#x}, end {:#x}",
@@ -83,58 +130,40 @@ pub fn init_frame_allocator(start: PhysPageNum, end: PhysPageNum) {
 
 /// Allocate a frame
 pub fn alloc_frame_tracker() -> FrameTracker {
-    let ret = FRAME_ALLOCATOR
-        .allocator
-        .lock()
-        .alloc()
-        .map(|u| FrameTracker::new(FRAME_ALLOCATOR.range_ppn().start + u));
-    if let Some(ret) = ret {
-        ret
-    } else {
-        call_interface!(FrameReleaseIf::release_frames());
-        FRAME_ALLOCATOR
-            .allocator
-            .lock()
-            .alloc()
-            .map(|u| FrameTracker::new(FRAME_ALLOCATOR.range_ppn().start + u))
-            .expect("frame space not enough")
-    }
+    FrameTracker::new(FRAME_ALLOCATOR.alloc_for_kernel(1))
+}
+
+/// Allocate a frame for user memory, which fails instead of using up frames
+/// reserved for the kernel, so that the caller can invoke the OOM killer after
+/// releasing its locks.
+pub fn try_alloc_frame_tracker() -> Option<FrameTracker> {
+    FRAME_ALLOCATOR
+        .alloc_or_reclaim(1, min_watermark())
+        .map(FrameTracker::new)
 }
 
 /// Allocate contiguous frames
 pub fn alloc_frame_trackers(size: usize) -> Vec<FrameTracker> {
-    if let Some(first_frame) = FRAME_ALLOCATOR.allocator.lock().alloc_contiguous(size, 0) {
-        (first_frame..first_frame + size)
-            .map(|u| FrameTracker::new(FRAME_ALLOCATOR.range_ppn().start + u))
-            .collect()
-    } else {
-        call_interface!(FrameReleaseIf::release_frames());
-        let first_frame = FRAME_ALLOCATOR
-            .allocator
-            .lock()
-            .alloc_contiguous(size, 0)
-            .unwrap();
-        (first_frame..first_frame + size)
-            .map(|u| FrameTracker::new(FRAME_ALLOCATOR.range_ppn().start + u))
-            .collect()
-    }
+    let first_ppn = FRAME_ALLOCATOR.alloc_for_kernel(size);
+    (first_ppn..first_ppn + size)
+        .map(FrameTracker::new)
+        .collect()
 }
 
 /// Allocate contiguous frames
 pub fn alloc_frames(size: usize) -> PhysAddr {
-    if let Some(first_frame) = FRAME_ALLOCATOR.allocator.lock().alloc_contiguous(size, 0) {
-        let ppn = FRAME_ALLOCATOR.range_ppn().start + first_frame;
-        ppn.to_paddr()
-    } else {
-        call_interface!(FrameReleaseIf::release_frames());
-        let ppn = FRAME_ALLOCATOR.range_ppn().start
-            + FRAME_ALLOCATOR
-                .allocator
-                .lock()
-                .alloc_contiguous(size, 0)
-                .unwrap();
-        ppn.to_paddr()
-    }
+    FRAME_ALLOCATOR.alloc_for_kernel(size).to_paddr()
+}
+
+/// Count of all frames managed by the frame allocator.
+pub fn total_frames() -> usize {
+    let range_ppn = FRAME_ALLOCATOR.range_ppn();
+    range_ppn.end - range_ppn.start
+}
+
+/// Count of free frames.
+pub fn free_frames() -> usize {
+    FRAME_ALLOCATOR.free.load(Ordering::Relaxed)
 }
 
 /// Deallocate a frame
@@ -143,9 +172,5 @@ pub fn dealloc_frame(ppn: PhysPageNum) {
         .allocator
         .lock()
         .dealloc(ppn - FRAME_ALLOCATOR.range_ppn().start);
-}
-
-#[crate_interface::def_interface]
-pub trait FrameReleaseIf {
-    fn release_frames();
+    FRAME_ALLOCATOR.free.fetch_add(1, Ordering::Relaxed);
 }
@@ -14,6 +14,7 @@ pub mod frame;
 pub mod heap;
 pub mod page_table;
 pub mod pte;
+pub mod reclaim;
 
 pub use address::*;
 pub use frame::*;
@@ -9,7 +9,7 @@ use riscv::register::satp;
 use crate::{
     address::{PhysPageNum, VirtAddr, VirtPageNum},
     frame::{alloc_frame_tracker, FrameTracker},
-    pte::PTEFlags,
+    pte::{PTEFlags, SwapEntry},
     PageTableEntry, PhysAddr,
 };
 
@@ -143,6 +143,42 @@ impl PageTable {
         *pte = PageTableEntry::empty();
     }
 
+    /// Find the leaf pte whether it is valid or not.
+    ///
+    /// Return `None` if the page table of the leaf pte does not exist.
+    fn find_leaf_pte_raw(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        let idxs = vpn.indices();
+        let mut ppn = self.root_ppn;
+        for &idx in &idxs[..2] {
+            let pte = ppn.pte(idx);
+            if !pte.is_valid() {
+                return None;
+            }
+            ppn = pte.ppn();
+        }
+        Some(ppn.pte(idxs[2]))
+    }
+
+    /// Find the `SwapEntry` of `VirtPageNum` if its page is swapped out.
+    pub fn find_swap_entry(&self, vpn: VirtPageNum) -> Option<SwapEntry> {
+        self.find_leaf_pte_raw(vpn)?.swap_entry()
+    }
+
+    /// Map `VirtPageNum` to a `SwapEntry`, which replaces the old mapping.
+    pub fn map_swap(&mut self, vpn: VirtPageNum, entry: SwapEntry) {
+        let pte = self.find_leaf_pte_create(vpn);
+        *pte = PageTableEntry::new_swap(entry);
+    }
+
+    /// Unmap a `VirtPageNum` whose page is swapped out.
+    pub fn unmap_swap(&mut self, vpn: VirtPageNum) {
+        let pte = self
+            .find_leaf_pte_raw(vpn)
+            .expect("page table of swap pte not found");
+        debug_assert!(pte.swap_entry().is_some(), "vpn {vpn:?} is not swapped out");
+        *pte = PageTableEntry::empty();
+    }
+
     pub fn map_kernel_region(&mut self, range_va: Range<VirtAddr>, flags: PTEFlags) {
         let range_vpn = range_va.start.floor()..range_va.end.floor();
         for vpn in range_vpn {
@@ -16,6 +16,34 @@ bitflags! {
         const A = 1 << 6;
         const D = 1 << 7;
         const COW = 1 << 8;
+        /// The page is swapped out, and the ppn field holds its `SwapEntry`.
+        /// Only valid when `V` is not set.
+        const SWAP = 1 << 9;
+    }
+}
+
+/// Location of a swapped out page, i.e. the swap area it belongs to and its
+/// slot in the area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapEntry(usize);
+
+impl SwapEntry {
+    /// Bits of the area index.
+    pub const AREA_BITS: usize = 5;
+    /// Max count of swap areas.
+    pub const MAX_AREAS: usize = 1 << Self::AREA_BITS;
+
+    pub fn new(area: usize, slot: usize) -> Self {
+        debug_assert!(area < Self::MAX_AREAS);
+        Self(slot << Self::AREA_BITS | area)
+    }
+
+    pub fn area(&self) -> usize {
+        self.0 & (Self::MAX_AREAS - 1)
+    }
+
+    pub fn slot(&self) -> usize {
+        self.0 >> Self::AREA_BITS
     }
 }
 
@@ -39,6 +67,13 @@ impl PageTableEntry {
         PageTableEntry { bits: 0 }
     }
 
+    /// Create a non-present PTE of a swapped out page
+    pub fn new_swap(entry: SwapEntry) -> Self {
+        PageTableEntry {
+            bits: entry.0 << 10 | PTEFlags::SWAP.bits() as usize,
+        }
+    }
+
     /// Return 44bit ppn
     pub fn ppn(&self) -> PhysPageNum {
         (self.bits >> 10 & ((1usize << 44) - 1)).into()
@@ -46,7 +81,7 @@ impl PageTableEntry {
 
     /// Return 10bit flag
     pub fn flags(&self) -> PTEFlags {
-        PTEFlags::from_bits((self.bits & ((1 << 9) - 1)) as u16).unwrap()
+        PTEFlags::from_bits((self.bits & ((1 << 10) - 1)) as u16).unwrap()
     }
 
     ///
@@ -59,6 +94,13 @@ impl PageTableEntry {
         self.flags().contains(PTEFlags::V)
     }
 
+    /// Return the swap entry if the page is swapped out
+    pub fn swap_entry(&self) -> Option<SwapEntry> {
+        let flags = self.flags();
+        (!flags.contains(PTEFlags::V) && flags.contains(PTEFlags::SWAP))
+            .then_some(SwapEntry(self.bits >> 10))
+    }
+
     /// Check PTE readable
     pub fn readable(&self) -> bool {
         self.flags().contains(PTEFlags::R)
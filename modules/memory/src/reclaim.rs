@@ -0,0 +1,146 @@
+//! Memory reclaim through shrinkers registered by caches in other modules.
+//!
+//! When free frames drop below the low watermark after an allocation, the
+//! reclaim task waiting on [`LowMemoryFuture`] is woken to shrink caches until
+//! free frames reach the high watermark. When an allocation fails, caches are
+//! shrunk directly by the allocating context, except those whose shrinkers
+//! are not safe to call there, e.g. swapping anonymous pages out.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use sync::mutex::SpinNoIrqLock;
+
+use crate::frame::{free_frames, total_frames};
+
+/// Default cost of recreating an object freed by a shrinker.
+pub const DEFAULT_SEEKS: usize = 2;
+
+/// A cache whose objects can be freed to reclaim memory.
+pub trait Shrinker: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Relative cost of recreating a freed object, shrinkers of cheaper caches
+    /// are called first.
+    fn seeks(&self) -> usize {
+        DEFAULT_SEEKS
+    }
+
+    /// Try to free `nr_to_scan` objects, returns the count of objects freed.
+    ///
+    /// It may be called in any context that allocates frames, so it should
+    /// give up instead of waiting for locks that may be held by the caller.
+    fn scan_objects(&self, nr_to_scan: usize) -> usize;
+
+    /// Whether it can be called by direct reclaim in any context allocating
+    /// frames. Shrinkers doing I/O through file systems, which may allocate
+    /// frames with locks held, should only be called in contexts holding no
+    /// locks.
+    fn direct_reclaim(&self) -> bool {
+        true
+    }
+}
+
+static SHRINKERS: SpinNoIrqLock<Vec<Arc<dyn Shrinker>>> = SpinNoIrqLock::new(Vec::new());
+
+/// Whether reclaim is in progress, which prevents frames allocated by
+/// shrinkers from reclaiming recursively.
+static RECLAIMING: AtomicBool = AtomicBool::new(false);
+
+/// Reclaim task waiting for free frames to drop below the low watermark.
+static RECLAIM_WAKER: SpinNoIrqLock<Option<Waker>> = SpinNoIrqLock::new(None);
+
+/// Max rounds of calling all shrinkers in one reclaim.
+const MAX_RECLAIM_ROUNDS: usize = 4;
+
+pub fn register_shrinker(shrinker: Arc<dyn Shrinker>) {
+    log::info!("[register_shrinker] {}", shrinker.name());
+    let mut shrinkers = SHRINKERS.lock();
+    let pos = shrinkers.partition_point(|s| s.seeks() <= shrinker.seeks());
+    shrinkers.insert(pos, shrinker);
+}
+
+/// Frames below this are reserved for the kernel, and user memory allocation
+/// fails instead of using them.
+pub fn min_watermark() -> usize {
+    total_frames() / 128
+}
+
+/// Reclaim starts when free frames drop below this.
+pub fn low_watermark() -> usize {
+    total_frames() / 64
+}
+
+/// Reclaim stops when free frames reach this.
+pub fn high_watermark() -> usize {
+    total_frames() / 32
+}
+
+/// Whether some context is reclaiming memory now.
+pub fn is_reclaiming() -> bool {
+    RECLAIMING.load(Ordering::Relaxed)
+}
+
+/// Shrink registered caches until `nr_to_free` frames are freed or nothing
+/// more can be freed. Only shrinkers that can be called in any context are
+/// called if `direct` is true. Returns the count of frames freed.
+pub fn reclaim_frames(nr_to_free: usize, direct: bool) -> usize {
+    if RECLAIMING.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+    let shrinkers = SHRINKERS.lock().clone();
+    let free_before = free_frames();
+    let target = free_before + nr_to_free;
+    for _ in 0..MAX_RECLAIM_ROUNDS {
+        let mut scanned = 0;
+        for shrinker in shrinkers
+            .iter()
+            .filter(|shrinker| !direct || shrinker.direct_reclaim())
+        {
+            let free = free_frames();
+            if free >= target {
+                break;
+            }
+            let nr = shrinker.scan_objects(target - free);
+            log::debug!("[reclaim_frames] {} freed {nr} objects", shrinker.name());
+            scanned += nr;
+        }
+        if scanned == 0 || free_frames() >= target {
+            break;
+        }
+    }
+    RECLAIMING.store(false, Ordering::Release);
+    let freed = free_frames().saturating_sub(free_before);
+    log::info!("[reclaim_frames] want {nr_to_free} frames, freed {freed}");
+    freed
+}
+
+/// Wake the reclaim task if free frames drop below the low watermark.
+pub(crate) fn check_watermark() {
+    if free_frames() < low_watermark() {
+        if let Some(waker) = RECLAIM_WAKER.lock().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves when free frames drop below the low watermark.
+pub struct LowMemoryFuture;
+
+impl Future for LowMemoryFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut waker = RECLAIM_WAKER.lock();
+        if free_frames() < low_watermark() {
+            return Poll::Ready(());
+        }
+        *waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
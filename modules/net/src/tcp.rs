@@ -287,7 +287,7 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
+        self.block_on(false, || {
             let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
             info!("TCP socket accepted a new connection {}", peer_addr);
             Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
@@ -349,7 +349,10 @@ impl TcpSocket {
     }
 
     /// Receives data from the socket, stores it in the given buffer.
-    pub async fn recv(&self, buf: &mut [u8]) -> SysResult<usize> {
+    ///
+    /// Returns `EAGAIN` instead of blocking if `nonblock` is set, even though
+    /// the socket is in blocking mode.
+    pub async fn recv(&self, buf: &mut [u8], nonblock: bool) -> SysResult<usize> {
         let shutdown = unsafe { *self.shutdown.get() };
         if shutdown & RCV_SHUTDOWN != 0 {
             log::warn!("[TcpSocket::recv] shutdown closed read, recv return 0");
@@ -366,7 +369,7 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         let waker = get_waker().await;
-        self.block_on(|| {
+        self.block_on(nonblock, || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 log::info!("[TcpSocket::recv] handle{handle} state {} is trying to recv", socket.state());
                 if !socket.is_active() {
@@ -396,7 +399,10 @@ impl TcpSocket {
     }
 
     /// Transmits data in the given buffer.
-    pub async fn send(&self, buf: &[u8]) -> SysResult<usize> {
+    ///
+    /// Returns `EAGAIN` instead of blocking if `nonblock` is set, even though
+    /// the socket is in blocking mode.
+    pub async fn send(&self, buf: &[u8], nonblock: bool) -> SysResult<usize> {
         let shutdown = unsafe { *self.shutdown.get() };
         if shutdown & SEND_SHUTDOWN != 0 {
             log::warn!("[TcpSocket::send] shutdown closed write, send return 0");
@@ -412,7 +418,7 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         let waker = get_waker().await;
-        let ret = self.block_on(|| {
+        let ret = self.block_on(nonblock, || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() || !socket.may_send() {
                     // closed by remote
@@ -713,14 +719,15 @@ impl TcpSocket {
 
     /// Block the current thread until the given function completes or fails.
     ///
-    /// If the socket is non-blocking, it calls the function once and returns
-    /// immediately. Otherwise, it may call the function multiple times if it
-    /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    async fn block_on<F, T>(&self, mut f: F) -> SysResult<T>
+    /// If the socket or this call (`nonblock`) is non-blocking, it calls the
+    /// function once and returns immediately. Otherwise, it may call the
+    /// function multiple times if it returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock).
+    async fn block_on<F, T>(&self, nonblock: bool, mut f: F) -> SysResult<T>
     where
         F: FnMut() -> SysResult<T>,
     {
-        if self.is_nonblocking() {
+        if nonblock || self.is_nonblocking() {
             f()
         } else {
             loop {
@@ -162,18 +162,33 @@ impl UdpSocket {
 
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes written.
-    pub async fn send_to(&self, buf: &[u8], remote_addr: IpEndpoint) -> SysResult<usize> {
+    ///
+    /// With `nonblock` set, this call fails with `EAGAIN` rather than waiting
+    /// for room in the tx buffer, like a socket in nonblocking mode.
+    pub async fn send_to(
+        &self,
+        buf: &[u8],
+        remote_addr: IpEndpoint,
+        nonblock: bool,
+    ) -> SysResult<usize> {
         if remote_addr.port == 0 || remote_addr.addr.is_unspecified() {
             warn!("socket send_to() failed: invalid remote address");
             return Err(SysError::EINVAL);
         }
-        self.send_impl(buf, remote_addr).await
+        self.send_impl(buf, remote_addr, nonblock).await
     }
 
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes read and the origin.
-    pub async fn recv_from(&self, buf: &mut [u8]) -> SysResult<(usize, IpEndpoint)> {
-        self.recv_impl(|socket| match socket.recv_slice(buf) {
+    ///
+    /// With `nonblock` set, this call fails with `EAGAIN` rather than waiting
+    /// for a datagram, like a socket in nonblocking mode.
+    pub async fn recv_from(
+        &self,
+        buf: &mut [u8],
+        nonblock: bool,
+    ) -> SysResult<(usize, IpEndpoint)> {
+        self.recv_impl(nonblock, |socket| match socket.recv_slice(buf) {
             Ok((len, meta)) => Ok((len, meta.endpoint)),
             Err(e) => {
                 warn!("[UdpSocket::recv_from] socket {} failed {e:?}", self.handle);
@@ -186,8 +201,12 @@ impl UdpSocket {
     /// Receives a single datagram message on the socket, without removing it
     /// from the queue. On success, returns the number of bytes read and the
     /// origin.
-    pub async fn peek_from(&self, buf: &mut [u8]) -> SysResult<(usize, IpEndpoint)> {
-        self.recv_impl(|socket| match socket.peek_slice(buf) {
+    pub async fn peek_from(
+        &self,
+        buf: &mut [u8],
+        nonblock: bool,
+    ) -> SysResult<(usize, IpEndpoint)> {
+        self.recv_impl(nonblock, |socket| match socket.peek_slice(buf) {
             Ok((len, meta)) => Ok((len, meta.endpoint)),
             Err(_) => {
                 warn!("socket recv_from() failed");
@@ -223,16 +242,16 @@ impl UdpSocket {
     }
 
     /// Sends data on the socket to the remote address to which it is connected.
-    pub async fn send(&self, buf: &[u8]) -> SysResult<usize> {
+    pub async fn send(&self, buf: &[u8], nonblock: bool) -> SysResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.send_impl(buf, remote_endpoint).await
+        self.send_impl(buf, remote_endpoint, nonblock).await
     }
 
     /// Receives a single datagram message on the socket from the remote address
     /// to which it is connected. On success, returns the number of bytes read.
-    pub async fn recv(&self, buf: &mut [u8]) -> SysResult<usize> {
+    pub async fn recv(&self, buf: &mut [u8], nonblock: bool) -> SysResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.recv_impl(|socket| {
+        self.recv_impl(nonblock, |socket| {
             let (len, meta) = socket.recv_slice(buf).map_err(|_| {
                 warn!("socket recv()  failed");
                 SysError::EAGAIN
@@ -302,7 +321,12 @@ impl UdpSocket {
         }
     }
 
-    async fn send_impl(&self, buf: &[u8], remote_endpoint: IpEndpoint) -> SysResult<usize> {
+    async fn send_impl(
+        &self,
+        buf: &[u8],
+        remote_endpoint: IpEndpoint,
+        nonblock: bool,
+    ) -> SysResult<usize> {
         if self.local_addr.read().is_none() {
             warn!(
                 "[send_impl] UDP socket {}: not bound. Use 127.0.0.1",
@@ -312,7 +336,7 @@ impl UdpSocket {
         }
         let waker = get_waker().await;
         let bytes = self
-            .block_on(|| {
+            .block_on(nonblock, || {
                 SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
                     if socket.can_send() {
                         socket
@@ -346,7 +370,7 @@ impl UdpSocket {
         Ok(bytes)
     }
 
-    async fn recv_impl<F, T>(&self, mut op: F) -> SysResult<T>
+    async fn recv_impl<F, T>(&self, nonblock: bool, mut op: F) -> SysResult<T>
     where
         F: FnMut(&mut udp::Socket) -> SysResult<T>,
     {
@@ -356,7 +380,7 @@ impl UdpSocket {
         }
         let waker = get_waker().await;
         let ret = self
-            .block_on(|| {
+            .block_on(nonblock, || {
                 SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
                     if socket.can_recv() {
                         // data available
@@ -378,11 +402,11 @@ impl UdpSocket {
         ret
     }
 
-    async fn block_on<F, T>(&self, mut f: F) -> SysResult<T>
+    async fn block_on<F, T>(&self, nonblock: bool, mut f: F) -> SysResult<T>
     where
         F: FnMut() -> SysResult<T>,
     {
-        if self.is_nonblocking() {
+        if nonblock || self.is_nonblocking() {
             f()
         } else {
             loop {
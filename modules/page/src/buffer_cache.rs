@@ -67,6 +67,23 @@ impl BufferCache {
         }
     }
 
+    /// Write back all dirty blocks cached in block pages.
+    pub fn flush(&self) {
+        for (_, page) in self.pages.iter() {
+            page.flush()
+        }
+    }
+
+    /// Drop at most `nr` least recently used block pages, whose dirty blocks
+    /// are written back when dropped. Returns the count of pages dropped.
+    pub fn shrink(&mut self, nr: usize) -> usize {
+        let mut freed = 0;
+        while freed < nr && self.pages.pop_lru().is_some() {
+            freed += 1;
+        }
+        freed
+    }
+
     pub fn get_buffer_head_from_disk(&mut self, block_id: usize) -> Arc<BufferHead> {
         let device = self.device();
         if let Some(buffer_head) = self.buffer_heads.get_mut(&block_id).cloned() {
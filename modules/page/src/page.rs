@@ -1,5 +1,9 @@
 use alloc::sync::{Arc, Weak};
-use core::{cmp, fmt, ops::Range};
+use core::{
+    cmp, fmt,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use config::{
     board::BLOCK_SIZE,
@@ -8,7 +12,7 @@ use config::{
 use device_core::BlockDevice;
 use enum_as_inner::EnumAsInner;
 use intrusive_collections::LinkedList;
-use memory::{alloc_frame_tracker, FrameTracker, PhysPageNum};
+use memory::{alloc_frame_tracker, try_alloc_frame_tracker, FrameTracker, PhysPageNum};
 use sync::mutex::SpinNoIrqLock;
 
 use crate::{
@@ -41,6 +45,9 @@ impl fmt::Debug for PageKind {
 pub struct Page {
     frame: FrameTracker,
     kind: PageKind,
+    /// Whether the page has been looked up since memory reclaim last checked
+    /// it.
+    referenced: AtomicBool,
 }
 
 pub struct BufferInfo {
@@ -91,9 +98,21 @@ impl Page {
         Arc::new(Self {
             frame,
             kind: PageKind::Normal,
+            referenced: AtomicBool::new(false),
         })
     }
 
+    /// Create a `Page` for user memory, or `None` if there is not enough
+    /// memory.
+    pub fn try_new() -> Option<Arc<Self>> {
+        let frame = try_alloc_frame_tracker()?;
+        Some(Arc::new(Self {
+            frame,
+            kind: PageKind::Normal,
+            referenced: AtomicBool::new(false),
+        }))
+    }
+
     pub fn new_file(block_device: &Arc<dyn BlockDevice>) -> Arc<Self> {
         let frame = alloc_frame_tracker();
         Arc::new(Self {
@@ -103,6 +122,7 @@ impl Page {
                 buffer_heads: LinkedList::new(BufferHeadAdapter::new()),
                 buffer_head_cnts: 0,
             })),
+            referenced: AtomicBool::new(false),
         })
     }
 
@@ -115,6 +135,7 @@ impl Page {
                 buffer_heads: LinkedList::new(BufferHeadAdapter::new()),
                 buffer_head_cnts: 0,
             })),
+            referenced: AtomicBool::new(false),
         })
     }
 
@@ -126,6 +147,15 @@ impl Page {
         &self.kind
     }
 
+    pub fn mark_referenced(&self) {
+        self.referenced.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the page has been referenced since last call.
+    pub fn test_and_clear_referenced(&self) -> bool {
+        self.referenced.swap(false, Ordering::Relaxed)
+    }
+
     // WARN: user program may rely on cleared page, page is not cleared may cause
     // unknown bug
     pub fn fill_zero(&self) {
@@ -182,12 +212,12 @@ impl Page {
             PageKind::FileCache(inner) => inner.lock(),
             PageKind::BlockCache(inner) => inner.lock(),
         };
-        log::warn!("[Page::flush] sync buffer back to disk");
+        log::trace!("[Page::flush] sync buffer back to disk");
         let device = inner.device.upgrade().unwrap();
         for buffer_head in inner.buffer_heads.iter() {
             if buffer_head.bstate() == BufferState::Dirty {
-                let block_id = buffer_head.block_id();
                 device.base_write_blocks(buffer_head.block_id(), &buffer_head.bytes_array());
+                buffer_head.set_bstate(BufferState::Sync);
             }
         }
     }
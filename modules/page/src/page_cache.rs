@@ -1,26 +1,57 @@
-use alloc::sync::Arc;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
+use arch::time::get_time_duration;
 use config::mm::is_aligned_to_page;
 use hashbrown::HashMap;
 use sync::mutex::SpinNoIrqLock;
 
 use crate::Page;
 
+/// Count of dirty pages in all page caches.
+static NR_DIRTY_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Count of dirty pages in all page caches.
+pub fn nr_dirty_pages() -> usize {
+    NR_DIRTY_PAGES.load(Ordering::Relaxed)
+}
+
+/// A page taken off the dirty pages of a page cache to be written back.
+pub struct DirtyPage {
+    pub offset: usize,
+    /// Time the page was first modified.
+    pub dirtied: Duration,
+    pub page: Arc<Page>,
+}
+
 pub struct PageCache {
     /// Map from aligned file offset to page cache.
     pages: SpinNoIrqLock<HashMap<usize, Arc<Page>>>,
+    /// Map from aligned file offset of pages modified since they were last
+    /// written back to the time they were first modified.
+    dirty: SpinNoIrqLock<BTreeMap<usize, Duration>>,
 }
 
 impl PageCache {
     pub fn new() -> Self {
         Self {
             pages: SpinNoIrqLock::new(HashMap::new()),
+            dirty: SpinNoIrqLock::new(BTreeMap::new()),
         }
     }
 
     pub fn get_page(&self, offset_aligned: usize) -> Option<Arc<Page>> {
         debug_assert!(is_aligned_to_page(offset_aligned));
-        self.pages.lock().get(&offset_aligned).cloned()
+        let page = self.pages.lock().get(&offset_aligned).cloned()?;
+        page.mark_referenced();
+        Some(page)
+    }
+
+    pub fn contains_page(&self, offset_aligned: usize) -> bool {
+        self.pages.lock().contains_key(&offset_aligned)
     }
 
     pub fn insert_page(&self, offset_aligned: usize, page: Arc<Page>) {
@@ -28,8 +59,131 @@ impl PageCache {
         self.pages.lock().insert(offset_aligned, page);
     }
 
+    /// Mark the page at `offset_aligned` as dirty, so that it will be written
+    /// back on the next sync.
+    pub fn mark_dirty(&self, offset_aligned: usize) {
+        debug_assert!(is_aligned_to_page(offset_aligned));
+        let mut dirty = self.dirty.lock();
+        if !dirty.contains_key(&offset_aligned) {
+            dirty.insert(offset_aligned, get_time_duration());
+            NR_DIRTY_PAGES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count of dirty pages in this page cache.
+    pub fn nr_dirty(&self) -> usize {
+        self.dirty.lock().len()
+    }
+
+    /// Take all dirty pages in order of file offset, which are considered
+    /// clean from now on. Pages failed to be written back should be given
+    /// back by [`redirty`](Self::redirty).
+    pub fn take_dirty_pages(&self) -> Vec<DirtyPage> {
+        let dirty = core::mem::take(&mut *self.dirty.lock());
+        NR_DIRTY_PAGES.fetch_sub(dirty.len(), Ordering::Relaxed);
+        self.collect_pages(dirty.into_iter())
+    }
+
+    /// Take pages that have been dirty for at least `expire`, in order of file
+    /// offset.
+    pub fn take_expired_pages(&self, expire: Duration) -> Vec<DirtyPage> {
+        let now = get_time_duration();
+        let mut dirty = self.dirty.lock();
+        let expired: Vec<(usize, Duration)> = dirty
+            .iter()
+            .filter(|(_, &dirtied)| dirtied + expire <= now)
+            .map(|(&offset, &dirtied)| (offset, dirtied))
+            .collect();
+        for (offset, _) in expired.iter() {
+            dirty.remove(offset);
+        }
+        NR_DIRTY_PAGES.fetch_sub(expired.len(), Ordering::Relaxed);
+        drop(dirty);
+        self.collect_pages(expired.into_iter())
+    }
+
+    /// Mark taken pages that are not written back dirty again, keeping the
+    /// time they were first modified. Pages removed from the page cache in
+    /// the meantime are skipped.
+    pub fn redirty(&self, dirty_pages: Vec<DirtyPage>) {
+        let pages = self.pages.lock();
+        let mut dirty = self.dirty.lock();
+        for DirtyPage {
+            offset,
+            dirtied,
+            page,
+        } in dirty_pages
+        {
+            if !pages.get(&offset).is_some_and(|p| Arc::ptr_eq(p, &page)) {
+                continue;
+            }
+            match dirty.get_mut(&offset) {
+                // Modified again while being written back.
+                Some(time) => *time = (*time).min(dirtied),
+                None => {
+                    dirty.insert(offset, dirtied);
+                    NR_DIRTY_PAGES.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    fn collect_pages(&self, dirty: impl Iterator<Item = (usize, Duration)>) -> Vec<DirtyPage> {
+        let pages = self.pages.lock();
+        dirty
+            .filter_map(|(offset, dirtied)| {
+                pages.get(&offset).map(|page| DirtyPage {
+                    offset,
+                    dirtied,
+                    page: page.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether the page at `offset_aligned` has been looked up since
+    /// last call, or `None` if there is no such page.
+    pub fn test_and_clear_referenced(&self, offset_aligned: usize) -> Option<bool> {
+        self.pages
+            .lock()
+            .get(&offset_aligned)
+            .map(|page| page.test_and_clear_referenced())
+    }
+
+    /// Remove the page at `offset_aligned` if it is clean and not used by
+    /// anyone else, e.g. mapped into an address space. Returns whether the
+    /// page is evicted.
+    pub fn evict_page(&self, offset_aligned: usize) -> bool {
+        debug_assert!(is_aligned_to_page(offset_aligned));
+        let mut pages = self.pages.lock();
+        let dirty = self.dirty.lock();
+        match pages.get(&offset_aligned) {
+            Some(page) if Arc::strong_count(page) == 1 && !dirty.contains_key(&offset_aligned) => {
+                drop(dirty);
+                pages.remove(&offset_aligned);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove pages at or beyond `offset_aligned`.
+    pub fn truncate(&self, offset_aligned: usize) {
+        debug_assert!(is_aligned_to_page(offset_aligned));
+        self.pages
+            .lock()
+            .retain(|&offset, _| offset < offset_aligned);
+        let mut dirty = self.dirty.lock();
+        let len = dirty.len();
+        dirty.retain(|&offset, _| offset < offset_aligned);
+        NR_DIRTY_PAGES.fetch_sub(len - dirty.len(), Ordering::Relaxed);
+    }
+
     pub fn clear(&self) {
-        self.pages.lock().clear()
+        self.pages.lock().clear();
+        let mut dirty = self.dirty.lock();
+        NR_DIRTY_PAGES.fetch_sub(dirty.len(), Ordering::Relaxed);
+        dirty.clear();
     }
 
     pub fn flush(&self) {
@@ -38,3 +192,9 @@ impl PageCache {
         }
     }
 }
+
+impl Drop for PageCache {
+    fn drop(&mut self) {
+        NR_DIRTY_PAGES.fetch_sub(self.dirty.lock().len(), Ordering::Relaxed);
+    }
+}
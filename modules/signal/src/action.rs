@@ -74,6 +74,8 @@ pub struct SigPending {
     /// 如果在receive_siginfo的时候收到的信号位于should_wake信号集合中，
     /// 且task的wake存在，那么唤醒task
     pub should_wake: SigSet,
+    /// 通过signalfd等待信号的waker，在收到任何信号时都会被唤醒
+    pub signalfd_wakers: VecDeque<Waker>,
 }
 
 impl SigPending {
@@ -82,6 +84,7 @@ impl SigPending {
             queue: VecDeque::new(),
             bitmap: SigSet::empty(),
             should_wake: SigSet::empty(),
+            signalfd_wakers: VecDeque::new(),
         }
     }
 
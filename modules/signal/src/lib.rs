@@ -2,11 +2,13 @@
 #![no_main]
 
 pub mod action;
+pub mod sigevent;
 pub mod siginfo;
 pub mod signal_stack;
 pub mod sigset;
 
 pub use action::*;
+pub use sigevent::*;
 pub use siginfo::*;
 pub use signal_stack::*;
 pub use sigset::*;
@@ -0,0 +1,27 @@
+/// `struct sigevent`, which specifies how the process is notified when an
+/// event occurs.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct SigEvent {
+    /// Data passed with notification
+    pub sigev_value: usize,
+    /// Notification signal
+    pub sigev_signo: i32,
+    /// Notification method
+    pub sigev_notify: i32,
+    /// Union of thread id and `SIGEV_THREAD` function attributes
+    __pad: [u8; 48],
+}
+
+impl SigEvent {
+    /// Notify the process by sending the signal specified in `sigev_signo`.
+    pub const SIGEV_SIGNAL: i32 = 0;
+    /// Don't asynchronously notify when the event occurs.
+    pub const SIGEV_NONE: i32 = 1;
+    /// Notify the process by invoking `sigev_notify_function` "as if" it were
+    /// the start function of a new thread.
+    pub const SIGEV_THREAD: i32 = 2;
+    /// Like `SIGEV_SIGNAL`, but the signal is targeted at the thread whose ID
+    /// is given in `sigev_notify_thread_id`.
+    pub const SIGEV_THREAD_ID: i32 = 4;
+}
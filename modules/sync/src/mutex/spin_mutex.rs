@@ -72,6 +72,20 @@ impl<T, S: MutexSupport> SpinMutex<T, S> {
         }
     }
 
+    /// Try to acquire the lock without spinning, returns `None` if it is held
+    /// by someone else.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<MutexGuard<T, S>> {
+        let support_guard = S::before_lock();
+        self.lock
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard {
+                mutex: self,
+                support_guard,
+            })
+    }
+
     /// # Safety
     ///
     /// This is highly unsafe.
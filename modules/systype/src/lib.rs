@@ -102,8 +102,22 @@ pub enum SysError {
     ENOTEMPTY = 39,
     /// Too many symbolic links encountered
     ELOOP = 40,
+    /// No message of desired type
+    ENOMSG = 42,
+    /// Identifier removed
+    EIDRM = 43,
+    /// No data available
+    ENODATA = 61,
+    /// Timer expired
+    ETIME = 62,
     /// Socket operation on non-socket
     ENOTSOCK = 88,
+    /// Message too long
+    EMSGSIZE = 90,
+    /// Protocol wrong type for socket
+    EPROTOTYPE = 91,
+    /// Socket type not supported
+    ESOCKTNOSUPPORT = 94,
     /// Unsupported
     EOPNOTSUPP = 95,
     /// Socket address is already in use
@@ -116,11 +130,15 @@ pub enum SysError {
     EISCONN = 106,
     /// The socket is not connected
     ENOTCONN = 107,
+    /// Connection timed out
+    ETIMEDOUT = 110,
     /// Connection refused
     ECONNREFUSED = 111,
     /// The socket is nonblocking and the connection cannot be completed
     /// immediately.(connect.2)
     EINPROGRESS = 115,
+    /// Operation Canceled
+    ECANCELED = 125,
 }
 
 impl SysError {
@@ -168,15 +186,24 @@ impl SysError {
             ENOSYS => "Invalid system call number",
             ENOTEMPTY => "Directory not empty",
             ELOOP => "Too many symbolic links encountered",
+            ENOMSG => "No message of desired type",
+            EIDRM => "Identifier removed",
+            ENODATA => "No data available",
+            ETIME => "Timer expired",
             ENOTSOCK => "Socket operation on non-socket",
+            EMSGSIZE => "Message too long",
+            EPROTOTYPE => "Protocol wrong type for socket",
+            ESOCKTNOSUPPORT => "Socket type not supported",
             ENOTCONN => "Transport endpoint is not connected",
             EOPNOTSUPP => "Unsupported Error",
             EADDRNOTAVAIL => "Address not available",
             EADDRINUSE => "Address already in use",
             EISCONN => "Transport endpoint is already connected",
             ECONNRESET => "Connection reset",
+            ETIMEDOUT => "Connection timed out",
             ECONNREFUSED => "Connection refused",
             EINPROGRESS => "Operation now in progress",
+            ECANCELED => "Operation Canceled",
         }
     }
 
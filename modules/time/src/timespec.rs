@@ -41,3 +41,18 @@ impl From<TimeSpec> for Duration {
         Duration::new(time_spec.tv_sec as u64, time_spec.tv_nsec as u32)
     }
 }
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct ITimerSpec {
+    /// Interval for periodic timer
+    pub it_interval: TimeSpec,
+    /// Time until next expiration
+    pub it_value: TimeSpec,
+}
+
+impl ITimerSpec {
+    pub fn is_valid(&self) -> bool {
+        self.it_interval.is_valid() && self.it_value.is_valid()
+    }
+}
@@ -8,7 +8,11 @@ use core::{default, fmt::Error, mem::MaybeUninit, str::FromStr};
 use sync::mutex::spin_mutex::SpinMutex;
 use systype::{SysError, SysResult, SyscallResult};
 
-use crate::{inode::Inode, File, InodeMode, InodeState, InodeType, Mutex, RenameFlags, SuperBlock};
+use crate::{
+    inode::Inode,
+    inotify::{alloc_cookie, fsnotify_inode, fsnotify_parent, fsnotify_remove_inode, InotifyMask},
+    File, InodeMode, InodeState, InodeType, Mutex, RenameFlags, SuperBlock,
+};
 
 pub struct DentryMeta {
     /// Name of this file or directory.
@@ -207,6 +211,7 @@ impl dyn Dentry {
         let child = self.get_child_or_create(name);
         if child.is_negetive() {
             self.clone().base_create(name, mode)?;
+            fsnotify_parent(&child, InotifyMask::CREATE, 0);
         }
         Ok(child)
     }
@@ -216,8 +221,14 @@ impl dyn Dentry {
             return Err(SysError::ENOTDIR);
         }
         let sub_dentry = self.get_child(name).ok_or(SysError::ENOENT)?;
-        sub_dentry.inode()?.set_state(InodeState::Removed);
+        let sub_inode = sub_dentry.inode()?;
+        if sub_inode.is_swap_file() {
+            return Err(SysError::EPERM);
+        }
+        sub_inode.set_state(InodeState::Removed);
         self.clone().base_unlink(name)?;
+        fsnotify_parent(&sub_dentry, InotifyMask::DELETE, 0);
+        fsnotify_remove_inode(sub_inode.as_ref());
         sub_dentry.clear_inode();
         Ok(())
     }
@@ -238,7 +249,18 @@ impl dyn Dentry {
         } else if flags.contains(RenameFlags::RENAME_NOREPLACE) {
             return Err(SysError::EEXIST);
         }
-        self.clone().base_rename_to(new.clone(), flags)
+        let inode = self.inode()?;
+        self.clone().base_rename_to(new.clone(), flags)?;
+        let cookie = alloc_cookie();
+        let isdir = if inode.itype().is_dir() {
+            InotifyMask::ISDIR
+        } else {
+            InotifyMask::empty()
+        };
+        fsnotify_parent(self, InotifyMask::MOVED_FROM | isdir, cookie);
+        fsnotify_parent(new, InotifyMask::MOVED_TO | isdir, cookie);
+        fsnotify_inode(inode.as_ref(), InotifyMask::MOVE_SELF);
+        Ok(())
     }
 
     pub fn symlink(self: &Arc<Self>, name: &str, target: &str) -> SysResult<()> {
@@ -247,7 +269,9 @@ impl dyn Dentry {
         }
         let child = self.get_child_or_create(name);
         if child.is_negetive() {
-            self.clone().base_symlink(name, target)
+            self.clone().base_symlink(name, target)?;
+            fsnotify_parent(&child, InotifyMask::CREATE, 0);
+            Ok(())
         } else {
             Err(SysError::EEXIST)
         }
@@ -260,7 +284,12 @@ impl dyn Dentry {
             Err(SysError::EEXIST)
         } else {
             let ret = self.clone().base_link(new);
-            self.inode()?.meta().inner.lock().nlink += 1;
+            let inode = self.inode()?;
+            inode.meta().inner.lock().nlink += 1;
+            if ret.is_ok() {
+                fsnotify_parent(new, InotifyMask::CREATE, 0);
+                fsnotify_inode(inode.as_ref(), InotifyMask::ATTRIB);
+            }
             ret
         }
     }
@@ -1,7 +1,7 @@
 use alloc::{boxed::Box, ffi::CString, string::String, sync::Arc, vec, vec::Vec};
 use core::{
     cmp,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     usize,
 };
 
@@ -20,8 +20,11 @@ use spin::Mutex;
 use systype::{SysError, SysResult, SyscallResult};
 
 use crate::{
-    inode, Dentry, DirEntry, Inode, InodeState, InodeType, OpenFlags, PollEvents, SeekFrom,
-    SuperBlock,
+    inode,
+    inotify::{fsnotify_dentry, InotifyMask},
+    reclaim::lru_add_page,
+    writeback::balance_dirty_pages,
+    Dentry, DirEntry, Inode, InodeState, InodeType, OpenFlags, PollEvents, SeekFrom, SuperBlock,
 };
 
 pub struct FileMeta {
@@ -33,6 +36,8 @@ pub struct FileMeta {
     /// WARN: may cause trouble if this is not locked with other things.
     pub pos: AtomicUsize,
     pub flags: Mutex<OpenFlags>,
+    /// Whether flock locks or OFD locks have been acquired through this file.
+    pub(crate) has_locks: AtomicBool,
 }
 
 impl FileMeta {
@@ -42,10 +47,17 @@ impl FileMeta {
             inode,
             pos: 0.into(),
             flags: Mutex::new(OpenFlags::empty()),
+            has_locks: AtomicBool::new(false),
         }
     }
 }
 
+impl Drop for FileMeta {
+    fn drop(&mut self) {
+        self.release_locks();
+    }
+}
+
 #[async_trait]
 pub trait File: Send + Sync + DowncastSync {
     fn meta(&self) -> &FileMeta;
@@ -109,6 +121,7 @@ pub trait File: Send + Sync + DowncastSync {
         // }
 
         page_cache.insert_page(offset_aligned, page.clone());
+        lru_add_page(&inode, offset_aligned);
 
         Ok(Some(page))
     }
@@ -184,14 +197,20 @@ pub trait File: Send + Sync + DowncastSync {
         );
 
         let inode = self.inode();
-        inode.set_state(InodeState::Dirty);
+        if inode.is_swap_file() {
+            return Err(SysError::ETXTBSY);
+        }
 
         let Some(page_cache) = inode.page_cache() else {
             log::debug!("[File::write] write without address_space");
+            inode.set_state(InodeState::Dirty);
             let count = self.base_write_at(offset, buf).await?;
             if offset + count > inode.size() {
                 inode.set_size(offset + count);
             }
+            if inode.itype().is_file() {
+                fsnotify_dentry(&self.dentry(), InotifyMask::MODIFY);
+            }
             return Ok(count);
         };
 
@@ -213,11 +232,13 @@ pub trait File: Send + Sync + DowncastSync {
                 log::info!("[File::write_at] create new page");
                 let page = Page::new_file(&device);
                 page_cache.insert_page(offset_aligned, page.clone());
+                lru_add_page(&inode, offset_aligned);
                 page
             };
             let len = (buf_it.len()).min(PAGE_SIZE - offset_in_page);
             page.bytes_array_range(offset_in_page..offset_in_page + len)
                 .copy_from_slice(&buf_it[0..len]);
+            page_cache.mark_dirty(offset_aligned);
             log::trace!("[File::write] write count {len}, buf len {}", buf_it.len());
             offset_it += len;
             buf_it = &buf_it[len..];
@@ -260,6 +281,9 @@ pub trait File: Send + Sync + DowncastSync {
             // }
             inode.set_size(new_size);
         }
+        inode.mark_dirty();
+        balance_dirty_pages(&inode);
+        fsnotify_dentry(&self.dentry(), InotifyMask::MODIFY);
         Ok(buf.len())
     }
 
@@ -384,6 +408,67 @@ impl dyn File {
         Ok(ret)
     }
 
+    /// Copy `len` bytes at `off_in` of this file to `off_out` of `out` within
+    /// the kernel. If this file has a page cache, data is written from its
+    /// pages directly, otherwise, e.g. a pipe, it is bounced through a kernel
+    /// buffer.
+    ///
+    /// Returns count of bytes actually copied, which may be less than `len`
+    /// if eof is reached.
+    pub async fn copy_file_range(
+        &self,
+        off_in: usize,
+        out: &dyn File,
+        off_out: usize,
+        len: usize,
+    ) -> SyscallResult {
+        let inode = self.inode();
+        let mut copied = 0;
+
+        if inode.page_cache().is_none() {
+            let mut buf = vec![0; len.min(PAGE_SIZE)];
+            while copied < len {
+                let count = (len - copied).min(buf.len());
+                let read = self.read_at(off_in + copied, &mut buf[..count]).await?;
+                if read == 0 {
+                    break;
+                }
+                let written = out.write_at(off_out + copied, &buf[..read]).await?;
+                copied += written;
+                // Stop at a short read, which may block the next time.
+                if written < read || read < count {
+                    break;
+                }
+            }
+            return Ok(copied);
+        }
+
+        let size = self.size();
+        if off_in >= size {
+            return Ok(0);
+        }
+        let len = len.min(size - off_in);
+        while copied < len {
+            let (offset_aligned, offset_in_page) = align_offset_to_page(off_in + copied);
+            let Some(page) = self.get_page_at(offset_aligned).await? else {
+                break;
+            };
+            let count = (len - copied).min(PAGE_SIZE - offset_in_page);
+            let written = out
+                .write_at(
+                    off_out + copied,
+                    page.bytes_array_range(offset_in_page..offset_in_page + count),
+                )
+                .await?;
+            copied += written;
+            if written < count {
+                break;
+            }
+        }
+        log::info!("[File::copy_file_range] copy count {copied}");
+        Ok(copied)
+    }
+
     /// Given interested events, keep track of these events and return events
     /// that is ready.
     // NOTE: async function but always returns `Ready`. Why async, to take the
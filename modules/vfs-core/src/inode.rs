@@ -1,13 +1,24 @@
-use alloc::sync::{Arc, Weak};
-use core::mem::MaybeUninit;
-
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{mem::MaybeUninit, time::Duration};
+
+use config::mm::PAGE_SIZE;
 use device_core::DevId;
 use downcast_rs::{impl_downcast, DowncastSync};
-use page::PageCache;
-use systype::{SysResult, SyscallResult};
+use page::{DirtyPage, PageCache};
+use systype::{SysError, SysResult, SyscallResult};
 use time::timespec::TimeSpec;
 
-use crate::{alloc_ino, Mutex, Stat, SuperBlock};
+use crate::{
+    alloc_ino,
+    inotify::{fsnotify_inode, InotifyMask},
+    lock::FileLockContext,
+    xattr::{check_xattr_name, XattrFlags, XATTR_SIZE_MAX, XATTR_USER_PREFIX},
+    Dentry, Mutex, Stat, SuperBlock,
+};
 
 pub struct InodeMeta {
     /// Inode number.
@@ -18,6 +29,8 @@ pub struct InodeMeta {
     pub super_block: Weak<dyn SuperBlock>,
 
     pub page_cache: Option<PageCache>,
+    /// Advisory file locks on this inode.
+    pub locks: FileLockContext,
     pub inner: Mutex<InodeMetaInner>,
 }
 
@@ -34,6 +47,9 @@ pub struct InodeMetaInner {
     pub ctime: TimeSpec,
     ///
     pub state: InodeState,
+    /// Whether the inode is the file of an active swap area, which must not
+    /// be written, truncated or unlinked.
+    pub swap_file: bool,
 }
 
 impl Drop for InodeMeta {
@@ -68,6 +84,7 @@ impl InodeMeta {
             super_block: Arc::downgrade(&super_block),
             dev_id: None,
             page_cache: address_space,
+            locks: FileLockContext::new(),
             inner: Mutex::new(InodeMetaInner {
                 size,
                 atime: TimeSpec::default(),
@@ -75,6 +92,7 @@ impl InodeMeta {
                 ctime: TimeSpec::default(),
                 state: InodeState::UnInit,
                 nlink: 1,
+                swap_file: false,
             }),
         }
     }
@@ -95,6 +113,45 @@ pub trait Inode: Send + Sync + DowncastSync {
         todo!()
     }
 
+    /// Get the value of the extended attribute `name`. `dentry` is the dentry
+    /// through which this inode is looked up, for file systems that address
+    /// inodes by path.
+    fn base_getxattr(&self, _dentry: &dyn Dentry, _name: &str) -> SysResult<Vec<u8>> {
+        Err(SysError::EOPNOTSUPP)
+    }
+
+    /// Set the value of the extended attribute `name`, creating it if it does
+    /// not exist unless `XattrFlags::REPLACE` is given.
+    fn base_setxattr(
+        &self,
+        _dentry: &dyn Dentry,
+        _name: &str,
+        _value: &[u8],
+        _flags: XattrFlags,
+    ) -> SysResult<()> {
+        Err(SysError::EOPNOTSUPP)
+    }
+
+    /// List the names of all extended attributes, with namespace prefixes.
+    fn base_listxattr(&self, _dentry: &dyn Dentry) -> SysResult<Vec<String>> {
+        Err(SysError::EOPNOTSUPP)
+    }
+
+    fn base_removexattr(&self, _dentry: &dyn Dentry, _name: &str) -> SysResult<()> {
+        Err(SysError::EOPNOTSUPP)
+    }
+
+    /// Write `buf` of the page cache back to the file at `offset`. File
+    /// systems without backing storage keep the data in the page cache only.
+    fn base_write_back(&self, _offset: usize, _buf: &[u8]) -> SysResult<()> {
+        Ok(())
+    }
+
+    /// Write the metadata of this inode back to the file system.
+    fn base_sync(&self) -> SysResult<()> {
+        Ok(())
+    }
+
     fn size(&self) -> usize {
         self.meta().inner.lock().size
     }
@@ -125,6 +182,14 @@ impl dyn Inode {
         self.meta().page_cache.as_ref()
     }
 
+    pub fn is_swap_file(&self) -> bool {
+        self.meta().inner.lock().swap_file
+    }
+
+    pub fn set_swap_file(&self, swap_file: bool) {
+        self.meta().inner.lock().swap_file = swap_file;
+    }
+
     pub fn set_state(&self, state: InodeState) {
         let mut inner = self.meta().inner.lock();
         if inner.state == InodeState::Removed {
@@ -134,12 +199,183 @@ impl dyn Inode {
         }
     }
 
+    /// Mark the inode dirty after its page cache is written, and queue it on
+    /// the super block to be written back.
+    pub fn mark_dirty(self: &Arc<Self>) {
+        let mut inner = self.meta().inner.lock();
+        if matches!(inner.state, InodeState::Dirty | InodeState::Removed) {
+            return;
+        }
+        inner.state = InodeState::Dirty;
+        drop(inner);
+        if let Some(super_block) = self.meta().super_block.upgrade() {
+            super_block.meta().dirty_inodes.lock().push(self.clone());
+        }
+    }
+
+    /// Write back this inode taken off the dirty list of the super block.
+    pub(crate) fn write_back(&self) -> SysResult<()> {
+        {
+            // Set it clean before taking dirty pages, so that a racing write
+            // will queue it again.
+            let mut inner = self.meta().inner.lock();
+            if inner.state == InodeState::Dirty {
+                inner.state = InodeState::Sync;
+            }
+        }
+        self.write_dirty_pages()
+    }
+
+    /// Write back pages of this inode on the dirty list of the super block
+    /// that have been dirty for at least `expire`.
+    ///
+    /// Returns whether the inode still has dirty pages and should stay on the
+    /// dirty list.
+    pub(crate) fn write_back_expired(&self, expire: Duration) -> SysResult<bool> {
+        if let Some(page_cache) = self.meta().page_cache.as_ref() {
+            let mut pages = page_cache.take_expired_pages(expire);
+            if !pages.is_empty() && self.state() != InodeState::Removed {
+                if let Err(e) = self.write_pages(&mut pages) {
+                    // Retried on the next round with their original time.
+                    page_cache.redirty(pages);
+                    return Err(e);
+                }
+                self.base_sync()?;
+            }
+            // Check under the lock of inode, so that a racing write either
+            // finds the inode dirty or queues it again.
+            let mut inner = self.meta().inner.lock();
+            if page_cache.nr_dirty() > 0 {
+                return Ok(true);
+            }
+            if inner.state == InodeState::Dirty {
+                inner.state = InodeState::Sync;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Write back this inode and flush the file system and the device it is
+    /// on, called by fsync(2). The inode is left on the dirty list of the
+    /// super block if queued.
+    pub fn sync(&self) -> SysResult<()> {
+        // Pipes, sockets and other anonymous files can not be synced.
+        if self.is_dummy() || self.itype().is_fifo() || self.itype().is_socket() {
+            return Err(SysError::EINVAL);
+        }
+        self.write_dirty_pages()?;
+        match self.try_super_block() {
+            Some(super_block) => super_block.sync_fs_and_device(1),
+            // Files without a file system have nothing to flush.
+            None => Ok(()),
+        }
+    }
+
+    /// Write dirty pages in the page cache and the metadata of this inode
+    /// back to the file system.
+    pub fn write_dirty_pages(&self) -> SysResult<()> {
+        let Some(page_cache) = self.meta().page_cache.as_ref() else {
+            return match self.state() {
+                InodeState::Removed => Ok(()),
+                _ => self.base_sync(),
+            };
+        };
+        let mut pages = page_cache.take_dirty_pages();
+        if self.state() == InodeState::Removed {
+            // Data of a removed file is discarded.
+            return Ok(());
+        }
+        if let Err(e) = self.write_pages(&mut pages) {
+            page_cache.redirty(pages);
+            return Err(e);
+        }
+        self.base_sync()
+    }
+
+    /// Write `pages` back in order. Pages written are removed from `pages`, so
+    /// that the rest can be marked dirty again on error.
+    fn write_pages(&self, pages: &mut Vec<DirtyPage>) -> SysResult<()> {
+        let size = self.size();
+        let mut written = 0;
+        let ret = pages.iter().try_for_each(|DirtyPage { offset, page, .. }| {
+            if *offset < size {
+                let len = (size - offset).min(PAGE_SIZE);
+                self.base_write_back(*offset, page.bytes_array_range(0..len))?;
+            }
+            written += 1;
+            Ok(())
+        });
+        pages.drain(..written);
+        ret
+    }
+
     pub fn truncate(&self, len: usize) -> SyscallResult {
         log::info!(
             "[Inode::truncate] len:{len:#x}, origin size:{:#x}",
             self.size()
         );
-        self.base_truncate(len).map(|_| 0)
+        if self.is_swap_file() {
+            return Err(SysError::ETXTBSY);
+        }
+        self.base_truncate(len)?;
+        fsnotify_inode(self, InotifyMask::MODIFY);
+        Ok(0)
+    }
+
+    pub fn getxattr(&self, dentry: &dyn Dentry, name: &str) -> SysResult<Vec<u8>> {
+        check_xattr_name(name)?;
+        // User extended attributes are only allowed on regular files and
+        // directories.
+        if name.starts_with(XATTR_USER_PREFIX) && !self.allow_user_xattr() {
+            return Err(SysError::ENODATA);
+        }
+        self.base_getxattr(dentry, name)
+    }
+
+    pub fn setxattr(
+        &self,
+        dentry: &dyn Dentry,
+        name: &str,
+        value: &[u8],
+        flags: XattrFlags,
+    ) -> SysResult<()> {
+        if flags.contains(XattrFlags::CREATE | XattrFlags::REPLACE) {
+            return Err(SysError::EINVAL);
+        }
+        check_xattr_name(name)?;
+        if value.len() > XATTR_SIZE_MAX {
+            return Err(SysError::E2BIG);
+        }
+        if name.starts_with(XATTR_USER_PREFIX) && !self.allow_user_xattr() {
+            return Err(SysError::EPERM);
+        }
+        log::info!(
+            "[Inode::setxattr] name:{name}, len:{}, flags:{flags:?}",
+            value.len()
+        );
+        self.base_setxattr(dentry, name, value, flags)?;
+        fsnotify_inode(self, InotifyMask::ATTRIB);
+        Ok(())
+    }
+
+    pub fn listxattr(&self, dentry: &dyn Dentry) -> SysResult<Vec<String>> {
+        self.base_listxattr(dentry)
+    }
+
+    pub fn removexattr(&self, dentry: &dyn Dentry, name: &str) -> SysResult<()> {
+        check_xattr_name(name)?;
+        if name.starts_with(XATTR_USER_PREFIX) && !self.allow_user_xattr() {
+            return Err(SysError::EPERM);
+        }
+        log::info!("[Inode::removexattr] name:{name}");
+        self.base_removexattr(dentry, name)?;
+        fsnotify_inode(self, InotifyMask::ATTRIB);
+        Ok(())
+    }
+
+    fn allow_user_xattr(&self) -> bool {
+        let itype = self.itype();
+        itype.is_file() || itype.is_dir()
     }
 
     pub fn get_blk_idx(&self, offset: usize) -> SysResult<usize> {
@@ -149,6 +385,21 @@ impl dyn Inode {
     pub fn super_block(&self) -> Arc<dyn SuperBlock> {
         self.meta().super_block.upgrade().unwrap()
     }
+
+    /// Super block of this inode, or `None` for anonymous files that are not
+    /// on any file system.
+    pub fn try_super_block(&self) -> Option<Arc<dyn SuperBlock>> {
+        if self.is_dummy() {
+            return None;
+        }
+        self.meta().super_block.upgrade()
+    }
+
+    /// Whether this is the dummy inode of an anonymous file without any
+    /// metadata, e.g. a socket or an eventfd, which must not be accessed.
+    pub fn is_dummy(&self) -> bool {
+        self.is::<MaybeUninit<usize>>()
+    }
 }
 
 impl_downcast!(sync Inode);
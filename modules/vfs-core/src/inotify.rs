@@ -0,0 +1,459 @@
+//! inotify(7) support.
+//!
+//! Watches are attached to inodes and kept in a global table indexed by inode
+//! number, events are raised from the generic `Dentry`, `File` and `Inode`
+//! wrappers so that every file system gets them for free.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    mem::size_of,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use systype::{SysError, SysResult};
+
+use crate::{arc_zero, Dentry, File, FileMeta, Inode, Mutex, OpenFlags, PollEvents};
+
+bitflags::bitflags! {
+    // Defined in <sys/inotify.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct InotifyFlags: i32 {
+        const NONBLOCK = OpenFlags::O_NONBLOCK.bits();
+        const CLOEXEC = OpenFlags::O_CLOEXEC.bits();
+    }
+}
+
+bitflags::bitflags! {
+    // Defined in <sys/inotify.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct InotifyMask: u32 {
+        /// File was accessed.
+        const ACCESS = 0x00000001;
+        /// File was modified.
+        const MODIFY = 0x00000002;
+        /// Metadata changed.
+        const ATTRIB = 0x00000004;
+        /// Writtable file was closed.
+        const CLOSE_WRITE = 0x00000008;
+        /// Unwrittable file closed.
+        const CLOSE_NOWRITE = 0x00000010;
+        /// File was opened.
+        const OPEN = 0x00000020;
+        /// File was moved from X.
+        const MOVED_FROM = 0x00000040;
+        /// File was moved to Y.
+        const MOVED_TO = 0x00000080;
+        /// Subfile was created.
+        const CREATE = 0x00000100;
+        /// Subfile was deleted.
+        const DELETE = 0x00000200;
+        /// Self was deleted.
+        const DELETE_SELF = 0x00000400;
+        /// Self was moved.
+        const MOVE_SELF = 0x00000800;
+
+        /// Backing fs was unmounted.
+        const UNMOUNT = 0x00002000;
+        /// Event queued overflowed.
+        const Q_OVERFLOW = 0x00004000;
+        /// File was ignored.
+        const IGNORED = 0x00008000;
+
+        /// Only watch the path if it is a directory.
+        const ONLYDIR = 0x01000000;
+        /// Do not follow a sym link.
+        const DONT_FOLLOW = 0x02000000;
+        /// Exclude events on unlinked objects.
+        const EXCL_UNLINK = 0x04000000;
+        /// Only create watches.
+        const MASK_CREATE = 0x10000000;
+        /// Add to the mask of an already existing watch.
+        const MASK_ADD = 0x20000000;
+        /// Event occurred against dir.
+        const ISDIR = 0x40000000;
+        /// Only send event once.
+        const ONESHOT = 0x80000000;
+
+        const ALL_EVENTS = Self::ACCESS.bits() | Self::MODIFY.bits() | Self::ATTRIB.bits()
+            | Self::CLOSE_WRITE.bits() | Self::CLOSE_NOWRITE.bits() | Self::OPEN.bits()
+            | Self::MOVED_FROM.bits() | Self::MOVED_TO.bits() | Self::CREATE.bits()
+            | Self::DELETE.bits() | Self::DELETE_SELF.bits() | Self::MOVE_SELF.bits();
+    }
+}
+
+/// Header of `struct inotify_event`, followed by `len` bytes of name.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct InotifyEvent {
+    /// Watch descriptor.
+    pub wd: i32,
+    /// Mask describing event.
+    pub mask: u32,
+    /// Unique cookie associating related events.
+    pub cookie: u32,
+    /// Size of name field, including null bytes for alignment.
+    pub len: u32,
+}
+
+/// Max number of events that can be queued in an inotify instance.
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+struct Watch {
+    inotify: Weak<Mutex<InotifyInner>>,
+    wd: i32,
+    mask: InotifyMask,
+}
+
+/// Watches indexed by inode number.
+static WATCHES: Mutex<BTreeMap<usize, Vec<Watch>>> = Mutex::new(BTreeMap::new());
+
+static COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Allocate a cookie to connect the `MOVED_FROM` and `MOVED_TO` events of a
+/// rename.
+pub fn alloc_cookie() -> u32 {
+    COOKIE.fetch_add(1, Ordering::Relaxed)
+}
+
+struct InotifyInner {
+    events: VecDeque<(InotifyEvent, Option<String>)>,
+    /// Watch descriptor to inode number.
+    wds: BTreeMap<i32, usize>,
+    next_wd: i32,
+    read_waker: VecDeque<Waker>,
+}
+
+impl InotifyInner {
+    fn push_event(&mut self, wd: i32, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+        let len = name.map_or(0, |name| {
+            // Null terminated and aligned to the size of the event header.
+            (name.len() + 1).next_multiple_of(size_of::<InotifyEvent>())
+        });
+        let event = InotifyEvent {
+            wd,
+            mask: mask.bits(),
+            cookie,
+            len: len as u32,
+        };
+        let name = name.map(|name| name.to_string());
+        // Identical successive events are coalesced into a single event.
+        if let Some((last, last_name)) = self.events.back() {
+            if last.wd == event.wd
+                && last.mask == event.mask
+                && last.cookie == event.cookie
+                && *last_name == name
+            {
+                return;
+            }
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            let overflow = InotifyMask::Q_OVERFLOW.bits();
+            if self
+                .events
+                .back()
+                .map_or(true, |(last, _)| last.mask != overflow)
+            {
+                let event = InotifyEvent {
+                    wd: -1,
+                    mask: overflow,
+                    cookie: 0,
+                    len: 0,
+                };
+                self.events.push_back((event, None));
+            }
+        } else {
+            self.events.push_back((event, name));
+        }
+        while let Some(waker) = self.read_waker.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+/// Send `mask` to watches on the inode with `ino`.
+///
+/// `name` is the name of the child that the event happens on if the inode is
+/// a watched directory.
+pub fn fsnotify(ino: usize, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+    let mut watches = WATCHES.lock();
+    let Some(list) = watches.get_mut(&ino) else {
+        return;
+    };
+    let event = mask & !InotifyMask::ISDIR;
+    list.retain(|watch| {
+        let Some(inotify) = watch.inotify.upgrade() else {
+            return false;
+        };
+        if !watch.mask.intersects(event) && !event.contains(InotifyMask::IGNORED) {
+            return true;
+        }
+        let mut inner = inotify.lock();
+        inner.push_event(watch.wd, mask, cookie, name);
+        if watch.mask.contains(InotifyMask::ONESHOT) || event.contains(InotifyMask::IGNORED) {
+            if !event.contains(InotifyMask::IGNORED) {
+                inner.push_event(watch.wd, InotifyMask::IGNORED, 0, None);
+            }
+            inner.wds.remove(&watch.wd);
+            return false;
+        }
+        true
+    });
+    if list.is_empty() {
+        watches.remove(&ino);
+    }
+}
+
+fn isdir_mask(inode: &dyn Inode) -> InotifyMask {
+    if inode.itype().is_dir() {
+        InotifyMask::ISDIR
+    } else {
+        InotifyMask::empty()
+    }
+}
+
+/// Send `mask` to watches on the inode itself.
+pub fn fsnotify_inode(inode: &dyn Inode, mask: InotifyMask) {
+    if WATCHES.lock().is_empty() {
+        return;
+    }
+    fsnotify(inode.ino(), mask | isdir_mask(inode), 0, None);
+}
+
+/// Send `mask` to watches on the parent directory of `dentry`, with the name of
+/// `dentry`.
+pub fn fsnotify_parent(dentry: &Arc<dyn Dentry>, mask: InotifyMask, cookie: u32) {
+    if WATCHES.lock().is_empty() {
+        return;
+    }
+    let Some(parent) = dentry.parent() else {
+        return;
+    };
+    let Ok(parent_inode) = parent.inode() else {
+        return;
+    };
+    let isdir = dentry
+        .inode()
+        .map_or(InotifyMask::empty(), |inode| isdir_mask(inode.as_ref()));
+    fsnotify(
+        parent_inode.ino(),
+        mask | isdir,
+        cookie,
+        Some(dentry.name()),
+    );
+}
+
+/// Send `mask` to watches on both `dentry` and its parent directory.
+pub fn fsnotify_dentry(dentry: &Arc<dyn Dentry>, mask: InotifyMask) {
+    if WATCHES.lock().is_empty() {
+        return;
+    }
+    if let Ok(inode) = dentry.inode() {
+        fsnotify_inode(inode.as_ref(), mask);
+    }
+    fsnotify_parent(dentry, mask, 0);
+}
+
+/// Whether there are watches on the inode with `ino`, which must be kept in
+/// memory since watches refer to inode numbers. Assumed to be so if the
+/// watches are locked by someone else.
+pub(crate) fn is_watched(ino: usize) -> bool {
+    WATCHES
+        .try_lock()
+        .map_or(true, |watches| watches.contains_key(&ino))
+}
+
+/// Remove all watches on the inode, which is removed from the file system.
+pub fn fsnotify_remove_inode(inode: &dyn Inode) {
+    if WATCHES.lock().is_empty() {
+        return;
+    }
+    fsnotify_inode(inode, InotifyMask::DELETE_SELF);
+    fsnotify(inode.ino(), InotifyMask::IGNORED, 0, None);
+}
+
+pub struct InotifyFile {
+    meta: FileMeta,
+    inner: Arc<Mutex<InotifyInner>>,
+}
+
+impl InotifyFile {
+    pub fn new(flags: InotifyFlags) -> Arc<Self> {
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        let mut open_flags = OpenFlags::empty();
+        if flags.contains(InotifyFlags::NONBLOCK) {
+            open_flags |= OpenFlags::O_NONBLOCK;
+        }
+        *meta.flags.lock() = open_flags;
+        let inner = Arc::new(Mutex::new(InotifyInner {
+            events: VecDeque::new(),
+            wds: BTreeMap::new(),
+            next_wd: 1,
+            read_waker: VecDeque::new(),
+        }));
+        Arc::new(Self { meta, inner })
+    }
+
+    /// Add a new watch, or modify an existing watch, for the inode. Return the
+    /// watch descriptor.
+    pub fn add_watch(&self, inode: &Arc<dyn Inode>, mask: InotifyMask) -> SysResult<i32> {
+        if !mask.intersects(InotifyMask::ALL_EVENTS) {
+            return Err(SysError::EINVAL);
+        }
+        if mask.contains(InotifyMask::MASK_ADD | InotifyMask::MASK_CREATE) {
+            return Err(SysError::EINVAL);
+        }
+        let ino = inode.ino();
+        let mut watches = WATCHES.lock();
+        let list = watches.entry(ino).or_default();
+        if let Some(watch) = list
+            .iter_mut()
+            .find(|watch| Weak::as_ptr(&watch.inotify) == Arc::as_ptr(&self.inner))
+        {
+            if mask.contains(InotifyMask::MASK_CREATE) {
+                return Err(SysError::EEXIST);
+            }
+            if mask.contains(InotifyMask::MASK_ADD) {
+                watch.mask |= mask;
+            } else {
+                watch.mask = mask;
+            }
+            watch.mask.remove(InotifyMask::MASK_ADD);
+            return Ok(watch.wd);
+        }
+        let mut inner = self.inner.lock();
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.wds.insert(wd, ino);
+        list.push(Watch {
+            inotify: Arc::downgrade(&self.inner),
+            wd,
+            mask: mask - InotifyMask::MASK_CREATE,
+        });
+        Ok(wd)
+    }
+
+    /// Remove the watch associated with the watch descriptor `wd`.
+    pub fn rm_watch(&self, wd: i32) -> SysResult<()> {
+        let mut watches = WATCHES.lock();
+        let mut inner = self.inner.lock();
+        let ino = inner.wds.remove(&wd).ok_or(SysError::EINVAL)?;
+        if let Some(list) = watches.get_mut(&ino) {
+            list.retain(|watch| {
+                !(watch.wd == wd && Weak::as_ptr(&watch.inotify) == Arc::as_ptr(&self.inner))
+            });
+            if list.is_empty() {
+                watches.remove(&ino);
+            }
+        }
+        inner.push_event(wd, InotifyMask::IGNORED, 0, None);
+        Ok(())
+    }
+}
+
+impl Drop for InotifyFile {
+    fn drop(&mut self) {
+        let mut watches = WATCHES.lock();
+        let inner = self.inner.lock();
+        for ino in inner.wds.values() {
+            if let Some(list) = watches.get_mut(ino) {
+                list.retain(|watch| Weak::as_ptr(&watch.inotify) != Arc::as_ptr(&self.inner));
+                if list.is_empty() {
+                    watches.remove(ino);
+                }
+            }
+        }
+    }
+}
+
+struct InotifyReadFuture<'a> {
+    inotify: &'a InotifyFile,
+    len: usize,
+}
+
+impl Future for InotifyReadFuture<'_> {
+    type Output = SysResult<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.inotify.inner.lock();
+        if inner.events.is_empty() {
+            if self.inotify.flags().contains(OpenFlags::O_NONBLOCK) {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.read_waker.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let mut buf = Vec::new();
+        while let Some((event, _)) = inner.events.front() {
+            let event_len = size_of::<InotifyEvent>() + event.len as usize;
+            if buf.len() + event_len > self.len {
+                break;
+            }
+            let (event, name) = inner.events.pop_front().unwrap();
+            let header = unsafe {
+                core::slice::from_raw_parts(
+                    &event as *const InotifyEvent as *const u8,
+                    size_of::<InotifyEvent>(),
+                )
+            };
+            buf.extend_from_slice(header);
+            let name_start = buf.len();
+            buf.resize(name_start + event.len as usize, 0);
+            if let Some(name) = name {
+                buf[name_start..name_start + name.len()].copy_from_slice(name.as_bytes());
+            }
+        }
+        if buf.is_empty() {
+            // The buffer is too small to hold the next event.
+            return Poll::Ready(Err(SysError::EINVAL));
+        }
+        Poll::Ready(Ok(buf))
+    }
+}
+
+#[async_trait]
+impl File for InotifyFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// Each successful read(2) returns a buffer containing one or more
+    /// `inotify_event` structures.
+    async fn read_at(&self, _offset: usize, buf: &mut [u8]) -> SysResult<usize> {
+        let events = InotifyReadFuture {
+            inotify: self,
+            len: buf.len(),
+        }
+        .await?;
+        buf[..events.len()].copy_from_slice(&events);
+        Ok(events.len())
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut inner = self.inner.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if !inner.events.is_empty() {
+                res |= PollEvents::IN;
+            } else {
+                inner.read_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
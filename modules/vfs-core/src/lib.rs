@@ -6,16 +6,20 @@ mod dentry;
 mod file;
 mod file_system_type;
 mod inode;
+pub mod inotify;
+pub mod lock;
 mod path;
+pub mod reclaim;
 mod super_block;
 mod utils;
+pub mod writeback;
+mod xattr;
 
 extern crate alloc;
 
 use alloc::sync::Arc;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use memory::FrameReleaseIf;
 use sync::mutex::SpinNoIrqLock;
 
 type Mutex<T> = SpinNoIrqLock<T>;
@@ -37,3 +41,4 @@ pub use inode::*;
 pub use path::*;
 pub use super_block::*;
 pub use utils::*;
+pub use xattr::*;
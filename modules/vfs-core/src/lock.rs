@@ -0,0 +1,384 @@
+//! Advisory file locks, i.e. flock(2) locks, and POSIX record locks and open
+//! file description (OFD) locks of fcntl(2).
+//!
+//! Locks are kept in the `FileLockContext` of each inode. flock locks and OFD
+//! locks are owned by the open file description, and released when the last
+//! reference to the file is dropped. POSIX record locks are owned by the
+//! process, and released when the process closes any file descriptor referring
+//! to the inode, or exits.
+
+use alloc::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    ptr,
+    sync::atomic::Ordering,
+    task::{Context, Poll, Waker},
+};
+
+use systype::{SysError, SysResult};
+
+use crate::{FileMeta, Inode, Mutex};
+
+/// Maximum length of the chain of blocked owners walked by deadlock detection.
+const MAX_DEADLOCK_ITERATIONS: usize = 10;
+
+/// Owners of POSIX record locks that are waiting for a lock, mapped to the
+/// owner of the lock they are waiting for.
+static BLOCKED_ON: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// Inodes on which each process holds POSIX record locks, so that they can be
+/// released without looking into every inode the process closes.
+static POSIX_HOLDERS: Mutex<BTreeMap<usize, Vec<Weak<dyn Inode>>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLockType {
+    Read,
+    Write,
+    Unlock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLockClass {
+    /// Whole-file locks of flock(2).
+    Flock,
+    /// Byte-range locks of fcntl(2), both POSIX record locks and OFD locks.
+    Record,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockOwner {
+    /// POSIX record locks are owned by the process with this pid.
+    Process(usize),
+    /// flock locks and OFD locks are owned by the open file description.
+    File(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FileLock {
+    pub class: FileLockClass,
+    pub owner: LockOwner,
+    pub ltype: FileLockType,
+    /// First byte of the locked range.
+    pub start: usize,
+    /// Last byte of the locked range, where `usize::MAX` means the lock
+    /// extends to the end of file however it grows.
+    pub end: usize,
+}
+
+impl FileLock {
+    pub fn new_flock(owner: LockOwner, ltype: FileLockType) -> Self {
+        Self {
+            class: FileLockClass::Flock,
+            owner,
+            ltype,
+            start: 0,
+            end: usize::MAX,
+        }
+    }
+
+    pub fn new_record(owner: LockOwner, ltype: FileLockType, start: usize, end: usize) -> Self {
+        Self {
+            class: FileLockClass::Record,
+            owner,
+            ltype,
+            start,
+            end,
+        }
+    }
+
+    fn overlaps(&self, other: &FileLock) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn conflicts(&self, other: &FileLock) -> bool {
+        self.class == other.class
+            && self.owner != other.owner
+            && (self.ltype == FileLockType::Write || other.ltype == FileLockType::Write)
+            && self.overlaps(other)
+    }
+}
+
+pub struct FileLockContext {
+    inner: Mutex<FileLockContextInner>,
+}
+
+struct FileLockContextInner {
+    locks: Vec<FileLock>,
+    waiters: Vec<Waker>,
+}
+
+impl FileLockContextInner {
+    fn conflict(&self, lock: &FileLock) -> Option<FileLock> {
+        self.locks.iter().find(|l| l.conflicts(lock)).copied()
+    }
+
+    /// Apply `lock`, which should not conflict with other locks. A record lock
+    /// replaces the range of the locks of the same owner it overlaps with, and
+    /// is merged with the adjacent ones of the same type.
+    fn apply(&mut self, lock: FileLock) {
+        let mut new = lock;
+        let mut locks = Vec::with_capacity(self.locks.len() + 1);
+        for l in self.locks.drain(..) {
+            if l.class != lock.class || l.owner != lock.owner {
+                locks.push(l);
+                continue;
+            }
+            if lock.class == FileLockClass::Flock {
+                // An owner holds at most one flock lock.
+                continue;
+            }
+            if l.ltype == lock.ltype
+                && l.start <= new.end.saturating_add(1)
+                && new.start <= l.end.saturating_add(1)
+            {
+                new.start = new.start.min(l.start);
+                new.end = new.end.max(l.end);
+                continue;
+            }
+            if !l.overlaps(&lock) {
+                locks.push(l);
+                continue;
+            }
+            if l.start < lock.start {
+                locks.push(FileLock {
+                    end: lock.start - 1,
+                    ..l
+                });
+            }
+            if l.end > lock.end {
+                locks.push(FileLock {
+                    start: lock.end + 1,
+                    ..l
+                });
+            }
+        }
+        if new.ltype != FileLockType::Unlock {
+            locks.push(new);
+        }
+        self.locks = locks;
+    }
+
+    fn wake_waiters(&mut self) {
+        for waker in self.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Register `waker` to be woken when locks change, unless it has been.
+    fn add_waiter(&mut self, waker: &Waker) {
+        if !self.waiters.iter().any(|w| w.will_wake(waker)) {
+            self.waiters.push(waker.clone());
+        }
+    }
+}
+
+impl FileLockContext {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(FileLockContextInner {
+                locks: Vec::new(),
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns the first lock that conflicts with `lock`, if any.
+    pub fn test(&self, lock: &FileLock) -> Option<FileLock> {
+        self.inner.lock().conflict(lock)
+    }
+
+    /// Release all locks held by `owner`.
+    pub fn release(&self, owner: LockOwner) {
+        let mut inner = self.inner.lock();
+        let len = inner.locks.len();
+        inner.locks.retain(|l| l.owner != owner);
+        if inner.locks.len() != len {
+            inner.wake_waiters();
+        }
+    }
+}
+
+impl FileMeta {
+    /// Owner of flock locks and OFD locks acquired through this open file
+    /// description, which are released when the file is dropped.
+    pub fn lock_owner(&self) -> LockOwner {
+        self.has_locks.store(true, Ordering::Relaxed);
+        LockOwner::File(self as *const FileMeta as usize)
+    }
+
+    pub(crate) fn release_locks(&self) {
+        if self.has_locks.load(Ordering::Relaxed) {
+            let owner = LockOwner::File(self as *const FileMeta as usize);
+            self.inode.meta().locks.release(owner);
+        }
+    }
+}
+
+/// Try to acquire `lock` on the inode, or release it if it is of
+/// `FileLockType::Unlock`. If `wait` is true, wait until there is no
+/// conflicting lock, otherwise fails with `EAGAIN`.
+pub struct FileLockFuture {
+    inode: Arc<dyn Inode>,
+    lock: FileLock,
+    wait: bool,
+    blocked: bool,
+    /// Waker registered to the lock context while waiting, which is removed
+    /// if the future is dropped before woken, e.g. interrupted by a signal.
+    waker: Option<Waker>,
+}
+
+impl FileLockFuture {
+    pub fn new(inode: Arc<dyn Inode>, lock: FileLock, wait: bool) -> Self {
+        Self {
+            inode,
+            lock,
+            wait,
+            blocked: false,
+            waker: None,
+        }
+    }
+
+    fn unblock(&mut self) {
+        if let (true, LockOwner::Process(pid)) = (self.blocked, self.lock.owner) {
+            BLOCKED_ON.lock().remove(&pid);
+            self.blocked = false;
+        }
+    }
+}
+
+impl Future for FileLockFuture {
+    type Output = SysResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.lock;
+        let inode = self.inode.clone();
+        let mut inner = inode.meta().locks.inner.lock();
+        if lock.class == FileLockClass::Flock
+            && inner
+                .locks
+                .iter()
+                .any(|l| l.class == lock.class && l.owner == lock.owner && l.ltype != lock.ltype)
+        {
+            // Converting a flock lock is not atomic, the existing lock is
+            // removed first as Linux does.
+            inner.apply(FileLock {
+                ltype: FileLockType::Unlock,
+                ..lock
+            });
+            inner.wake_waiters();
+        }
+        if lock.ltype == FileLockType::Unlock {
+            inner.apply(lock);
+            inner.wake_waiters();
+            return Poll::Ready(Ok(()));
+        }
+        let Some(blocker) = inner.conflict(&lock) else {
+            inner.apply(lock);
+            drop(inner);
+            self.unblock();
+            if let LockOwner::Process(pid) = lock.owner {
+                register_posix_holder(pid, &inode);
+            }
+            return Poll::Ready(Ok(()));
+        };
+        if !self.wait {
+            return Poll::Ready(Err(SysError::EAGAIN));
+        }
+        if let (LockOwner::Process(pid), LockOwner::Process(blocker)) = (lock.owner, blocker.owner)
+        {
+            let mut blocked_on = BLOCKED_ON.lock();
+            if is_deadlock(&blocked_on, pid, blocker) {
+                log::info!("[FileLockFuture] deadlock between pid {pid} and pid {blocker}");
+                blocked_on.remove(&pid);
+                self.blocked = false;
+                return Poll::Ready(Err(SysError::EDEADLK));
+            }
+            blocked_on.insert(pid, blocker);
+            self.blocked = true;
+        }
+        inner.add_waiter(cx.waker());
+        drop(inner);
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for FileLockFuture {
+    fn drop(&mut self) {
+        self.unblock();
+        if let Some(waker) = self.waker.take() {
+            self.inode
+                .meta()
+                .locks
+                .inner
+                .lock()
+                .waiters
+                .retain(|w| !w.will_wake(&waker));
+        }
+    }
+}
+
+/// Whether `pid` waiting for `blocker` closes a cycle of waiting processes.
+fn is_deadlock(blocked_on: &BTreeMap<usize, usize>, pid: usize, blocker: usize) -> bool {
+    let mut owner = blocker;
+    for _ in 0..MAX_DEADLOCK_ITERATIONS {
+        if owner == pid {
+            return true;
+        }
+        match blocked_on.get(&owner) {
+            Some(&next) => owner = next,
+            None => return false,
+        }
+    }
+    false
+}
+
+fn register_posix_holder(pid: usize, inode: &Arc<dyn Inode>) {
+    let mut holders = POSIX_HOLDERS.lock();
+    let inodes = holders.entry(pid).or_default();
+    if !inodes
+        .iter()
+        .any(|i| ptr::addr_eq(i.as_ptr(), Arc::as_ptr(inode)))
+    {
+        inodes.push(Arc::downgrade(inode));
+    }
+}
+
+/// Release POSIX record locks of the process `pid` on `inode`, called when
+/// the process closes a file descriptor referring to `inode`.
+pub fn release_posix_locks(pid: usize, inode: &Arc<dyn Inode>) {
+    let inode = {
+        let mut holders = POSIX_HOLDERS.lock();
+        let Some(inodes) = holders.get_mut(&pid) else {
+            return;
+        };
+        let Some(i) = inodes
+            .iter()
+            .position(|i| ptr::addr_eq(i.as_ptr(), Arc::as_ptr(inode)))
+        else {
+            return;
+        };
+        let inode = inodes.swap_remove(i);
+        if inodes.is_empty() {
+            holders.remove(&pid);
+        }
+        inode
+    };
+    if let Some(inode) = inode.upgrade() {
+        inode.meta().locks.release(LockOwner::Process(pid));
+    }
+}
+
+/// Release all POSIX record locks of the process `pid`, called when the
+/// process exits.
+pub fn release_all_posix_locks(pid: usize) {
+    let inodes = POSIX_HOLDERS.lock().remove(&pid).unwrap_or_default();
+    for inode in inodes.iter().filter_map(|i| i.upgrade()) {
+        inode.meta().locks.release(LockOwner::Process(pid));
+    }
+}
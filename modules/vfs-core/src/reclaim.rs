@@ -0,0 +1,274 @@
+//! Shrinkers of the page cache and the dentry cache.
+//!
+//! Page cache pages backed by block devices are kept on LRU lists, and
+//! evicted by the page cache shrinker when memory runs low.
+//!
+//! Newly cached pages enter the inactive list. Pages referenced again while on
+//! the inactive list are promoted to the active list when scanned, and the
+//! active list is demoted to keep it no longer than the inactive list, giving
+//! referenced pages a second chance. Only clean pages that are not mapped into
+//! any address space are evicted, since they can always be read again.
+
+use alloc::{
+    collections::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+
+use memory::reclaim::Shrinker;
+
+use crate::{inotify::is_watched, Dentry, Inode, InodeState, Mutex};
+
+/// Prune stale entries when the lists grow beyond this count.
+const MIN_PRUNE_LEN: usize = 1024;
+
+static PAGE_LRU: Mutex<PageLru> = Mutex::new(PageLru::new());
+
+struct LruEntry {
+    inode: Weak<dyn Inode>,
+    offset: usize,
+}
+
+enum ScanResult {
+    /// The page is evicted.
+    Evicted,
+    /// The page has been referenced since last scan.
+    Referenced,
+    /// The page is dirty or used by someone else.
+    Busy,
+    /// The page or its inode no longer exists.
+    Gone,
+}
+
+impl LruEntry {
+    fn is_stale(&self) -> bool {
+        self.inode.upgrade().map_or(true, |inode| {
+            inode
+                .page_cache()
+                .map_or(true, |page_cache| !page_cache.contains_page(self.offset))
+        })
+    }
+
+    fn test_and_clear_referenced(&self) -> Option<bool> {
+        self.inode
+            .upgrade()?
+            .page_cache()?
+            .test_and_clear_referenced(self.offset)
+    }
+
+    fn scan(&self) -> ScanResult {
+        let Some(inode) = self.inode.upgrade() else {
+            return ScanResult::Gone;
+        };
+        let Some(page_cache) = inode.page_cache() else {
+            return ScanResult::Gone;
+        };
+        match page_cache.test_and_clear_referenced(self.offset) {
+            None => ScanResult::Gone,
+            Some(true) => ScanResult::Referenced,
+            Some(false) if page_cache.evict_page(self.offset) => ScanResult::Evicted,
+            Some(false) => ScanResult::Busy,
+        }
+    }
+}
+
+struct PageLru {
+    active: VecDeque<LruEntry>,
+    inactive: VecDeque<LruEntry>,
+    /// Length of the lists after last pruning.
+    pruned_len: usize,
+}
+
+impl PageLru {
+    const fn new() -> Self {
+        Self {
+            active: VecDeque::new(),
+            inactive: VecDeque::new(),
+            pruned_len: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.active.len() + self.inactive.len()
+    }
+
+    /// Drop entries of pages no longer cached, e.g. of truncated files or
+    /// dropped inodes, when the lists have doubled since last pruning.
+    fn prune_if_needed(&mut self) {
+        if self.len() < MIN_PRUNE_LEN.max(self.pruned_len * 2) {
+            return;
+        }
+        self.active.retain(|entry| !entry.is_stale());
+        self.inactive.retain(|entry| !entry.is_stale());
+        self.pruned_len = self.len();
+    }
+
+    /// Move the least recently added active page to the inactive list, or back
+    /// to the end of the active list if it has been referenced.
+    fn deactivate_one(&mut self) {
+        let Some(entry) = self.active.pop_front() else {
+            return;
+        };
+        match entry.test_and_clear_referenced() {
+            Some(true) => self.active.push_back(entry),
+            Some(false) => self.inactive.push_back(entry),
+            None => {}
+        }
+    }
+}
+
+/// Add the page at `offset_aligned` newly inserted into the page cache of
+/// `inode` to the LRU lists.
+pub fn lru_add_page(inode: &Arc<dyn Inode>, offset_aligned: usize) {
+    let mut lru = PAGE_LRU.lock();
+    lru.inactive.push_back(LruEntry {
+        inode: Arc::downgrade(inode),
+        offset: offset_aligned,
+    });
+    lru.prune_if_needed();
+}
+
+pub struct PageCacheShrinker;
+
+impl Shrinker for PageCacheShrinker {
+    fn name(&self) -> &'static str {
+        "page cache"
+    }
+
+    fn seeks(&self) -> usize {
+        1
+    }
+
+    fn scan_objects(&self, nr_to_scan: usize) -> usize {
+        let Some(mut lru) = PAGE_LRU.try_lock() else {
+            return 0;
+        };
+        let mut evicted = 0;
+        // Every page gets scanned at most twice, once more after being moved
+        // to the end of a list.
+        let mut budget = lru.len() * 2;
+        while evicted < nr_to_scan && budget > 0 {
+            budget -= 1;
+            if lru.inactive.len() < lru.active.len() {
+                lru.deactivate_one();
+            }
+            let Some(entry) = lru.inactive.pop_front() else {
+                break;
+            };
+            match entry.scan() {
+                ScanResult::Evicted => evicted += 1,
+                ScanResult::Referenced => lru.active.push_back(entry),
+                ScanResult::Busy => lru.inactive.push_back(entry),
+                ScanResult::Gone => {}
+            }
+        }
+        lru.pruned_len = lru.pruned_len.min(lru.len());
+        evicted
+    }
+}
+
+/// Shrinker of the dentry cache, which prunes dentries no one else refers to
+/// from the tree under `root`, together with their inodes.
+///
+/// Only dentries of file systems backed by block devices are pruned, since
+/// they can be looked up from disk again, while dentries of memory file
+/// systems are the files themselves.
+pub struct DentryShrinker {
+    root: Arc<dyn Dentry>,
+}
+
+impl DentryShrinker {
+    pub fn new(root: Arc<dyn Dentry>) -> Self {
+        Self { root }
+    }
+}
+
+impl Shrinker for DentryShrinker {
+    fn name(&self) -> &'static str {
+        "dentry cache"
+    }
+
+    fn seeks(&self) -> usize {
+        4
+    }
+
+    fn scan_objects(&self, nr_to_scan: usize) -> usize {
+        prune_dentries(&self.root, nr_to_scan)
+    }
+}
+
+/// Prune at most `nr_to_scan` unused dentries under `dentry`, children before
+/// their parents. Returns the count of dentries pruned.
+fn prune_dentries(dentry: &Arc<dyn Dentry>, nr_to_scan: usize) -> usize {
+    let Some(mut children) = dentry.meta().children.try_lock() else {
+        return 0;
+    };
+    let mut pruned = 0;
+    let mut victims = Vec::new();
+    for (name, child) in children.iter() {
+        if pruned >= nr_to_scan {
+            break;
+        }
+        pruned += prune_dentries(child, nr_to_scan - pruned);
+        if pruned < nr_to_scan && is_unused(dentry, child) {
+            victims.push(name.clone());
+            pruned += 1;
+        }
+    }
+    let victims: Vec<_> = victims
+        .iter()
+        .filter_map(|name| children.remove(name))
+        .collect();
+    drop(children);
+    // Children of the directory have to be loaded again from disk for
+    // `read_dir`.
+    if victims.iter().any(|child| !child.is_negetive()) {
+        if let Ok(inode) = dentry.inode() {
+            if inode.state() == InodeState::Sync {
+                inode.set_state(InodeState::UnInit);
+            }
+        }
+    }
+    pruned
+}
+
+/// Whether `child` of `parent` can be dropped and looked up again later.
+fn is_unused(parent: &Arc<dyn Dentry>, child: &Arc<dyn Dentry>) -> bool {
+    // Referenced only by the parent.
+    if Arc::strong_count(child) != 1 {
+        return false;
+    }
+    // Not the root of a file system mounted on the parent.
+    if !Weak::ptr_eq(&parent.meta().super_block, &child.meta().super_block) {
+        return false;
+    }
+    if !child
+        .meta()
+        .super_block
+        .upgrade()
+        .is_some_and(|sb| sb.meta().device.is_some())
+    {
+        return false;
+    }
+    if !child
+        .meta()
+        .children
+        .try_lock()
+        .is_some_and(|children| children.is_empty())
+    {
+        return false;
+    }
+    let Some(inode) = child.meta().inode.try_lock() else {
+        return false;
+    };
+    match inode.as_ref() {
+        None => true,
+        // Referenced only by the dentry, i.e. not opened, not dirty and not
+        // mapped.
+        Some(inode) => {
+            Arc::strong_count(inode) == 1
+                && inode.state() != InodeState::Dirty
+                && !is_watched(inode.ino())
+        }
+    }
+}
@@ -2,7 +2,7 @@ use alloc::{
     sync::{Arc, Weak},
     vec::Vec,
 };
-use core::mem::MaybeUninit;
+use core::{mem::MaybeUninit, time::Duration};
 
 use device_core::BlockDevice;
 use spin::Once;
@@ -18,6 +18,8 @@ pub struct SuperBlockMeta {
     pub fs_type: Weak<dyn FileSystemType>,
     /// Root dentry points to the mount point.
     pub root_dentry: Once<Arc<dyn Dentry>>,
+    /// Inodes whose page cache has dirty pages to be written back.
+    pub dirty_inodes: Mutex<Vec<Arc<dyn Inode>>>,
 }
 
 impl SuperBlockMeta {
@@ -25,6 +27,7 @@ impl SuperBlockMeta {
         Self {
             device,
             root_dentry: Once::new(),
+            dirty_inodes: Mutex::new(Vec::new()),
             fs_type: Arc::downgrade(&fs_type),
         }
     }
@@ -60,6 +63,79 @@ impl dyn SuperBlock {
     pub fn device(&self) -> Arc<dyn BlockDevice> {
         self.meta().device.as_ref().cloned().unwrap()
     }
+
+    /// Write back all dirty inodes of this file system, then the file system
+    /// itself and the underlying device.
+    pub fn sync(&self, wait: isize) -> SysResult<()> {
+        let inodes = core::mem::take(&mut *self.meta().dirty_inodes.lock());
+        log::info!("[SuperBlock::sync] write back {} inodes", inodes.len());
+        let mut ret = Ok(());
+        for inode in inodes {
+            // Go on with other inodes, but report the error.
+            if let Err(e) = inode.write_back() {
+                log::warn!(
+                    "[SuperBlock::sync] fail to write back inode {}, err {e:?}",
+                    inode.ino()
+                );
+                // Queue it again to retry the pages not written back.
+                inode.mark_dirty();
+                ret = Err(e);
+            }
+        }
+        self.sync_fs_and_device(wait)?;
+        ret
+    }
+
+    /// Write back pages of dirty inodes that have been dirty for at least
+    /// `expire`, then flush the file system and the underlying device. Called
+    /// periodically by the writeback task.
+    pub fn write_back_expired(&self, expire: Duration) -> SysResult<()> {
+        let inodes = core::mem::take(&mut *self.meta().dirty_inodes.lock());
+        if inodes.is_empty() {
+            return Ok(());
+        }
+        let mut ret = Ok(());
+        let mut still_dirty = Vec::new();
+        for inode in inodes {
+            match inode.write_back_expired(expire) {
+                Ok(false) => {}
+                Ok(true) => still_dirty.push(inode),
+                Err(e) => {
+                    log::warn!(
+                        "[SuperBlock::write_back_expired] fail to write back inode {}, err {e:?}",
+                        inode.ino()
+                    );
+                    still_dirty.push(inode);
+                    ret = Err(e);
+                }
+            }
+        }
+        // Inodes still dirty are not queued again by writers while taken off.
+        self.meta().dirty_inodes.lock().extend(still_dirty);
+        self.sync_fs_and_device(0)?;
+        ret
+    }
+
+    /// Count of dirty pages of this file system.
+    pub fn nr_dirty_pages(&self) -> usize {
+        self.meta()
+            .dirty_inodes
+            .lock()
+            .iter()
+            .filter_map(|inode| inode.meta().page_cache.as_ref())
+            .map(|page_cache| page_cache.nr_dirty())
+            .sum()
+    }
+
+    /// Flush the file system and the underlying device, without writing back
+    /// dirty inodes.
+    pub fn sync_fs_and_device(&self, wait: isize) -> SysResult<()> {
+        self.sync_fs(wait)?;
+        if let Some(device) = self.meta().device.as_ref() {
+            device.flush();
+        }
+        Ok(())
+    }
 }
 
 impl<T: Send + Sync + 'static> SuperBlock for MaybeUninit<T> {
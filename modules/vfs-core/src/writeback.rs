@@ -0,0 +1,41 @@
+//! Accounting of dirty pages in page caches, which are written back by the
+//! writeback task periodically, or by writers themselves when there are too
+//! many.
+
+use alloc::sync::Arc;
+
+use config::fs::{dirty_background_ratio, dirty_ratio};
+use memory::total_frames;
+use page::nr_dirty_pages;
+
+use crate::Inode;
+
+/// Count of dirty pages at which the writeback task writes back all dirty
+/// pages regardless of their age.
+pub fn dirty_background_limit() -> usize {
+    total_frames() * dirty_background_ratio() / 100
+}
+
+/// Count of dirty pages at which writers are throttled.
+pub fn dirty_limit() -> usize {
+    total_frames() * dirty_ratio() / 100
+}
+
+/// Throttle a writer of `inode` if dirty pages exceed the dirty limit, by
+/// making it write back dirty pages of the inode itself.
+pub fn balance_dirty_pages(inode: &Arc<dyn Inode>) {
+    let nr_dirty = nr_dirty_pages();
+    if nr_dirty <= dirty_limit() {
+        return;
+    }
+    log::info!(
+        "[balance_dirty_pages] {nr_dirty} dirty pages, throttle writer of inode {}",
+        inode.ino()
+    );
+    if let Err(e) = inode.write_dirty_pages() {
+        log::warn!(
+            "[balance_dirty_pages] fail to write back inode {}, err {e:?}",
+            inode.ino()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Extended attributes.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use systype::{SysError, SysResult};
+
+use crate::Mutex;
+
+/// Maximum length of the name of an extended attribute, in bytes.
+pub const XATTR_NAME_MAX: usize = 255;
+/// Maximum size of the value of an extended attribute, in bytes.
+pub const XATTR_SIZE_MAX: usize = 65536;
+/// Maximum size of the list of extended attribute names, in bytes.
+pub const XATTR_LIST_MAX: usize = 65536;
+
+pub const XATTR_USER_PREFIX: &str = "user.";
+pub const XATTR_TRUSTED_PREFIX: &str = "trusted.";
+pub const XATTR_SECURITY_PREFIX: &str = "security.";
+
+bitflags::bitflags! {
+    // Defined in <sys/xattr.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct XattrFlags: i32 {
+        /// Perform a pure create, which fails if the named attribute exists
+        /// already.
+        const CREATE = 1;
+        /// Perform a pure replace operation, which fails if the named
+        /// attribute does not already exist.
+        const REPLACE = 2;
+    }
+}
+
+/// Check that `name` is in one of the supported namespaces, i.e. user.*,
+/// trusted.* and security.*.
+pub fn check_xattr_name(name: &str) -> SysResult<()> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return Err(SysError::ERANGE);
+    }
+    let suffix = [
+        XATTR_USER_PREFIX,
+        XATTR_TRUSTED_PREFIX,
+        XATTR_SECURITY_PREFIX,
+    ]
+    .iter()
+    .find_map(|prefix| name.strip_prefix(prefix))
+    .ok_or(SysError::EOPNOTSUPP)?;
+    if suffix.is_empty() {
+        return Err(SysError::EINVAL);
+    }
+    Ok(())
+}
+
+/// Extended attributes kept in memory, for file systems without backing
+/// storage.
+pub struct XattrMap {
+    attrs: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl XattrMap {
+    pub const fn new() -> Self {
+        Self {
+            attrs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> SysResult<Vec<u8>> {
+        self.attrs
+            .lock()
+            .get(name)
+            .cloned()
+            .ok_or(SysError::ENODATA)
+    }
+
+    pub fn set(&self, name: &str, value: &[u8], flags: XattrFlags) -> SysResult<()> {
+        let mut attrs = self.attrs.lock();
+        let exists = attrs.contains_key(name);
+        if flags.contains(XattrFlags::CREATE) && exists {
+            return Err(SysError::EEXIST);
+        }
+        if flags.contains(XattrFlags::REPLACE) && !exists {
+            return Err(SysError::ENODATA);
+        }
+        attrs.insert(name.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.attrs.lock().keys().cloned().collect()
+    }
+
+    pub fn remove(&self, name: &str) -> SysResult<()> {
+        self.attrs
+            .lock()
+            .remove(name)
+            .map(|_| ())
+            .ok_or(SysError::ENODATA)
+    }
+}
@@ -0,0 +1,399 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Weak},
+    task::Wake,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{arc_zero, File, FileMeta, InodeType, PollEvents};
+
+use crate::fd_table::Fd;
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// Maximum depth of nested epoll instances, including the outermost one.
+const EPOLL_MAX_NESTS: usize = 5;
+
+bitflags::bitflags! {
+    // Defined in <sys/epoll.h>.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct EpollEvents: u32 {
+        const IN = 0x001;
+        const PRI = 0x002;
+        const OUT = 0x004;
+        const ERR = 0x008;
+        const HUP = 0x010;
+        const RDNORM = 0x040;
+        const RDBAND = 0x080;
+        const WRNORM = 0x100;
+        const WRBAND = 0x200;
+        const MSG = 0x400;
+        const RDHUP = 0x2000;
+        /// Sets exclusive wakeup mode for the epoll file descriptor.
+        const EXCLUSIVE = 1 << 28;
+        /// Prevents system suspend while the event is pending.
+        const WAKEUP = 1 << 29;
+        /// Disables the file descriptor after one event is reported.
+        const ONESHOT = 1 << 30;
+        /// Requests edge-triggered notification.
+        const ET = 1 << 31;
+    }
+}
+
+impl EpollEvents {
+    pub fn to_poll_events(self) -> PollEvents {
+        PollEvents::from_bits_truncate(self.bits() as i16)
+    }
+
+    pub fn from_poll_events(events: PollEvents) -> Self {
+        Self::from_bits_truncate(events.bits() as u16 as u32)
+    }
+}
+
+/// `struct epoll_event`. Note that it is only packed on x86_64.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct EpollEvent {
+    pub events: EpollEvents,
+    pub data: u64,
+}
+
+struct EpollItem {
+    /// Epoll does not hold a reference to the file, an item is removed
+    /// automatically once the file is closed by all its owners.
+    file: Weak<dyn File>,
+    event: EpollEvent,
+    /// Waker registered to the file when it is not ready, it puts the item
+    /// back to the ready list when the file wakes it.
+    waker: Waker,
+    /// Whether the item is in the ready list.
+    queued: bool,
+    /// A oneshot item that has reported an event is disabled until it is
+    /// rearmed by `EPOLL_CTL_MOD`.
+    disabled: bool,
+}
+
+struct EpollInner {
+    items: BTreeMap<Fd, EpollItem>,
+    /// Fds of items that should be polled in the next wait. These are newly
+    /// added or modified items, items woken by their files, and items that
+    /// were ready last time.
+    ready: VecDeque<Fd>,
+    /// Set when an item is woken by its file, cleared when the ready list is
+    /// taken by a scan.
+    woken: bool,
+    /// Wakers of tasks waiting on this epoll instance.
+    waiters: VecDeque<Waker>,
+}
+
+impl EpollInner {
+    fn enqueue(&mut self, fd: Fd) {
+        if let Some(item) = self.items.get_mut(&fd) {
+            if !item.queued {
+                item.queued = true;
+                self.ready.push_back(fd);
+            }
+        }
+    }
+
+    /// Put the item into the ready list and take all waiters out to wake
+    /// them up after the lock is released.
+    fn enqueue_and_notify(&mut self, fd: Fd) -> VecDeque<Waker> {
+        self.enqueue(fd);
+        self.woken = true;
+        mem::take(&mut self.waiters)
+    }
+
+    /// Add `waker` to the waiters unless it is there already, since a task
+    /// may poll the instance many times before it is woken.
+    fn add_waiter(&mut self, waker: &Waker) {
+        if !self.waiters.iter().any(|w| w.will_wake(waker)) {
+            self.waiters.push_back(waker.clone());
+        }
+    }
+}
+
+struct EpollItemWaker {
+    epoll: Weak<Mutex<EpollInner>>,
+    fd: Fd,
+}
+
+impl Wake for EpollItemWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let Some(epoll) = self.epoll.upgrade() else {
+            return;
+        };
+        let waiters = epoll.lock().enqueue_and_notify(self.fd);
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
+
+pub struct EpollFile {
+    meta: FileMeta,
+    inner: Arc<Mutex<EpollInner>>,
+}
+
+impl EpollFile {
+    pub fn new() -> Arc<Self> {
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        let inner = Arc::new(Mutex::new(EpollInner {
+            items: BTreeMap::new(),
+            ready: VecDeque::new(),
+            woken: false,
+            waiters: VecDeque::new(),
+        }));
+        Arc::new(Self { meta, inner })
+    }
+
+    pub fn add(&self, fd: Fd, file: &Arc<dyn File>, event: EpollEvent) -> SysResult<()> {
+        // Regular files and directories are always ready and do not support
+        // polling.
+        if !file.inode().is_dummy() && matches!(file.itype(), InodeType::File | InodeType::Dir) {
+            return Err(SysError::EPERM);
+        }
+        if let Ok(epoll) = file.clone().downcast_arc::<EpollFile>() {
+            self.check_nest(&epoll, 2)?;
+        }
+        let mut inner = self.inner.lock();
+        if let Some(item) = inner.items.get(&fd) {
+            // The old file has been closed but we have not noticed it yet.
+            if item.file.strong_count() != 0 {
+                return Err(SysError::EEXIST);
+            }
+            inner.items.remove(&fd);
+        }
+        let waker = Arc::new(EpollItemWaker {
+            epoll: Arc::downgrade(&self.inner),
+            fd,
+        })
+        .into();
+        let item = EpollItem {
+            file: Arc::downgrade(file),
+            event,
+            waker,
+            queued: false,
+            disabled: false,
+        };
+        inner.items.insert(fd, item);
+        let waiters = inner.enqueue_and_notify(fd);
+        drop(inner);
+        for waker in waiters {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Return `ELOOP` if `epoll`, which is at `depth` below this instance
+    /// counting this instance as 1, contains this instance or nests epoll
+    /// instances deeper than `EPOLL_MAX_NESTS`.
+    ///
+    /// A cycle would make polling the instance recurse forever.
+    fn check_nest(&self, epoll: &EpollFile, depth: usize) -> SysResult<()> {
+        if Arc::ptr_eq(&self.inner, &epoll.inner) || depth > EPOLL_MAX_NESTS {
+            return Err(SysError::ELOOP);
+        }
+        // NOTE: the lock is not held while checking nested instances, since
+        // one of them may be this instance.
+        let nested: Vec<Arc<EpollFile>> = epoll
+            .inner
+            .lock()
+            .items
+            .values()
+            .filter_map(|item| item.file.upgrade()?.downcast_arc::<EpollFile>().ok())
+            .collect();
+        nested
+            .iter()
+            .try_for_each(|nested| self.check_nest(nested, depth + 1))
+    }
+
+    pub fn modify(&self, fd: Fd, event: EpollEvent) -> SysResult<()> {
+        let mut inner = self.inner.lock();
+        let item = inner.items.get_mut(&fd).ok_or(SysError::ENOENT)?;
+        if item.event.events.contains(EpollEvents::EXCLUSIVE) {
+            return Err(SysError::EINVAL);
+        }
+        item.event = event;
+        item.disabled = false;
+        let waiters = inner.enqueue_and_notify(fd);
+        drop(inner);
+        for waker in waiters {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    pub fn delete(&self, fd: Fd) -> SysResult<()> {
+        let mut inner = self.inner.lock();
+        inner.items.remove(&fd).ok_or(SysError::ENOENT)?;
+        inner.ready.retain(|&e| e != fd);
+        Ok(())
+    }
+
+    /// Poll items in the ready list and return at most `maxevents` events.
+    ///
+    /// Only items in the ready list are polled, an item that is not ready
+    /// registers its own waker to the file, which puts it back to the ready
+    /// list when the file wakes it up.
+    ///
+    /// If `consume` is false, the state of items will not be changed, this is
+    /// used when the epoll file itself is polled.
+    fn scan(&self, maxevents: usize, consume: bool) -> Vec<EpollEvent> {
+        let polls = {
+            let mut inner = self.inner.lock();
+            inner.woken = false;
+            let ready = mem::take(&mut inner.ready);
+            let mut polls = Vec::with_capacity(ready.len());
+            for fd in ready {
+                let Some(item) = inner.items.get_mut(&fd) else {
+                    continue;
+                };
+                item.queued = false;
+                match item.file.upgrade() {
+                    Some(file) if !item.disabled => {
+                        let events = item.event.events.to_poll_events();
+                        polls.push((fd, file, events, item.waker.clone()));
+                    }
+                    Some(_) => {}
+                    None => {
+                        inner.items.remove(&fd);
+                    }
+                }
+            }
+            polls
+        };
+
+        // NOTE: files are polled without holding the lock since they may wake
+        // the item wakers synchronously.
+        let polls: Vec<(Fd, PollEvents)> = polls
+            .into_iter()
+            .map(|(fd, file, events, waker)| {
+                let mut cx = Context::from_waker(&waker);
+                let mut future = file.poll(events);
+                let revents = match unsafe { Pin::new_unchecked(&mut future) }.poll(&mut cx) {
+                    Poll::Ready(revents) => revents,
+                    Poll::Pending => PollEvents::empty(),
+                };
+                (fd, revents & (events | PollEvents::ERR | PollEvents::HUP))
+            })
+            .collect();
+
+        let mut inner = self.inner.lock();
+        let mut ret = Vec::new();
+        for (fd, revents) in polls {
+            let Some(item) = inner.items.get_mut(&fd) else {
+                continue;
+            };
+            if revents.is_empty() {
+                // The waker has been registered to the file.
+                continue;
+            }
+            if ret.len() >= maxevents {
+                inner.enqueue(fd);
+                continue;
+            }
+            // NOTE: a file only registers the waker when it is not ready, so
+            // no wakeup comes for new data arriving at a file that is still
+            // ready, nor for a file drained and refilled between two waits.
+            // Hence an edge-triggered item that is still ready is reported
+            // again rather than risking a missed edge, which is harmless as
+            // users consume the file until `EAGAIN` anyway.
+            ret.push(EpollEvent {
+                events: EpollEvents::from_poll_events(revents),
+                data: item.event.data,
+            });
+            if !consume {
+                inner.enqueue(fd);
+                continue;
+            }
+            if item.event.events.contains(EpollEvents::ONESHOT) {
+                item.disabled = true;
+            } else {
+                inner.enqueue(fd);
+            }
+        }
+        ret
+    }
+
+    /// Return ready events without blocking.
+    pub fn try_wait(&self, maxevents: usize) -> Vec<EpollEvent> {
+        self.scan(maxevents, true)
+    }
+}
+
+/// Wait until there are events ready in the epoll instance.
+pub struct EpollWaitFuture {
+    epoll: Arc<EpollFile>,
+    maxevents: usize,
+}
+
+impl EpollWaitFuture {
+    pub fn new(epoll: Arc<EpollFile>, maxevents: usize) -> Self {
+        Self { epoll, maxevents }
+    }
+}
+
+impl Future for EpollWaitFuture {
+    type Output = Vec<EpollEvent>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let events = self.epoll.try_wait(self.maxevents);
+            if !events.is_empty() {
+                return Poll::Ready(events);
+            }
+            let mut inner = self.epoll.inner.lock();
+            // Some items are woken while scanning.
+            if inner.woken {
+                continue;
+            }
+            inner.add_waiter(cx.waker());
+            return Poll::Pending;
+        }
+    }
+}
+
+#[async_trait]
+impl File for EpollFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if !events.contains(PollEvents::IN) {
+            return res;
+        }
+        if !self.scan(1, false).is_empty() {
+            res |= PollEvents::IN;
+        } else {
+            self.inner.lock().add_waiter(&waker);
+        }
+        res
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+use core::{
+    future::Future,
+    mem::{self, size_of},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{arc_zero, File, FileMeta, OpenFlags, PollEvents};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+bitflags::bitflags! {
+    // Defined in <sys/eventfd.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct EventFdFlags: i32 {
+        /// Provide semaphore-like semantics for reads from the new file
+        /// descriptor.
+        const SEMAPHORE = 1;
+        const NONBLOCK = OpenFlags::O_NONBLOCK.bits();
+        const CLOEXEC = OpenFlags::O_CLOEXEC.bits();
+    }
+}
+
+/// The maximum value of the counter.
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+pub struct EventFdFile {
+    meta: FileMeta,
+    inner: Mutex<EventFdInner>,
+}
+
+struct EventFdInner {
+    count: u64,
+    semaphore: bool,
+    read_waker: VecDeque<Waker>,
+    write_waker: VecDeque<Waker>,
+}
+
+impl EventFdFile {
+    pub fn new(initval: u64, flags: EventFdFlags) -> Arc<Self> {
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        let mut open_flags = OpenFlags::O_RDWR;
+        if flags.contains(EventFdFlags::NONBLOCK) {
+            open_flags |= OpenFlags::O_NONBLOCK;
+        }
+        *meta.flags.lock() = open_flags;
+        let inner = Mutex::new(EventFdInner {
+            count: initval,
+            semaphore: flags.contains(EventFdFlags::SEMAPHORE),
+            read_waker: VecDeque::new(),
+            write_waker: VecDeque::new(),
+        });
+        Arc::new(Self { meta, inner })
+    }
+
+    fn is_nonblock(&self) -> bool {
+        self.flags().contains(OpenFlags::O_NONBLOCK)
+    }
+
+    /// Add `value` to the counter from the kernel, e.g. to notify the
+    /// completion of AIO requests. It never blocks, and the counter saturates
+    /// at the maximum instead.
+    pub fn signal(&self, value: u64) {
+        let mut inner = self.inner.lock();
+        inner.count = inner.count.saturating_add(value).min(EVENTFD_MAX);
+        if inner.count > 0 {
+            while let Some(waker) = inner.read_waker.pop_front() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct EventFdReadFuture<'a> {
+    eventfd: &'a EventFdFile,
+}
+
+impl Future for EventFdReadFuture<'_> {
+    type Output = SysResult<u64>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.eventfd.inner.lock();
+        if inner.count == 0 {
+            if self.eventfd.is_nonblock() {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.read_waker.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let value = if inner.semaphore {
+            inner.count -= 1;
+            1
+        } else {
+            mem::take(&mut inner.count)
+        };
+        while let Some(waker) = inner.write_waker.pop_front() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(value))
+    }
+}
+
+struct EventFdWriteFuture<'a> {
+    eventfd: &'a EventFdFile,
+    value: u64,
+}
+
+impl Future for EventFdWriteFuture<'_> {
+    type Output = SysResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.eventfd.inner.lock();
+        if EVENTFD_MAX - inner.count < self.value {
+            if self.eventfd.is_nonblock() {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.write_waker.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        inner.count += self.value;
+        if inner.count > 0 {
+            while let Some(waker) = inner.read_waker.pop_front() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[async_trait]
+impl File for EventFdFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// Each successful read(2) returns an 8-byte integer. If the eventfd
+    /// counter is zero, the call either blocks until the counter becomes
+    /// nonzero, or fails with the error EAGAIN if the file descriptor has been
+    /// made nonblocking.
+    async fn read_at(&self, _offset: usize, buf: &mut [u8]) -> SysResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(SysError::EINVAL);
+        }
+        let value = EventFdReadFuture { eventfd: self }.await?;
+        log::info!("[EventFdFile::read_at] read value {value}");
+        buf[..size_of::<u64>()].copy_from_slice(&value.to_ne_bytes());
+        Ok(size_of::<u64>())
+    }
+
+    /// A write(2) call adds the 8-byte integer value supplied in its buffer to
+    /// the counter. The maximum value that may be stored in the counter is
+    /// 0xfffffffffffffffe. If the addition would cause the counter to exceed
+    /// the maximum, then the write(2) either blocks until a read(2) is
+    /// performed, or fails with the error EAGAIN if the file descriptor has
+    /// been made nonblocking.
+    async fn write_at(&self, _offset: usize, buf: &[u8]) -> SysResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(SysError::EINVAL);
+        }
+        let value = u64::from_ne_bytes(buf[..size_of::<u64>()].try_into().unwrap());
+        if value == u64::MAX {
+            return Err(SysError::EINVAL);
+        }
+        log::info!("[EventFdFile::write_at] write value {value}");
+        EventFdWriteFuture {
+            eventfd: self,
+            value,
+        }
+        .await?;
+        Ok(size_of::<u64>())
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut inner = self.inner.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if inner.count > 0 {
+                res |= PollEvents::IN;
+            } else {
+                inner.read_waker.push_back(waker.clone());
+            }
+        }
+        if events.contains(PollEvents::OUT) {
+            if inner.count < EVENTFD_MAX {
+                res |= PollEvents::OUT;
+            } else {
+                inner.write_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
@@ -3,27 +3,38 @@
 #![feature(format_args_nl)]
 
 pub mod devfs;
+pub mod epoll;
+pub mod eventfd;
 pub mod fd_table;
+pub mod memfd;
+pub mod mqueue;
 pub mod pipefs;
 pub mod procfs;
+pub mod reclaim;
 pub mod simplefs;
 pub mod sockfs;
+pub mod timerfd;
 mod tmpfs;
+pub mod writeback;
 
 extern crate alloc;
 
-use alloc::{collections::BTreeMap, string::String, sync::Arc};
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
 
 use driver::BLOCK_DEVICE;
-use memory::FrameReleaseIf;
+use memory::reclaim::register_shrinker;
 use procfs::init_procfs;
 use sockfs::SockFsType;
 use spin::Once;
 use sync::mutex::SpinNoIrqLock;
-use vfs_core::{Dentry, DentryState, FileSystemType, InodeMode, MountFlags, OpenFlags, Path};
+use vfs_core::{
+    reclaim::{DentryShrinker, PageCacheShrinker},
+    Dentry, DentryState, FileSystemType, InodeMode, MountFlags, SuperBlock,
+};
 
 use crate::{
     devfs::{init_devfs, DevFsType},
+    mqueue::{init_mqueue, MqueueFsType},
     procfs::ProcFsType,
     tmpfs::TmpFsType,
 };
@@ -57,6 +68,9 @@ fn register_all_fs() {
     let sockfs = SockFsType::new();
     FS_MANAGER.lock().insert(sockfs.name_string(), sockfs);
 
+    let mqueue = MqueueFsType::new();
+    FS_MANAGER.lock().insert(mqueue.name_string(), mqueue);
+
     log::info!("[vfs] register fs success");
 }
 
@@ -94,7 +108,14 @@ pub fn init() {
         .mount("dev", Some(diskfs_root.clone()), MountFlags::empty(), None)
         .unwrap();
     devfs_dentry.set_state(DentryState::Sync);
-    init_devfs(devfs_dentry).unwrap();
+    init_devfs(devfs_dentry.clone()).unwrap();
+
+    let mqueue = FS_MANAGER.lock().get("mqueue").unwrap().clone();
+    let mqueue_dentry = mqueue
+        .mount("mqueue", Some(devfs_dentry), MountFlags::empty(), None)
+        .unwrap();
+    mqueue_dentry.set_state(DentryState::Sync);
+    init_mqueue(mqueue_dentry);
 
     let procfs = FS_MANAGER.lock().get("procfs").unwrap().clone();
     let procfs_dentry = procfs
@@ -118,27 +139,23 @@ pub fn init() {
     SYS_ROOT_DENTRY.call_once(|| diskfs_root);
 
     sys_root_dentry().open().unwrap().load_dir().unwrap();
+
+    register_shrinker(Arc::new(PageCacheShrinker));
+    register_shrinker(Arc::new(DentryShrinker::new(sys_root_dentry())));
 }
 
 pub fn sys_root_dentry() -> Arc<dyn Dentry> {
     SYS_ROOT_DENTRY.get().unwrap().clone()
 }
 
-struct FrameReleaseIfImpl;
-
-#[crate_interface::impl_interface]
-impl FrameReleaseIf for FrameReleaseIfImpl {
-    fn release_frames() {
-        let ltp_dentry = Path::new(sys_root_dentry(), sys_root_dentry(), "/ltp/testcases/bin/")
-            .walk(OpenFlags::empty())
-            .unwrap();
-        for (_, child) in ltp_dentry.children() {
-            if let Ok(inode) = child.inode() {
-                if let Some(page_cache) = inode.page_cache() {
-                    page_cache.clear();
-                    inode.set_state(vfs_core::InodeState::UnInit)
-                }
-            }
-        }
-    }
+/// Super blocks of all mounted file systems.
+pub fn all_super_blocks() -> Vec<Arc<dyn SuperBlock>> {
+    FS_MANAGER
+        .lock()
+        .values()
+        .flat_map(|fs_type| {
+            let supers = fs_type.meta().supers.lock();
+            supers.values().cloned().collect::<Vec<_>>()
+        })
+        .collect()
 }
@@ -0,0 +1,272 @@
+//! Anonymous memory-backed files created by `memfd_create`.
+//!
+//! Data lives only in the page cache of the inode, like a file on tmpfs that
+//! has been unlinked. Seals added by `fcntl(F_ADD_SEALS)` restrict further
+//! modifications of the file.
+
+use alloc::{boxed::Box, sync::Arc};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use config::mm::{align_offset_to_page, round_up_to_page, PAGE_SIZE};
+use page::{Page, PageCache};
+use spin::Once;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult, SyscallResult};
+use vfs_core::{
+    arc_zero, File, FileMeta, Inode, InodeMeta, InodeMode, InodeState, OpenFlags, Stat, SuperBlock,
+};
+
+use crate::{simplefs::file::SimpleFileFile, tmpfs::TmpSuperBlock, FS_MANAGER};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// Max length of the name passed to `memfd_create`, without the terminating
+/// null byte.
+pub const MFD_NAME_MAX_LEN: usize = 249;
+
+bitflags::bitflags! {
+    // Defined in <linux/memfd.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MemfdFlags: u32 {
+        /// Set the close-on-exec flag on the new file descriptor.
+        const CLOEXEC = 0x1;
+        /// Allow sealing operations on the file.
+        const ALLOW_SEALING = 0x2;
+        /// Create the file in the hugetlbfs, which is not supported.
+        const HUGETLB = 0x4;
+    }
+
+    // Defined in <linux/fcntl.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SealFlags: u32 {
+        /// Prevent further seals from being set.
+        const SEAL = 0x1;
+        /// Prevent the file from shrinking.
+        const SHRINK = 0x2;
+        /// Prevent the file from growing.
+        const GROW = 0x4;
+        /// Prevent writes to the file.
+        const WRITE = 0x8;
+        /// Prevent future writes while existing mappings are still writable.
+        const FUTURE_WRITE = 0x10;
+    }
+}
+
+/// Super block of all memfds, like the internal tmpfs mount of Linux, which
+/// is not mounted anywhere.
+static MEMFD_SUPER_BLOCK: Once<Arc<dyn SuperBlock>> = Once::new();
+
+fn memfd_super_block() -> Arc<dyn SuperBlock> {
+    MEMFD_SUPER_BLOCK
+        .call_once(|| {
+            let tmpfs = FS_MANAGER.lock().get("tmpfs").unwrap().clone();
+            TmpSuperBlock::new(None, tmpfs)
+        })
+        .clone()
+}
+
+pub struct MemfdInode {
+    meta: InodeMeta,
+    seals: Mutex<SealFlags>,
+    /// Count of writable shared mappings of the file in all processes.
+    write_mappings: AtomicUsize,
+}
+
+impl MemfdInode {
+    pub fn new(allow_sealing: bool) -> Arc<Self> {
+        let mode = InodeMode::FILE | InodeMode::OWNER_MASK;
+        let mut meta = InodeMeta::new(mode, memfd_super_block(), 0);
+        meta.page_cache = Some(PageCache::new());
+        // Data is never written back, the same as files on tmpfs.
+        meta.inner.lock().state = InodeState::Removed;
+        // Files that do not allow sealing have `F_SEAL_SEAL` set initially.
+        let seals = if allow_sealing {
+            SealFlags::empty()
+        } else {
+            SealFlags::SEAL
+        };
+        Arc::new(Self {
+            meta,
+            seals: Mutex::new(seals),
+            write_mappings: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn seals(&self) -> SealFlags {
+        *self.seals.lock()
+    }
+
+    /// Add `seals` to the set of seals of the file. `F_SEAL_WRITE` can not be
+    /// added while the file is mapped shared and writable.
+    pub fn add_seals(&self, seals: SealFlags) -> SysResult<()> {
+        let mut cur = self.seals.lock();
+        if cur.contains(SealFlags::SEAL) {
+            return Err(SysError::EPERM);
+        }
+        if seals.contains(SealFlags::WRITE) && self.write_mappings.load(Ordering::Relaxed) > 0 {
+            return Err(SysError::EBUSY);
+        }
+        cur.insert(seals);
+        Ok(())
+    }
+
+    /// Account a new writable shared mapping of the file, which fails with
+    /// `EPERM` if the file is sealed against writing.
+    pub fn map_writable(self: &Arc<Self>) -> SysResult<MemfdWriteMapping> {
+        let seals = self.seals.lock();
+        if seals.intersects(SealFlags::WRITE | SealFlags::FUTURE_WRITE) {
+            return Err(SysError::EPERM);
+        }
+        self.write_mappings.fetch_add(1, Ordering::Relaxed);
+        Ok(MemfdWriteMapping(self.clone()))
+    }
+
+    /// Whether the file can not be written, through both `write` and writable
+    /// shared mappings.
+    pub fn is_write_sealed(&self) -> bool {
+        self.seals()
+            .intersects(SealFlags::WRITE | SealFlags::FUTURE_WRITE)
+    }
+
+    fn page_cache(&self) -> &PageCache {
+        self.meta.page_cache.as_ref().unwrap()
+    }
+}
+
+impl Inode for MemfdInode {
+    fn meta(&self) -> &InodeMeta {
+        &self.meta
+    }
+
+    fn get_attr(&self) -> SysResult<Stat> {
+        let inner = self.meta.inner.lock();
+        let len = inner.size;
+        Ok(Stat {
+            st_dev: 0,
+            st_ino: self.meta.ino as u64,
+            st_mode: self.meta.mode.bits(),
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: len as u64,
+            st_blksize: PAGE_SIZE as u32,
+            __pad2: 0,
+            st_blocks: (round_up_to_page(len) / 512) as u64,
+            st_atime: inner.atime,
+            st_mtime: inner.mtime,
+            st_ctime: inner.ctime,
+            unused: 0,
+        })
+    }
+
+    fn base_truncate(&self, len: usize) -> SysResult<()> {
+        let size = self.size();
+        let seals = self.seals();
+        if (len < size && seals.contains(SealFlags::SHRINK))
+            || (len > size && seals.contains(SealFlags::GROW))
+        {
+            return Err(SysError::EPERM);
+        }
+        let page_cache = self.page_cache();
+        if len < size {
+            // Zero the tail of the last page so that it reads as zero if the
+            // file grows again.
+            let (offset_aligned, offset_in_page) = align_offset_to_page(len);
+            if offset_in_page != 0 {
+                if let Some(page) = page_cache.get_page(offset_aligned) {
+                    page.bytes_array_range(offset_in_page..PAGE_SIZE).fill(0);
+                }
+            }
+            page_cache.truncate(round_up_to_page(len));
+        } else {
+            for offset_aligned in (round_up_to_page(size)..len).step_by(PAGE_SIZE) {
+                let page = Page::new();
+                page.fill_zero();
+                page_cache.insert_page(offset_aligned, page);
+            }
+        }
+        self.set_size(len);
+        Ok(())
+    }
+}
+
+/// A writable shared mapping of a memfd, which keeps `F_SEAL_WRITE` from
+/// being added until dropped. It is cloned along with the mapping, e.g. on
+/// fork.
+pub struct MemfdWriteMapping(Arc<MemfdInode>);
+
+impl Clone for MemfdWriteMapping {
+    fn clone(&self) -> Self {
+        self.0.write_mappings.fetch_add(1, Ordering::Relaxed);
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for MemfdWriteMapping {
+    fn drop(&mut self) {
+        self.0.write_mappings.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct MemfdFile {
+    meta: FileMeta,
+    /// Reads and writes go through the page cache the same way as files on
+    /// tmpfs.
+    file: Arc<SimpleFileFile>,
+    memfd: Arc<MemfdInode>,
+}
+
+impl MemfdFile {
+    pub fn new(flags: MemfdFlags) -> Arc<Self> {
+        let memfd = MemfdInode::new(flags.contains(MemfdFlags::ALLOW_SEALING));
+        let meta = FileMeta::new(arc_zero(), memfd.clone());
+        *meta.flags.lock() = OpenFlags::O_RDWR;
+        Arc::new(Self {
+            meta,
+            file: SimpleFileFile::new(arc_zero(), memfd.clone()),
+            memfd,
+        })
+    }
+
+    pub fn memfd(&self) -> &Arc<MemfdInode> {
+        &self.memfd
+    }
+}
+
+#[async_trait]
+impl File for MemfdFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    async fn read_at(&self, offset: usize, buf: &mut [u8]) -> SyscallResult {
+        self.file.read_at(offset, buf).await
+    }
+
+    async fn write_at(&self, offset: usize, buf: &[u8]) -> SyscallResult {
+        if self.memfd.is_write_sealed() {
+            return Err(SysError::EPERM);
+        }
+        let end = offset + buf.len();
+        if end > self.size() {
+            // Grow the file with zero pages first, which also fills the hole
+            // between the end of the file and `offset`.
+            self.memfd.base_truncate(end)?;
+        }
+        // The dentry of a memfd is a dummy one, which must not be notified.
+        self.file.write_pages(offset, buf)
+    }
+
+    /// Pages within the file are always present since they are allocated
+    /// when the file grows.
+    async fn get_page_at(&self, offset_aligned: usize) -> SysResult<Option<Arc<Page>>> {
+        self.file.get_page_at(offset_aligned).await
+    }
+
+    fn flush(&self) -> SysResult<usize> {
+        Ok(0)
+    }
+}
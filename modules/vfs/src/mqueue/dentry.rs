@@ -0,0 +1,72 @@
+use alloc::sync::Arc;
+
+use systype::{SysError, SysResult};
+use vfs_core::{Dentry, DentryMeta, File, Inode, InodeMode, InodeType, SuperBlock};
+
+use super::{file::MqueueFile, inode::MqueueInode};
+use crate::simplefs::file::SimpleDirFile;
+
+pub struct MqueueDentry {
+    meta: DentryMeta,
+}
+
+impl MqueueDentry {
+    pub fn new(
+        name: &str,
+        super_block: Arc<dyn SuperBlock>,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            meta: DentryMeta::new(name, super_block, parent),
+        })
+    }
+
+    pub fn into_dyn(self: Arc<Self>) -> Arc<dyn Dentry> {
+        self.clone()
+    }
+}
+
+impl Dentry for MqueueDentry {
+    fn meta(&self) -> &DentryMeta {
+        &self.meta
+    }
+
+    fn base_open(self: Arc<Self>) -> SysResult<Arc<dyn File>> {
+        let inode = self.inode()?;
+        match inode.itype() {
+            InodeType::Dir => Ok(SimpleDirFile::new(self.clone(), inode)),
+            InodeType::File => {
+                let mqueue = inode
+                    .downcast_arc::<MqueueInode>()
+                    .map_err(|_| SysError::EINVAL)?;
+                Ok(MqueueFile::new(self.clone(), mqueue))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn base_lookup(self: Arc<Self>, name: &str) -> SysResult<Arc<dyn Dentry>> {
+        let sub_dentry = self.into_dyn().get_child_or_create(name);
+        Ok(sub_dentry)
+    }
+
+    /// Message queues created by open(2) get the default attributes.
+    fn base_create(self: Arc<Self>, name: &str, mode: InodeMode) -> SysResult<Arc<dyn Dentry>> {
+        if !mode.to_type().is_file() {
+            return Err(SysError::EPERM);
+        }
+        let sb = self.super_block();
+        let sub_dentry = self.into_dyn().get_child_or_create(name);
+        let sub_inode: Arc<dyn Inode> = MqueueInode::new(mode, sb);
+        sub_dentry.set_inode(sub_inode);
+        Ok(sub_dentry)
+    }
+
+    fn base_unlink(self: Arc<Self>, name: &str) -> SysResult<()> {
+        self.remove_child(name).ok_or(SysError::ENOENT).map(|_| ())
+    }
+
+    fn base_new_child(self: Arc<Self>, name: &str) -> Arc<dyn Dentry> {
+        Self::new(name, self.super_block(), Some(self))
+    }
+}
@@ -0,0 +1,117 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+
+use async_trait::async_trait;
+use async_utils::get_waker;
+use systype::{SysError, SysResult};
+use vfs_core::{Dentry, File, FileMeta, OpenFlags, PollEvents};
+
+use super::inode::{MqAttr, MqRecvFuture, MqSendFuture, MqueueInode};
+
+pub struct MqueueFile {
+    meta: FileMeta,
+    mqueue: Arc<MqueueInode>,
+}
+
+impl MqueueFile {
+    pub fn new(dentry: Arc<dyn Dentry>, mqueue: Arc<MqueueInode>) -> Arc<Self> {
+        Arc::new(Self {
+            meta: FileMeta::new(dentry, mqueue.clone()),
+            mqueue,
+        })
+    }
+
+    pub fn mqueue(&self) -> &Arc<MqueueInode> {
+        &self.mqueue
+    }
+
+    fn is_nonblock(&self) -> bool {
+        self.flags().contains(OpenFlags::O_NONBLOCK)
+    }
+
+    /// Return a future adding the message to the queue, `pid` is the pid of
+    /// the sender.
+    pub fn send(&self, msg: Vec<u8>, prio: u32, pid: usize) -> SysResult<MqSendFuture> {
+        if msg.len() > self.mqueue.msgsize() {
+            return Err(SysError::EMSGSIZE);
+        }
+        Ok(MqSendFuture::new(
+            self.mqueue.clone(),
+            msg,
+            prio,
+            self.is_nonblock(),
+            pid,
+        ))
+    }
+
+    /// Return a future removing the oldest message with the highest priority
+    /// from the queue, `len` is the size of the buffer provided by the
+    /// receiver.
+    pub fn receive(&self, len: usize) -> SysResult<MqRecvFuture> {
+        if len < self.mqueue.msgsize() {
+            return Err(SysError::EMSGSIZE);
+        }
+        Ok(MqRecvFuture::new(self.mqueue.clone(), self.is_nonblock()))
+    }
+
+    pub fn getattr(&self) -> MqAttr {
+        let mut attr = self.mqueue.attr();
+        if self.is_nonblock() {
+            attr.mq_flags = OpenFlags::O_NONBLOCK.bits() as isize;
+        }
+        attr
+    }
+
+    /// Only `O_NONBLOCK` in `mq_flags` can be changed, other fields are
+    /// ignored.
+    pub fn setattr(&self, attr: &MqAttr) {
+        let mut flags = self.flags();
+        flags.set(
+            OpenFlags::O_NONBLOCK,
+            attr.mq_flags & OpenFlags::O_NONBLOCK.bits() as isize != 0,
+        );
+        self.set_flags(flags);
+    }
+}
+
+#[async_trait]
+impl File for MqueueFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// Reading the file returns the status of the queue.
+    async fn read_at(&self, offset: usize, buf: &mut [u8]) -> SysResult<usize> {
+        let status = self.mqueue.status();
+        let status = status.as_bytes();
+        if offset >= status.len() {
+            return Ok(0);
+        }
+        let len = buf.len().min(status.len() - offset);
+        buf[..len].copy_from_slice(&status[offset..offset + len]);
+        Ok(len)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if self.mqueue.is_readable() {
+                res |= PollEvents::IN;
+            } else {
+                self.mqueue.register_recv_waker(waker.clone());
+            }
+        }
+        if events.contains(PollEvents::OUT) {
+            if self.mqueue.is_writable() {
+                res |= PollEvents::OUT;
+            } else {
+                self.mqueue.register_send_waker(waker);
+            }
+        }
+        res
+    }
+}
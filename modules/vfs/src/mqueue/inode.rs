@@ -0,0 +1,340 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    format,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use vfs_core::{Inode, InodeMeta, InodeMode, Stat, SuperBlock};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// Default max number of messages in a queue.
+const MQ_MAXMSG_DEFAULT: usize = 10;
+/// Default max size of a message.
+const MQ_MSGSIZE_DEFAULT: usize = 8192;
+/// Upper limit of `mq_maxmsg`.
+const MQ_MAXMSG_MAX: usize = 65536;
+/// Upper limit of `mq_msgsize`.
+const MQ_MSGSIZE_MAX: usize = 16 * 1024 * 1024;
+/// Messages should have a priority less than this value.
+pub const MQ_PRIO_MAX: u32 = 32768;
+
+/// `struct mq_attr`.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct MqAttr {
+    /// Flags: 0 or O_NONBLOCK
+    pub mq_flags: isize,
+    /// Max. # of messages on queue
+    pub mq_maxmsg: isize,
+    /// Max. message size (bytes)
+    pub mq_msgsize: isize,
+    /// # of messages currently in queue
+    pub mq_curmsgs: isize,
+    __reserved: [isize; 4],
+}
+
+/// Registration of `mq_notify`.
+pub struct MqNotify {
+    /// Pid of the process registered for notification.
+    pub pid: usize,
+    /// Signal to be delivered, zero for `SIGEV_NONE`.
+    pub signo: i32,
+    /// Called with the pid of the sender when a message arrives on the empty
+    /// queue, `None` for `SIGEV_NONE`.
+    pub callback: Option<Box<dyn FnOnce(usize) + Send + Sync>>,
+}
+
+pub struct MqueueInode {
+    meta: InodeMeta,
+    inner: Mutex<MqueueInner>,
+}
+
+struct MqueueInner {
+    maxmsg: usize,
+    msgsize: usize,
+    /// Messages indexed by priority, messages with the same priority are
+    /// received in FIFO order.
+    msgs: BTreeMap<u32, VecDeque<Vec<u8>>>,
+    curmsgs: usize,
+    /// Number of bytes of all messages in the queue.
+    qsize: usize,
+    /// Number of tasks blocked in `mq_timedreceive`.
+    receivers: usize,
+    notify: Option<MqNotify>,
+    send_wakers: VecDeque<Waker>,
+    recv_wakers: VecDeque<Waker>,
+}
+
+impl MqueueInode {
+    pub fn new(mode: InodeMode, super_block: Arc<dyn SuperBlock>) -> Arc<Self> {
+        Arc::new(Self {
+            meta: InodeMeta::new(mode, super_block, 0),
+            inner: Mutex::new(MqueueInner {
+                maxmsg: MQ_MAXMSG_DEFAULT,
+                msgsize: MQ_MSGSIZE_DEFAULT,
+                msgs: BTreeMap::new(),
+                curmsgs: 0,
+                qsize: 0,
+                receivers: 0,
+                notify: None,
+                send_wakers: VecDeque::new(),
+                recv_wakers: VecDeque::new(),
+            }),
+        })
+    }
+
+    /// Set `mq_maxmsg` and `mq_msgsize` of a newly created queue.
+    pub fn set_limits(&self, attr: &MqAttr) -> SysResult<()> {
+        if attr.mq_maxmsg <= 0 || attr.mq_msgsize <= 0 {
+            return Err(SysError::EINVAL);
+        }
+        let (maxmsg, msgsize) = (attr.mq_maxmsg as usize, attr.mq_msgsize as usize);
+        if maxmsg > MQ_MAXMSG_MAX || msgsize > MQ_MSGSIZE_MAX {
+            return Err(SysError::EINVAL);
+        }
+        let mut inner = self.inner.lock();
+        inner.maxmsg = maxmsg;
+        inner.msgsize = msgsize;
+        Ok(())
+    }
+
+    /// Return the attributes of the queue, `mq_flags` is left zero since it
+    /// belongs to the open file description.
+    pub fn attr(&self) -> MqAttr {
+        let inner = self.inner.lock();
+        MqAttr {
+            mq_maxmsg: inner.maxmsg as isize,
+            mq_msgsize: inner.msgsize as isize,
+            mq_curmsgs: inner.curmsgs as isize,
+            ..Default::default()
+        }
+    }
+
+    pub fn msgsize(&self) -> usize {
+        self.inner.lock().msgsize
+    }
+
+    /// Register or unregister the process with `pid` for notification.
+    pub fn set_notify(&self, pid: usize, notify: Option<MqNotify>) -> SysResult<()> {
+        let mut inner = self.inner.lock();
+        match notify {
+            Some(notify) => {
+                if inner.notify.is_some() {
+                    return Err(SysError::EBUSY);
+                }
+                inner.notify = Some(notify);
+            }
+            None => {
+                if inner
+                    .notify
+                    .as_ref()
+                    .is_some_and(|notify| notify.pid == pid)
+                {
+                    inner.notify = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the status of the queue read from the file, which is formatted
+    /// like Linux.
+    pub fn status(&self) -> String {
+        let inner = self.inner.lock();
+        let (notify, signo, pid) = match inner.notify.as_ref() {
+            // SIGEV_SIGNAL
+            Some(notify) if notify.callback.is_some() => (0, notify.signo, notify.pid),
+            // SIGEV_NONE
+            Some(notify) => (1, 0, notify.pid),
+            None => (0, 0, 0),
+        };
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            inner.qsize, notify, signo, pid
+        )
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.inner.lock().curmsgs > 0
+    }
+
+    pub fn is_writable(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.curmsgs < inner.maxmsg
+    }
+
+    pub fn register_recv_waker(&self, waker: Waker) {
+        self.inner.lock().recv_wakers.push_back(waker);
+    }
+
+    pub fn register_send_waker(&self, waker: Waker) {
+        self.inner.lock().send_wakers.push_back(waker);
+    }
+}
+
+impl Inode for MqueueInode {
+    fn meta(&self) -> &InodeMeta {
+        &self.meta
+    }
+
+    fn get_attr(&self) -> SysResult<Stat> {
+        let inner = self.meta.inner.lock();
+        let mode = self.meta.mode.bits();
+        Ok(Stat {
+            st_dev: 0,
+            st_ino: self.meta.ino as u64,
+            st_mode: mode,
+            st_nlink: inner.nlink as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: 512,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime: inner.atime,
+            st_mtime: inner.mtime,
+            st_ctime: inner.ctime,
+            unused: 0,
+        })
+    }
+}
+
+/// Send a message to the queue, waiting until the queue is not full unless
+/// `nonblock` is set.
+pub struct MqSendFuture {
+    mqueue: Arc<MqueueInode>,
+    msg: Option<Vec<u8>>,
+    prio: u32,
+    nonblock: bool,
+    pid: usize,
+}
+
+impl MqSendFuture {
+    pub fn new(
+        mqueue: Arc<MqueueInode>,
+        msg: Vec<u8>,
+        prio: u32,
+        nonblock: bool,
+        pid: usize,
+    ) -> Self {
+        Self {
+            mqueue,
+            msg: Some(msg),
+            prio,
+            nonblock,
+            pid,
+        }
+    }
+}
+
+impl Future for MqSendFuture {
+    type Output = SysResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut inner = this.mqueue.inner.lock();
+        if inner.curmsgs >= inner.maxmsg {
+            if this.nonblock {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.send_wakers.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let msg = this.msg.take().unwrap();
+        inner.qsize += msg.len();
+        inner.msgs.entry(this.prio).or_default().push_back(msg);
+        inner.curmsgs += 1;
+        // Notification occurs when a new message arrives on an empty queue
+        // and no task is blocked in `mq_timedreceive`, and then the
+        // registration is removed.
+        let notify = if inner.curmsgs == 1 && inner.receivers == 0 {
+            inner.notify.take()
+        } else {
+            None
+        };
+        for waker in inner.recv_wakers.drain(..) {
+            waker.wake();
+        }
+        drop(inner);
+        if let Some(callback) = notify.and_then(|notify| notify.callback) {
+            callback(this.pid);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receive the oldest message of the highest priority from the queue,
+/// waiting until the queue is not empty unless `nonblock` is set.
+pub struct MqRecvFuture {
+    mqueue: Arc<MqueueInode>,
+    nonblock: bool,
+    /// Whether this is counted in `receivers`.
+    waiting: bool,
+}
+
+impl MqRecvFuture {
+    pub fn new(mqueue: Arc<MqueueInode>, nonblock: bool) -> Self {
+        Self {
+            mqueue,
+            nonblock,
+            waiting: false,
+        }
+    }
+}
+
+impl Future for MqRecvFuture {
+    type Output = SysResult<(Vec<u8>, u32)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut inner = this.mqueue.inner.lock();
+        let Some(mut entry) = inner.msgs.last_entry() else {
+            if this.nonblock {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            if !this.waiting {
+                this.waiting = true;
+                inner.receivers += 1;
+            }
+            inner.recv_wakers.push_back(cx.waker().clone());
+            return Poll::Pending;
+        };
+        let prio = *entry.key();
+        let msg = entry.get_mut().pop_front().unwrap();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        inner.curmsgs -= 1;
+        inner.qsize -= msg.len();
+        if this.waiting {
+            this.waiting = false;
+            inner.receivers -= 1;
+        }
+        for waker in inner.send_wakers.drain(..) {
+            waker.wake();
+        }
+        Poll::Ready(Ok((msg, prio)))
+    }
+}
+
+impl Drop for MqRecvFuture {
+    fn drop(&mut self) {
+        // Interrupted by a signal or timeout.
+        if self.waiting {
+            self.mqueue.inner.lock().receivers -= 1;
+        }
+    }
+}
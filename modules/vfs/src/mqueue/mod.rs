@@ -0,0 +1,104 @@
+//! POSIX message queue file system.
+//!
+//! Each message queue is a regular file in the root directory of the file
+//! system, which is mounted at /dev/mqueue.
+
+use alloc::sync::Arc;
+
+use device_core::BlockDevice;
+use spin::Once;
+use systype::SysResult;
+use vfs_core::{
+    Dentry, FileSystemType, FileSystemTypeMeta, InodeMode, MountFlags, StatFs, SuperBlock,
+    SuperBlockMeta,
+};
+
+use self::dentry::MqueueDentry;
+use crate::simplefs::inode::SimpleDirInode;
+
+mod dentry;
+pub mod file;
+pub mod inode;
+
+pub use file::MqueueFile;
+pub use inode::{MqAttr, MqNotify, MqueueInode, MQ_PRIO_MAX};
+
+/// Root of the message queues used by `mq_open` and `mq_unlink`.
+static MQUEUE_ROOT: Once<Arc<dyn Dentry>> = Once::new();
+
+pub fn init_mqueue(root_dentry: Arc<dyn Dentry>) {
+    MQUEUE_ROOT.call_once(|| root_dentry);
+}
+
+pub fn mqueue_root() -> Arc<dyn Dentry> {
+    MQUEUE_ROOT.get().unwrap().clone()
+}
+
+pub struct MqueueFsType {
+    meta: FileSystemTypeMeta,
+}
+
+impl MqueueFsType {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            meta: FileSystemTypeMeta::new("mqueue"),
+        })
+    }
+}
+
+impl FileSystemType for MqueueFsType {
+    fn meta(&self) -> &FileSystemTypeMeta {
+        &self.meta
+    }
+
+    fn base_mount(
+        self: Arc<Self>,
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+        _flags: MountFlags,
+        dev: Option<Arc<dyn BlockDevice>>,
+    ) -> SysResult<Arc<dyn Dentry>> {
+        let sb = MqueueSuperBlock::new(dev, self.clone());
+        let mount_dentry = MqueueDentry::new(name, sb.clone(), parent.clone());
+        let mount_inode = SimpleDirInode::new(InodeMode::DIR, sb.clone(), 0);
+        mount_dentry.set_inode(mount_inode.clone());
+        if let Some(parent) = parent {
+            parent.insert(mount_dentry.clone());
+        }
+        self.insert_sb(&mount_dentry.path(), sb);
+        Ok(mount_dentry)
+    }
+
+    fn kill_sb(&self, _sb: Arc<dyn SuperBlock>) -> SysResult<()> {
+        todo!()
+    }
+}
+
+pub struct MqueueSuperBlock {
+    meta: SuperBlockMeta,
+}
+
+impl MqueueSuperBlock {
+    pub fn new(
+        device: Option<Arc<dyn BlockDevice>>,
+        fs_type: Arc<dyn FileSystemType>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            meta: SuperBlockMeta::new(device, fs_type),
+        })
+    }
+}
+
+impl SuperBlock for MqueueSuperBlock {
+    fn meta(&self) -> &SuperBlockMeta {
+        &self.meta
+    }
+
+    fn stat_fs(&self) -> SysResult<StatFs> {
+        todo!()
+    }
+
+    fn sync_fs(&self, _wait: isize) -> SysResult<()> {
+        Ok(())
+    }
+}
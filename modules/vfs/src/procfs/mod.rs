@@ -1,5 +1,6 @@
 mod meminfo;
 mod mounts;
+mod oom;
 mod self_;
 
 use alloc::sync::Arc;
@@ -15,6 +16,7 @@ use vfs_core::{
 use self::{
     meminfo::{MemInfoDentry, MemInfoInode},
     mounts::{MountsDentry, MountsInode},
+    oom::{OomDentry, OomInode, OomKind},
     self_::{ExeDentry, ExeFile, ExeInode},
 };
 use crate::simplefs::{dentry::SimpleDentry, inode::SimpleDirInode};
@@ -66,6 +68,13 @@ pub fn init_procfs(root_dentry: Arc<dyn Dentry>) -> SysResult<()> {
     let exe_inode = ExeInode::new(root_dentry.super_block(), 0);
     exe_dentry.set_inode(exe_inode);
     self_dentry.insert(exe_dentry);
+    for kind in [OomKind::Score, OomKind::ScoreAdj] {
+        let oom_dentry: Arc<dyn Dentry> =
+            OomDentry::new(kind, root_dentry.super_block(), Some(self_dentry.clone()));
+        let oom_inode = OomInode::new(root_dentry.super_block(), 0);
+        oom_dentry.set_inode(oom_inode);
+        self_dentry.insert(oom_dentry);
+    }
 
     root_dentry.insert(self_dentry.clone());
 
@@ -137,6 +146,6 @@ impl SuperBlock for ProcSuperBlock {
     }
 
     fn sync_fs(&self, _wait: isize) -> SysResult<()> {
-        todo!()
+        Ok(())
     }
 }
@@ -0,0 +1,174 @@
+use alloc::{boxed::Box, format, string::String, sync::Arc};
+use core::cmp;
+
+use async_trait::async_trait;
+use crate_interface::call_interface;
+use systype::{SysError, SysResult, SyscallResult};
+use vfs_core::{
+    Dentry, DentryMeta, DirEntry, File, FileMeta, Inode, InodeMeta, InodeMode, Stat, SuperBlock,
+};
+
+use super::self_;
+
+/// Files under `/proc/self` about the out-of-memory killer.
+#[derive(Clone, Copy)]
+pub enum OomKind {
+    /// `oom_score`, badness of the process in [0, 1000], read only.
+    Score,
+    /// `oom_score_adj`, adjustment of the badness in [-1000, 1000].
+    ScoreAdj,
+}
+
+impl OomKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Score => "oom_score",
+            Self::ScoreAdj => "oom_score_adj",
+        }
+    }
+
+    fn serialize(self) -> String {
+        match self {
+            Self::Score => format!("{}\n", call_interface!(self_::KernelProcIf::oom_score())),
+            Self::ScoreAdj => format!(
+                "{}\n",
+                call_interface!(self_::KernelProcIf::oom_score_adj())
+            ),
+        }
+    }
+}
+
+pub struct OomDentry {
+    meta: DentryMeta,
+    kind: OomKind,
+}
+
+impl OomDentry {
+    pub fn new(
+        kind: OomKind,
+        super_block: Arc<dyn SuperBlock>,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            meta: DentryMeta::new(kind.name(), super_block, parent),
+            kind,
+        })
+    }
+}
+
+impl Dentry for OomDentry {
+    fn meta(&self) -> &DentryMeta {
+        &self.meta
+    }
+
+    fn base_open(self: Arc<Self>) -> SysResult<Arc<dyn File>> {
+        Ok(Arc::new(OomFile {
+            meta: FileMeta::new(self.clone(), self.inode()?),
+            kind: self.kind,
+        }))
+    }
+
+    fn base_lookup(self: Arc<Self>, _name: &str) -> SysResult<Arc<dyn Dentry>> {
+        Err(SysError::ENOTDIR)
+    }
+
+    fn base_create(self: Arc<Self>, _name: &str, _mode: InodeMode) -> SysResult<Arc<dyn Dentry>> {
+        Err(SysError::ENOTDIR)
+    }
+
+    fn base_unlink(self: Arc<Self>, _name: &str) -> SysResult<()> {
+        Err(SysError::ENOTDIR)
+    }
+}
+
+pub struct OomInode {
+    meta: InodeMeta,
+}
+
+impl OomInode {
+    pub fn new(super_block: Arc<dyn SuperBlock>, _size: usize) -> Arc<Self> {
+        Arc::new(Self {
+            meta: InodeMeta::new(InodeMode::FILE, super_block, 0),
+        })
+    }
+}
+
+impl Inode for OomInode {
+    fn meta(&self) -> &InodeMeta {
+        &self.meta
+    }
+
+    fn get_attr(&self) -> SysResult<Stat> {
+        let inner = self.meta.inner.lock();
+        let mode = self.meta.mode.bits();
+        let len = inner.size;
+        Ok(Stat {
+            st_dev: 0,
+            st_ino: self.meta.ino as u64,
+            st_mode: mode,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: len as u64,
+            st_blksize: 512,
+            __pad2: 0,
+            st_blocks: (len / 512) as u64,
+            st_atime: inner.atime,
+            st_mtime: inner.mtime,
+            st_ctime: inner.ctime,
+            unused: 0,
+        })
+    }
+
+    fn base_truncate(&self, _len: usize) -> SysResult<()> {
+        Ok(())
+    }
+}
+
+pub struct OomFile {
+    meta: FileMeta,
+    kind: OomKind,
+}
+
+#[async_trait]
+impl File for OomFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    async fn base_read_at(&self, offset: usize, buf: &mut [u8]) -> SyscallResult {
+        let info = self.kind.serialize();
+        if offset >= info.len() {
+            return Ok(0);
+        }
+        let len = cmp::min(info.len() - offset, buf.len());
+        buf[..len].copy_from_slice(&info.as_bytes()[offset..offset + len]);
+        Ok(len)
+    }
+
+    async fn base_write_at(&self, _offset: usize, buf: &[u8]) -> SyscallResult {
+        let OomKind::ScoreAdj = self.kind else {
+            return Err(SysError::EACCES);
+        };
+        let adj = core::str::from_utf8(buf)
+            .ok()
+            .and_then(|s| {
+                s.trim_matches(|c: char| c.is_whitespace() || c == '\0')
+                    .parse()
+                    .ok()
+            })
+            .ok_or(SysError::EINVAL)?;
+        call_interface!(self_::KernelProcIf::set_oom_score_adj(adj))?;
+        Ok(buf.len())
+    }
+
+    fn base_read_dir(&self) -> SysResult<Option<DirEntry>> {
+        Err(SysError::ENOTDIR)
+    }
+
+    fn flush(&self) -> SysResult<usize> {
+        Ok(0)
+    }
+}
@@ -11,6 +11,9 @@ use vfs_core::{
 #[crate_interface::def_interface]
 pub trait KernelProcIf {
     fn exe() -> alloc::string::String;
+    fn oom_score() -> usize;
+    fn oom_score_adj() -> i32;
+    fn set_oom_score_adj(adj: i32) -> SysResult<()>;
 }
 
 pub struct ExeDentry {
@@ -0,0 +1,26 @@
+//! Reclaim task which shrinks caches when free frames run low.
+
+use memory::{
+    free_frames,
+    reclaim::{high_watermark, reclaim_frames, LowMemoryFuture},
+};
+use timer::timelimited_task::ksleep_ms;
+
+/// Time to wait before trying again when nothing can be reclaimed.
+const RECLAIM_RETRY_MS: usize = 100;
+
+/// Wait for free frames to drop below the low watermark, and reclaim memory
+/// until they reach the high watermark, including by shrinkers unsafe for
+/// direct reclaim.
+pub async fn reclaim_task() {
+    loop {
+        LowMemoryFuture.await;
+        let free = free_frames();
+        log::info!("[reclaim_task] {free} free frames, start reclaim");
+        if reclaim_frames(high_watermark().saturating_sub(free), false) == 0 {
+            ksleep_ms(RECLAIM_RETRY_MS).await;
+        } else {
+            async_utils::yield_now().await;
+        }
+    }
+}
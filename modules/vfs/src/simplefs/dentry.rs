@@ -5,7 +5,7 @@ use vfs_core::{Dentry, DentryMeta, File, Inode, InodeMode, InodeType, SuperBlock
 
 use super::{
     file::{SimpleDirFile, SimpleFileFile},
-    inode::{SimpleDirInode, SimpleFileInode},
+    inode::{SimpleDirInode, SimpleFileInode, SimpleSockInode},
 };
 
 pub struct SimpleDentry {
@@ -38,7 +38,8 @@ impl Dentry for SimpleDentry {
         match inode.itype() {
             InodeType::Dir => Ok(SimpleDirFile::new(self.clone(), inode)),
             InodeType::File => Ok(SimpleFileFile::new(self.clone(), inode)),
-            InodeType::Socket => Ok(SimpleFileFile::new(self.clone(), inode)),
+            // Sockets bound to the path are reached through socket fds.
+            InodeType::Socket => Err(SysError::ENXIO),
             _ => unreachable!(),
         }
     }
@@ -54,6 +55,7 @@ impl Dentry for SimpleDentry {
         let sub_inode: Arc<dyn Inode> = match mode.to_type() {
             InodeType::Dir => SimpleDirInode::new(mode, sb, 0),
             InodeType::File => SimpleFileInode::new(mode, sb, 0),
+            InodeType::Socket => SimpleSockInode::new(mode, sb),
             _ => return Err(SysError::EPERM),
         };
         sub_dentry.set_inode(sub_inode);
@@ -4,7 +4,10 @@ use async_trait::async_trait;
 use config::mm::{align_offset_to_page, PAGE_SIZE};
 use page::Page;
 use systype::{SysError, SysResult, SyscallResult};
-use vfs_core::{Dentry, DirEntry, File, FileMeta, Inode};
+use vfs_core::{
+    inotify::{fsnotify_dentry, InotifyMask},
+    Dentry, DirEntry, File, FileMeta, Inode,
+};
 
 pub struct SimpleDirFile {
     meta: FileMeta,
@@ -55,6 +58,53 @@ impl SimpleFileFile {
             meta: FileMeta::new(dentry, inode),
         })
     }
+
+    /// Write `buf` into the page cache at `offset` and grow the file if
+    /// written beyond its end, without raising any inotify event.
+    pub fn write_pages(&self, offset: usize, buf: &[u8]) -> SyscallResult {
+        log::info!(
+            "[File::write] file {}, offset {offset}, buf len {}",
+            self.dentry().path(),
+            buf.len()
+        );
+
+        let inode = self.inode();
+
+        let page_cache = inode.page_cache().unwrap();
+        if offset > self.size() {
+            todo!("offset greater than size, will create hole");
+        }
+
+        let mut buf_it = buf;
+        let mut offset_it = offset;
+
+        while !buf_it.is_empty() {
+            let (offset_aligned, offset_in_page) = align_offset_to_page(offset_it);
+            let page = if let Some(page) = page_cache.get_page(offset_aligned) {
+                page
+            } else {
+                log::info!("[File::write_at] create new page");
+                let page = Page::new();
+                page_cache.insert_page(offset_aligned, page.clone());
+                page
+            };
+            let len = (buf_it.len()).min(PAGE_SIZE - offset_in_page);
+            page.bytes_array_range(offset_in_page..offset_in_page + len)
+                .copy_from_slice(&buf_it[0..len]);
+            log::trace!("[File::write] write count {len}, buf len {}", buf_it.len());
+            offset_it += len;
+            buf_it = &buf_it[len..];
+        }
+        if offset_it > self.size() {
+            log::warn!(
+                "[File::write_at] write beyond file, offset_it:{offset_it}, size:{}",
+                self.size()
+            );
+            let new_size = offset_it;
+            inode.set_size(new_size);
+        }
+        Ok(buf.len())
+    }
 }
 
 #[async_trait]
@@ -120,48 +170,9 @@ impl File for SimpleFileFile {
     }
 
     async fn write_at(&self, offset: usize, buf: &[u8]) -> SyscallResult {
-        log::info!(
-            "[File::write] file {}, offset {offset}, buf len {}",
-            self.dentry().path(),
-            buf.len()
-        );
-
-        let inode = self.inode();
-
-        let page_cache = inode.page_cache().unwrap();
-        if offset > self.size() {
-            todo!("offset greater than size, will create hole");
-        }
-
-        let mut buf_it = buf;
-        let mut offset_it = offset;
-
-        while !buf_it.is_empty() {
-            let (offset_aligned, offset_in_page) = align_offset_to_page(offset_it);
-            let page = if let Some(page) = page_cache.get_page(offset_aligned) {
-                page
-            } else {
-                log::info!("[File::write_at] create new page");
-                let page = Page::new();
-                page_cache.insert_page(offset_aligned, page.clone());
-                page
-            };
-            let len = (buf_it.len()).min(PAGE_SIZE - offset_in_page);
-            page.bytes_array_range(offset_in_page..offset_in_page + len)
-                .copy_from_slice(&buf_it[0..len]);
-            log::trace!("[File::write] write count {len}, buf len {}", buf_it.len());
-            offset_it += len;
-            buf_it = &buf_it[len..];
-        }
-        if offset_it > self.size() {
-            log::warn!(
-                "[File::write_at] write beyond file, offset_it:{offset_it}, size:{}",
-                self.size()
-            );
-            let new_size = offset_it;
-            inode.set_size(new_size);
-        }
-        Ok(buf.len())
+        let count = self.write_pages(offset, buf)?;
+        fsnotify_dentry(&self.dentry(), InotifyMask::MODIFY);
+        Ok(count)
     }
 
     async fn get_page_at(&self, offset_aligned: usize) -> SysResult<Option<Arc<Page>>> {
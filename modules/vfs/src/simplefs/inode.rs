@@ -1,12 +1,15 @@
-use alloc::sync::Arc;
+use alloc::{string::String, sync::Arc, vec::Vec};
 
 use config::mm::{round_up_to_page, PAGE_SIZE};
 use page::{Page, PageCache};
 use systype::SysResult;
-use vfs_core::{Inode, InodeMeta, InodeMode, InodeState, Stat, SuperBlock};
+use vfs_core::{
+    Dentry, Inode, InodeMeta, InodeMode, InodeState, Stat, SuperBlock, XattrFlags, XattrMap,
+};
 
 pub struct SimpleFileInode {
     meta: InodeMeta,
+    xattrs: XattrMap,
 }
 
 impl SimpleFileInode {
@@ -15,7 +18,10 @@ impl SimpleFileInode {
         let mut meta = InodeMeta::new(mode, super_block, size);
         meta.page_cache = Some(PageCache::new());
         meta.inner.lock().state = InodeState::Removed;
-        Arc::new(Self { meta })
+        Arc::new(Self {
+            meta,
+            xattrs: XattrMap::new(),
+        })
     }
 }
 
@@ -65,10 +71,33 @@ impl Inode for SimpleFileInode {
             Ok(())
         }
     }
+
+    fn base_getxattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<Vec<u8>> {
+        self.xattrs.get(name)
+    }
+
+    fn base_setxattr(
+        &self,
+        _dentry: &dyn Dentry,
+        name: &str,
+        value: &[u8],
+        flags: XattrFlags,
+    ) -> SysResult<()> {
+        self.xattrs.set(name, value, flags)
+    }
+
+    fn base_listxattr(&self, _dentry: &dyn Dentry) -> SysResult<Vec<String>> {
+        Ok(self.xattrs.list())
+    }
+
+    fn base_removexattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<()> {
+        self.xattrs.remove(name)
+    }
 }
 
 pub struct SimpleDirInode {
     meta: InodeMeta,
+    xattrs: XattrMap,
 }
 
 impl SimpleDirInode {
@@ -76,6 +105,7 @@ impl SimpleDirInode {
         debug_assert!(mode.to_type().is_dir());
         Arc::new(Self {
             meta: InodeMeta::new(mode, super_block, size),
+            xattrs: XattrMap::new(),
         })
     }
 }
@@ -108,10 +138,33 @@ impl Inode for SimpleDirInode {
             unused: 0,
         })
     }
+
+    fn base_getxattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<Vec<u8>> {
+        self.xattrs.get(name)
+    }
+
+    fn base_setxattr(
+        &self,
+        _dentry: &dyn Dentry,
+        name: &str,
+        value: &[u8],
+        flags: XattrFlags,
+    ) -> SysResult<()> {
+        self.xattrs.set(name, value, flags)
+    }
+
+    fn base_listxattr(&self, _dentry: &dyn Dentry) -> SysResult<Vec<String>> {
+        Ok(self.xattrs.list())
+    }
+
+    fn base_removexattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<()> {
+        self.xattrs.remove(name)
+    }
 }
 
 pub struct SimpleLinkInode {
     meta: InodeMeta,
+    xattrs: XattrMap,
 }
 
 impl SimpleLinkInode {
@@ -119,6 +172,7 @@ impl SimpleLinkInode {
         debug_assert!(mode.to_type().is_symlink());
         Arc::new(Self {
             meta: InodeMeta::new(mode, super_block, size),
+            xattrs: XattrMap::new(),
         })
     }
 }
@@ -151,4 +205,68 @@ impl Inode for SimpleLinkInode {
             unused: 0,
         })
     }
+
+    fn base_getxattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<Vec<u8>> {
+        self.xattrs.get(name)
+    }
+
+    fn base_setxattr(
+        &self,
+        _dentry: &dyn Dentry,
+        name: &str,
+        value: &[u8],
+        flags: XattrFlags,
+    ) -> SysResult<()> {
+        self.xattrs.set(name, value, flags)
+    }
+
+    fn base_listxattr(&self, _dentry: &dyn Dentry) -> SysResult<Vec<String>> {
+        Ok(self.xattrs.list())
+    }
+
+    fn base_removexattr(&self, _dentry: &dyn Dentry, name: &str) -> SysResult<()> {
+        self.xattrs.remove(name)
+    }
+}
+
+pub struct SimpleSockInode {
+    meta: InodeMeta,
+}
+
+impl SimpleSockInode {
+    pub fn new(mode: InodeMode, super_block: Arc<dyn SuperBlock>) -> Arc<Self> {
+        debug_assert!(mode.to_type().is_socket());
+        Arc::new(Self {
+            meta: InodeMeta::new(mode, super_block, 0),
+        })
+    }
+}
+
+impl Inode for SimpleSockInode {
+    fn meta(&self) -> &InodeMeta {
+        &self.meta
+    }
+
+    fn get_attr(&self) -> SysResult<Stat> {
+        let inner = self.meta.inner.lock();
+        let mode = self.meta.mode.bits();
+        Ok(Stat {
+            st_dev: 0,
+            st_ino: self.meta.ino as u64,
+            st_mode: mode,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: 512,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime: inner.atime,
+            st_mtime: inner.mtime,
+            st_ctime: inner.ctime,
+            unused: 0,
+        })
+    }
 }
@@ -74,7 +74,7 @@ impl SuperBlock for SockSuperBlock {
     }
 
     fn sync_fs(&self, _wait: isize) -> SysResult<()> {
-        todo!()
+        Ok(())
     }
 }
 
@@ -0,0 +1,288 @@
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    mem::{self, size_of},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use arch::time::get_time_duration;
+use async_trait::async_trait;
+use async_utils::get_waker;
+use sync::mutex::SpinNoIrqLock;
+use systype::{SysError, SysResult};
+use time::{
+    timespec::{ITimerSpec, TimeSpec},
+    CLOCK_DEVIATION, CLOCK_MONOTONIC, CLOCK_REALTIME,
+};
+use timer::{Timer, TimerEvent, TIMER_MANAGER};
+use vfs_core::{arc_zero, File, FileMeta, OpenFlags, PollEvents};
+
+type Mutex<T> = SpinNoIrqLock<T>;
+
+/// Timers armed with `TFD_TIMER_CANCEL_ON_SET`, canceled when the realtime
+/// clock is set.
+static CANCEL_ON_SET: Mutex<Vec<Weak<Mutex<TimerFdInner>>>> = Mutex::new(Vec::new());
+
+bitflags::bitflags! {
+    // Defined in <sys/timerfd.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TimerFdFlags: i32 {
+        const NONBLOCK = OpenFlags::O_NONBLOCK.bits();
+        const CLOEXEC = OpenFlags::O_CLOEXEC.bits();
+    }
+}
+
+bitflags::bitflags! {
+    // Defined in <sys/timerfd.h>.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TimerFdSetFlags: i32 {
+        /// Interpret new_value.it_value as an absolute value on the timer's
+        /// clock.
+        const ABSTIME = 1 << 0;
+        /// Cancel the timer if the realtime clock undergoes a discontinuous
+        /// change.
+        const CANCEL_ON_SET = 1 << 1;
+    }
+}
+
+pub struct TimerFdFile {
+    meta: FileMeta,
+    inner: Arc<Mutex<TimerFdInner>>,
+}
+
+struct TimerFdInner {
+    clockid: usize,
+    /// Next expiration time in the time of `get_time_duration`, `None` means
+    /// the timer is disarmed.
+    next_expire: Option<Duration>,
+    interval: Duration,
+    /// Number of expirations that have not been read.
+    ticks: u64,
+    /// Changed when the timer is set, timers with an old id in the
+    /// `TIMER_MANAGER` will be ignored.
+    id: usize,
+    /// Whether the timer is an absolute realtime timer set with
+    /// `TFD_TIMER_CANCEL_ON_SET`.
+    cancel_on_set: bool,
+    /// Set when the realtime clock is set, the next read fails with
+    /// `ECANCELED`.
+    canceled: bool,
+    read_waker: VecDeque<Waker>,
+}
+
+impl TimerFdInner {
+    fn itimerspec(&self) -> ITimerSpec {
+        let it_value = match self.next_expire {
+            Some(expire) => expire.saturating_sub(get_time_duration()).into(),
+            None => TimeSpec::default(),
+        };
+        ITimerSpec {
+            it_interval: self.interval.into(),
+            it_value,
+        }
+    }
+}
+
+struct TimerFdEvent {
+    timerfd: Weak<Mutex<TimerFdInner>>,
+    id: usize,
+}
+
+impl TimerEvent for TimerFdEvent {
+    fn callback(self: Box<Self>) -> Option<Timer> {
+        let timerfd = self.timerfd.upgrade()?;
+        let mut inner = timerfd.lock();
+        if inner.id != self.id {
+            return None;
+        }
+        let expire = inner.next_expire?;
+        let current = get_time_duration();
+        let next_expire = if inner.interval.is_zero() {
+            inner.ticks += 1;
+            None
+        } else {
+            // Count expirations that are missed.
+            let late = current.saturating_sub(expire).as_nanos();
+            let interval = inner.interval.as_nanos();
+            let overrun = u64::try_from(late / interval + 1).unwrap_or(u64::MAX);
+            inner.ticks = inner.ticks.saturating_add(overrun);
+            Some(current + inner.interval - Duration::from_nanos((late % interval) as u64))
+        };
+        inner.next_expire = next_expire;
+        while let Some(waker) = inner.read_waker.pop_front() {
+            waker.wake();
+        }
+        next_expire.map(|expire| Timer::new(expire, self))
+    }
+}
+
+impl TimerFdFile {
+    pub fn new(clockid: usize, flags: TimerFdFlags) -> SysResult<Arc<Self>> {
+        if clockid != CLOCK_REALTIME && clockid != CLOCK_MONOTONIC {
+            return Err(SysError::EINVAL);
+        }
+        let meta = FileMeta::new(arc_zero(), arc_zero());
+        let mut open_flags = OpenFlags::O_RDWR;
+        if flags.contains(TimerFdFlags::NONBLOCK) {
+            open_flags |= OpenFlags::O_NONBLOCK;
+        }
+        *meta.flags.lock() = open_flags;
+        let inner = Arc::new(Mutex::new(TimerFdInner {
+            clockid,
+            next_expire: None,
+            interval: Duration::ZERO,
+            ticks: 0,
+            id: 0,
+            cancel_on_set: false,
+            canceled: false,
+            read_waker: VecDeque::new(),
+        }));
+        Ok(Arc::new(Self { meta, inner }))
+    }
+
+    fn is_nonblock(&self) -> bool {
+        self.flags().contains(OpenFlags::O_NONBLOCK)
+    }
+
+    /// Return the current setting of the timer, `it_value` is the amount of
+    /// time until the timer will next expire.
+    pub fn gettime(&self) -> ITimerSpec {
+        self.inner.lock().itimerspec()
+    }
+
+    /// Arm or disarm the timer, return the old setting of the timer.
+    pub fn settime(&self, flags: TimerFdSetFlags, new: ITimerSpec) -> ITimerSpec {
+        let current = get_time_duration();
+        let mut inner = self.inner.lock();
+        let old = inner.itimerspec();
+        inner.id += 1;
+        inner.ticks = 0;
+        inner.canceled = false;
+        inner.cancel_on_set = inner.clockid == CLOCK_REALTIME
+            && flags.contains(TimerFdSetFlags::ABSTIME | TimerFdSetFlags::CANCEL_ON_SET);
+        inner.interval = new.it_interval.into();
+        let value: Duration = new.it_value.into();
+        inner.next_expire = if value.is_zero() {
+            None
+        } else if flags.contains(TimerFdSetFlags::ABSTIME) {
+            Some(value.saturating_sub(unsafe { CLOCK_DEVIATION }[inner.clockid]))
+        } else {
+            Some(current + value)
+        };
+        let timer = inner.next_expire.map(|expire| {
+            let event = TimerFdEvent {
+                timerfd: Arc::downgrade(&self.inner),
+                id: inner.id,
+            };
+            Timer::new(expire, Box::new(event))
+        });
+        let cancel_on_set = inner.cancel_on_set;
+        // NOTE: `TIMER_MANAGER` calls the callback with its lock held, so we
+        // should not add the timer with our lock held.
+        drop(inner);
+        if cancel_on_set {
+            let weak = Arc::downgrade(&self.inner);
+            let mut list = CANCEL_ON_SET.lock();
+            if !list.iter().any(|timerfd| timerfd.ptr_eq(&weak)) {
+                list.push(weak);
+            }
+        }
+        if let Some(timer) = timer {
+            TIMER_MANAGER.add_timer(timer);
+        }
+        old
+    }
+}
+
+/// Called when the realtime clock is set. Timers armed with
+/// `TFD_TIMER_CANCEL_ON_SET` are canceled, and they become readable to
+/// report it.
+pub fn clock_was_set() {
+    let timerfds: Vec<Arc<Mutex<TimerFdInner>>> = {
+        let mut list = CANCEL_ON_SET.lock();
+        list.retain(|timerfd| timerfd.strong_count() != 0);
+        list.iter().filter_map(Weak::upgrade).collect()
+    };
+    for timerfd in timerfds {
+        let mut inner = timerfd.lock();
+        if !inner.cancel_on_set {
+            continue;
+        }
+        inner.canceled = true;
+        inner.ticks = inner.ticks.saturating_add(1);
+        while let Some(waker) = inner.read_waker.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+struct TimerFdReadFuture<'a> {
+    timerfd: &'a TimerFdFile,
+}
+
+impl Future for TimerFdReadFuture<'_> {
+    type Output = SysResult<u64>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.timerfd.inner.lock();
+        if mem::take(&mut inner.canceled) {
+            inner.ticks = 0;
+            return Poll::Ready(Err(SysError::ECANCELED));
+        }
+        if inner.ticks == 0 {
+            if self.timerfd.is_nonblock() {
+                return Poll::Ready(Err(SysError::EAGAIN));
+            }
+            inner.read_waker.push_back(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(mem::take(&mut inner.ticks)))
+    }
+}
+
+#[async_trait]
+impl File for TimerFdFile {
+    fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// If the timer has already expired one or more times since its settings
+    /// were last modified using timerfd_settime(), or since the last
+    /// successful read(2), then the buffer given to read(2) returns an
+    /// unsigned 8-byte integer containing the number of expirations that have
+    /// occurred.
+    async fn read_at(&self, _offset: usize, buf: &mut [u8]) -> SysResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(SysError::EINVAL);
+        }
+        let ticks = TimerFdReadFuture { timerfd: self }.await?;
+        log::info!("[TimerFdFile::read_at] expirations {ticks}");
+        buf[..size_of::<u64>()].copy_from_slice(&ticks.to_ne_bytes());
+        Ok(size_of::<u64>())
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> SysResult<usize> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut inner = self.inner.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if inner.ticks > 0 {
+                res |= PollEvents::IN;
+            } else {
+                inner.read_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
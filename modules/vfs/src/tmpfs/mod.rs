@@ -74,6 +74,6 @@ impl SuperBlock for TmpSuperBlock {
     }
 
     fn sync_fs(&self, _wait: isize) -> SysResult<()> {
-        todo!()
+        Ok(())
     }
 }
@@ -0,0 +1,42 @@
+//! Writeback task which writes back dirty pages of all file systems
+//! periodically.
+
+use core::time::Duration;
+
+use config::fs::{dirty_expire_ms, dirty_writeback_interval_ms};
+use page::nr_dirty_pages;
+use timer::timelimited_task::ksleep_ms;
+use vfs_core::writeback::dirty_background_limit;
+
+use crate::all_super_blocks;
+
+/// Write back pages that have been dirty for longer than the expire time
+/// every writeback interval, or all dirty pages when they exceed the
+/// background limit.
+pub async fn writeback_task() {
+    loop {
+        ksleep_ms(dirty_writeback_interval_ms()).await;
+        let nr_dirty = nr_dirty_pages();
+        if nr_dirty == 0 {
+            continue;
+        }
+        let expire = if nr_dirty > dirty_background_limit() {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(dirty_expire_ms() as u64)
+        };
+        for sb in all_super_blocks() {
+            log::debug!(
+                "[writeback_task] {} dirty pages in {}, expire {expire:?}",
+                sb.nr_dirty_pages(),
+                sb.fs_type().name()
+            );
+            if let Err(e) = sb.write_back_expired(expire) {
+                log::warn!(
+                    "[writeback_task] fail to write back {}, err {e:?}",
+                    sb.fs_type().name()
+                );
+            }
+        }
+    }
+}